@@ -0,0 +1,126 @@
+use crate::edge::Edge;
+use crate::fluid::Fluid;
+use crate::friction::FrictionModel;
+
+// Internal discretisation of a pipe or bend for the method of characteristics
+#[derive(Clone, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Reaches {
+    pub segments: usize,            // Number of reaches
+    pub wave_speed: f64,            // Wave speed adjusted to the time step [m/s]
+    pub impedance: f64,             // Characteristic impedance B = a / ( g A ) [s/m^2]
+    pub dt: f64,                    // Time step [s]
+    pub head: Vec<f64>,             // Head at the reach ends, from node first [m]
+    pub flow: Vec<f64>,             // Volume flow rate arriving at the reach ends [m^3/s]
+    pub downstream: Vec<f64>,       // Volume flow rate leaving the reach ends (differs at a cavity) [m^3/s]
+    pub cavity: Vec<f64>,           // Vapour cavity volume at the reach ends [m^3]
+    pub vapour_head: Vec<f64>,      // Head at which a vapour cavity forms [m]
+}
+
+impl Reaches {
+    // None for edges that do not carry pressure waves
+    pub fn new( edge: &Edge, fluid: &Fluid, dt: f64, g: f64 ) -> Option<Self> {
+        let length = edge.conduit_length()?;
+        // Courant number of one: the wave speed is adjusted to fit the number of reaches
+        let segments = edge.reach_count( fluid, dt, true );
+        let wave_speed = length / ( segments as f64 * dt );
+        Some( Reaches {
+            segments,
+            wave_speed,
+            impedance: wave_speed / ( g * edge.area() ),
+            dt,
+            head: vec![ 0.0; segments + 1 ],
+            flow: vec![ 0.0; segments + 1 ],
+            downstream: vec![ 0.0; segments + 1 ],
+            cavity: vec![ 0.0; segments + 1 ],
+            vapour_head: vec![ f64::NEG_INFINITY; segments + 1 ],
+        })
+    }
+
+    // Steady profile with uniform flow and a linear head loss
+    pub fn initialise(&mut self, q: f64, h_from: f64, h_to: f64 ) {
+        for i in 0..=self.segments {
+            let x = i as f64 / self.segments as f64;
+            self.head[i] = h_from + x * ( h_to - h_from );
+            self.flow[i] = q;
+            self.downstream[i] = q;
+            self.cavity[i] = 0.0;
+        }
+    }
+
+    // Vapour cavities form in the interior when the head falls below the vapour head
+    pub fn set_vapour_head(&mut self, from: f64, to: f64 ) {
+        for i in 0..=self.segments {
+            let x = i as f64 / self.segments as f64;
+            self.vapour_head[i] = from + x * ( to - from );
+        }
+    }
+
+    // Characteristic constants ( C- at the from node, C+ at the to node )
+    pub fn end_characteristics(&self, edge: &Edge, nu: f64, g: f64, step: usize, friction: FrictionModel ) -> (f64, f64) {
+        ( self.c_minus( 1, edge, nu, g, step, friction ), self.c_plus( self.segments - 1, edge, nu, g, step, friction ) )
+    }
+
+    // Flow rate at the to node from the C+ characteristic
+    pub fn to_flow(&self, c_plus: f64, h_to: f64 ) -> f64 {
+        ( c_plus - h_to ) / self.impedance
+    }
+
+    // Move the interior points forward one step and set the ends from the network solution
+    pub fn advance(&mut self, edge: &Edge, nu: f64, g: f64, step: usize, ends: (f64, f64, f64), friction: FrictionModel ) {
+        let ( h_from, q_from, h_to ) = ends;
+        let n = self.segments;
+        let ( mut head, mut flow ) = ( self.head.clone(), self.flow.clone() );
+        let ( mut downstream, mut cavity ) = ( self.downstream.clone(), self.cavity.clone() );
+        for i in 1..n {
+            let cp = self.c_plus( i - 1, edge, nu, g, step, friction );
+            let cm = self.c_minus( i + 1, edge, nu, g, step, friction );
+            head[i] = 0.5 * ( cp + cm );
+            flow[i] = 0.5 * ( cp - cm ) / self.impedance;
+            downstream[i] = flow[i];
+            cavity[i] = 0.0;
+            if self.cavity[i] > 0.0 || head[i] < self.vapour_head[i] {
+                // Discrete vapour cavity: the head is held at the vapour head
+                let hv = self.vapour_head[i];
+                let ( qu, qd ) = ( ( cp - hv ) / self.impedance, ( hv - cm ) / self.impedance );
+                let growth = ( qd - qu ) + ( self.downstream[i] - self.flow[i] );
+                let volume = self.cavity[i] + 0.5 * self.dt * growth;
+                // The cavity collapses when its volume becomes negative 
+                if volume > 0.0 || self.cavity[i] == 0.0 {
+                    head[i] = hv;
+                    flow[i] = qu;
+                    downstream[i] = qd;
+                    cavity[i] = volume.max( 0.0 );
+                }
+            }
+        }
+        let cp = self.c_plus( n - 1, edge, nu, g, step, friction );
+        head[0] = h_from;
+        flow[0] = q_from;
+        downstream[0] = q_from;
+        head[n] = h_to;
+        flow[n] = self.to_flow( cp, h_to );
+        downstream[n] = flow[n];
+        self.head = head;
+        self.flow = flow;
+        self.downstream = downstream;
+        self.cavity = cavity;
+    }
+
+    fn c_plus(&self, i: usize, edge: &Edge, nu: f64, g: f64, step: usize, friction: FrictionModel ) -> f64 {
+        let q = self.downstream[i];
+        self.head[i] + self.impedance * q - self.head_loss( edge, q, nu, g, step, friction )
+    }
+
+    fn c_minus(&self, i: usize, edge: &Edge, nu: f64, g: f64, step: usize, friction: FrictionModel ) -> f64 {
+        let q = self.flow[i];
+        self.head[i] - self.impedance * q + self.head_loss( edge, q, nu, g, step, friction )
+    }
+
+    // Head loss over one reach (the edge resistance is linear in the head difference)
+    fn head_loss(&self, edge: &Edge, q: f64, nu: f64, g: f64, step: usize, friction: FrictionModel ) -> f64 {
+        if q == 0.0 { return 0.0; }
+        let r0 = edge.resistance( q, 0.0, nu, g, step, friction );
+        let r1 = edge.resistance( q, 1.0, nu, g, step, friction );
+        - r0 / ( ( r1 - r0 ) * self.segments as f64 )
+    }
+}
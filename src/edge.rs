@@ -11,10 +11,37 @@ use crate::edges::{
     bursting_disk::BurstingDisk,
     generic::Generic,
     open_pipe::OpenPipe,
+    control_valve::ControlValve,
 };
 use crate::fluid::Fluid;
 use crate::events::TransientEvent;
+use crate::series::TimeSeries;
 use crate::utility;
+use crate::friction::FrictionModel;
+
+pub const MAX_REACHES: usize = 1000;  // Reaches allowed in one edge in transient runs
+
+// The nodes at the ends of an edge, taken from the graph when the edge needs their state
+#[derive(Clone, Copy, Debug)]
+pub struct EdgeContext<'a> {
+    pub from: &'a Node,
+    pub to: &'a Node,
+}
+
+// Run state of an edge that the solver carries from one time step to the next
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct EdgeState {
+    pub restart: Option<(usize, f64)>,  // Pump ( event, run-down speed [rpm] ) when an event after a power failure began
+    pub pid: (f64, f64),                // Control valve ( integral of the error [s], last error )
+    pub lifted: bool,                   // Safety valve lifted at set_dp and not yet reseated
+}
+
+impl EdgeContext<'_> {
+    // Pressure difference from the from node to the to node at a time step [Pa]
+    pub fn dp(&self, step: usize ) -> f64 {
+        self.from.pressure_at( step ) - self.to.pressure_at( step )
+    }
+}
 
 #[derive(Clone, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
 pub enum Edge {
@@ -29,6 +56,7 @@ pub enum Edge {
     BurstingDisk(BurstingDisk),
     Generic(Generic),
     OpenPipe(OpenPipe),
+    ControlValve(ControlValve),
 }
 
 impl std::fmt::Display for Edge {
@@ -45,6 +73,7 @@ impl std::fmt::Display for Edge {
             Edge::BurstingDisk(_edge) => write!(f, "Bursting Disk"),
             Edge::Generic(_edge) => write!(f, "Generic"),
             Edge::OpenPipe(_edge) => write!(f, "Open Pipe"),
+            Edge::ControlValve(edge) => write!(f, "{}", edge.mode.text()),
         }
     }
 }
@@ -64,21 +93,24 @@ macro_rules! match_edge {
             Edge::BurstingDisk($edge) => $block,
             Edge::Generic($edge) => $block,
             Edge::OpenPipe($edge) => $block,
+            Edge::ControlValve($edge) => $block,
         }
     };
 }
 
 impl Edge {
-    pub fn from(&self) -> Node {
-        match_edge!(self, edge, {edge.from.clone()})
+    // From node id
+    pub fn from(&self) -> usize {
+        match_edge!(self, edge, {edge.from})
     }
 
-    pub fn to(&self) -> Node {
-        match_edge!(self, edge, {edge.to.clone()})
+    // To node id
+    pub fn to(&self) -> usize {
+        match_edge!(self, edge, {edge.to})
     }
 
     pub fn id(&self) -> (usize, usize) {
-        match_edge!(self, edge, {(edge.from.id(), edge.to.id())})
+        match_edge!(self, edge, {(edge.from, edge.to)})
     }
 
     pub fn mass_flow(&mut self) -> &mut Vec<f64> {
@@ -108,6 +140,43 @@ impl Edge {
         }
     }
 
+    // Length of the edges that carry pressure waves (pipes and bends)
+    pub fn conduit_length(&self) -> Option<f64> {
+        match self {
+            Edge::Pipe(edge) => Some( edge.length ),
+            Edge::Bend(edge) => Some( edge.length() ),
+            _ => None,
+        }
+    }
+
+    pub fn wave_speed(&self, fluid: &Fluid ) -> Option<f64> {
+        match self {
+            Edge::Pipe(edge) => Some( edge.wave_speed( fluid ) ),
+            Edge::Bend(edge) => Some( edge.wave_speed( fluid ) ),
+            _ => None,
+        }
+    }
+
+    // Number of reaches of a pipe or bend given by its segments or, if automatic, chosen to resolve
+    // pressure waves (the wave travels one reach per time step)
+    pub fn reach_count(&self, fluid: &Fluid, dt: f64, automatic: bool ) -> usize {
+        match self {
+            Edge::Pipe(edge) if edge.segments > 0 => edge.segments,
+            _ if !automatic => 1,
+            _ => match ( self.conduit_length(), self.wave_speed( fluid ) ) {
+                ( Some( length ), Some( a ) ) => ( ( length / ( a * dt ) ).round() as usize ).max( 1 ),
+                _ => 1,
+            },
+        }
+    }
+
+    pub fn segments(&mut self) -> Option<&mut usize> {
+        match self {
+            Edge::Pipe(edge) => Some(&mut edge.segments),
+            _ => None,
+        }
+    }
+
     pub fn coefficients(&mut self) -> Option<&mut (f64, f64, f64)> {
         match self {
             Edge::Generic(edge) => Some(&mut edge.coefficients),
@@ -149,6 +218,7 @@ impl Edge {
             Edge::BurstingDisk(edge) => Some(&mut edge.diameter),
             Edge::Generic(_edge) => None,
             Edge::OpenPipe(edge) => Some(&mut edge.diameter),
+            Edge::ControlValve(edge) => Some(&mut edge.diameter),
         }
     }
 
@@ -164,6 +234,13 @@ impl Edge {
         }
     }
 
+    pub fn pressure_rating(&mut self) -> Option<&mut f64> {
+        match self {
+            Edge::Pipe(edge) => Some(&mut edge.pressure_rating),
+            _ => None,
+        }
+    }
+
     pub fn thickness(&mut self) -> Option<&mut f64> {
         match self {
             Edge::Pipe(edge) => Some( &mut edge.thickness ),
@@ -192,6 +269,7 @@ impl Edge {
             Edge::SafetyValve(edge) => Some(&mut edge.open_percent),
             Edge::ReliefValve(edge) => Some(&mut edge.open_percent),
             Edge::BurstingDisk(edge) => Some(&mut edge.open_percent),
+            Edge::ControlValve(edge) => Some(&mut edge.open_percent),
             _ => None,
         }
     }
@@ -221,6 +299,7 @@ impl Edge {
             Edge::SafetyValve(edge) => Some(&mut edge.invk),
             Edge::ReliefValve(edge) => Some(&mut edge.invk),
             Edge::BurstingDisk(edge) => Some(&mut edge.invk),
+            Edge::ControlValve(edge) => Some(&mut edge.invk),
             _ => None,
         }
     }
@@ -248,6 +327,7 @@ impl Edge {
             Edge::SafetyValve(edge) => Some( 1.0 / edge.invk( step ) ),
             Edge::ReliefValve(edge) => Some( 1.0 / edge.invk( step ) ),
             Edge::BurstingDisk(edge) => Some( 1.0 / edge.invk( step ) ),
+            Edge::ControlValve(edge) => Some( 1.0 / edge.invk( step ) ),
             Edge::OpenPipe(edge) => Some( edge.k ),
             _ => None,
         }
@@ -270,30 +350,44 @@ impl Edge {
             Edge::SafetyValve(edge) => edge.b_coefficient( step ),
             Edge::ReliefValve(edge) => edge.b_coefficient( step ),
             Edge::BurstingDisk(edge) => edge.b_coefficient( step ),
+            Edge::ControlValve(edge) => edge.b_coefficient( step ),
             _ => 1.0,
         }
     }
 
-    pub fn drdq(&self, q: f64, dh: f64, nu: f64, g: f64, step: usize ) -> f64 {
-        let delta = 1.0e-8;
-        let r_plus = self.resistance( q + delta, dh, nu, g, step );
-        let r_minus = self.resistance( q - delta, dh, nu, g, step );
-        ( r_plus - r_minus ) / ( 2.0 * delta )
+    // The solver default friction model applies to pipes and bends without a model of their own
+    pub fn drdq(&self, q: f64, dh: f64, nu: f64, g: f64, step: usize, friction: FrictionModel ) -> f64 {
+        match self {
+            Edge::Pipe(edge) => edge.resistance_derivatives( q, nu, g, friction ).0,
+            Edge::Bend(edge) => edge.resistance_derivatives( q, nu, g, friction ).0,
+            _ => {
+                let delta = 1.0e-8;
+                let r_plus = self.resistance( q + delta, dh, nu, g, step, friction );
+                let r_minus = self.resistance( q - delta, dh, nu, g, step, friction );
+                ( r_plus - r_minus ) / ( 2.0 * delta )
+            },
+        }
     }
 
-    pub fn drdkh(&self, q:f64, dh: f64, nu: f64, g: f64, step: usize ) -> f64 {
-        let delta = 1.0e-8;
-        let r_plus = self.resistance( q, dh + delta, nu, g, step );
-        let r_minus = self.resistance( q, dh - delta, nu, g, step );
-        ( r_plus - r_minus ) / ( 2.0 * delta )
+    pub fn drdkh(&self, q:f64, dh: f64, nu: f64, g: f64, step: usize, friction: FrictionModel ) -> f64 {
+        match self {
+            Edge::Pipe(edge) => edge.resistance_derivatives( q, nu, g, friction ).1,
+            Edge::Bend(edge) => edge.resistance_derivatives( q, nu, g, friction ).1,
+            _ => {
+                let delta = 1.0e-8;
+                let r_plus = self.resistance( q, dh + delta, nu, g, step, friction );
+                let r_minus = self.resistance( q, dh - delta, nu, g, step, friction );
+                ( r_plus - r_minus ) / ( 2.0 * delta )
+            },
+        }
     }
 
-    pub fn resistance(&self, q: f64, dh: f64, nu: f64, g: f64, step: usize ) -> f64 {
+    pub fn resistance(&self, q: f64, dh: f64, nu: f64, g: f64, step: usize, friction: FrictionModel ) -> f64 {
         match self {
-            Edge::Pipe(edge) => edge.resistance( q, dh, nu, g ),
+            Edge::Pipe(edge) => edge.resistance( q, dh, nu, g, friction ),
             Edge::Valve(edge) => edge.resistance( q, dh, nu, g, step ),
             Edge::Pump(edge) => edge.resistance( q, dh, nu, g, step ),
-            Edge::Bend(edge) => edge.resistance( q, dh, nu, g ),
+            Edge::Bend(edge) => edge.resistance( q, dh, nu, g, friction ),
             Edge::SizeChange(edge) => edge.resistance( q, dh, nu, g ),
             Edge::CheckValve(edge) => edge.resistance( q, dh, nu, g, step ),
             Edge::SafetyValve(edge) => edge.resistance( q, dh, nu, g, step ),
@@ -301,6 +395,7 @@ impl Edge {
             Edge::BurstingDisk(edge) => edge.resistance( q, dh, nu, g, step ),
             Edge::Generic(edge) => edge.resistance( q, dh, nu, g ),
             Edge::OpenPipe(edge) => edge.resistance( q, dh, nu, g ),
+            Edge::ControlValve(edge) => edge.resistance( q, dh, nu, g, step ),
         }
     }
 
@@ -312,18 +407,44 @@ impl Edge {
         match_edge!(self, edge, {edge.darcy_approx(head_loss, g)})
     }
 
-    pub fn add_transient_value(&mut self, time: f64 ) {
+    pub fn add_transient_value(&mut self, time: f64, dt: f64, spawned: &[TransientEvent], context: EdgeContext, 
+        state: &mut EdgeState ) {
         match self {
-            Edge::Valve(edge) => edge.add_transient_value( time ), 
-            Edge::Pump(edge) => edge.add_transient_value( time ),
-            Edge::CheckValve(edge) => edge.add_transient_value( time ),
-            Edge::SafetyValve(edge) => edge.add_transient_value( time ),
-            Edge::ReliefValve(edge) => edge.add_transient_value( time ),
-            Edge::BurstingDisk(edge) => edge.add_transient_value( time ),
+            Edge::Valve(edge) => edge.add_transient_value( time, spawned ), 
+            Edge::Pump(edge) => edge.add_transient_value( time, spawned, &mut state.restart ),
+            Edge::CheckValve(edge) => edge.add_transient_value( time, context ),
+            Edge::SafetyValve(edge) => edge.add_transient_value( dt, context, &mut state.lifted ),
+            Edge::ReliefValve(edge) => edge.add_transient_value( time, context ),
+            Edge::BurstingDisk(edge) => edge.add_transient_value( time, context ),
+            Edge::ControlValve(edge) => edge.add_transient_value(),
             _ => {},
         }
     }
 
+    // Update the speed of a pump that has lost power from the flow rates ( q_old, q_new )
+    pub fn run_down(&mut self, q: (f64, f64), dt: f64, step: usize ) {
+        if let Edge::Pump(edge) = self {
+            edge.run_down( q, dt, step );
+        }
+    }
+
+    // Move the disc of a dynamic check valve for the flow rate q and head difference dh
+    pub fn swing(&mut self, q: f64, dh: f64, dt: f64, step: usize, density: f64, g: f64 ) {
+        if let Edge::CheckValve(edge) = self {
+            if edge.is_dynamic() {
+                edge.swing( q, dh, dt, step, density, g );
+            }
+        }
+    }
+
+    // Move the opening of a control valve with the flow rate and end pressures ( from, to ) at the
+    // start of a step
+    pub fn actuate(&mut self, mass_flow: f64, p: (f64, f64), dt: f64, step: usize, density: f64, state: &mut EdgeState ) {
+        if let Edge::ControlValve(edge) = self {
+            edge.actuate( mass_flow, p, dt, step, density, &mut state.pid );
+        }
+    }
+
     pub fn events(&mut self) -> Option<&mut Vec<TransientEvent>> {
         match self {
             Edge::Valve(edge) => Some(&mut edge.events),
@@ -348,19 +469,28 @@ impl Edge {
         }
     }
 
-    pub fn selected( &mut self, select: bool ) {
-        match_edge!(self, edge, {edge.selected = select})
+    // Valve opening (0 to 1) or pump speed [rpm] against time
+    pub fn series(&mut self) -> Option<&mut Option<TimeSeries>> {
+        match self {
+            Edge::Valve(edge) => Some(&mut edge.series),
+            Edge::Pump(edge) => Some(&mut edge.series),
+            _ => None,
+        }
     }
 
-    pub fn is_selected(&self) -> bool {
-        match_edge!(self, edge, {edge.selected})
+    pub fn start_series(&mut self) {
+        match self {
+            Edge::Valve(edge) => edge.start_series(),
+            Edge::Pump(edge) => edge.start_series(),
+            _ => {},
+        }
     }
 
-    pub fn update_from(&mut self, node: Node ) {
-        match_edge!(self, edge, {edge.from = node})
+    pub fn selected( &mut self, select: bool ) {
+        match_edge!(self, edge, {edge.selected = select})
     }
 
-    pub fn update_to(&mut self, node: Node ) {
-        match_edge!(self, edge, {edge.to = node})
+    pub fn is_selected(&self) -> bool {
+        match_edge!(self, edge, {edge.selected})
     }
 }
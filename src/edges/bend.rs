@@ -1,13 +1,12 @@
 use std::f64::consts::PI;
-use crate::node::Node;
 use crate::fluid::Fluid;
-use crate::utility;
+use crate::friction::FrictionModel;
 
 #[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub struct Bend {
-    pub from: Node,
-    pub to: Node,
+    pub from: usize,                // From node id
+    pub to: usize,                  // To node id
     pub mass_flow: Vec<f64>,        // [kg/s]
     pub radius: f64,                // Bend radius [m]
     pub diameter: f64,              // [m]
@@ -15,12 +14,14 @@ pub struct Bend {
     pub roughness: f64,             // [m]
     pub thickness: f64,             // [m]
     pub youngs_modulus: f64,        // [Pa]
+    #[serde(default)]
+    pub friction: FrictionModel,    // Default follows the solver
     pub width: f32,
     pub selected: bool,
 }
 
 impl Bend {
-    pub fn new(from: Node, to: Node ) -> Self {
+    pub fn new(from: usize, to: usize ) -> Self {
         Bend { 
             from, 
             to, 
@@ -31,12 +32,14 @@ impl Bend {
             roughness: 0.05e-3,             // 0.05mm
             thickness: 5.0e-3,              // 5mm pipe
             youngs_modulus: 2.0e11,         // Steel pipe
+            friction: FrictionModel::Default,
             width: 5.0, 
             selected: false,
         }
     }
 
-    pub fn new_params(from: Node, to: Node, radius: f64, diameter: f64, angle: f64, roughness: f64, 
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_params(from: usize, to: usize, radius: f64, diameter: f64, angle: f64, roughness: f64, 
         thickness: f64, youngs_modulus: f64 ) -> Self 
     {
         Bend { 
@@ -49,6 +52,7 @@ impl Bend {
             roughness,
             thickness,
             youngs_modulus,
+            friction: FrictionModel::Default,
             width: 5.0, 
             selected: false,
         }
@@ -74,29 +78,43 @@ impl Bend {
         flow_rate * self.diameter / ( self.area() * nu )
     }
 
-    pub fn friction_factor(&self, flow_rate: f64, nu: f64 ) -> f64 {
-        let relative: f64 = self.roughness / self.diameter;
-        let re = self.reynolds( flow_rate, nu );
-        utility::friction_factor( relative, re )
+    // Friction factor with the model of the bend or the default model of the solver
+    pub fn friction_factor(&self, flow_rate: f64, nu: f64, default: FrictionModel ) -> f64 {
+        self.friction.or( default ).friction_factor( flow_rate, self.diameter, self.roughness, nu )
     }
 
-    pub fn k(&self, flow_rate: f64, nu: f64 ) -> f64 {
-        let f = self.friction_factor( flow_rate, nu );
+    pub fn k(&self, flow_rate: f64, nu: f64, default: FrictionModel ) -> f64 {
+        self.loss( flow_rate, nu, default ).0
+    }
+
+    // Loss coefficient and its derivative with respect to the flow rate magnitude with the
+    // default model of the solver (the loss coefficient is linear in the friction factor)
+    pub fn loss(&self, flow_rate: f64, nu: f64, default: FrictionModel ) -> (f64, f64) {
+        let ( f, df ) = self.friction.or( default ).friction( flow_rate, self.diameter, self.roughness, nu );
         let rd = self.radius / self.diameter;
         let s = ( 0.5 * self.angle ).sin();
         let pow = rd.powf( 4. * self.angle / PI );
-        f * self.angle * rd + ( 0.1 + 2.4 * f ) * s + ( 6.6 * f * ( s.sqrt() + s ) / pow ) 
+        let slope = self.angle * rd + 2.4 * s + ( 6.6 * ( s.sqrt() + s ) / pow );
+        ( f * self.angle * rd + ( 0.1 + 2.4 * f ) * s + ( 6.6 * f * ( s.sqrt() + s ) / pow ), df * slope )
     }
 
-    pub fn resistance(&self, q: f64, dh: f64, nu: f64, g: f64 ) -> f64 {
+    pub fn resistance(&self, q: f64, dh: f64, nu: f64, g: f64, default: FrictionModel ) -> f64 {
         if q == 0.0 {
             0.0
         } else {
-            let k = self.k( q, nu );
+            let ( k, _ ) = self.loss( q, nu, default );
             - ( k * q * q.abs() / ( 2. * self.area() ) ) + g * self.area() * dh
         }
     }
 
+    // Derivatives of the resistance with respect to the flow rate and the head difference
+    pub fn resistance_derivatives(&self, q: f64, nu: f64, g: f64, default: FrictionModel ) -> (f64, f64) {
+        // The laminar friction factor is singular at zero flow but its product with q|q| is not
+        let q_abs = q.abs().max( f64::EPSILON );
+        let ( k, dk ) = self.loss( q_abs, nu, default );
+        ( - ( 2.0 * k * q_abs + dk * q_abs * q_abs ) / ( 2. * self.area() ), g * self.area() )
+    }
+
     //TODO: implement k_laminar
     pub fn k_laminar(&self, nu: f64 ) -> f64 {
         PI * 9.806 * self.diameter.powi( 4 ) / ( 128.0 * self.length() * nu )
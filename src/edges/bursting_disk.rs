@@ -1,11 +1,11 @@
 use std::f64::consts::PI;
-use crate::node::Node;
+use crate::edge::EdgeContext;
 
 #[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub struct BurstingDisk {
-    pub from: Node,
-    pub to: Node,
+    pub from: usize,                // From node id
+    pub to: usize,                  // To node id
     pub mass_flow: Vec<f64>,
     pub diameter: f64,
     pub thickness: f64,
@@ -18,7 +18,7 @@ pub struct BurstingDisk {
 }
 
 impl BurstingDisk {
-    pub fn new(from: Node, to: Node, burst_dp: f64 ) -> Self {
+    pub fn new(from: usize, to: usize, burst_dp: f64 ) -> Self {
         BurstingDisk { 
             from, 
             to, 
@@ -70,11 +70,9 @@ impl BurstingDisk {
         result.sqrt()
     }
 
-    pub fn add_transient_value( &mut self, _time: f64 ) {
+    pub fn add_transient_value( &mut self, _time: f64, context: EdgeContext ) {
         let step = self.open_percent.len() - 1;
-        let p_from = self.from.pressure()[ step ];
-        let p_to = self.to.pressure()[ step ];
-        let dp = p_from - p_to;
+        let dp = context.dp( step );
         let last_open_percent = self.open_percent[ step ];
         // The bursting disk is open if the pressure difference is greater than the burst pressure
         // or if it was open last time step
@@ -1,23 +1,36 @@
 use std::f64::consts::PI;
-use crate::node::Node;
+use crate::edge::EdgeContext;
+use crate::utility;
 
 #[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub struct CheckValve {
-    pub from: Node,
-    pub to: Node,
+    pub from: usize,                // From node id
+    pub to: usize,                  // To node id
     pub mass_flow: Vec<f64>,
     pub diameter: f64,
     pub thickness: f64,
     pub youngs_modulus: f64,
     pub invk: Vec<(f64, f64)>, // (% open, k^-1)
     pub open_percent: Vec<f64>,
+    #[serde(default)]
+    pub disc_inertia: f64,              // Disc moment of inertia (0 = instantaneous open/close) [kg m^2]
+    #[serde(default = "default_spring_torque")]
+    pub spring_torque: f64,             // Closing torque from the spring and disc weight [N m]
+    #[serde(default = "default_flow_torque")]
+    pub flow_torque: f64,               // Opening torque per unit pressure on the disc [m^3]
+    #[serde(default = "default_open_angle")]
+    pub open_angle: f64,                // Disc travel from closed to fully open [rad]
+    #[serde(default)]
+    pub closure_time: f64,              // Shortest full stroke allowed by the damper (0 = undamped) [s]
+    #[serde(default = "default_disc_speed")]
+    pub disc_speed: Vec<f64>,           // Disc angular velocity at each time step [rad/s]
     pub width: f32,
     pub selected: bool,
 }
 
 impl CheckValve {
-    pub fn new(from: Node, to: Node ) -> Self {
+    pub fn new(from: usize, to: usize ) -> Self {
         CheckValve { 
             from, 
             to, 
@@ -27,6 +40,12 @@ impl CheckValve {
             youngs_modulus: 2.0e11, // Steel pipe TODO should be able to modify
             invk: default_check_valve_data(),
             open_percent: vec![ 1.0 ], // 100% open by default
+            disc_inertia: 0.0,
+            spring_torque: default_spring_torque(),
+            flow_torque: default_flow_torque(),
+            open_angle: default_open_angle(),
+            closure_time: 0.0,
+            disc_speed: default_disc_speed(),
             width: 15.0, 
             selected: false,
         }
@@ -37,12 +56,50 @@ impl CheckValve {
     }
 
     pub fn invk(&self, step: usize ) -> f64 {
-        if self.open_percent[step] < 1.0 { // If closed 
+        if self.is_dynamic() { // Partially open while the disc travels
+            let ( open, invk ) = utility::split_into_two_vectors( &self.invk );
+            utility::interpolate( self.open_percent[step], &open, &invk )
+        } else if self.open_percent[step] < 1.0 { // If closed 
             0.0
         } else {
             self.invk[1].1
         }
-        // TODO eventually we may want to allow for partially open check valves during opening/closing.
+    }
+
+    // The disc moves under the flow and spring torques rather than snapping open or shut
+    pub fn is_dynamic(&self) -> bool {
+        self.disc_inertia > 0.0
+    }
+
+    // Disc position at the next step from I dw/dt = T_flow - T_spring (semi-implicit Euler). The 
+    // flow acts through the dynamic pressure on an open disc and the pressure difference on a closed one.
+    pub fn swing(&mut self, q: f64, dh: f64, dt: f64, step: usize, density: f64, g: f64 ) {
+        let open = self.open_percent[ step ];
+        let pressure = if open > 0.0 {
+            let v = q / self.area();
+            0.5 * density * v * v.abs()
+        } else {
+            density * g * dh
+        };
+        let torque = self.flow_torque * pressure - self.spring_torque;
+        let mut speed = self.disc_speed[ step ] + dt * torque / self.disc_inertia;
+        if self.closure_time > 0.0 {
+            let limit = self.open_angle / self.closure_time;
+            speed = speed.clamp( -limit, limit );
+        }
+        let mut angle = open * self.open_angle + dt * speed;
+        if angle <= 0.0 || angle >= self.open_angle {
+            angle = angle.clamp( 0.0, self.open_angle );
+            speed = 0.0;
+        }
+        self.open_percent[ step + 1 ] = angle / self.open_angle;
+        self.disc_speed[ step + 1 ] = speed;
+    }
+
+    // Flow velocity through the valve as the disc first seats (negative for reverse flow) [m/s]
+    pub fn slam_velocity(&self, density: f64 ) -> Option<f64> {
+        let k = self.open_percent.windows( 2 ).position( |x| x[0] > 0.0 && x[1] == 0.0 )?;
+        Some( self.mass_flow[ k ] / ( density * self.area() ) )
     }
 
     pub fn area(&self) -> f64 {
@@ -69,11 +126,15 @@ impl CheckValve {
         result.sqrt()
     }
 
-    pub fn add_transient_value( &mut self, _time: f64 ) {
+    pub fn add_transient_value( &mut self, _time: f64, context: EdgeContext ) {
+        // The solver replaces these with the disc motion
+        if self.is_dynamic() {
+            self.open_percent.push( *self.open_percent.last().unwrap() );
+            self.disc_speed.push( *self.disc_speed.last().unwrap() );
+            return;
+        }
         let step = self.open_percent.len() - 1;
-        let p_from = self.from.pressure()[ step ];
-        let p_to = self.to.pressure()[ step ];
-        let dp = p_from - p_to;
+        let dp = context.dp( step );
         if dp > 0.0 {
             self.open_percent.push( 1.0 )
         } else {
@@ -83,6 +144,23 @@ impl CheckValve {
 
 }
 
+fn default_spring_torque() -> f64 {
+    0.02                                    // 0.02 N m
+}
+
+// Disc area times the lever arm for the default 52.5mm valve
+fn default_flow_torque() -> f64 {
+    5.0e-5                                  // 5e-5 m^3
+}
+
+fn default_open_angle() -> f64 {
+    PI / 3.0                                // 60 degrees
+}
+
+fn default_disc_speed() -> Vec<f64> {
+    vec![ 0.0 ]
+}
+
 fn default_check_valve_data() -> Vec<(f64, f64)> {
     vec![ 
         ( 0., 0.0 ),
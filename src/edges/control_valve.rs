@@ -0,0 +1,275 @@
+use std::f64::consts::PI;
+use crate::utility;
+
+// The quantity a control valve holds at its setpoint
+#[derive(Clone, Copy, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub enum ControlMode {
+    #[default]
+    PressureReducing,       // Pressure at the to node [Pa]
+    PressureSustaining,     // Pressure at the from node [Pa]
+    FlowControl,            // Mass flow rate through the valve [kg/s]
+}
+
+impl ControlMode {
+    pub fn text(&self) -> String {
+        match self {
+            ControlMode::PressureReducing => "Pressure Reducing Valve".to_string(),
+            ControlMode::PressureSustaining => "Pressure Sustaining Valve".to_string(),
+            ControlMode::FlowControl => "Flow Control Valve".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub enum ControlStatus {
+    #[default]
+    Active,                 // Throttling to hold the setpoint
+    Open,                   // Fully open and unable to reach the setpoint
+    Closed,                 // Shut against reverse flow
+}
+
+// How the opening follows the setpoint in transient runs
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum Actuator {
+    // Moves towards the opening that would hold the setpoint at the current flow [s]
+    FirstOrder { time_constant: f64 },
+    // Acts on the error relative to the setpoint about the steady opening (0 = no integral or
+    // derivative action) [s]
+    Pid { gain: f64, integral_time: f64, derivative_time: f64 },
+}
+
+impl Default for Actuator {
+    fn default() -> Self {
+        Actuator::FirstOrder { time_constant: 1.0 }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "persistence", serde(default))]
+pub struct ControlValve {
+    pub from: usize,                // From node id
+    pub to: usize,                  // To node id
+    pub mass_flow: Vec<f64>,
+    pub diameter: f64,
+    pub thickness: f64,
+    pub youngs_modulus: f64,
+    pub invk: Vec<(f64, f64)>,      // (% open, k^-1)
+    pub open_percent: Vec<f64>,
+    pub mode: ControlMode,
+    pub setpoint: f64,              // Pressure [Pa] or mass flow rate [kg/s]
+    pub status: ControlStatus,      // Status at the last solution
+    pub actuator: Actuator,
+    pub width: f32,
+    pub selected: bool,
+}
+
+impl ControlValve {
+    pub fn new( from: usize, to: usize, mode: ControlMode, setpoint: f64 ) -> Self {
+        ControlValve {
+            from,
+            to,
+            mass_flow: vec![ 0.0 ],
+            diameter: 52.5e-3,
+            thickness: 0.005, // 5mm pipe
+            youngs_modulus: 2.0e11, // Steel pipe
+            invk: default_control_valve_data(),
+            open_percent: vec![ 1.0 ],
+            mode,
+            setpoint,
+            status: ControlStatus::Active,
+            actuator: Actuator::default(),
+            width: 15.0,
+            selected: false,
+        }
+    }
+
+    pub fn invk_values(&mut self) -> &mut Vec<(f64, f64)> {
+        &mut self.invk
+    }
+
+    pub fn invk(&self, step: usize ) -> f64 {
+        self.interpolate_invk( self.open_percent[step] )
+    }
+
+    pub fn interpolate_invk(&self, open_percent: f64 ) -> f64 {
+        let ( open, invk ) = utility::split_into_two_vectors( &self.invk );
+        utility::interpolate( open_percent, &open, &invk )
+    }
+
+    // Opening with an inverse loss coefficient from the valve data (0 to 1)
+    pub fn opening_for(&self, invk: f64 ) -> f64 {
+        let last = self.invk.len() - 1;
+        if invk <= self.invk[0].1 { return self.invk[0].0; }
+        if invk >= self.invk[last].1 { return self.invk[last].0; }
+        let i = self.invk.iter().position( |point| point.1 >= invk ).unwrap();
+        let ( ( x_1, y_1 ), ( x_2, y_2 ) ) = ( self.invk[i - 1], self.invk[i] );
+        x_1 + ( x_2 - x_1 ) * ( invk - y_1 ) / ( y_2 - y_1 )
+    }
+
+    pub fn area(&self) -> f64 {
+        PI * self.diameter * self.diameter / 4.0
+    }
+
+    pub fn resistance(&self, q: f64, dh: f64, _nu: f64, g: f64, step: usize ) -> f64 {
+        - q * q.abs() / ( 2. * self.area() ) + self.invk( step ) * g * self.area() * dh
+    }
+
+    // Loss coefficient at an opening (a closed valve is given a large finite loss)
+    pub fn loss_coefficient(&self, open: f64 ) -> f64 {
+        1.0 / self.interpolate_invk( open ).max( 1.0e-6 )
+    }
+
+    // Loss coefficient when fully open
+    pub fn open_loss(&self) -> f64 {
+        1.0 / self.invk.last().unwrap().1
+    }
+
+    // Resistance with the loss coefficient k in place of the opening. The steady solver takes k
+    // as the unknown as the resistance is linear in it.
+    pub fn resistance_k(&self, q: f64, dh: f64, g: f64, k: f64 ) -> f64 {
+        - k * q * q.abs() / ( 2. * self.area() ) + g * self.area() * dh
+    }
+
+    // Derivatives of resistance_k with respect to the flow rate, the head difference and k
+    pub fn resistance_k_derivatives(&self, q: f64, g: f64, k: f64 ) -> (f64, f64, f64) {
+        ( - k * q.abs() / self.area(), g * self.area(), - q * q.abs() / ( 2. * self.area() ) )
+    }
+
+    pub fn b_coefficient(&self, step: usize ) -> f64 {
+        self.invk( step )
+    }
+
+    pub fn k_laminar(&self, nu: f64 ) -> f64 {
+        let f = 0.1; // assumed friction factor for initial guess
+        let equivalent_length = ( 1.0 / self.invk( 0 ) ) * self.diameter / f;
+        PI * 9.806 * self.diameter.powi( 4 ) / ( 128.0 * equivalent_length * nu )
+    }
+
+    pub fn darcy_approx(&self, head_loss: f64, g: f64 ) -> f64 {
+        let a = self.area();
+        let result = 2.0 * g * a * a / ( ( 1.0 / self.invk( 0 ) ) * head_loss.abs() );
+        result.sqrt()
+    }
+
+    // Setpoint in the units of the steady solver: the head at the controlled node [m] (given its
+    // elevation) or the volume flow rate [m^3/s]
+    pub fn target(&self, elevation: f64, rho: f64, g: f64 ) -> f64 {
+        match self.mode {
+            ControlMode::FlowControl => self.setpoint / rho,
+            _ => elevation + self.setpoint / ( rho * g ),
+        }
+    }
+
+    // Status after a steady solution with the loss coefficient, flow rate, end heads ( from, to )
+    // and target
+    pub fn next_status(&self, status: ControlStatus, k: f64, q: f64, h: (f64, f64), target: f64 ) -> ControlStatus {
+        match status {
+            ControlStatus::Active if q < 0.0 => ControlStatus::Closed,
+            // Less loss than fully open (or adding head to the flow)
+            ControlStatus::Active if k < self.open_loss() => ControlStatus::Open,
+            ControlStatus::Open if q < 0.0 => ControlStatus::Closed,
+            ControlStatus::Open => {
+                let throttle = match self.mode {
+                    ControlMode::PressureReducing => h.1 > target,
+                    ControlMode::PressureSustaining => h.0 < target,
+                    ControlMode::FlowControl => q > target,
+                };
+                if throttle { ControlStatus::Active } else { ControlStatus::Open }
+            },
+            ControlStatus::Closed => {
+                let forward = h.0 > h.1 && match self.mode {
+                    ControlMode::PressureReducing => h.1 < target,
+                    ControlMode::PressureSustaining => h.0 > target,
+                    ControlMode::FlowControl => true,
+                };
+                if forward { ControlStatus::Active } else { ControlStatus::Closed }
+            },
+            _ => status,
+        }
+    }
+
+    // The solver replaces this with the actuator response
+    pub fn add_transient_value( &mut self ) {
+        self.open_percent.push( *self.open_percent.last().unwrap() );
+    }
+
+    // Opening that would hold the setpoint with the mass flow rate and pressures ( from, to ) at
+    // the start of a step. Without forward flow the valve opens if the setpoint calls for flow.
+    pub fn required_opening(&self, mass_flow: f64, p: (f64, f64), rho: f64 ) -> f64 {
+        let ( loss, open ) = match self.mode {
+            ControlMode::PressureReducing => ( p.0 - self.setpoint, p.1 < self.setpoint ),
+            ControlMode::PressureSustaining => ( self.setpoint - p.1, p.0 > self.setpoint ),
+            ControlMode::FlowControl => ( p.0 - p.1, true ),
+        };
+        if mass_flow <= 0.0 {
+            return if open && p.0 > p.1 { 1.0 } else { 0.0 };
+        }
+        if loss <= 0.0 { return 1.0; }
+        // The flow rate the valve should pass with the loss it should take
+        let flow = match self.mode {
+            ControlMode::FlowControl => self.setpoint,
+            _ => mass_flow,
+        };
+        let a = self.area();
+        self.opening_for( flow * flow / ( 2.0 * rho * a * a * loss ) )
+    }
+
+    // Move the opening over a step from the state at the start of the step (the solver keeps the
+    // integral and last error of a PID controller for the run)
+    pub fn actuate(&mut self, mass_flow: f64, p: (f64, f64), dt: f64, step: usize, rho: f64, pid: &mut (f64, f64) ) {
+        let open = self.open_percent[ step ];
+        let next = match self.actuator {
+            Actuator::FirstOrder { time_constant } => {
+                let required = self.required_opening( mass_flow, p, rho );
+                if time_constant > 0.0 {
+                    open + ( required - open ) * ( 1.0 - ( - dt / time_constant ).exp() )
+                } else {
+                    required
+                }
+            },
+            Actuator::Pid { gain, integral_time, derivative_time } => {
+                let error = match self.mode {
+                    ControlMode::PressureReducing => ( self.setpoint - p.1 ) / self.setpoint,
+                    ControlMode::PressureSustaining => ( p.0 - self.setpoint ) / self.setpoint,
+                    ControlMode::FlowControl => ( self.setpoint - mass_flow ) / self.setpoint,
+                };
+                let ( integral, last ) = *pid;
+                let derivative = if step == 0 { 0.0 } else { ( error - last ) / dt };
+                let output = |integral: f64| {
+                    let integral = if integral_time > 0.0 { integral / integral_time } else { 0.0 };
+                    self.open_percent[0] + gain * ( error + integral + derivative_time * derivative )
+                };
+                // The integral is held while the valve is at a limit
+                let mut next = output( integral + error * dt );
+                if ( 0.0..=1.0 ).contains( &next ) {
+                    pid.0 = integral + error * dt;
+                } else {
+                    next = output( integral );
+                }
+                pid.1 = error;
+                next
+            },
+        };
+        let next = next.clamp( 0.0, 1.0 );
+        self.status = if next <= 0.0 {
+            ControlStatus::Closed
+        } else if next >= 1.0 {
+            ControlStatus::Open
+        } else {
+            ControlStatus::Active
+        };
+        self.open_percent[ step + 1 ] = next;
+    }
+}
+
+fn default_control_valve_data() -> Vec<(f64, f64)> {
+    vec![
+        (0.0, 0.0 ),
+        (0.2, 1. / 200. ),
+        (0.4, 1. / 30. ),
+        (0.6, 1. / 6. ),
+        (0.8, 1. / 1.5 ),
+        (1.0, 1. / 0.5 ),
+    ]
+}
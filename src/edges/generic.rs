@@ -1,11 +1,10 @@
 // A generic resistance component of the form dH = A + B * Q^n + C * Q^m
-use crate::node::Node;
 
 #[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub struct Generic {
-    pub from: Node,
-    pub to: Node,
+    pub from: usize,                    // From node id
+    pub to: usize,                      // To node id
     pub mass_flow: Vec<f64>,            // [kg/s]
     pub coefficients: (f64, f64, f64),  // Coefficients ( A, B, C )   
     pub exponents: (f64, f64),          // Exponents ( n, m )
@@ -14,7 +13,7 @@ pub struct Generic {
 }
 
 impl Generic {
-    pub fn new( from: Node, to: Node ) -> Self {
+    pub fn new( from: usize, to: usize ) -> Self {
         Generic { 
             from, 
             to, 
@@ -26,7 +25,7 @@ impl Generic {
         }
     }
 
-    pub fn new_params(from: Node, to: Node, coefficients: (f64, f64, f64),
+    pub fn new_params(from: usize, to: usize, coefficients: (f64, f64, f64),
         exponents: (f64, f64) ) -> Self 
     {
         Generic { 
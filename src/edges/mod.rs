@@ -8,4 +8,5 @@ pub mod safety_valve;
 pub mod relief_valve;
 pub mod bursting_disk;
 pub mod generic;
-pub mod open_pipe;
\ No newline at end of file
+pub mod open_pipe;
+pub mod control_valve;
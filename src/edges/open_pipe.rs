@@ -1,11 +1,10 @@
 use std::f64::consts::PI;
-use crate::node::Node;
 
 #[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub struct OpenPipe {
-    pub from: Node,
-    pub to: Node,
+    pub from: usize,                // From node id
+    pub to: usize,                  // To node id
     pub mass_flow: Vec<f64>,        // [kg/s]
     pub diameter: f64,              // From diameter [m]
     pub k: f64,                     // Loss coefficient 
@@ -14,7 +13,7 @@ pub struct OpenPipe {
 }
 
 impl OpenPipe {
-    pub fn new(from: Node, to: Node ) -> Self {
+    pub fn new(from: usize, to: usize ) -> Self {
         OpenPipe { 
             from, 
             to, 
@@ -26,7 +25,7 @@ impl OpenPipe {
         }
     }
 
-    pub fn new_params(from: Node, to: Node, diameter: f64, k: f64 ) -> Self 
+    pub fn new_params(from: usize, to: usize, diameter: f64, k: f64 ) -> Self 
     {
         OpenPipe { 
             from, 
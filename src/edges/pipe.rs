@@ -1,25 +1,30 @@
 use std::f64::consts::PI;
-use crate::node::Node;
 use crate::fluid::Fluid;
-use crate::utility;
+use crate::friction::FrictionModel;
 
 #[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub struct Pipe {
-    pub from: Node,
-    pub to: Node,
+    pub from: usize,                // From node id
+    pub to: usize,                  // To node id
     pub mass_flow: Vec<f64>,        // [kg/s]
     pub length: f64,                // [m]
     pub diameter: f64,              // [m]
     pub roughness: f64,             // [m]
     pub thickness: f64,             // [m]
     pub youngs_modulus: f64,        // [Pa]
+    #[serde(default)]
+    pub segments: usize,            // Hidden reaches in transient runs (0 = one, or automatic if the solver splits pipes)
+    #[serde(default)]
+    pub pressure_rating: f64,       // Maximum allowable gauge pressure (0 = not rated) [Pa]
+    #[serde(default)]
+    pub friction: FrictionModel,    // Default follows the solver
     pub width: f32,
     pub selected: bool,
 }
 
 impl Pipe {
-    pub fn new(from: Node, to: Node ) -> Self {
+    pub fn new(from: usize, to: usize ) -> Self {
         Pipe { 
             from, 
             to, 
@@ -29,12 +34,15 @@ impl Pipe {
             roughness: 0.05e-3,
             thickness: 5.0e-3, // 5mm pipe
             youngs_modulus: 2.0e11, // Steel pipe
+            segments: 0,
+            pressure_rating: 0.0,
+            friction: FrictionModel::Default,
             width: 5.0, 
             selected: false,
         }
     }
 
-    pub fn new_params(from: Node, to: Node, length: f64, diameter: f64, roughness: f64, 
+    pub fn new_params(from: usize, to: usize, length: f64, diameter: f64, roughness: f64, 
         thickness: f64, youngs_modulus: f64 ) -> Self 
     {
         Pipe { 
@@ -46,6 +54,9 @@ impl Pipe {
             roughness,
             thickness,
             youngs_modulus,
+            segments: 0,
+            pressure_rating: 0.0,
+            friction: FrictionModel::Default,
             width: 5.0, 
             selected: false,
         }
@@ -73,22 +84,36 @@ impl Pipe {
         flow_rate * self.diameter / ( self.area() * nu )
     }
 
-    pub fn friction_factor(&self, flow_rate: f64, nu: f64 ) -> f64 {
-        let relative: f64 = self.roughness / self.diameter;
-        let re = self.reynolds( flow_rate, nu );
-        utility::friction_factor( relative, re )
+    // Friction factor with the model of the pipe or the default model of the solver
+    pub fn friction_factor(&self, flow_rate: f64, nu: f64, default: FrictionModel ) -> f64 {
+        self.friction( flow_rate, nu, default ).0
     }
 
-    pub fn resistance(&self, q: f64, dh: f64, nu: f64, g: f64 ) -> f64 {
+    // Friction factor and its derivative with respect to the flow rate magnitude with the
+    // default model of the solver
+    pub fn friction(&self, flow_rate: f64, nu: f64, default: FrictionModel ) -> (f64, f64) {
+        self.friction.or( default ).friction( flow_rate, self.diameter, self.roughness, nu )
+    }
+
+    pub fn resistance(&self, q: f64, dh: f64, nu: f64, g: f64, default: FrictionModel ) -> f64 {
         if q == 0.0 {
             0.0
         } else {
-            let friction = self.friction_factor( q.abs(), nu );
+            let ( friction, _ ) = self.friction( q, nu, default );
             let r = - friction * q * q.abs() / ( 2. * self.diameter * self.area() );
             r + dh * ( g * self.area() / self.length )
         }
     }
 
+    // Derivatives of the resistance with respect to the flow rate and the head difference
+    pub fn resistance_derivatives(&self, q: f64, nu: f64, g: f64, default: FrictionModel ) -> (f64, f64) {
+        // The laminar friction factor is singular at zero flow but its product with q|q| is not
+        let q_abs = q.abs().max( f64::EPSILON );
+        let ( f, df ) = self.friction( q_abs, nu, default );
+        let drdq = - ( 2.0 * f * q_abs + df * q_abs * q_abs ) / ( 2. * self.diameter * self.area() );
+        ( drdq, g * self.area() / self.length )
+    }
+
     pub fn k_laminar(&self, nu: f64 ) -> f64 {
         PI * 9.806 * self.diameter.powi( 4 ) / ( 128.0 * self.length * nu )
     }
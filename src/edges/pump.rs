@@ -1,12 +1,12 @@
 use std::f64::consts::PI;
-use crate::node::Node;
-use crate::events::TransientEvent;
+use crate::events::{ self, TransientEvent };
+use crate::series::TimeSeries;
 
 #[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub struct Pump {
-    pub from: Node,
-    pub to: Node,
+    pub from: usize,                    // From node id
+    pub to: usize,                      // To node id
     pub mass_flow: Vec<f64>,
     pub head_data: Vec<(f64, f64)>,     // ( theta [rad], F_h )
     pub torque_data: Vec<(f64, f64)>,   // ( theta [rad], F_tau )
@@ -17,13 +17,23 @@ pub struct Pump {
     pub speed:  Vec<f64>,               // Speed [rpm] at each time step
     pub thickness: f64,                 // [m]
     pub youngs_modulus: f64,            // [Pa]
+    #[serde(default = "default_inertia")]
+    pub inertia: f64,                   // Impeller and entrained fluid moment of inertia [kg m^2]
+    #[serde(default = "default_motor_inertia")]
+    pub motor_inertia: f64,             // Motor moment of inertia [kg m^2]
+    #[serde(default)]
+    pub flywheel: f64,                  // Flywheel moment of inertia (0 = no flywheel) [kg m^2]
+    #[serde(default = "default_rated_torque")]
+    pub rated_torque: f64,              // Shaft torque at the rated conditions [N m]
     pub events: Vec<TransientEvent>,
+    #[serde(default)]
+    pub series: Option<TimeSeries>,     // Speed [rpm] against time
     pub width: f32,
     pub selected: bool,
 }
 
 impl Pump {
-    pub fn new( from: Node, to: Node ) -> Self {
+    pub fn new( from: usize, to: usize ) -> Self {
         Pump {
             from,
             to,
@@ -37,7 +47,12 @@ impl Pump {
             speed: vec![ 11300.0 ],                 // 11300 rpm
             thickness: 5.0e-3,                      // 5mm
             youngs_modulus: 2.0e11,                 // Steel
+            inertia: default_inertia(),
+            motor_inertia: default_motor_inertia(),
+            flywheel: 0.0,
+            rated_torque: default_rated_torque(),
             events: vec![],
+            series: None,
             width: 15.0, 
             selected: false,
             //TODO do we need a max/min speed?
@@ -56,33 +71,84 @@ impl Pump {
         theta
     }
 
-    //TODO interpolation utility function -> split head and torque data into separate vectors
     pub fn f_h(&self, theta: f64 ) -> f64 {
-        let mut xlower = self.head_data[0].0;
-        let mut xupper = self.head_data[1].0;
-        let mut ylower = self.head_data[0].1;
-        let mut yupper = self.head_data[1].1;
-        
-        for (index, value) in self.head_data.iter().enumerate() {
-            if value.0 < theta {
-                xlower = self.head_data[index].0;
-                ylower = self.head_data[index].1;
-            } else {
-                xupper = self.head_data[index].0;
-                yupper = self.head_data[index].1;
-                break;
-            }
+        interpolate( &self.head_data, theta )
+    }
+
+    pub fn f_tau(&self, theta: f64 ) -> f64 {
+        interpolate( &self.torque_data, theta )
+    }
+
+    // Moment of inertia of everything turning with the shaft [kg m^2]
+    pub fn total_inertia(&self) -> f64 {
+        self.inertia + self.motor_inertia + self.flywheel
+    }
+
+    // Hydraulic torque resisting the rotation at speed n [rpm] and flow rate q [m^3/s]
+    pub fn hydraulic_torque(&self, n: f64, q: f64 ) -> f64 {
+        let alpha = n / self.n_rated;
+        let qj = q / self.q_rated;
+        let theta = Pump::theta( alpha, qj );
+        self.rated_torque * ( alpha * alpha + qj * qj ) * self.f_tau( theta )
+    }
+
+    // True if the last event to have started (including those spawned by rules) is a power failure
+    pub fn power_failed(&self, time: f64, spawned: &[TransientEvent] ) -> bool {
+        let started = events::timeline( &self.events, spawned ).into_iter().rfind( |( event, _ )| time >= event.time() );
+        matches!( started, Some( ( TransientEvent::PowerFailure( _ ), _ ) ) )
+    }
+
+    // Speed at the next step after a power failure from I dw/dt = - T (trapezoidal rule)
+    pub fn run_down(&mut self, q: (f64, f64), dt: f64, step: usize ) {
+        let n_old = self.speed[ step ];
+        let torque_old = self.hydraulic_torque( n_old, q.0 );
+        let rate = dt * 30.0 / ( PI * self.total_inertia() );
+        let mut n = self.speed[ step + 1 ];
+        for _ in 0..50 {
+            let next = n_old - 0.5 * rate * ( torque_old + self.hydraulic_torque( n, q.1 ) );
+            let converged = ( next - n ).abs() < 1.0e-10 * self.n_rated;
+            n = next;
+            if converged { break; }
         }
+        self.speed[ step + 1 ] = n;
+    }
 
-        if xlower == xupper {
-            ylower
-        } else {
-            let dy = ylower - yupper;
-            let dx = xlower - xupper;
-            let m = dy / dx;
-            let y = yupper + m * (theta - xupper);
-            y
+    // Replace the normal pump zone of the head data with a head curve ( Q [m^3/s], H [m] ) at the
+    // rated speed. The rated point is the point of the curve with the greatest hydraulic power.
+    pub fn set_head_curve(&mut self, curve: &[(f64, f64)] ) {
+        let Some( rated ) = curve.iter().copied()
+            .max_by( |a, b| ( a.0 * a.1 ).total_cmp( &( b.0 * b.1 ) ) ) else { return };
+        if rated.0 <= 0.0 || rated.1 <= 0.0 { return; }
+        ( self.q_rated, self.h_rated ) = rated;
+        let points: Vec<(f64, f64)> = curve.iter().map( |( q, h )| {
+            let qj = q / self.q_rated;
+            ( Pump::theta( 1.0, qj ), h / ( self.h_rated * ( 1.0 + qj * qj ) ) )
+        }).collect();
+        let lower = points.iter().map( |point| point.0 ).fold( f64::INFINITY, f64::min );
+        let upper = points.iter().map( |point| point.0 ).fold( f64::NEG_INFINITY, f64::max );
+        let mut data: Vec<(f64, f64)> = self.head_data.iter().copied()
+            .filter( |( theta, _ )| *theta < lower || *theta > upper ).chain( points ).collect();
+        data.sort_by( |a, b| a.0.total_cmp( &b.0 ) );
+        self.head_data = data;
+    }
+
+    // Head curve ( Q [m^3/s], H [m] ) at the rated speed from zero flow to zero head (or to three 
+    // times the rated flow), stopping early if the head starts to rise
+    pub fn head_curve(&self, points: usize ) -> Vec<(f64, f64)> {
+        let mut curve: Vec<(f64, f64)> = Vec::with_capacity( points );
+        for i in 0..points {
+            let qj = 3.0 * i as f64 / ( points - 1 ) as f64;
+            let ( q, head ) = ( qj * self.q_rated, self.h_rated * ( 1.0 + qj * qj ) * self.f_h( Pump::theta( 1.0, qj ) ) );
+            match curve.last() {
+                Some( last ) if head >= last.1 => break,
+                Some( last ) if head <= 0.0 => {
+                    curve.push( ( last.0 + ( q - last.0 ) * last.1 / ( last.1 - head ), 0.0 ) );
+                    break;
+                },
+                _ => curve.push( ( q, head ) ),
+            }
         }
+        curve
     }
 
     pub fn resistance(&self, q: f64, dh: f64, _nu: f64, g: f64, step: usize ) -> f64 {
@@ -123,13 +189,38 @@ impl Pump {
         //-0.001
     }
 
-    pub fn add_transient_value( &mut self, time: f64 ) {
-        let steady = self.speed[0];
-        for event in self.events.iter() {
-            self.speed.push( event.pump_speed( time, steady ) );
-        }
-        if self.events.len() == 0 {
+    // Speed at the next time step with the restart the solver keeps for the run
+    pub fn add_transient_value( &mut self, time: f64, spawned: &[TransientEvent], restart: &mut Option<(usize, f64)> ) {
+        // The solver replaces this with the run-down speed
+        if self.power_failed( time, spawned ) {
             self.speed.push( *self.speed.last().unwrap() );
+            return;
+        }
+        // Events apply in time order from the speed given by the series (or the steady speed)
+        let mut speed = match &self.series {
+            Some( series ) => series.value( time ),
+            None => self.speed[0],
+        };
+        let ordered = events::timeline( &self.events, spawned );
+        for (k, &( event, next )) in ordered.iter().enumerate() {
+            if time < event.time() { break; }
+            // An event after a power failure starts from the run-down speed when it began
+            if k > 0 && matches!( ordered[k - 1].0, TransientEvent::PowerFailure( _ ) ) {
+                speed = match *restart {
+                    Some( ( event, run_down ) ) if event == k => run_down,
+                    _ => *self.speed.last().unwrap(),
+                };
+                *restart = Some( ( k, speed ) );
+            }
+            speed = event.pump_speed( time.min( next ), speed );
+        }
+        self.speed.push( speed );
+    }
+
+    // The series gives the steady speed at t = 0
+    pub fn start_series( &mut self ) {
+        if let Some( series ) = &self.series {
+            self.speed[0] = series.value( 0.0 );
         }
     }
 
@@ -157,11 +248,50 @@ impl Pump {
             let dy = ylower - yupper;
             let dx = xlower - xupper;
             let m = dy / dx;
-            let y = yupper + m * (h_loss - xupper);
-            y
+            yupper + m * (h_loss - xupper)
+        }
+    }
+
+}
+
+fn interpolate( data: &[(f64, f64)], theta: f64 ) -> f64 {
+    let mut xlower = data[0].0;
+    let mut xupper = data[1].0;
+    let mut ylower = data[0].1;
+    let mut yupper = data[1].1;
+    
+    for value in data.iter() {
+        if value.0 < theta {
+            xlower = value.0;
+            ylower = value.1;
+        } else {
+            xupper = value.0;
+            yupper = value.1;
+            break;
         }
     }
 
+    if xlower == xupper {
+        ylower
+    } else {
+        let dy = ylower - yupper;
+        let dx = xlower - xupper;
+        let m = dy / dx;
+        yupper + m * (theta - xupper)
+    }
+}
+
+fn default_inertia() -> f64 {
+    0.5                                     // 0.5 kg m^2
+}
+
+fn default_motor_inertia() -> f64 {
+    8.0                                     // 8 kg m^2
+}
+
+// rho g Q_r H_r / ( eta w_r ) for the default pump with an efficiency of 0.8
+fn default_rated_torque() -> f64 {
+    570.0                                   // 570 N m
 }
 
 // N_s = 0.46 from Chaudry p. 523-24
@@ -1,12 +1,12 @@
 use std::f64::consts::PI;
-use crate::node::Node;
+use crate::edge::EdgeContext;
 use crate::utility;
 
 #[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub struct ReliefValve {
-    pub from: Node,
-    pub to: Node,
+    pub from: usize,                // From node id
+    pub to: usize,                  // To node id
     pub mass_flow: Vec<f64>,
     pub diameter: f64,
     pub thickness: f64,
@@ -19,7 +19,7 @@ pub struct ReliefValve {
 }
 
 impl ReliefValve {
-    pub fn new(from: Node, to: Node, dp_open: f64, dp_full: f64 ) -> Self {
+    pub fn new(from: usize, to: usize, dp_open: f64, dp_full: f64 ) -> Self {
         ReliefValve { 
             from, 
             to, 
@@ -77,11 +77,9 @@ impl ReliefValve {
         result.sqrt()
     }
 
-    pub fn add_transient_value( &mut self, _time: f64 ) {
+    pub fn add_transient_value( &mut self, _time: f64, context: EdgeContext ) {
         let step = self.open_percent.len() - 1;
-        let p_from = self.from.pressure()[ step ];
-        let p_to = self.to.pressure()[ step ];
-        let dp = p_from - p_to;
+        let dp = context.dp( step );
 
         let open_percent = self.open_percent_from_dp( dp );
         self.open_percent.push( open_percent );
@@ -1,11 +1,20 @@
 use std::f64::consts::PI;
-use crate::node::Node;
+use crate::edge::EdgeContext;
+use crate::utility;
+
+const TOLERANCE: f64 = 1.0e-9;          // Lift within which the valve reaches its target
+
+// A spring loaded safety valve lifts when the pressure difference rises above set_dp and stays 
+// open until it falls to the reseat pressure difference ( set_dp - blowdown ). While open the lift
+// rises from zero at reseat to full lift at set_dp + overpressure, so a valve with a blowdown pops
+// open part way at set_dp. The lift moves towards this at a rate limited by the opening and 
+// closing times. The defaults give a valve that snaps fully open above set_dp and shuts below it.
 
 #[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub struct SafetyValve {
-    pub from: Node,
-    pub to: Node,
+    pub from: usize,                // From node id
+    pub to: usize,                  // To node id
     pub mass_flow: Vec<f64>,
     pub diameter: f64,
     pub thickness: f64,
@@ -15,10 +24,18 @@ pub struct SafetyValve {
     pub open_percent: Vec<f64>,
     pub width: f32,
     pub selected: bool,
+    #[serde(default)]
+    pub overpressure: f64,          // Rise above set_dp to full lift [Pa]
+    #[serde(default)]
+    pub blowdown: f64,              // Fall below set_dp at which the valve reseats [Pa]
+    #[serde(default)]
+    pub opening_time: f64,          // Time to lift fully from closed (0 = instant) [s]
+    #[serde(default)]
+    pub closing_time: f64,          // Time to close from full lift (0 = instant) [s]
 }
 
 impl SafetyValve {
-    pub fn new(from: Node, to: Node, set_dp: f64 ) -> Self {
+    pub fn new(from: usize, to: usize, set_dp: f64 ) -> Self {
         SafetyValve { 
             from, 
             to, 
@@ -31,6 +48,10 @@ impl SafetyValve {
             open_percent: vec![ 0.0 ], // closed by default
             width: 15.0, 
             selected: false,
+            overpressure: 0.0,
+            blowdown: 0.0,
+            opening_time: 0.0,
+            closing_time: 0.0,
         }
     }
 
@@ -39,11 +60,8 @@ impl SafetyValve {
     }
 
     pub fn invk(&self, step: usize ) -> f64 {
-        if self.open_percent[step] < 1.0 { // If closed 
-            0.0
-        } else {
-            self.invk[1].1
-        }
+        let ( open, invk ) = utility::split_into_two_vectors( &self.invk );
+        utility::interpolate( self.open_percent[step], &open, &invk )
     }
 
     pub fn area(&self) -> f64 {
@@ -70,16 +88,32 @@ impl SafetyValve {
         result.sqrt()
     }
 
-    pub fn add_transient_value( &mut self, _time: f64 ) {
+    // Lift at the end of a time step of dt (the solver keeps whether the valve has lifted for the run)
+    pub fn add_transient_value( &mut self, dt: f64, context: EdgeContext, lifted: &mut bool ) {
         let step = self.open_percent.len() - 1;
-        let p_from = self.from.pressure()[ step ];
-        let p_to = self.to.pressure()[ step ];
-        let dp = p_from - p_to;
-        if dp > self.set_dp {
-            self.open_percent.push( 1.0 )
+        if step == 0 { *lifted = self.open_percent[0] > 0.0; }
+        let lift = self.open_percent[step];
+        let target = self.target_lift( context.dp( step ), lifted );
+        let travel = |stroke: f64| if stroke > 0.0 { dt / stroke } else { f64::INFINITY };
+        let next = if target > lift {
+            lift + ( target - lift ).min( travel( self.opening_time ) )
         } else {
-            self.open_percent.push( 0.0 );
-        }
+            lift - ( lift - target ).min( travel( self.closing_time ) )
+        };
+        let next = if ( next - target ).abs() < TOLERANCE { target } else { next };
+        self.open_percent.push( next );
+    }
+
+    // Lift the valve moves towards with a pressure difference, lifting above set_dp and 
+    // reseating at set_dp - blowdown
+    pub fn target_lift( &self, dp: f64, lifted: &mut bool ) -> f64 {
+        let reseat = self.set_dp - self.blowdown;
+        if *lifted && dp <= reseat { *lifted = false; }
+        if !*lifted && dp > self.set_dp { *lifted = true; }
+        if !*lifted { return 0.0; }
+        let full = self.set_dp + self.overpressure;
+        if full <= reseat { return 1.0; }
+        ( ( dp - reseat ) / ( full - reseat ) ).clamp( 0.0, 1.0 )
     }
 
 }
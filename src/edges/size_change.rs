@@ -1,11 +1,10 @@
 use std::f64::consts::PI;
-use crate::node::Node;
 
 #[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub struct SizeChange {
-    pub from: Node,
-    pub to: Node,
+    pub from: usize,                // From node id
+    pub to: usize,                  // To node id
     pub mass_flow: Vec<f64>,        // [kg/s]
     pub diameter: f64,              // From diameter [m]
     pub beta: f64,                  // Ratio to diameter / from diameter (d_k / d_i) 
@@ -14,7 +13,7 @@ pub struct SizeChange {
 }
 
 impl SizeChange {
-    pub fn new(from: Node, to: Node ) -> Self {
+    pub fn new(from: usize, to: usize ) -> Self {
         SizeChange { 
             from, 
             to, 
@@ -26,7 +25,7 @@ impl SizeChange {
         }
     }
 
-    pub fn new_params(from: Node, to: Node, diameter: f64, beta: f64 ) -> Self 
+    pub fn new_params(from: usize, to: usize, diameter: f64, beta: f64 ) -> Self 
     {
         SizeChange { 
             from, 
@@ -56,6 +55,15 @@ impl SizeChange {
         ( 1. - beta.powi(2) ).powi(2)
     }
 
+    // Loss coefficient for flow from the from node to the to node (based on the from diameter)
+    pub fn k(&self) -> f64 {
+        if self.beta < 1.0 {
+            Self::k_contraction( self.beta )
+        } else {
+            Self::k_expansion( 1.0 / self.beta )
+        }
+    }
+
     pub fn resistance(&self, q: f64, dh: f64, _nu: f64, g: f64 ) -> f64 {
         let mut area = self.area();
         let k;
@@ -1,13 +1,17 @@
 use std::f64::consts::PI;
-use crate::node::Node;
-use crate::events::TransientEvent;
-use crate::utility;
+use crate::events::{ self, TransientEvent };
+use crate::series::TimeSeries;
+use crate::utility::{ self, BAR, PSI, GALLON };
 
-#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+const WATER: f64 = 1000.0;              // Density of the water that defines Kv and Cv [kg/m^3]
+const RANGEABILITY: f64 = 50.0;         // Ratio of the largest to the smallest equal percentage flow coefficient
+
+#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub enum ValveType {
     AngleGlobe,
     Ball,
+    #[default]
     Butterfly,
     Diaphragm,
     Gate,
@@ -18,12 +22,6 @@ pub enum ValveType {
     Slide,
 }
 
-impl Default for ValveType {
-    fn default() -> Self {
-        ValveType::Butterfly
-    }
-}
-
 impl ValveType {
     pub fn text(&self) -> String {
         match self {
@@ -39,13 +37,105 @@ impl ValveType {
             ValveType::Slide => "Slide".to_string(),
         }
     }
+
+    // Inherent characteristic as ( opening, flow coefficient relative to fully open )
+    pub fn characteristic(&self) -> Vec<(f64, f64)> {
+        let relative: [f64; 11] = match self {
+            ValveType::Butterfly => {
+                // The default valve data is a butterfly valve
+                let data = default_valve_data();
+                let open = data.last().unwrap().1;
+                return data.iter().map( |( x, invk )| ( *x, ( invk / open ).sqrt() ) ).collect();
+            },
+            ValveType::Gate | ValveType::Slide => 
+                [ 0.0, 0.03, 0.07, 0.12, 0.19, 0.27, 0.38, 0.52, 0.70, 0.87, 1.0 ],
+            ValveType::Ball => [ 0.0, 0.02, 0.05, 0.09, 0.15, 0.23, 0.33, 0.46, 0.62, 0.80, 1.0 ],
+            ValveType::Plug => [ 0.0, 0.03, 0.07, 0.13, 0.20, 0.29, 0.40, 0.53, 0.68, 0.84, 1.0 ],
+            ValveType::Globe | ValveType::AngleGlobe => 
+                [ 0.0, 0.08, 0.17, 0.27, 0.37, 0.47, 0.57, 0.67, 0.78, 0.89, 1.0 ],
+            ValveType::Needle => [ 0.0, 0.01, 0.04, 0.09, 0.16, 0.25, 0.36, 0.49, 0.64, 0.81, 1.0 ],
+            // Quick opening
+            ValveType::Diaphragm | ValveType::Pinch => 
+                [ 0.0, 0.20, 0.38, 0.53, 0.66, 0.76, 0.84, 0.90, 0.95, 0.98, 1.0 ],
+        };
+        relative.iter().enumerate().map( |(i, c)| ( i as f64 / 10.0, *c ) ).collect()
+    }
+}
+
+// How the flow coefficient of a valve varies with its opening
+#[derive(Clone, Copy, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub enum Trim {
+    #[default]
+    Inherent,                           // The characteristic of the valve type
+    Linear,                             // Proportional to the opening
+    EqualPercentage,                    // Equal steps in opening give equal ratios
+}
+
+impl Trim {
+    pub fn text(&self) -> String {
+        match self {
+            Trim::Inherent => "Inherent".to_string(),
+            Trim::Linear => "Linear".to_string(),
+            Trim::EqualPercentage => "Equal Percentage".to_string(),
+        }
+    }
+
+    // Characteristic of a valve type with this trim as ( opening, relative flow coefficient )
+    pub fn characteristic(&self, valve_type: &ValveType ) -> Vec<(f64, f64)> {
+        let points = ( 0..=10 ).map( |i| i as f64 / 10.0 );
+        match self {
+            Trim::Inherent => valve_type.characteristic(),
+            Trim::Linear => points.map( |x| ( x, x ) ).collect(),
+            // Shut off below the smallest flow coefficient
+            Trim::EqualPercentage => points.map( |x| 
+                ( x, if x > 0.0 { RANGEABILITY.powf( x - 1.0 ) } else { 0.0 } ) ).collect(),
+        }
+    }
+}
+
+// Size of a valve when fully open
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum ValveSize {
+    K( f64 ),                           // Loss coefficient
+    Kv( f64 ),                          // Flow of water with a loss of 1 bar [m^3/h]
+    Cv( f64 ),                          // Flow of water with a loss of 1 psi [US gal/min]
+}
+
+impl ValveSize {
+    // Loss coefficient of a valve with a diameter [m]
+    pub fn loss_coefficient(&self, diameter: f64 ) -> f64 {
+        match *self {
+            ValveSize::K( k ) => k,
+            ValveSize::Kv( kv ) => loss_at( kv / 3600.0, BAR, diameter ),
+            ValveSize::Cv( cv ) => loss_at( cv * GALLON / 60.0, PSI, diameter ),
+        }
+    }
+
+    pub fn kv(&self, diameter: f64 ) -> f64 {
+        flow_at( self.loss_coefficient( diameter ), BAR, diameter ) * 3600.0
+    }
+
+    pub fn cv(&self, diameter: f64 ) -> f64 {
+        flow_at( self.loss_coefficient( diameter ), PSI, diameter ) * 60.0 / GALLON
+    }
+}
+
+// Loss coefficient of a valve passing a volume flow rate of water [m^3/s] with a pressure loss [Pa]
+fn loss_at( q: f64, dp: f64, diameter: f64 ) -> f64 {
+    let v = q / ( PI * diameter * diameter / 4.0 );
+    2.0 * dp / ( WATER * v * v )
+}
+
+// Volume flow rate of water through a loss coefficient with a pressure loss [m^3/s]
+fn flow_at( k: f64, dp: f64, diameter: f64 ) -> f64 {
+    PI * diameter * diameter / 4.0 * ( 2.0 * dp / ( WATER * k ) ).sqrt()
 }
 
 #[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "persistence", serde(default))]
 pub struct Valve {
-    pub from: Node,
-    pub to: Node,
+    pub from: usize,                // From node id
+    pub to: usize,                  // To node id
     pub mass_flow: Vec<f64>,
     pub diameter: f64,
     pub thickness: f64,
@@ -56,10 +146,14 @@ pub struct Valve {
     pub width: f32,
     pub selected: bool,
     pub valve_type: ValveType,
+    #[serde(default)]
+    pub series: Option<TimeSeries>, // Opening (0 to 1) against time
+    #[serde(default)]
+    pub trim: Trim,
 }
 
 impl Valve {
-    pub fn new(from: Node, to: Node ) -> Self {
+    pub fn new(from: usize, to: usize ) -> Self {
         Valve { 
             from, 
             to, 
@@ -73,6 +167,8 @@ impl Valve {
             width: 15.0, 
             selected: false,
             valve_type: ValveType::Butterfly,
+            series: None,
+            trim: Trim::Inherent,
         }
     }
 
@@ -93,6 +189,35 @@ impl Valve {
         PI * self.diameter * self.diameter / 4.0
     }
 
+    // Size when fully open
+    pub fn size(&self) -> ValveSize {
+        ValveSize::K( 1.0 / self.invk.last().unwrap().1 )
+    }
+
+    // Scale the valve data to a size when fully open keeping its characteristic
+    pub fn set_size(&mut self, size: ValveSize ) {
+        let scale = 1.0 / ( size.loss_coefficient( self.diameter ) * self.invk.last().unwrap().1 );
+        self.invk.iter_mut().for_each( |point| point.1 *= scale );
+    }
+
+    // Change the valve type and replace the valve data with its characteristic (and the trim)
+    // keeping the size when fully open
+    pub fn set_valve_type(&mut self, valve_type: ValveType ) {
+        self.valve_type = valve_type;
+        self.apply_characteristic();
+    }
+
+    pub fn set_trim(&mut self, trim: Trim ) {
+        self.trim = trim;
+        self.apply_characteristic();
+    }
+
+    fn apply_characteristic(&mut self) {
+        let k = self.size().loss_coefficient( self.diameter );
+        self.invk = self.trim.characteristic( &self.valve_type ).iter()
+            .map( |( x, c )| ( *x, c * c / k ) ).collect();
+    }
+
     pub fn resistance(&self, q: f64, dh: f64, _nu: f64, g: f64, step: usize ) -> f64 {
         - ( q * q.abs() / ( 2. * self.area()  ) ) + self.invk( step ) * g * self.area() * dh
     }
@@ -113,14 +238,25 @@ impl Valve {
         result.sqrt()
     }
 
-    pub fn add_transient_value( &mut self, time: f64 ) {
-        let steady = self.open_percent[0];
-        for event in self.events.iter() {
-            self.open_percent.push( event.open_percent( time, steady ) );
+    // The series gives the steady opening at t = 0
+    pub fn start_series( &mut self ) {
+        if let Some( series ) = &self.series {
+            self.open_percent[0] = series.value( 0.0 );
         }
-        if self.events.len() == 0 {
-            self.open_percent.push( *self.open_percent.last().unwrap() );
+    }
+
+    // Events apply in time order from the opening given by the series (or the steady opening),
+    // each until the next one starts
+    pub fn add_transient_value( &mut self, time: f64, spawned: &[TransientEvent] ) {
+        let mut open = match &self.series {
+            Some( series ) => series.value( time ),
+            None => self.open_percent[0],
+        };
+        for ( event, next ) in events::timeline( &self.events, spawned ) {
+            if time < event.time() { break; }
+            open = event.open_percent( time.min( next ), open );
         }
+        self.open_percent.push( open );
     }
 
 }
@@ -0,0 +1,741 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::fluid::Fluid;
+use crate::graph::Graph;
+use crate::node::Node;
+use crate::nodes::{ connection::Connection, flow::Flow, pressure::Pressure, tank::Tank };
+use crate::edge::Edge;
+use crate::edges::{ pipe::Pipe, pump::Pump, valve::Valve, control_valve::{ ControlValve, ControlMode } };
+use crate::series::TimeSeries;
+use crate::utility;
+use crate::friction::FrictionModel;
+
+const ATMOSPHERIC: f64 = 101325.0;      // EPANET heads are gauge heads [Pa]
+const EFFICIENCY: f64 = 0.8;            // Pump efficiency used for the rated torque
+const MATCHING_VELOCITY: f64 = 1.0;     // Velocity at which the headloss formulas agree [m/s]
+
+// Problems that stop an EPANET file from being read
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum EpanetError {
+    Io( String ),                                   // The file could not be read
+    Parse { line: usize, message: String },         // Malformed entry in a supported section
+}
+
+impl std::fmt::Display for EpanetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EpanetError::Io( message ) => write!(f, "Could not read the file: {}", message),
+            EpanetError::Parse { line, message } => write!(f, "Line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for EpanetError {}
+
+// Network read from an EPANET .inp file
+#[derive(Clone, Default)]
+pub struct Imported {
+    pub graph: Graph,
+    pub node_ids: HashMap<String, usize>,   // EPANET node id -> eki node id
+    pub edge_ids: HashMap<String, usize>,   // EPANET link id -> index of the edge in the graph
+    pub duration: Option<f64>,              // Simulation duration [s]
+    pub hydraulic_step: Option<f64>,        // Hydraulic time step [s]
+    pub warnings: Vec<String>,              // Sections, options and settings that were not imported
+}
+
+// Conversion factors from the units of the file to SI
+struct Units {
+    flow: f64,                  // [m^3/s]
+    length: f64,                // [m]
+    diameter: f64,              // [m]
+    roughness: f64,             // Darcy-Weisbach roughness [m]
+    psi: bool,                  // Pressures in psi rather than metres of head
+}
+
+impl Units {
+    fn new( flow_units: &str ) -> Option<Self> {
+        let flow = match flow_units {
+            "CFS" => 0.028316847,
+            "GPM" => 6.3090196e-5,
+            "MGD" => 0.043812636,
+            "IMGD" => 0.052616782,
+            "AFD" => 0.014276410,
+            "LPS" => 1.0e-3,
+            "LPM" => 1.0e-3 / 60.0,
+            "MLD" => 1.0e3 / 86400.0,
+            "CMH" => 1.0 / 3600.0,
+            "CMD" => 1.0 / 86400.0,
+            _ => return None,
+        };
+        let us = matches!( flow_units, "CFS" | "GPM" | "MGD" | "IMGD" | "AFD" );
+        Some( if us {
+            Units { flow, length: 0.3048, diameter: 0.0254, roughness: 0.3048e-3, psi: true }
+        } else {
+            Units { flow, length: 1.0, diameter: 1.0e-3, roughness: 1.0e-3, psi: false }
+        })
+    }
+}
+
+// Entries of one section with their line numbers
+type Section = Vec<( usize, Vec<String> )>;
+
+struct Reader {
+    sections: HashMap<String, Section>,
+    warnings: Vec<String>,
+}
+
+impl Reader {
+    fn new( text: &str ) -> Self {
+        let mut sections: HashMap<String, Section> = HashMap::new();
+        let mut order = Vec::new();
+        let mut current = String::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split( ';' ).next().unwrap_or( "" ).trim();
+            if line.is_empty() { continue; }
+            if line.starts_with( '[' ) {
+                current = line.trim_matches( |c| c == '[' || c == ']' ).trim().to_uppercase();
+                if !sections.contains_key( &current ) { order.push( current.clone() ); }
+                sections.entry( current.clone() ).or_default();
+                continue;
+            }
+            let fields = line.split_whitespace().map( |field| field.to_string() ).collect();
+            sections.entry( current.clone() ).or_default().push( ( i + 1, fields ) );
+        }
+        let supported = [ "TITLE", "JUNCTIONS", "RESERVOIRS", "TANKS", "PIPES", "PUMPS", "VALVES",
+            "STATUS", "PATTERNS", "CURVES", "TIMES", "OPTIONS", "COORDINATES", "END" ];
+        let warnings = order.iter().filter( |name| !supported.contains( &name.as_str() ) )
+            .map( |name| format!( "Section [{}] is not supported and was ignored", name ) ).collect();
+        Reader { sections, warnings }
+    }
+
+    fn section( &self, name: &str ) -> Section {
+        self.sections.get( name ).cloned().unwrap_or_default()
+    }
+}
+
+fn number( fields: &[String], index: usize, line: usize ) -> Result<f64, EpanetError> {
+    let field = fields.get( index ).ok_or_else( || EpanetError::Parse {
+        line, message: format!( "Expected at least {} values", index + 1 )
+    })?;
+    field.parse::<f64>().map_err( |_| EpanetError::Parse {
+        line, message: format!( "'{}' is not a number", field )
+    })
+}
+
+fn optional( fields: &[String], index: usize, line: usize ) -> Result<Option<f64>, EpanetError> {
+    if fields.len() > index { number( fields, index, line ).map( Some ) } else { Ok( None ) }
+}
+
+// Duration in hours, "h:mm" or with a unit ( SEC, MIN, HOURS, DAYS ) [s]
+fn duration( fields: &[String] ) -> Option<f64> {
+    let value = fields.first()?;
+    let seconds = if let Some( ( hours, minutes ) ) = value.split_once( ':' ) {
+        let minutes = minutes.split( ':' ).map( |part| part.parse::<f64>() ).collect::<Result<Vec<_>, _>>().ok()?;
+        hours.parse::<f64>().ok()? * 3600.0 + minutes.iter().zip( [ 60.0, 1.0 ] ).map( |( v, s )| v * s ).sum::<f64>()
+    } else {
+        let scale = match fields.get( 1 ).map( |unit| unit.to_uppercase() ) {
+            Some( unit ) if unit.starts_with( "SEC" ) => 1.0,
+            Some( unit ) if unit.starts_with( "MIN" ) => 60.0,
+            Some( unit ) if unit.starts_with( "DAY" ) => 86400.0,
+            _ => 3600.0,
+        };
+        value.parse::<f64>().ok()? * scale
+    };
+    Some( seconds )
+}
+
+// Sand roughness giving the same Darcy friction factor f at a Reynolds number re (Colebrook) [m]
+fn equivalent_roughness( f: f64, diameter: f64, re: f64 ) -> f64 {
+    let root = f.sqrt();
+    ( 3.7 * diameter * ( 10.0_f64.powf( - 0.5 / root ) - 2.51 / ( re * root ) ) ).max( 0.0 )
+}
+
+// Headloss formula of an EPANET file
+#[derive(Clone, Copy, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub enum Headloss {
+    #[default]
+    HazenWilliams,
+    DarcyWeisbach,
+    ChezyManning,
+}
+
+impl Headloss {
+    pub fn text(&self) -> String {
+        match self {
+            Headloss::HazenWilliams => "H-W".to_string(),
+            Headloss::DarcyWeisbach => "D-W".to_string(),
+            Headloss::ChezyManning => "C-M".to_string(),
+        }
+    }
+
+    fn from_text( text: &str ) -> Option<Self> {
+        match text {
+            "H-W" => Some( Headloss::HazenWilliams ),
+            "D-W" => Some( Headloss::DarcyWeisbach ),
+            "C-M" => Some( Headloss::ChezyManning ),
+            _ => None,
+        }
+    }
+
+    // Darcy-Weisbach roughness [m] for the roughness coefficient of a pipe in the file. Hazen-Williams 
+    // and Chezy-Manning coefficients give the same friction factor at the matching velocity.
+    fn roughness(&self, value: f64, diameter: f64, units: &Units, nu: f64, g: f64 ) -> f64 {
+        let v = MATCHING_VELOCITY;
+        let f = match self {
+            Headloss::DarcyWeisbach => return value * units.roughness,
+            Headloss::HazenWilliams => {
+                let q = v * 0.25 * PI * diameter * diameter;
+                let gradient = 10.67 * q.powf( 1.852 ) / ( value.powf( 1.852 ) * diameter.powf( 4.8704 ) );
+                gradient * 2.0 * g * diameter / ( v * v )
+            },
+            Headloss::ChezyManning => 2.0 * g * diameter * value * value / ( 0.25 * diameter ).powf( 4.0 / 3.0 ),
+        };
+        equivalent_roughness( f, diameter, v * diameter / nu )
+    }
+
+    // Roughness coefficient in the file for a friction model and Darcy-Weisbach roughness [m] (SI
+    // units). A model of the formula is written as it is, any other gives the same friction factor
+    // at the matching velocity.
+    fn coefficient(&self, model: FrictionModel, roughness: f64, diameter: f64, nu: f64, g: f64 ) -> f64 {
+        match ( self, model ) {
+            ( Headloss::HazenWilliams, FrictionModel::HazenWilliams { c } ) => return c,
+            ( Headloss::ChezyManning, FrictionModel::Manning { n } ) => return n,
+            ( Headloss::DarcyWeisbach, FrictionModel::HazenWilliams { .. } | FrictionModel::Manning { .. } 
+                | FrictionModel::Fixed { .. } ) => {},
+            ( Headloss::DarcyWeisbach, _ ) => return roughness * 1.0e3,
+            _ => {},
+        }
+        let v = MATCHING_VELOCITY;
+        let q = v * 0.25 * PI * diameter * diameter;
+        let f = model.friction_factor( q, diameter, roughness, nu );
+        let gradient = f * v * v / ( 2.0 * g * diameter );
+        match self {
+            Headloss::DarcyWeisbach => equivalent_roughness( f, diameter, v * diameter / nu ) * 1.0e3,
+            Headloss::HazenWilliams => ( 10.67 * q.powf( 1.852 ) / ( gradient * diameter.powf( 4.8704 ) ) ).powf( 1.0 / 1.852 ),
+            Headloss::ChezyManning => ( gradient * ( 0.25 * diameter ).powf( 4.0 / 3.0 ) ).sqrt() / v,
+        }
+    }
+}
+
+// Read an EPANET .inp file
+pub fn read( path: &str, fluid: &Fluid, g: f64 ) -> Result<Imported, EpanetError> {
+    let text = std::fs::read_to_string( path ).map_err( |error| EpanetError::Io( error.to_string() ) )?;
+    import( &text, fluid, g )
+}
+
+// Build a network from the text of an EPANET .inp file
+pub fn import( text: &str, fluid: &Fluid, g: f64 ) -> Result<Imported, EpanetError> {
+    let reader = Reader::new( text );
+    let mut imported = Imported { warnings: reader.warnings.clone(), ..Default::default() };
+    let rho = fluid.density();
+
+    // Options
+    let ( mut flow_units, mut formula ) = ( "GPM".to_string(), Headloss::HazenWilliams );
+    for (line, fields) in reader.section( "OPTIONS" ) {
+        let key = fields[0].to_uppercase();
+        let value = fields.get( 1 ).map( |value| value.to_uppercase() ).unwrap_or_default();
+        match key.as_str() {
+            "UNITS" => flow_units = value,
+            "HEADLOSS" => formula = Headloss::from_text( &value ).ok_or_else( || EpanetError::Parse {
+                line, message: format!( "Unknown headloss formula {}", value )
+            })?,
+            _ => imported.warnings.push( format!( "Option {} on line {} was ignored", fields[0], line ) ),
+        }
+    }
+    let units = Units::new( &flow_units ).ok_or_else( || EpanetError::Parse {
+        line: 0, message: format!( "Unknown flow units {}", flow_units )
+    })?;
+    let head = units.length;
+    let mut pattern_step = 3600.0;
+    for (_line, fields) in reader.section( "TIMES" ) {
+        let key = fields[0].to_uppercase();
+        let value = if fields.get( 1 ).is_some_and( |field| field.to_uppercase() == "TIMESTEP" ) {
+            &fields[2..]
+        } else {
+            &fields[1..]
+        };
+        match ( key.as_str(), fields.get( 1 ).map( |field| field.to_uppercase() ).as_deref() ) {
+            ( "DURATION", _ ) => imported.duration = duration( value ),
+            ( "HYDRAULIC", Some( "TIMESTEP" ) ) => imported.hydraulic_step = duration( value ),
+            ( "PATTERN", Some( "TIMESTEP" ) ) => pattern_step = duration( value ).unwrap_or( pattern_step ),
+            _ => {},
+        }
+    }
+
+    // Patterns and curves
+    let mut patterns: HashMap<String, Vec<f64>> = HashMap::new();
+    for (line, fields) in reader.section( "PATTERNS" ) {
+        let pattern = patterns.entry( fields[0].clone() ).or_default();
+        for index in 1..fields.len() {
+            pattern.push( number( &fields, index, line )? );
+        }
+    }
+    let mut curves: HashMap<String, Vec<(f64, f64)>> = HashMap::new();
+    for (line, fields) in reader.section( "CURVES" ) {
+        let point = ( number( &fields, 1, line )?, number( &fields, 2, line )? );
+        curves.entry( fields[0].clone() ).or_default().push( point );
+    }
+
+    // Nodes ( the EPANET ids are kept when they are all numbers )
+    let labels: Vec<String> = [ "JUNCTIONS", "RESERVOIRS", "TANKS" ].iter()
+        .flat_map( |name| reader.section( name ) ).map( |(_line, fields)| fields[0].clone() ).collect();
+    let numeric = labels.iter().all( |label| label.parse::<usize>().is_ok() );
+    for (i, label) in labels.iter().enumerate() {
+        let id = if numeric { label.parse::<usize>().unwrap() } else { i + 1 };
+        imported.node_ids.insert( label.clone(), id );
+    }
+    let mut nodes: HashMap<String, Node> = HashMap::new();
+    for (line, fields) in reader.section( "JUNCTIONS" ) {
+        let id = imported.node_ids[&fields[0]];
+        let elevation = number( &fields, 1, line )? * head;
+        let demand = optional( &fields, 2, line )?.unwrap_or( 0.0 ) * units.flow;
+        let node = if demand == 0.0 {
+            Node::Connection( Connection::new_elevation( id, elevation ) )
+        } else {
+            let mut flow = Flow::new_elevation( id, elevation );
+            flow.consumption = vec![ - rho * demand ];
+            // The demand pattern multiplies the base demand
+            if let Some( pattern ) = fields.get( 3 ) {
+                match patterns.get( pattern ) {
+                    Some( multipliers ) => {
+                        let consumptions: Vec<f64> = multipliers.iter().map( |m| - rho * demand * m ).collect();
+                        flow.series = Some( TimeSeries::pattern( &consumptions, pattern_step ) );
+                    },
+                    None => imported.warnings.push( format!( "Unknown pattern {} on line {}", pattern, line ) ),
+                }
+            }
+            Node::Flow( flow )
+        };
+        nodes.insert( fields[0].clone(), node );
+    }
+    for (line, fields) in reader.section( "RESERVOIRS" ) {
+        let id = imported.node_ids[&fields[0]];
+        let elevation = number( &fields, 1, line )? * head;
+        let mut reservoir = Pressure::new_elevation( id, elevation );
+        // The head pattern multiplies the total head of the reservoir
+        if let Some( pattern ) = fields.get( 2 ) {
+            match patterns.get( pattern ) {
+                Some( multipliers ) => {
+                    let pressures: Vec<f64> = multipliers.iter()
+                        .map( |m| ATMOSPHERIC + rho * g * ( m - 1.0 ) * elevation ).collect();
+                    reservoir.series = Some( TimeSeries::pattern( &pressures, pattern_step ) );
+                },
+                None => imported.warnings.push( format!( "Unknown pattern {} on line {}", pattern, line ) ),
+            }
+        }
+        nodes.insert( fields[0].clone(), Node::Pressure( reservoir ) );
+    }
+    for (line, fields) in reader.section( "TANKS" ) {
+        let id = imported.node_ids[&fields[0]];
+        let values = ( 1..6 ).map( |index| number( &fields, index, line ) ).collect::<Result<Vec<f64>, _>>()?;
+        let mut tank = Tank::new_with_values( id, ATMOSPHERIC, rho, g, values[4] * units.length,
+            values[1] * head, values[2] * head, values[3] * head );
+        tank.elevation = values[0] * head;
+        if let Some( name ) = fields.get( 7 ).filter( |name| name.as_str() != "*" ) {
+            match curves.get( name ) {
+                // Area from the slope of the volume curve ( level, volume )
+                Some( curve ) if curve.len() >= 2 => {
+                    tank.area_data = curve.windows( 2 ).map( |pair| {
+                        let level = 0.5 * ( pair[0].0 + pair[1].0 ) * head;
+                        let area = ( pair[1].1 - pair[0].1 ) / ( pair[1].0 - pair[0].0 ) * head * head;
+                        ( level, area )
+                    }).collect();
+                },
+                _ => imported.warnings.push( format!( "Volume curve {} of tank {} was ignored", name, fields[0] ) ),
+            }
+        }
+        nodes.insert( fields[0].clone(), Node::Tank( tank ) );
+    }
+    for (line, fields) in reader.section( "COORDINATES" ) {
+        if let Some( node ) = nodes.get_mut( &fields[0] ) {
+            let ( x, y ) = ( number( &fields, 1, line )?, number( &fields, 2, line )? );
+            node.update_location( x as f32, y as f32 );
+        }
+    }
+    for label in labels.iter() {
+        imported.graph.add_node( nodes[label].clone() );
+    }
+
+    // Links
+    let ends = |fields: &[String], line: usize| -> Result<(usize, usize), EpanetError> {
+        let node = |index: usize| fields.get( index ).and_then( |label| nodes.get( label ) ).map( Node::id )
+            .ok_or_else( || EpanetError::Parse { line, message: format!( "Unknown node in link {}", fields[0] ) } );
+        Ok( ( node( 1 )?, node( 2 )? ) )
+    };
+    let nu = fluid.kinematic_viscosity();
+    for (line, fields) in reader.section( "PIPES" ) {
+        let ( from, to ) = ends( &fields, line )?;
+        let length = number( &fields, 3, line )? * units.length;
+        let diameter = number( &fields, 4, line )? * units.diameter;
+        let value = number( &fields, 5, line )?;
+        let mut pipe = Pipe::new( from, to );
+        pipe.length = length;
+        pipe.diameter = diameter;
+        pipe.roughness = formula.roughness( value, diameter, &units, nu, g );
+        // Hazen-Williams and Chezy-Manning pipes keep their formula (with the matching roughness)
+        pipe.friction = match formula {
+            Headloss::HazenWilliams => FrictionModel::HazenWilliams { c: value },
+            Headloss::ChezyManning => FrictionModel::Manning { n: value },
+            Headloss::DarcyWeisbach => FrictionModel::Default,
+        };
+        if optional( &fields, 6, line )?.unwrap_or( 0.0 ) != 0.0 {
+            imported.warnings.push( format!( "Minor loss of pipe {} was ignored", fields[0] ) );
+        }
+        if let Some( status ) = fields.get( 7 ).filter( |status| status.to_uppercase() != "OPEN" ) {
+            imported.warnings.push( format!( "Status {} of pipe {} was ignored", status, fields[0] ) );
+        }
+        imported.edge_ids.insert( fields[0].clone(), imported.graph.num_edges() );
+        imported.graph.add_edge( Edge::Pipe( pipe ) );
+    }
+    for (line, fields) in reader.section( "PUMPS" ) {
+        let ( from, to ) = ends( &fields, line )?;
+        let mut pump = Pump::new( from, to );
+        let mut speed = 1.0;
+        let mut multipliers = None;
+        for pair in fields[3..].chunks( 2 ) {
+            let keyword = pair[0].to_uppercase();
+            match ( keyword.as_str(), pair.get( 1 ) ) {
+                ( "HEAD", Some( name ) ) => match curves.get( name ) {
+                    Some( curve ) => {
+                        // A single design point ( Q, H ) gives a shutoff head of 1.33 H and a maximum flow of 2 Q
+                        let mut curve: Vec<(f64, f64)> = curve.iter()
+                            .map( |( q, h )| ( q * units.flow, h * head ) ).collect();
+                        if curve.len() == 1 {
+                            let ( q, h ) = curve[0];
+                            curve = vec![ ( 0.0, 1.33 * h ), ( q, h ), ( 2.0 * q, 0.0 ) ];
+                        }
+                        pump.set_head_curve( &curve );
+                    },
+                    None => imported.warnings.push( format!( "Unknown head curve {} of pump {}", name, fields[0] ) ),
+                },
+                ( "SPEED", Some( _ ) ) => speed = number( pair, 1, line )?,
+                ( "PATTERN", Some( name ) ) => match patterns.get( name ) {
+                    Some( pattern ) => multipliers = Some( pattern.clone() ),
+                    None => imported.warnings.push( format!( "Unknown pattern {} of pump {}", name, fields[0] ) ),
+                },
+                _ => imported.warnings.push( format!( "Pump {} setting {} was ignored", fields[0], pair[0] ) ),
+            }
+        }
+        let omega = pump.n_rated * PI / 30.0;
+        pump.rated_torque = rho * g * pump.q_rated * pump.h_rated / ( EFFICIENCY * omega );
+        pump.speed = vec![ speed * pump.n_rated ];
+        // The speed pattern multiplies the relative speed
+        if let Some( multipliers ) = multipliers {
+            let speeds: Vec<f64> = multipliers.iter().map( |m| m * speed * pump.n_rated ).collect();
+            pump.series = Some( TimeSeries::pattern( &speeds, pattern_step ) );
+        }
+        imported.edge_ids.insert( fields[0].clone(), imported.graph.num_edges() );
+        imported.graph.add_edge( Edge::Pump( pump ) );
+    }
+    for (line, fields) in reader.section( "VALVES" ) {
+        let ( from, to ) = ends( &fields, line )?;
+        let mut valve = Valve::new( from, to );
+        valve.diameter = number( &fields, 3, line )? * units.diameter;
+        let kind = fields.get( 4 ).map( |kind| kind.to_uppercase() ).unwrap_or_default();
+        let minor_loss = optional( &fields, 6, line )?.unwrap_or( 0.0 );
+        // Pressure settings are gauge and the fully open loss is the minor loss
+        let control = match kind.as_str() {
+            "PRV" | "PSV" => {
+                let gauge = number( &fields, 5, line )? * if units.psi { utility::PSI } else { rho * g };
+                let mode = if kind == "PRV" { ControlMode::PressureReducing } else { ControlMode::PressureSustaining };
+                Some( ( mode, ATMOSPHERIC + gauge ) )
+            },
+            "FCV" => Some( ( ControlMode::FlowControl, number( &fields, 5, line )? * units.flow * rho ) ),
+            _ => None,
+        };
+        if let Some( ( mode, setpoint ) ) = control {
+            let mut control = ControlValve::new( from, to, mode, setpoint );
+            control.diameter = valve.diameter;
+            if minor_loss > 0.0 {
+                let scale = 1.0 / ( minor_loss * control.invk.last().unwrap().1 );
+                control.invk.iter_mut().for_each( |point| point.1 *= scale );
+            }
+            imported.edge_ids.insert( fields[0].clone(), imported.graph.num_edges() );
+            imported.graph.add_edge( Edge::ControlValve( control ) );
+            continue;
+        }
+        let area = valve.area();
+        let k = match kind.as_str() {
+            // A throttle control valve is set by its loss coefficient
+            "TCV" => optional( &fields, 5, line )?.unwrap_or( 0.0 ),
+            // The loss coefficient of a general purpose valve is taken from the last point of its curve
+            "GPV" => match fields.get( 5 ).and_then( |name| curves.get( name ) ).and_then( |curve| curve.last() ) {
+                Some( ( q, h ) ) if *q > 0.0 => {
+                    let v = q * units.flow / area;
+                    imported.warnings.push( format!( "GPV valve {} was imported as a fixed loss", fields[0] ) );
+                    2.0 * g * h * head / ( v * v )
+                },
+                _ => {
+                    imported.warnings.push( format!( "GPV valve {} has no headloss curve", fields[0] ) );
+                    minor_loss
+                },
+            },
+            _ => {
+                imported.warnings.push( format!( "{} valve {} was imported as an open valve", kind, fields[0] ) );
+                minor_loss
+            },
+        };
+        if k > 0.0 {
+            valve.invk = vec![ ( 0.0, 0.0 ), ( 1.0, 1.0 / k ) ];
+        }
+        imported.edge_ids.insert( fields[0].clone(), imported.graph.num_edges() );
+        imported.graph.add_edge( Edge::Valve( valve ) );
+    }
+
+    // Initial status of valves and pumps
+    for (line, fields) in reader.section( "STATUS" ) {
+        let status = fields.get( 1 ).map( |status| status.to_uppercase() ).unwrap_or_default();
+        let Some( &index ) = imported.edge_ids.get( &fields[0] ) else {
+            imported.warnings.push( format!( "Status of unknown link {} on line {}", fields[0], line ) );
+            continue;
+        };
+        match ( &mut imported.graph.edges[index], status.as_str() ) {
+            ( Edge::Pipe( _ ), "OPEN" ) => {},
+            ( Edge::Valve( valve ), "CLOSED" ) => valve.open_percent = vec![ 0.0 ],
+            ( Edge::Valve( valve ), "OPEN" ) => valve.open_percent = vec![ 1.0 ],
+            ( Edge::Pump( pump ), "CLOSED" ) => pump.speed = vec![ 0.0 ],
+            ( Edge::Pump( pump ), "OPEN" ) => pump.speed = vec![ pump.n_rated ],
+            ( Edge::Pump( pump ), _ ) if status.parse::<f64>().is_ok() => {
+                pump.speed = vec![ number( &fields, 1, line )? * pump.n_rated ];
+            },
+            _ => imported.warnings.push( format!( "Status {} of link {} was ignored", status, fields[0] ) ),
+        }
+    }
+
+    // Tank area tables are used in preference to the diameter
+    for node in imported.graph.nodes.iter_mut() {
+        if let Node::Tank( tank ) = node {
+            if tank.area_data.len() >= 2 {
+                let ( levels, areas ) = utility::split_into_two_vectors( &tank.area_data );
+                if levels.windows( 2 ).any( |pair| pair[1] <= pair[0] ) || areas.iter().any( |area| *area <= 0.0 ) {
+                    imported.warnings.push( format!( "Volume curve of tank {} is not increasing", tank.id ) );
+                    tank.area_data.clear();
+                }
+            }
+        }
+    }
+    imported.graph.create_id_to_index();
+    Ok( imported )
+}
+
+// Sections written by export in the order they appear in the file
+const SECTIONS: [&str; 13] = [ "TITLE", "JUNCTIONS", "RESERVOIRS", "TANKS", "PIPES", "PUMPS", "VALVES", 
+    "STATUS", "PATTERNS", "CURVES", "TIMES", "OPTIONS", "COORDINATES" ];
+
+// Network written as an EPANET .inp file ( SI units with flow rates in LPS )
+#[derive(Clone, Default)]
+pub struct Exported {
+    pub text: String,
+    pub report: Vec<String>,                // Components written as an approximate EPANET equivalent
+}
+
+// Write a network to an EPANET .inp file and return the report
+pub fn write( path: &str, graph: &Graph, fluid: &Fluid, g: f64, headloss: Headloss, friction: FrictionModel ) 
+    -> Result<Vec<String>, EpanetError> 
+{
+    let exported = export( graph, fluid, g, headloss, friction );
+    std::fs::write( path, exported.text ).map_err( |error| EpanetError::Io( error.to_string() ) )?;
+    Ok( exported.report )
+}
+
+// Loss coefficients are written as short smooth pipes one diameter long
+fn short_pipe( id: usize, ends: (usize, usize), diameter: f64, k: f64, status: &str, coefficient: f64 ) -> String {
+    format!( " {:<8} {:<8} {:<8} {:<12} {:<12} {:<12} {:<12} {}\n", 
+        id, ends.0, ends.1, diameter, diameter * 1.0e3, coefficient, k, status )
+}
+
+// Headloss curve ( Q [LPS], h [m] ) of a loss coefficient up to a velocity of 5 m/s
+fn loss_curve( name: &str, diameter: f64, k: f64, g: f64 ) -> String {
+    let area = 0.25 * PI * diameter * diameter;
+    ( 0..5 ).map( |i| {
+        let v = 1.25 * i as f64;
+        format!( " {:<8} {:<12} {}\n", name, v * area * 1.0e3, k * v * v / ( 2.0 * g ) )
+    }).collect()
+}
+
+// Write a network in the EPANET .inp format with pipe roughness for the chosen headloss formula.
+// Components without an EPANET counterpart become minor losses or general purpose valves and are
+// listed in the report. Pipes and bends without a friction model of their own and bend losses use
+// the default friction model of the solver.
+pub fn export( graph: &Graph, fluid: &Fluid, g: f64, headloss: Headloss, friction: FrictionModel ) -> Exported {
+    let ( rho, nu ) = ( fluid.density(), fluid.kinematic_viscosity() );
+    let mut report = Vec::new();
+    let mut sections = vec![ String::new(); SECTIONS.len() ];
+    let mut push = |name: &str, text: String| {
+        let index = SECTIONS.iter().position( |section| *section == name ).unwrap();
+        sections[ index ].push_str( &text );
+    };
+    push( "TITLE", " Exported from eki\n".to_string() );
+
+    // Nodes
+    let mut pattern_step: Option<f64> = None;
+    for node in graph.nodes.iter() {
+        let ( id, loc ) = ( node.id(), node.loc() );
+        push( "COORDINATES", format!( " {:<8} {:<12} {}\n", id, loc.x, loc.y ) );
+        let mut series = node.clone().series().is_some_and( |series| series.is_some() );
+        match node {
+            Node::Pressure( reservoir ) => {
+                let head = reservoir.elevation + ( reservoir.pressure[0] - ATMOSPHERIC ) / ( rho * g );
+                push( "RESERVOIRS", format!( " {:<8} {}\n", id, head ) );
+            },
+            Node::Tank( tank ) => {
+                let mut curve = String::new();
+                if tank.area_data.len() >= 2 {
+                    // Volume curve ( level, volume ) from the area table
+                    let ( mut level, mut area ) = ( 0.0, tank.area_data[0].1 );
+                    let mut volume = 0.0;
+                    for ( next_level, next_area ) in tank.area_data.iter() {
+                        volume += 0.5 * ( area + next_area ) * ( next_level - level );
+                        ( level, area ) = ( *next_level, *next_area );
+                        push( "CURVES", format!( " {:<8} {:<12} {}\n", format!( "t{}", id ), level, volume ) );
+                    }
+                    curve = format!( "t{}", id );
+                }
+                push( "TANKS", format!( " {:<8} {:<12} {:<12} {:<12} {:<12} {:<12} 0 {}\n", id, tank.elevation, 
+                    tank.z_init, tank.z_min, tank.z_max, tank.diameter, curve ) );
+            },
+            Node::Flow( flow ) => {
+                let mut demand = - flow.consumption[0] / rho * 1.0e3;
+                let mut pattern = String::new();
+                // A series made by a pattern is written as multipliers of the steady consumption
+                // (or of its largest value when there is no steady consumption)
+                if let Some( ( values, step ) ) = flow.series.as_ref().and_then( TimeSeries::as_pattern ) {
+                    let largest = values.iter().fold( 0.0, |base: f64, value| if value.abs() > base.abs() { *value } else { base } );
+                    let base = if flow.consumption[0] != 0.0 { flow.consumption[0] } else { largest };
+                    if base != 0.0 {
+                        demand = - base / rho * 1.0e3;
+                        pattern = format!( "d{}", id );
+                        let multipliers: Vec<String> = values.iter().map( |value| ( value / base ).to_string() ).collect();
+                        push( "PATTERNS", format!( " {:<8} {}\n", pattern, multipliers.join( " " ) ) );
+                        match pattern_step {
+                            Some( other ) if other != step => report.push( format!( 
+                                "Node {}: pattern step {} s written with the step {} s of the other patterns", 
+                                id, step, other ) ),
+                            _ => pattern_step = Some( step ),
+                        }
+                        series = false;
+                    }
+                }
+                push( "JUNCTIONS", format!( " {:<8} {:<12} {:<12} {}\n", id, flow.elevation, demand, pattern ) );
+            },
+            Node::AirValve( _ ) | Node::Accumulator( _ ) => {
+                report.push( format!( "Node {}: {} written as a junction", id, node ) );
+                push( "JUNCTIONS", format!( " {:<8} {:<12} 0\n", id, *node.clone().elevation() ) );
+            },
+            Node::Connection( _ ) | Node::Hidden( _ ) => {
+                push( "JUNCTIONS", format!( " {:<8} {:<12} 0\n", id, *node.clone().elevation() ) );
+            },
+        }
+        if series {
+            report.push( format!( "Node {}: time series is not written", id ) );
+        }
+    }
+
+    // Links ( numbered from one in the order of the edges )
+    let smooth = |diameter: f64| headloss.coefficient( FrictionModel::Default.or( friction ), 0.0, diameter, nu, g );
+    for (j, edge) in graph.edges.iter().enumerate() {
+        let ( link, ends ) = ( j + 1, edge.id() );
+        let q = edge.clone().steady_mass_flow().abs() / rho;
+        if edge.clone().series().is_some_and( |series| series.is_some() ) {
+            report.push( format!( "Link {}: time series is not written", link ) );
+        }
+        match edge {
+            Edge::Pipe( pipe ) => {
+                let coefficient = headloss.coefficient( pipe.friction.or( friction ), pipe.roughness, pipe.diameter, nu, g );
+                push( "PIPES", format!( " {:<8} {:<8} {:<8} {:<12} {:<12} {:<12} 0 Open\n", link, ends.0, ends.1, 
+                    pipe.length, pipe.diameter * 1.0e3, coefficient ) );
+            },
+            Edge::Bend( bend ) => {
+                // The bend loss less the friction along its length at the steady flow rate
+                let q = q.max( MATCHING_VELOCITY * bend.area() );
+                let k = bend.k( q, nu, friction ) - bend.friction_factor( q, nu, friction ) * bend.length() / bend.diameter;
+                let coefficient = headloss.coefficient( bend.friction.or( friction ), bend.roughness, bend.diameter, nu, g );
+                push( "PIPES", format!( " {:<8} {:<8} {:<8} {:<12} {:<12} {:<12} {:<12} Open\n", link, ends.0, ends.1, 
+                    bend.length(), bend.diameter * 1.0e3, coefficient, k ) );
+                report.push( format!( "Link {}: Bend written as a pipe with a minor loss of {:.4}", link, k ) );
+            },
+            Edge::SizeChange( change ) => {
+                push( "PIPES", short_pipe( link, ends, change.diameter, change.k(), "Open", smooth( change.diameter ) ) );
+                report.push( format!( "Link {}: Size Change written as a minor loss of {:.4} for forward flow", link, change.k() ) );
+            },
+            Edge::OpenPipe( open ) => {
+                push( "PIPES", short_pipe( link, ends, open.diameter, open.k, "Open", smooth( open.diameter ) ) );
+                report.push( format!( "Link {}: Open Pipe written as a minor loss of {:.4}", link, open.k ) );
+            },
+            Edge::CheckValve( valve ) => {
+                let k = 1.0 / valve.invk.last().unwrap().1;
+                push( "PIPES", short_pipe( link, ends, valve.diameter, k, "CV", smooth( valve.diameter ) ) );
+                if valve.is_dynamic() {
+                    report.push( format!( "Link {}: Check Valve disc dynamics are not written", link ) );
+                }
+            },
+            Edge::Valve( valve ) => {
+                let invk = valve.interpolate_invk( valve.open_percent[0] );
+                let k = if invk > 0.0 { 1.0 / invk } else { 0.0 };
+                push( "VALVES", format!( " {:<8} {:<8} {:<8} {:<12} TCV {:<12} 0\n", link, ends.0, ends.1, 
+                    valve.diameter * 1.0e3, k ) );
+                if invk <= 0.0 { push( "STATUS", format!( " {:<8} Closed\n", link ) ); }
+            },
+            Edge::ControlValve( valve ) => {
+                let ( kind, setting ) = match valve.mode {
+                    ControlMode::PressureReducing => ( "PRV", ( valve.setpoint - ATMOSPHERIC ) / ( rho * g ) ),
+                    ControlMode::PressureSustaining => ( "PSV", ( valve.setpoint - ATMOSPHERIC ) / ( rho * g ) ),
+                    ControlMode::FlowControl => ( "FCV", valve.setpoint / rho * 1.0e3 ),
+                };
+                push( "VALVES", format!( " {:<8} {:<8} {:<8} {:<12} {} {:<12} {}\n", link, ends.0, ends.1, 
+                    valve.diameter * 1.0e3, kind, setting, 1.0 / valve.invk.last().unwrap().1 ) );
+                report.push( format!( "Link {}: {} written with its fully open loss (the opening characteristic \
+                    and actuator are not written)", link, edge ) );
+            },
+            Edge::SafetyValve( _ ) | Edge::ReliefValve( _ ) | Edge::BurstingDisk( _ ) => {
+                let ( diameter, invk, open ) = match edge {
+                    Edge::SafetyValve( valve ) => ( valve.diameter, valve.invk.last().unwrap().1, valve.open_percent[0] ),
+                    Edge::ReliefValve( valve ) => ( valve.diameter, valve.invk.last().unwrap().1, valve.open_percent[0] ),
+                    Edge::BurstingDisk( disk ) => ( disk.diameter, disk.invk.last().unwrap().1, disk.open_percent[0] ),
+                    _ => unreachable!(),
+                };
+                let curve = format!( "g{}", link );
+                push( "CURVES", loss_curve( &curve, diameter, 1.0 / invk, g ) );
+                push( "VALVES", format!( " {:<8} {:<8} {:<8} {:<12} GPV {:<12} 0\n", link, ends.0, ends.1, 
+                    diameter * 1.0e3, curve ) );
+                if open <= 0.0 { push( "STATUS", format!( " {:<8} Closed\n", link ) ); }
+                report.push( format!( "Link {}: {} written as a general purpose valve with its fully open loss \
+                    (opening on pressure is not written)", link, edge ) );
+            },
+            Edge::Generic( generic ) => {
+                let ( a, b, c ) = generic.coefficients;
+                let ( n, m ) = generic.exponents;
+                let q_max = 2.0 * q.max( 1.0e-3 );
+                let curve = format!( "g{}", link );
+                for i in 0..5 {
+                    let q = q_max * i as f64 / 4.0;
+                    let loss = a + b * q.powf( n ) + c * q.powf( m );
+                    push( "CURVES", format!( " {:<8} {:<12} {}\n", curve, q * 1.0e3, loss ) );
+                }
+                push( "VALVES", format!( " {:<8} {:<8} {:<8} 100 GPV {:<12} 0\n", link, ends.0, ends.1, curve ) );
+                report.push( format!( "Link {}: Generic written as a general purpose valve for forward flow", link ) );
+            },
+            Edge::Pump( pump ) => {
+                let curve = format!( "h{}", link );
+                for ( q, h ) in pump.head_curve( 25 ) {
+                    push( "CURVES", format!( " {:<8} {:<12} {}\n", curve, q * 1.0e3, h ) );
+                }
+                push( "PUMPS", format!( " {:<8} {:<8} {:<8} HEAD {} SPEED {}\n", link, ends.0, ends.1, curve, 
+                    pump.speed[0] / pump.n_rated ) );
+            },
+        }
+    }
+
+    if let Some( step ) = pattern_step {
+        push( "TIMES", format!( " Pattern Timestep {}:{:02}\n", ( step / 3600.0 ).floor(), ( ( step % 3600.0 ) / 60.0 ).round() ) );
+    }
+    push( "OPTIONS", format!( " Units            LPS\n Headloss         {}\n", headloss.text() ) );
+
+    let mut text = String::new();
+    for ( name, body ) in SECTIONS.iter().zip( sections.iter() ) {
+        text.push_str( &format!( "[{}]\n{}\n", name, body ) );
+    }
+    text.push_str( "[END]\n" );
+    Exported { text, report }
+}
@@ -4,20 +4,16 @@ pub struct Time(pub f64);
 #[derive(Debug, PartialEq, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Value(pub f64);
 
-#[derive(Debug, PartialEq, Clone, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Default, PartialEq, Clone, serde::Deserialize, serde::Serialize)]
 pub enum TransientEvent {
+    #[default]
     None,
     InstantaneousChange(Value, Time),
     ValveClosure(Value, Time, Time),
     ValveOpening(Value, Time, Time),
     PumpShutdown(Value, Time, Time),
     PumpStartup(Value, Value, Time, Time),
-}
-
-impl Default for TransientEvent {
-    fn default() -> Self {
-        TransientEvent::None
-    }
+    PowerFailure(Time),
 }
 
 impl TransientEvent {
@@ -29,6 +25,7 @@ impl TransientEvent {
             TransientEvent::ValveOpening(_, _, _) => "Valve opening".to_string(),
             TransientEvent::PumpShutdown(_,_,_) => "Linear shutdown".to_string(),
             TransientEvent::PumpStartup(_,_,_,_) => "Linear startup".to_string(),
+            TransientEvent::PowerFailure(_) => "Power failure".to_string(),
         }
     }
 
@@ -40,6 +37,7 @@ impl TransientEvent {
             TransientEvent::ValveOpening(_, event_time, _) => event_time.0,
             TransientEvent::PumpShutdown(_,event_time, _) => event_time.0,
             TransientEvent::PumpStartup(_,_, event_time, _) => event_time.0,
+            TransientEvent::PowerFailure(event_time) => event_time.0,
         }
     }
 
@@ -51,6 +49,7 @@ impl TransientEvent {
             TransientEvent::ValveOpening(exponent,_,_) => exponent.0,
             TransientEvent::PumpShutdown(exponent,_,_) => exponent.0,
             TransientEvent::PumpStartup(value,_,_,_) => value.0,
+            TransientEvent::PowerFailure(_) => 0.0,
         }
     }
 
@@ -62,12 +61,31 @@ impl TransientEvent {
             TransientEvent::ValveOpening(_, _, closing_time) => closing_time.0,
             TransientEvent::PumpShutdown(_,_, shutdown_time) => shutdown_time.0,
             TransientEvent::PumpStartup(_,_,_,startup_time) => startup_time.0,
+            TransientEvent::PowerFailure(_) => 0.0,
+        }
+    }
+
+    // The same event starting delay seconds later
+    pub fn delayed(&self, delay: f64 ) -> Self {
+        let mut event = self.clone();
+        match &mut event {
+            TransientEvent::None => {},
+            TransientEvent::InstantaneousChange(_, time) | TransientEvent::ValveClosure(_, time, _)
+            | TransientEvent::ValveOpening(_, time, _) | TransientEvent::PumpShutdown(_, time, _)
+            | TransientEvent::PumpStartup(_, _, time, _) | TransientEvent::PowerFailure(time) => time.0 += delay,
         }
+        event
+    }
+
+    // Time at which the event has finished [s]
+    pub fn end_time(&self) -> f64 {
+        self.time() + self.closing_time()
     }
 
+    // Opening at a time for an event that starts from the opening steady_open
     pub fn open_percent(&self, time: f64, steady_open: f64 ) -> f64 {
         match self {
-            TransientEvent::None => 0.0,
+            TransientEvent::None => steady_open,
             TransientEvent::InstantaneousChange(value, event_time) => {
                 if time < event_time.0 {
                     steady_open
@@ -95,15 +113,19 @@ impl TransientEvent {
                     1.0
                 }
             },
-            TransientEvent::PumpShutdown(_,_,_) => 0.0,
-            TransientEvent::PumpStartup(_,_,_,_) => 0.0,
+            TransientEvent::PumpShutdown(_,_,_) => steady_open,
+            TransientEvent::PumpStartup(_,_,_,_) => steady_open,
+            TransientEvent::PowerFailure(_) => steady_open,
         }
     }
 
+    // Speed at a time for an event that starts from the speed steady_speed
     pub fn pump_speed(&self, time: f64, steady_speed: f64 ) -> f64 {
         match self {
             TransientEvent::None => steady_speed,
-            TransientEvent::InstantaneousChange(_,_) => steady_speed, //TODO: implement instantaneous change
+            TransientEvent::InstantaneousChange(value, event_time) => {
+                if time < event_time.0 { steady_speed } else { value.0 }
+            },
             TransientEvent::ValveClosure(_,_,_) => steady_speed,
             TransientEvent::ValveOpening(_,_,_) => steady_speed,
             TransientEvent::PumpShutdown(exponent, event_time, shutdown_time) => {
@@ -118,14 +140,49 @@ impl TransientEvent {
             },
             TransientEvent::PumpStartup( value, exponent, event_time, startup_time) => {
                 if time < event_time.0 {
-                    0.0
+                    steady_speed
                 } else if time < event_time.0 + startup_time.0 {
                     let tau = (time - event_time.0) / startup_time.0;
-                    value.0 * tau.powf( exponent.0 )
+                    steady_speed + ( value.0 - steady_speed ) * tau.powf( exponent.0 )
                 } else {
                     value.0
                 }
             },
+            // The run-down is computed by the solver from the pump inertia
+            TransientEvent::PowerFailure(_) => steady_speed,
         }
     }
-}
\ No newline at end of file
+}
+
+// Events of a component in time order
+pub fn in_order( events: &[TransientEvent] ) -> Vec<&TransientEvent> {
+    let mut ordered: Vec<&TransientEvent> = events.iter().collect();
+    ordered.sort_by( |a, b| a.time().total_cmp( &b.time() ) );
+    ordered
+}
+
+// Events of a component with the events spawned on it by rules in time order, each with the time
+// at which the next event takes over. The scheduled events follow one another while a spawned
+// event supersedes any event still in progress when it starts (and one spawned at the same time).
+pub fn timeline<'a>( events: &'a [TransientEvent], spawned: &'a [TransientEvent] ) -> Vec<(&'a TransientEvent, f64)> {
+    let mut ordered = in_order( events );
+    ordered.extend( spawned );
+    ordered.sort_by( |a, b| a.time().total_cmp( &b.time() ) );
+    let next: Vec<f64> = ordered.iter().skip( 1 ).map( |event| event.time() ).chain( [ f64::INFINITY ] ).collect();
+    ordered.into_iter().zip( next ).collect()
+}
+
+// Each event of a component starts from the state the previous one left, so an event may not 
+// start before the previous one has finished. The error gives the indices ( earlier, later ) of 
+// the first pair of overlapping events.
+pub fn check_timeline( events: &[TransientEvent] ) -> Result<(), (usize, usize)> {
+    let mut order: Vec<usize> = ( 0..events.len() ).collect();
+    order.sort_by( |a, b| events[*a].time().total_cmp( &events[*b].time() ) );
+    for pair in order.windows( 2 ) {
+        let ( earlier, later ) = ( &events[pair[0]], &events[pair[1]] );
+        if later.time() < earlier.end_time() || later.time() == earlier.time() {
+            return Err( ( pair[0], pair[1] ) );
+        }
+    }
+    Ok( () )
+}
@@ -57,6 +57,13 @@ impl Fluid {
         }
     }
 
+    pub fn vapour(&mut self) -> Option<&mut f64> {
+        match self {
+            Fluid::BasicFluid(fluid) => Some(&mut fluid.vapour),
+            Fluid::Water(_fluid) => None,
+        }
+    }
+
     pub fn temperature(&mut self) -> Option<&mut f64> {
         match self {
             Fluid::BasicFluid(_fluid) => None,
@@ -105,6 +112,13 @@ impl Fluid {
             Fluid::Water(fluid) => fluid.bulk_modulus(),
         }
     }
+
+    pub fn vapour_pressure(&self) -> f64 {
+        match self {
+            Fluid::BasicFluid(fluid) => fluid.vapour_pressure(),
+            Fluid::Water(fluid) => fluid.vapour_pressure(),
+        }
+    }
 }
 
 /*#[derive(serde::Deserialize, serde::Serialize)]
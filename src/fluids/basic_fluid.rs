@@ -3,6 +3,12 @@ pub struct BasicFluid {
     pub rho: f64,       // Density [kg/m^3]
     pub nu: f64,        // Kinematic viscosity [m^2/s]
     pub bulk: f64,      // Bulk modulus of elasticity [Pa]
+    #[serde(default = "default_vapour")]
+    pub vapour: f64,    // Vapour pressure [Pa]
+}
+
+fn default_vapour() -> f64 {
+    1705.0
 }
 
 impl Default for BasicFluid {
@@ -11,19 +17,21 @@ impl Default for BasicFluid {
             rho: 999.1,
             nu: 1.1385e-6,
             bulk: 2.15e9,
+            vapour: default_vapour(),
         }
     }
 }
 
 impl BasicFluid {
     pub fn new(rho: f64, nu: f64, bulk: f64) -> Self {
-        BasicFluid { rho, nu, bulk }
+        BasicFluid { rho, nu, bulk, vapour: default_vapour() }
     }
 
     pub fn reset_parameters(&mut self) {
         self.rho = 999.1;
         self.nu = 1.1385e-6;
         self.bulk = 2.15e9;
+        self.vapour = default_vapour();
     }
 
     pub fn density(&self) -> f64 {
@@ -37,4 +45,8 @@ impl BasicFluid {
     pub fn bulk_modulus(&self) -> f64 {
         self.bulk
     }
+
+    pub fn vapour_pressure(&self) -> f64 {
+        self.vapour
+    }
 }
\ No newline at end of file
@@ -7,6 +7,8 @@ pub struct Water {
     rho_data: Vec<f64>,         // Density data [kg/m^3]
     sound_data: Vec<f64>,       // Sound speed data [m/s]
     viscosity_data: Vec<f64>,   // Dynamic viscosity data [Pa s]
+    #[serde(default = "vapour_data")]
+    vapour_data: Vec<f64>,      // Vapour pressure data [Pa]
 }
 
 impl Default for Water {
@@ -17,6 +19,7 @@ impl Default for Water {
             rho_data: rho_data(),
             sound_data: sound_data(),
             viscosity_data: viscosity_data(),
+            vapour_data: vapour_data(),
         }
     }
 }
@@ -29,6 +32,7 @@ impl Water {
             rho_data: rho_data(),
             sound_data: sound_data(),
             viscosity_data: viscosity_data(),
+            vapour_data: vapour_data(),
         }
     }
 
@@ -60,12 +64,16 @@ impl Water {
         utility::interpolate( self.temperature, &self.temp_data, &self.sound_data )
     }
 
+    pub fn vapour_pressure(&self) -> f64 {
+        utility::interpolate( self.temperature, &self.temp_data, &self.vapour_data )
+    }
+
     pub fn max_temperature(&self) -> f64 {
-        self.temp_data.last().unwrap().clone()
+        *self.temp_data.last().unwrap()
     }
 
     pub fn min_temperature(&self) -> f64 {
-        self.temp_data.first().unwrap().clone()
+        *self.temp_data.first().unwrap()
     }
 }
 
@@ -293,4 +301,62 @@ fn viscosity_data() -> Vec<f64> {
         2.878e-4,
         2.818e-4,
     ]
-}
\ No newline at end of file
+}
+
+/* --- Saturation pressure ( IAPWS, Wagner & Pruss ) */
+
+fn vapour_data() -> Vec<f64> {
+    vec![
+        611.2,
+        706.0,
+        813.5,
+        935.3,
+        1073.0,
+        1228.0,
+        1403.0,
+        1599.0,
+        1819.0,
+        2065.0,
+        2339.0,
+        2645.0,
+        2986.0,
+        3364.0,
+        3783.0,
+        4247.0,
+        4760.0,
+        5325.0,
+        5948.0,
+        6633.0,
+        7385.0,
+        8210.0,
+        9113.0,
+        10100.0,
+        11180.0,
+        12350.0,
+        13630.0,
+        15020.0,
+        16530.0,
+        18170.0,
+        19950.0,
+        21870.0,
+        23940.0,
+        26180.0,
+        28600.0,
+        31200.0,
+        34000.0,
+        37010.0,
+        40240.0,
+        43700.0,
+        47420.0,
+        51390.0,
+        55640.0,
+        60170.0,
+        65020.0,
+        70180.0,
+        75690.0,
+        81540.0,
+        87770.0,
+        94390.0,
+        101400.0,
+    ]
+}
@@ -0,0 +1,163 @@
+//! Friction models
+//!
+//! A `FrictionModel` gives the Darcy friction factor of a pipe or bend and its derivative with
+//! respect to the flow rate, which the steady and transient solvers use in their Jacobians. Pipes
+//! and bends follow the default of the solver unless they are given a model of their own.
+//!
+//! The Colebrook-White family (including the explicit approximations) uses the laminar friction
+//! factor below a Reynolds number of 2100 and a smooth transition up to 3000. Hazen-Williams and
+//! Manning are empirical SI formulas for water with standard gravity and apply at any flow rate.
+
+use std::f64::consts::{ LN_10, PI };
+
+const LAMINAR: f64 = 2100.0;            // Reynolds number below which the flow is laminar
+const TURBULENT: f64 = 3000.0;          // Reynolds number above which the flow is turbulent
+const G: f64 = 9.80665;                 // Standard gravity of the empirical formulas [m/s^2]
+const COLEBROOK_TOLERANCE: f64 = 1.0e-12;
+const COLEBROOK_ITERATIONS: usize = 50;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum FrictionModel {
+    #[default]
+    Default,                            // The solver default
+    PraksBrkic,                         // Explicit approximation of Colebrook-White
+    ColebrookWhite,                     // Iterated
+    SwameeJain,
+    Haaland,
+    HazenWilliams { c: f64 },           // C factor
+    Manning { n: f64 },                 // [s/m^(1/3)]
+    Fixed { f: f64 },                   // Darcy friction factor
+}
+
+impl FrictionModel {
+    pub fn text(&self) -> String {
+        match self {
+            FrictionModel::Default => "Default".to_string(),
+            FrictionModel::PraksBrkic => "Praks-Brkic".to_string(),
+            FrictionModel::ColebrookWhite => "Colebrook-White".to_string(),
+            FrictionModel::SwameeJain => "Swamee-Jain".to_string(),
+            FrictionModel::Haaland => "Haaland".to_string(),
+            FrictionModel::HazenWilliams { .. } => "Hazen-Williams".to_string(),
+            FrictionModel::Manning { .. } => "Manning".to_string(),
+            FrictionModel::Fixed { .. } => "Fixed".to_string(),
+        }
+    }
+
+    // The model to use with a default in place of Default (Praks-Brkic when both are Default)
+    pub fn or(&self, default: FrictionModel ) -> FrictionModel {
+        match ( self, default ) {
+            ( FrictionModel::Default, FrictionModel::Default ) => FrictionModel::PraksBrkic,
+            ( FrictionModel::Default, default ) => default,
+            ( model, _ ) => *model,
+        }
+    }
+
+    pub fn friction_factor(&self, flow_rate: f64, diameter: f64, roughness: f64, nu: f64 ) -> f64 {
+        self.friction( flow_rate, diameter, roughness, nu ).0
+    }
+
+    // Darcy friction factor and its derivative with respect to the flow rate for the magnitude
+    // of a volume flow rate [m^3/s] in a pipe with a diameter [m] and roughness [m]
+    pub fn friction(&self, flow_rate: f64, diameter: f64, roughness: f64, nu: f64 ) -> (f64, f64) {
+        let area = PI * diameter * diameter / 4.0;
+        let q = flow_rate.abs();
+        let reynolds = q * diameter / ( area * nu );
+        match *self {
+            FrictionModel::HazenWilliams { c } => {
+                // Head loss per unit length 10.67 q^1.852 / ( c^1.852 d^4.8704 )
+                let f = 10.67 * 2.0 * G * diameter * area * area * q.powf( -0.148 ) / ( c.powf( 1.852 ) * diameter.powf( 4.8704 ) );
+                ( f, -0.148 * f / q )
+            },
+            FrictionModel::Manning { n } => ( 2.0 * G * diameter * n * n / ( 0.25 * diameter ).powf( 4.0 / 3.0 ), 0.0 ),
+            FrictionModel::Fixed { f } => ( f, 0.0 ),
+            _ => {
+                let ( f, dfdre ) = self.darcy( roughness / diameter, reynolds );
+                ( f, dfdre * diameter / ( area * nu ) )
+            },
+        }
+    }
+
+    // Darcy friction factor of the Colebrook-White family and its derivative with respect to the
+    // Reynolds number for a relative roughness (Praks-Brkic for the empirical models)
+    pub fn darcy(&self, relative: f64, reynolds: f64 ) -> (f64, f64) {
+        if reynolds < LAMINAR {
+            ( 64.0 / reynolds, - 64.0 / ( reynolds * reynolds ) )
+        } else if reynolds > TURBULENT {
+            match self {
+                FrictionModel::ColebrookWhite => colebrook_white( relative, reynolds ),
+                FrictionModel::SwameeJain => swamee_jain( relative, reynolds ),
+                FrictionModel::Haaland => haaland( relative, reynolds ),
+                _ => praks_brkic( relative, reynolds ),
+            }
+        } else {
+            transition( relative, reynolds )
+        }
+    }
+}
+
+// Praks and Brkic (2018) approximation of Colebrook-White with its derivative with respect to
+// the Reynolds number
+fn praks_brkic( relative: f64, reynolds: f64 ) -> (f64, f64) {
+    let a = reynolds * relative / 8.0897;
+    let b = reynolds.ln() - 0.779626;
+    let x = a + b;
+    let c = x.ln();
+    let d = x - 0.5588 * c + 1.2079;
+    let k = 0.8685972 * ( b - c + ( c / d ) );
+    let dx = relative / 8.0897 + 1.0 / reynolds;
+    let dc = dx / x;
+    let dd = dx - 0.5588 * dc;
+    let dk = 0.8685972 * ( 1.0 / reynolds - dc + ( dc * d - c * dd ) / ( d * d ) );
+    ( 1.0 / ( k * k ), - 2.0 * dk / ( k * k * k ) )
+}
+
+// Colebrook-White solved by Newton iteration for s = 1 / sqrt( f ) starting from Swamee-Jain
+fn colebrook_white( relative: f64, reynolds: f64 ) -> (f64, f64) {
+    let a = relative / 3.7;
+    let b = 2.51 / reynolds;
+    let mut s = 1.0 / swamee_jain( relative, reynolds ).0.sqrt();
+    for _ in 0..COLEBROOK_ITERATIONS {
+        let x = a + b * s;
+        let residual = s + 2.0 * x.log10();
+        let slope = 1.0 + 2.0 * b / ( x * LN_10 );
+        s -= residual / slope;
+        if ( residual / slope ).abs() < COLEBROOK_TOLERANCE * s { break; }
+    }
+    // Implicit derivative of s + 2 log10( a + b s ) = 0
+    let x = a + b * s;
+    let ds = ( 2.0 * b * s / ( reynolds * x * LN_10 ) ) / ( 1.0 + 2.0 * b / ( x * LN_10 ) );
+    ( 1.0 / ( s * s ), - 2.0 * ds / ( s * s * s ) )
+}
+
+fn swamee_jain( relative: f64, reynolds: f64 ) -> (f64, f64) {
+    let x = relative / 3.7 + 5.74 / reynolds.powf( 0.9 );
+    let l = x.log10();
+    let dl = - 0.9 * 5.74 / ( reynolds.powf( 1.9 ) * x * LN_10 );
+    ( 0.25 / ( l * l ), - 0.5 * dl / ( l * l * l ) )
+}
+
+fn haaland( relative: f64, reynolds: f64 ) -> (f64, f64) {
+    let x = ( relative / 3.7 ).powf( 1.11 ) + 6.9 / reynolds;
+    let y = - 1.8 * x.log10();
+    let dy = 1.8 * 6.9 / ( reynolds * reynolds * x * LN_10 );
+    ( 1.0 / ( y * y ), - 2.0 * dy / ( y * y * y ) )
+}
+
+// Churchill (1977) between laminar and turbulent flow
+fn transition( relative: f64, reynolds: f64 ) -> (f64, f64) {
+    let k1 = ( 64.0 / reynolds ).powi( 12 );
+    let c = 1.0 / ( ( 0.833 * reynolds.powf( 1.282 ) / reynolds.powf( 1.007 ) ) + ( 0.27 * relative )
+        + ( 110.0 * relative / reynolds ) );
+    let a = 0.8687 * ( c.powi( 16 ) ).ln();
+    let b = ( 13269.0 / reynolds ).powi( 16 );
+    let k2 = ( a + b ).powf( -1.5 );
+    let exponent = 0.08333333333;
+    let f = ( k1 + k2 ).powf( exponent );
+    let dk1 = - 12.0 * k1 / reynolds;
+    let dc = - c * c * ( 0.833 * 0.275 * reynolds.powf( -0.725 ) - 110.0 * relative / ( reynolds * reynolds ) );
+    let da = 0.8687 * 16.0 * dc / c;
+    let db = - 16.0 * b / reynolds;
+    let dk2 = - 1.5 * ( a + b ).powf( -2.5 ) * ( da + db );
+    ( f, exponent * ( k1 + k2 ).powf( exponent - 1.0 ) * ( dk1 + dk2 ) )
+}
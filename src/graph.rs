@@ -1,15 +1,70 @@
 use ohsl::{vector::Vec64, matrix::Mat64};
-use std::collections::HashMap;
+use std::collections::{ HashMap, HashSet };
 
 use crate::node::Node;
-use crate::edge::Edge;
+use crate::edge::{ Edge, EdgeContext, EdgeState, MAX_REACHES };
 use crate::fluid::Fluid;
+use crate::sparse::SparseMatrix;
+use crate::events;
+use crate::rules::{ Rule, RuleState, Condition, Target };
+use crate::edges::control_valve::{ ControlMode, ControlStatus };
+use crate::series::SeriesError;
+use crate::solver::Component;
+
+// Problems found in a network by Graph::validate, or by the solver at the start of a transient
+// run (edges are identified by their index)
+#[derive(Clone, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
+pub enum Diagnostic {
+    DuplicateNodeId { id: usize },                  // More than one node has this id
+    DanglingEdge { edge: usize, id: usize },        // Edge refers to a node id that does not exist
+    NoHeadReference { nodes: Vec<usize> },          // Connected nodes without a pressure or tank node
+    ZeroLength { edge: usize },                     // Pipe or bend with zero (or negative) length
+    ZeroDiameter { edge: usize },                   // Pipe or bend with zero (or negative) diameter
+    NonMonotoneValveTable { edge: usize },          // Valve data not in increasing order
+    EmptyPumpCurve { edge: usize },                 // Pump head or torque data missing
+    OverlappingNodeEvents { id: usize, events: (usize, usize) },    // Event starts before the previous one ends
+    OverlappingEdgeEvents { edge: usize, events: (usize, usize) },  // Event starts before the previous one ends
+    InvalidRule { rule: usize },                    // Rule refers to a missing or unsuitable node or edge
+    InvalidSetpoint { edge: usize },                // Control valve set on a known head or with no setpoint
+    TooManyReaches { edge: usize, reaches: usize }, // Pipe or bend split into more than MAX_REACHES
+    InvalidSeries { component: Component, error: SeriesError },     // Time series that cannot be evaluated
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Diagnostic::DuplicateNodeId { id } => write!(f, "Duplicate node id {}", id),
+            Diagnostic::DanglingEdge { edge, id } => write!(f, "Edge {} refers to unknown node id {}", edge, id),
+            Diagnostic::NoHeadReference { nodes } => write!(f, "Nodes {:?} have no pressure or tank boundary", nodes),
+            Diagnostic::ZeroLength { edge } => write!(f, "Edge {} has zero length", edge),
+            Diagnostic::ZeroDiameter { edge } => write!(f, "Edge {} has zero diameter", edge),
+            Diagnostic::NonMonotoneValveTable { edge } => write!(f, "Edge {} has valve data that is not increasing", edge),
+            Diagnostic::EmptyPumpCurve { edge } => write!(f, "Edge {} has missing pump curve data", edge),
+            Diagnostic::OverlappingNodeEvents { id, events } => 
+                write!(f, "Node {} has event {} starting before event {} has finished", id, events.1, events.0),
+            Diagnostic::OverlappingEdgeEvents { edge, events } => 
+                write!(f, "Edge {} has event {} starting before event {} has finished", edge, events.1, events.0),
+            Diagnostic::InvalidRule { rule } => write!(f, "Rule {} refers to a missing or unsuitable node or edge", rule),
+            Diagnostic::InvalidSetpoint { edge } => write!(f, "Edge {} has a setpoint it cannot control", edge),
+            Diagnostic::TooManyReaches { edge, reaches } => 
+                write!(f, "Edge {} needs {} reaches, more than the limit of {}", edge, reaches, MAX_REACHES),
+            Diagnostic::InvalidSeries { component, error } => match component {
+                Component::Node( id ) => write!(f, "Node {} has an invalid time series: {}", id, error),
+                Component::Edge( from, to ) => write!(f, "Edge ( {}, {} ) has an invalid time series: {}", from, to, error),
+            },
+        }
+    }
+}
 
 #[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
 pub struct Graph {
     pub nodes: Vec<Node>,
     pub edges: Vec<Edge>,
-    pub id_to_index: HashMap<usize, usize>
+    pub id_to_index: HashMap<usize, usize>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(skip)]
+    spawned: HashMap<Target, Vec<events::TransientEvent>>,  // Events spawned by the rules in the current run
 }
 
 impl Graph {
@@ -17,7 +72,7 @@ impl Graph {
         let nodes: Vec<Node> = Vec::new();
         let edges: Vec<Edge> = Vec::new();
         let id_to_index: HashMap<usize, usize> = HashMap::new();
-        Graph { nodes, edges, id_to_index }
+        Graph { nodes, edges, id_to_index, rules: Vec::new(), spawned: HashMap::new() }
     }
 
     pub fn nodes(&self) -> Vec<Node> {
@@ -63,6 +118,158 @@ impl Graph {
         self.id_to_index.get(&id).copied().unwrap()
     }
 
+    // Check the network for problems that prevent it from being solved
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        // Node ids must be unique
+        let mut index: HashMap<usize, usize> = HashMap::new();
+        let mut duplicates: HashSet<usize> = HashSet::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            let id = node.id();
+            if index.insert( id, i ).is_some() && duplicates.insert( id ) {
+                diagnostics.push( Diagnostic::DuplicateNodeId { id } );
+            }
+        }
+
+        // Edges must connect existing nodes
+        let mut parent: Vec<usize> = ( 0..self.num_nodes() ).collect();
+        for (j, edge) in self.edges.iter().enumerate() {
+            let (ifrom, ito) = edge.id();
+            let from = index.get( &ifrom ).copied();
+            let to = index.get( &ito ).copied();
+            for (id, i) in [ (ifrom, from), (ito, to) ] {
+                if i.is_none() {
+                    diagnostics.push( Diagnostic::DanglingEdge { edge: j, id } );
+                }
+            }
+            if let ( Some( a ), Some( b ) ) = ( from, to ) {
+                let ( ra, rb ) = ( find_root( &mut parent, a ), find_root( &mut parent, b ) );
+                parent[ra] = rb;
+            }
+        }
+
+        // Every connected part of the network needs a known head
+        let mut islands: Vec<Vec<usize>> = Vec::new();
+        let mut island_index: HashMap<usize, usize> = HashMap::new();
+        let mut referenced: Vec<bool> = Vec::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if duplicates.contains( &node.id() ) && index[&node.id()] != i { continue; }
+            let root = find_root( &mut parent, i );
+            let k = *island_index.entry( root ).or_insert_with( || {
+                islands.push( Vec::new() );
+                referenced.push( false );
+                islands.len() - 1
+            });
+            islands[k].push( node.id() );
+            referenced[k] |= node.is_known_pressure() || node.is_tank();
+        }
+        for (k, nodes) in islands.into_iter().enumerate() {
+            if !referenced[k] {
+                diagnostics.push( Diagnostic::NoHeadReference { nodes } );
+            }
+        }
+
+        // Component data
+        for (j, edge) in self.edges.iter().enumerate() {
+            let ( length, diameter ) = match edge {
+                Edge::Pipe(pipe) => ( pipe.length, pipe.diameter ),
+                Edge::Bend(bend) => ( bend.length(), bend.diameter ),
+                _ => ( 1.0, 1.0 ),
+            };
+            if length <= 0.0 { diagnostics.push( Diagnostic::ZeroLength { edge: j } ); }
+            if diameter <= 0.0 { diagnostics.push( Diagnostic::ZeroDiameter { edge: j } ); }
+            let valve_data = match edge {
+                Edge::ReliefValve(valve) => increasing( &valve.invk ) && increasing( &valve.open_dp ),
+                Edge::Valve(valve) => increasing( &valve.invk ),
+                Edge::CheckValve(valve) => increasing( &valve.invk ),
+                Edge::SafetyValve(valve) => increasing( &valve.invk ),
+                Edge::BurstingDisk(disk) => increasing( &disk.invk ),
+                Edge::ControlValve(valve) => increasing( &valve.invk ),
+                _ => true,
+            };
+            if !valve_data { diagnostics.push( Diagnostic::NonMonotoneValveTable { edge: j } ); }
+            // A control valve cannot set the pressure of a node with a known head
+            if let Edge::ControlValve(valve) = edge {
+                let controlled = match valve.mode {
+                    ControlMode::PressureReducing => index.get( &valve.to ),
+                    ControlMode::PressureSustaining => index.get( &valve.from ),
+                    ControlMode::FlowControl => None,
+                };
+                let fixed = controlled.is_some_and( |i| self.nodes[*i].is_known_pressure() || self.nodes[*i].is_tank() );
+                if fixed || valve.setpoint <= 0.0 { diagnostics.push( Diagnostic::InvalidSetpoint { edge: j } ); }
+            }
+            if let Edge::Pump(pump) = edge {
+                if pump.head_data.len() < 2 || pump.torque_data.len() < 2 {
+                    diagnostics.push( Diagnostic::EmptyPumpCurve { edge: j } );
+                }
+            }
+        }
+
+        // Events of a component apply one after another
+        for node in self.nodes.iter() {
+            let events = match node {
+                Node::Pressure(node) => &node.events,
+                Node::Flow(node) => &node.events,
+                _ => continue,
+            };
+            if let Err( events ) = events::check_timeline( events ) {
+                diagnostics.push( Diagnostic::OverlappingNodeEvents { id: node.id(), events } );
+            }
+        }
+        for (j, edge) in self.edges.iter().enumerate() {
+            let events = match edge {
+                Edge::Valve(valve) => &valve.events,
+                Edge::Pump(pump) => &pump.events,
+                _ => continue,
+            };
+            if let Err( events ) = events::check_timeline( events ) {
+                diagnostics.push( Diagnostic::OverlappingEdgeEvents { edge: j, events } );
+            }
+        }
+
+        // Time series must have increasing times within their period
+        for node in self.nodes.iter() {
+            let series = match node {
+                Node::Pressure(node) => &node.series,
+                Node::Flow(node) => &node.series,
+                _ => continue,
+            };
+            if let Some( Err( error ) ) = series.as_ref().map( |series| series.check() ) {
+                diagnostics.push( Diagnostic::InvalidSeries { component: Component::Node( node.id() ), error } );
+            }
+        }
+        for edge in self.edges.iter() {
+            let series = match edge {
+                Edge::Valve(valve) => &valve.series,
+                Edge::Pump(pump) => &pump.series,
+                _ => continue,
+            };
+            if let Some( Err( error ) ) = series.as_ref().map( |series| series.check() ) {
+                let (from, to) = edge.id();
+                diagnostics.push( Diagnostic::InvalidSeries { component: Component::Edge( from, to ), error } );
+            }
+        }
+
+        // Rules read values from existing nodes and edges and add events to ones that have them
+        let node = |id: usize| index.get( &id ).map( |i| &self.nodes[*i] );
+        let edge = |from: usize, to: usize| self.edges.iter().find( |edge| edge.id() == ( from, to ) );
+        for (r, rule) in self.rules.iter().enumerate() {
+            let conditions = rule.conditions.iter().all( |condition| match condition {
+                Condition::Pressure { id, .. } | Condition::Head { id, .. } => node( *id ).is_some(),
+                Condition::Level { id, .. } => node( *id ).is_some_and( |node| node.is_tank() ),
+                Condition::Flow { from, to, .. } => edge( *from, *to ).is_some(),
+                Condition::Time { .. } => true,
+            });
+            let actions = rule.actions.iter().all( |action| match action.target {
+                Target::Node { id } => node( id ).is_some_and( |node| node.clone().events().is_some() ),
+                Target::Edge { from, to } => edge( from, to ).is_some_and( |edge| edge.clone().events().is_some() ),
+            });
+            if !conditions || !actions { diagnostics.push( Diagnostic::InvalidRule { rule: r } ); }
+        }
+        diagnostics
+    }
+
     pub fn k_matrix(&self) -> Mat64 {
         let (m, n) = ( self.num_edges(), self.num_nodes() );
         let mut mat = Mat64::new( m, n, 0.0 );
@@ -81,11 +288,34 @@ impl Graph {
         mat
     }
 
+    // Return the (from, to) node indices of each edge
+    pub fn edge_indices(&self) -> Vec<(usize, usize)> {
+        self.edges.iter().map( |edge| {
+            let (ifrom, ito) = edge.id();
+            ( self.index(ifrom), self.index(ito) )
+        }).collect()
+    }
+
+    // Return the matrix K in sparse format
+    pub fn k_sparse(&self) -> SparseMatrix {
+        let mut triplets = Vec::with_capacity( 2 * self.num_edges() );
+        for (i, (from, to)) in self.edge_indices().into_iter().enumerate() {
+            triplets.push( ( i, from, 1.0 ) );
+            triplets.push( ( i, to, -1.0 ) );
+        }
+        SparseMatrix::from_triplets( self.num_edges(), self.num_nodes(), &triplets )
+    }
+
+    // Return the incidence matrix K^T in sparse format
+    pub fn incidence_sparse(&self) -> SparseMatrix {
+        self.k_sparse().transpose()
+    }
+
     pub fn kplus_matrix(&self) -> Mat64 {
         let (m, n) = ( self.num_edges(), self.num_nodes() );
         let mut mat = Mat64::new( m, n, 0.0 );
         for i in 0..m {
-            let ifrom = self.edges[i].from().id();
+            let ifrom = self.edges[i].from();
             mat[i][self.index(ifrom)] = 1.0;
         }
         mat
@@ -95,7 +325,7 @@ impl Graph {
         let (m, n) = ( self.num_edges(), self.num_nodes() );
         let mut mat = Mat64::new( m, n, 0.0 );
         for i in 0..m {
-            let ito = self.edges[i].to().id();
+            let ito = self.edges[i].to();
             mat[i][self.index(ito)] = 1.0;
         }
         mat
@@ -110,26 +340,13 @@ impl Graph {
         m
     }
 
-    fn mult_m_diag(&mut self, mut kmat: Mat64, fluid: &Fluid, g: f64 ) -> Mat64{
-        let m = self.num_edges();
-        let m_diag = self.m_diag( fluid, g );
-        for j in 0..m {
-            kmat[j] = m_diag[j] * kmat[j].clone();
-        }
-        kmat
-    }
-
     // Return the diagonal matrix D = K+^T M K+ + K-^T M K- 
     pub fn d_diag(&mut self, fluid: &Fluid, g: f64 ) -> Vec64 {
         let mut d = Vec64::new( self.num_nodes(), 0.0 );
-        let ( mut kplus, mut kminus ) = ( self.kplus_matrix(), self.kminus_matrix() );
-        let ( kplust, kminust ) = ( kplus.transpose(), kminus.transpose() );
-        kplus = self.mult_m_diag( kplus, fluid, g );
-        let plus = kplust * kplus;
-        kminus = self.mult_m_diag( kminus, fluid, g );
-        let minus = kminust * kminus;
-        for i in 0..self.num_nodes() {
-            d[i] = plus[i][i] + minus[i][i];
+        let m_diag = self.m_diag( fluid, g );
+        for (j, (from, to)) in self.edge_indices().into_iter().enumerate() {
+            d[from] += m_diag[j];
+            d[to] += m_diag[j];
         }
         d
     }
@@ -243,17 +460,107 @@ impl Graph {
         }
     }
 
+    // Extend the node and edge values for a time step of dt ending at time (edges see the nodes at
+    // their ends and their run state, one for each edge)
+    pub fn add_transient_values(&mut self, time: f64, dt: f64, states: &mut [EdgeState] ) {
+        let spawned = |target: Target| self.spawned.get( &target ).map_or( &[][..], |events| events.as_slice() );
+        for node in self.nodes.iter_mut() {
+            let id = node.id();
+            node.add_transient_value( time, spawned( Target::Node { id } ) );
+        }
+        let ( nodes, index ) = ( &self.nodes, &self.id_to_index );
+        for (edge, state) in self.edges.iter_mut().zip( states.iter_mut() ) {
+            let ( from, to ) = edge.id();
+            let context = EdgeContext { from: &nodes[ index[&from] ], to: &nodes[ index[&to] ] };
+            edge.add_transient_value( time, dt, spawned( Target::Edge { from, to } ), context, state );
+        }
+    }
+
+    // Set the steady values of the nodes and edges with a time series from its value at t = 0
+    pub fn start_series(&mut self) {
+        for node in self.nodes.iter_mut() {
+            node.start_series();
+        }
+        for edge in self.edges.iter_mut() {
+            edge.start_series();
+        }
+    }
+
+    // Check the rules with the solution at a step and add the events of the rules that fire (the
+    // rules see their run state, one for each rule). Returns the indices of the rules that fired.
+    pub fn apply_rules(&mut self, time: f64, step: usize, rho: f64, g: f64, states: &mut [RuleState] ) -> Vec<usize> {
+        if self.rules.is_empty() { return vec![]; }
+        let ( q, h ) = self.current_solution_qh( rho, g, step );
+        let mut fired = vec![];
+        for (r, state) in states.iter_mut().enumerate().take( self.rules.len() ) {
+            let holds = self.rules[r].conditions.iter().all( |condition| {
+                let value = match condition {
+                    Condition::Pressure { id, .. } => self.id_to_index.get( id ).map( |i| self.nodes[*i].pressure_at( step ) ),
+                    Condition::Head { id, .. } => self.id_to_index.get( id ).map( |i| h[*i] ),
+                    Condition::Level { id, .. } => match self.id_to_index.get( id ).map( |i| &self.nodes[*i] ) {
+                        Some( Node::Tank( tank ) ) => tank.level.get( step ).or( tank.level.last() ).copied(),
+                        _ => None,
+                    },
+                    Condition::Flow { from, to, .. } => self.edges.iter()
+                        .position( |edge| edge.id() == ( *from, *to ) ).map( |j| q[j] * rho ),
+                    Condition::Time { .. } => Some( time ),
+                };
+                value.is_some_and( |value| condition.comparison().holds( value, condition.threshold() ) )
+            });
+            if !self.rules[r].update( state, time, holds ) { continue; }
+            fired.push( r );
+            for action in self.rules[r].actions.clone() {
+                self.spawned.entry( action.target ).or_default().push( action.event.delayed( time ) );
+            }
+        }
+        fired
+    }
+
+    // Events spawned on a node or edge by the rules in the current run (kept apart from its own
+    // events so they are neither validated nor saved)
+    pub fn spawned(&self, target: Target ) -> &[events::TransientEvent] {
+        self.spawned.get( &target ).map_or( &[], |events| events.as_slice() )
+    }
+
+    // Remove the events spawned by the rules in a previous run
+    pub fn clear_spawned(&mut self) {
+        self.spawned.clear();
+    }
+
+    // Pumps that have lost power at a time
+    pub fn power_failures(&self, time: f64 ) -> Vec<bool> {
+        self.edges.iter().map( |edge| match edge {
+            Edge::Pump(pump) => pump.power_failed( time, self.spawned( Target::Edge { from: pump.from, to: pump.to } ) ),
+            _ => false,
+        }).collect()
+    }
+
+    // Indices of the control valves (their openings are extra unknowns in the steady solver)
+    pub fn control_indices(&self) -> Vec<usize> {
+        ( 0..self.num_edges() ).filter( |&j| matches!( self.edges[j], Edge::ControlValve(_) ) ).collect()
+    }
+
+    // Set the openings and statuses of the control valves at a step from the steady solver
+    pub fn set_control_states(&mut self, step: usize, states: &[(f64, ControlStatus)] ) {
+        for (j, state) in self.control_indices().into_iter().zip( states ) {
+            if let Edge::ControlValve(valve) = &mut self.edges[j] {
+                valve.open_percent[step] = state.0;
+                valve.status = state.1;
+            }
+        }
+    }
+
     pub fn add_boundary_value( &mut self, id: usize, value: f64 ) {
         let index = self.index(id);
         self.nodes[index].add_boundary_value( value );
     }
 
-    // TODO check doesn't already exist
+    // Duplicate ids are reported by validate()
     pub fn add_node(&mut self, node: Node ) {
         self.nodes.push( node );
     }
 
-    // TODO check doesn't already exist
+    // Edges to missing nodes are reported by validate()
     pub fn add_edge(&mut self, edge: Edge ) {
         self.edges.push( edge );
     }
@@ -265,10 +572,8 @@ impl Graph {
     }
 
     pub fn update_edge(&mut self, edge: Edge ) {
-        let from_id = edge.from().id();
-        let to_id = edge.to().id();
-        let result = self.edges.iter().position(|edge| 
-            (*edge).from().id() == from_id && (*edge).to().id() == to_id );
+        let id = edge.id();
+        let result = self.edges.iter().position(|edge| (*edge).id() == id );
         if let Some( index ) = result { self.edges[ index ] = edge; }
     }
 
@@ -286,4 +591,17 @@ impl Graph {
         }
     }
 
-}
\ No newline at end of file
+}
+
+fn find_root( parent: &mut [usize], mut i: usize ) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+// True if the first values of the table are strictly increasing and the second are non-decreasing
+fn increasing( data: &[(f64, f64)] ) -> bool {
+    data.len() >= 2 && data.windows( 2 ).all( |pair| pair[1].0 > pair[0].0 && pair[1].1 >= pair[0].1 )
+}
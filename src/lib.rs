@@ -13,6 +13,16 @@ pub mod solver;
 pub mod utility;
 pub mod location;
 pub mod events;
+pub mod series;
+pub mod rules;
+pub mod friction;
+pub mod sparse;
+pub mod characteristics;
+pub mod profile;
+pub mod epanet;
+pub mod project;
+pub mod results;
+pub mod report;
 
 //Re-exports ???
 pub use self::fluid::Fluid;
@@ -24,9 +34,9 @@ pub use self::edge::Edge;
 pub use self::edges::{
     pipe::Pipe, valve::Valve, pump::Pump
 };
-pub use self::graph::Graph;
+pub use self::graph::{ Graph, Diagnostic };
 pub use self::solver::{ 
-    Solver, SolverType
+    Solver, SolverType, SolverError
 };
 
 
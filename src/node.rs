@@ -4,10 +4,13 @@ use crate::nodes::{
     connection::Connection,
     hidden::Hidden,
     tank::Tank,
+    air_valve::AirValve,
+    accumulator::Accumulator,
 };
 use crate::location::Location;
 use crate::utility;
 use crate::events::TransientEvent;
+use crate::series::TimeSeries;
 
 
 #[derive(Clone, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
@@ -17,6 +20,8 @@ pub enum Node {
     Connection(Connection),     // Connection Node
     Hidden(Hidden),             // Hidden Node
     Tank(Tank),                 // Tank (surge tank)
+    AirValve(AirValve),         // Air release / vacuum breaker valve
+    Accumulator(Accumulator),   // Closed air vessel
 }
 
 impl Default for Node {
@@ -33,6 +38,8 @@ impl std::fmt::Display for Node {
             Node::Connection(_node) => write!(f, "Connection"),
             Node::Hidden(_node) => write!(f, "Hidden"),
             Node::Tank(_node) => write!(f, "Tank"),
+            Node::AirValve(_node) => write!(f, "Air Valve"),
+            Node::Accumulator(_node) => write!(f, "Accumulator"),
         }
     }
 }
@@ -46,6 +53,8 @@ impl Node {
             Node::Connection(node) => node.id,
             Node::Hidden(node) => node.id,
             Node::Tank(node) => node.id,
+            Node::AirValve(node) => node.id,
+            Node::Accumulator(node) => node.id,
         }
     }
 
@@ -65,6 +74,14 @@ impl Node {
         matches!(self, Node::Tank(_node))  
     }
 
+    pub fn is_air_valve(&self) -> bool {
+        matches!(self, Node::AirValve(_node))  
+    }
+
+    pub fn is_accumulator(&self) -> bool {
+        matches!(self, Node::Accumulator(_node))  
+    }
+
     //TODO this should probably return an option
     pub fn area(&self) -> f64 {
         match self {
@@ -76,6 +93,7 @@ impl Node {
     pub fn diameter(&mut self) -> Option<&mut f64> {
         match self {
             Node::Tank(node) => Some( &mut node.diameter ),
+            Node::Accumulator(node) => Some( &mut node.diameter ),
             _ => None,
         }
     }
@@ -90,6 +108,7 @@ impl Node {
     pub fn z_max(&mut self) -> Option<&mut f64> {
         match self {
             Node::Tank(node) => Some( &mut node.z_max ),
+            Node::Accumulator(node) => Some( &mut node.z_max ),
             _ => None,
         }
     }
@@ -97,6 +116,21 @@ impl Node {
     pub fn z_min(&mut self) -> Option<&mut f64> {
         match self {
             Node::Tank(node) => Some( &mut node.z_min ),
+            Node::Accumulator(node) => Some( &mut node.z_min ),
+            _ => None,
+        }
+    }
+
+    pub fn air_volume(&mut self) -> Option<&mut Vec<f64>> {
+        match self {
+            Node::AirValve(node) => Some( &mut node.air_volume ),
+            _ => None,
+        }
+    }
+
+    pub fn liquid_volume(&mut self) -> Option<&mut Vec<f64>> {
+        match self {
+            Node::Accumulator(node) => Some( &mut node.liquid_volume ),
             _ => None,
         }
     }
@@ -108,6 +142,8 @@ impl Node {
             Node::Connection(node) => &mut node.elevation,
             Node::Hidden(node) => &mut node.elevation, //TODO should be None
             Node::Tank(node) => &mut node.elevation,
+            Node::AirValve(node) => &mut node.elevation,
+            Node::Accumulator(node) => &mut node.elevation,
         }
     }
 
@@ -118,6 +154,8 @@ impl Node {
             Node::Connection(node) => &mut node.pressure,
             Node::Hidden(node) => &mut node.pressure,
             Node::Tank(node) => &mut node.pressure,
+            Node::AirValve(node) => &mut node.pressure,
+            Node::Accumulator(node) => &mut node.pressure,
         }
     }
 
@@ -129,6 +167,18 @@ impl Node {
         *self.pressure().last().unwrap()
     }
 
+    pub fn pressure_at(&self, step: usize) -> f64 {
+        match self {
+            Node::Pressure(node) => node.pressure[step],
+            Node::Flow(node) => node.pressure[step],
+            Node::Connection(node) => node.pressure[step],
+            Node::Hidden(node) => node.pressure[step],
+            Node::Tank(node) => node.pressure[step],
+            Node::AirValve(node) => node.pressure[step],
+            Node::Accumulator(node) => node.pressure[step],
+        }
+    }
+
     pub fn consumption(&mut self) -> &mut Vec<f64> {
         match self {
             Node::Pressure(node) => &mut node.consumption,
@@ -136,6 +186,8 @@ impl Node {
             Node::Connection(node) => &mut node.consumption,
             Node::Hidden(node) => &mut node.consumption,
             Node::Tank(node) => &mut node.consumption,
+            Node::AirValve(node) => &mut node.consumption,
+            Node::Accumulator(node) => &mut node.consumption,
         }
     }
 
@@ -193,6 +245,23 @@ impl Node {
         }
     }
 
+    // Boundary value against time (pressure [Pa] or consumption [kg/s])
+    pub fn series(&mut self) -> Option<&mut Option<TimeSeries>> {
+        match self {
+            Node::Pressure(node) => Some(&mut node.series),
+            Node::Flow(node) => Some(&mut node.series),
+            _ => None,
+        }
+    }
+
+    pub fn start_series(&mut self) {
+        match self {
+            Node::Pressure(node) => node.start_series(),
+            Node::Flow(node) => node.start_series(),
+            _ => (),
+        }
+    }
+
     //TODO do we need this???
     pub fn create_transient_values(&mut self, tnodes: &[f64] ) {
         match self {
@@ -201,16 +270,20 @@ impl Node {
             Node::Connection(node) => node.create_transient_values( tnodes ),
             Node::Hidden(_node) => (),
             Node::Tank(_node) => (),
+            Node::AirValve(_node) => (),
+            Node::Accumulator(_node) => (),
         }
     }
 
-    pub fn add_transient_value(&mut self, time: f64 ) {
+    pub fn add_transient_value(&mut self, time: f64, spawned: &[TransientEvent] ) {
         match self {
-            Node::Pressure(node) => node.add_transient_value( time ),
-            Node::Flow(node) => node.add_transient_value( time ),
+            Node::Pressure(node) => node.add_transient_value( time, spawned ),
+            Node::Flow(node) => node.add_transient_value( time, spawned ),
             Node::Connection(_node) => {},
             Node::Hidden(_node) => {},
             Node::Tank(_node) => {},
+            Node::AirValve(_node) => {},
+            Node::Accumulator(_node) => {},
         }
     }
 
@@ -221,6 +294,8 @@ impl Node {
             Node::Connection(node) => node.id = id,
             Node::Hidden(node) => node.id = id,
             Node::Tank(node) => node.id = id,
+            Node::AirValve(node) => node.id = id,
+            Node::Accumulator(node) => node.id = id,
         }
     }
 
@@ -231,6 +306,8 @@ impl Node {
             Node::Connection(_node) => {},
             Node::Hidden(_node) => {},
             Node::Tank(_node) => {},
+            Node::AirValve(_node) => {},
+            Node::Accumulator(_node) => {},
         }
     }
 
@@ -241,6 +318,8 @@ impl Node {
             Node::Connection(node) => node.selected = selected,
             Node::Hidden(node) => node.selected = selected,
             Node::Tank(node) => node.selected = selected,
+            Node::AirValve(node) => node.selected = selected,
+            Node::Accumulator(node) => node.selected = selected,
         }
     }
 
@@ -251,6 +330,8 @@ impl Node {
             Node::Connection(node) => node.selected,
             Node::Hidden(node) => node.selected,
             Node::Tank(node) => node.selected,
+            Node::AirValve(node) => node.selected,
+            Node::Accumulator(node) => node.selected,
         }
     }
 
@@ -265,6 +346,8 @@ impl Node {
             Node::Connection(node) => node.loc,
             Node::Hidden(node) => node.loc,
             Node::Tank(node) => node.loc,
+            Node::AirValve(node) => node.loc,
+            Node::Accumulator(node) => node.loc,
         }
     }
 
@@ -275,6 +358,8 @@ impl Node {
             Node::Connection(node) => node.r,
             Node::Hidden(node) => node.r,
             Node::Tank(node) => node.r,
+            Node::AirValve(node) => node.r,
+            Node::Accumulator(node) => node.r,
         }
     }
 
@@ -292,6 +377,8 @@ impl Node {
             Node::Connection(node) => node.loc = Location::new( x, y ),
             Node::Hidden(node) => node.loc = Location::new( x, y ),
             Node::Tank(node) => node.loc = Location::new( x, y ),
+            Node::AirValve(node) => node.loc = Location::new( x, y ),
+            Node::Accumulator(node) => node.loc = Location::new( x, y ),
         }
     }
 
@@ -302,6 +389,8 @@ impl Node {
             Node::Connection(node) => node.r = radius,
             Node::Hidden(node) => node.r = radius,
             Node::Tank(node) => node.r = radius,
+            Node::AirValve(node) => node.r = radius,
+            Node::Accumulator(node) => node.r = radius,
         }
     }
 
@@ -0,0 +1,120 @@
+use crate::location::Location;
+use std::f64::consts::PI;
+
+// Closed air vessel (hydropneumatic accumulator) connected to the network through an orifice
+#[derive(Clone, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Accumulator {
+    pub id: usize,
+    pub elevation: f64,             // Elevation of the vessel bottom [m]
+    pub pressure: Vec<f64>,
+    pub consumption: Vec<f64>,
+    pub diameter: f64,              // Vessel diameter [m]
+    pub gas_volume: f64,            // Gas pre-charge volume (the vessel volume) [m^3]
+    pub gas_pressure: f64,          // Gas pre-charge pressure [Pa]
+    pub exponent: f64,              // Polytropic exponent (1 = isothermal, 1.4 = adiabatic)
+    pub orifice_diameter: f64,      // Connecting orifice diameter [m]
+    pub k_in: f64,                  // Orifice loss coefficient for flow into the vessel
+    pub k_out: f64,                 // Orifice loss coefficient for flow out of the vessel
+    pub z_min: f64,                 // Minimum liquid level [m]
+    pub z_max: f64,                 // Maximum liquid level [m]
+    pub liquid_volume: Vec<f64>,    // Liquid volume in the vessel at each time step [m^3]
+    pub loc: Location,
+    pub r: f32,
+    pub selected: bool,
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Accumulator::new( 0 )
+    }
+}
+
+impl Accumulator {
+    pub fn new( id: usize ) -> Self {
+        Accumulator {
+            id,
+            elevation: 0.0,
+            pressure: vec![ 101325.0 ],
+            consumption: vec![ 0.0 ],
+            diameter: 1.0,                  // 1m
+            gas_volume: 2.0,                // 2m^3 (2.55m tall)
+            gas_pressure: 101325.0,         // Atmospheric
+            exponent: 1.2,
+            orifice_diameter: 0.1,          // 100mm
+            k_in: 1.0,
+            k_out: 0.5,
+            z_min: 0.1,
+            z_max: 2.4,
+            liquid_volume: vec![ 0.0 ],
+            loc: Location::new( 0.0, 0.0 ),
+            r: 20.0,
+            selected: false,
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        0.25 * PI * self.diameter * self.diameter
+    }
+
+    pub fn level(&self, volume: f64 ) -> f64 {
+        volume / self.area()
+    }
+
+    // Gas pressure from p V^n = p_0 V_0^n [Pa]
+    pub fn gas_pressure_at(&self, volume: f64 ) -> f64 {
+        let gas = ( self.gas_volume - volume ).max( 1.0e-9 * self.gas_volume );
+        self.gas_pressure * ( self.gas_volume / gas ).powf( self.exponent )
+    }
+
+    // Head at the liquid surface in the vessel for a liquid volume [m]
+    pub fn vessel_head(&self, volume: f64, rho: f64, g: f64 ) -> f64 {
+        self.elevation + self.level( volume ) + self.gas_pressure_at( volume ) / ( rho * g )
+    }
+
+    // Rate of change of the liquid volume with the vessel head [m^2]
+    pub fn capacitance(&self, volume: f64, rho: f64, g: f64 ) -> f64 {
+        let gas = ( self.gas_volume - volume ).max( 1.0e-9 * self.gas_volume );
+        let dhdv = 1.0 / self.area() + self.exponent * self.gas_pressure_at( volume ) / ( rho * g * gas );
+        1.0 / dhdv
+    }
+
+    // Liquid volume with the vessel in equilibrium with the network head
+    pub fn initialise(&mut self, head: f64, rho: f64, g: f64 ) {
+        let ( mut lower, mut upper ) = ( 0.0, self.gas_volume );
+        if self.vessel_head( lower, rho, g ) < head {
+            for _ in 0..100 {
+                let volume = 0.5 * ( lower + upper );
+                if self.vessel_head( volume, rho, g ) < head { lower = volume; } else { upper = volume; }
+            }
+        }
+        let limits = ( self.z_min * self.area(), self.z_max * self.area() );
+        self.liquid_volume = vec![ lower.clamp( limits.0, limits.1 ) ];
+    }
+
+    // Capacitance and reference head for the next step starting from the network head. The orifice
+    // loss is linearised about the flow rate at the last step and the vessel stops filling or
+    // emptying at the level limits.
+    pub fn storage(&self, head: f64, dt: f64, rho: f64, g: f64 ) -> (f64, f64) {
+        let step = self.liquid_volume.len() - 1;
+        let volume = self.liquid_volume[ step ];
+        let h_vessel = self.vessel_head( volume, rho, g );
+        let level = self.level( volume );
+        if ( level <= self.z_min && head < h_vessel ) || ( level >= self.z_max && head > h_vessel ) {
+            return ( 0.0, h_vessel );
+        }
+        let q = if step == 0 { 0.0 } else { ( volume - self.liquid_volume[ step - 1 ] ) / dt };
+        let k = if q < 0.0 { self.k_out } else { self.k_in };
+        let a = 0.25 * PI * self.orifice_diameter * self.orifice_diameter;
+        let resistance = k / ( 2.0 * g * a * a );
+        let c = self.capacitance( volume, rho, g );
+        ( c / ( 1.0 + c * resistance * q.abs() / dt ), h_vessel )
+    }
+
+    pub fn add_liquid_value(&mut self, head_old: f64, head: f64, dt: f64, rho: f64, g: f64 ) {
+        let ( c, h_vessel ) = self.storage( head_old, dt, rho, g );
+        let volume = *self.liquid_volume.last().unwrap() + c * ( head - h_vessel );
+        let limits = ( self.z_min * self.area(), self.z_max * self.area() );
+        self.liquid_volume.push( volume.clamp( limits.0, limits.1 ) );
+    }
+
+}
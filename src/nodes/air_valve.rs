@@ -0,0 +1,136 @@
+use crate::location::Location;
+use std::f64::consts::PI;
+
+const GAS_CONSTANT: f64 = 287.058;      // Specific gas constant for air [J/(kg K)]
+const CRITICAL_RATIO: f64 = 0.528;      // Pressure ratio for choked air flow
+const LINEAR_RATIO: f64 = 0.98;         // Above this pressure ratio the orifice flow is linearised
+
+// Air release / vacuum breaker valve at a high point
+#[derive(Clone, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
+pub struct AirValve {
+    pub id: usize,
+    pub elevation: f64,
+    pub pressure: Vec<f64>,
+    pub consumption: Vec<f64>,
+    pub inflow_diameter: f64,       // Orifice admitting air [m]
+    pub outflow_diameter: f64,      // Orifice expelling air [m]
+    pub discharge_coefficient: (f64, f64),  // ( inflow, outflow ) orifice discharge coefficients
+    pub atmospheric: f64,           // Pressure outside the valve [Pa]
+    pub temperature: f64,           // Air temperature [K]
+    pub exponent: f64,              // Polytropic exponent (1 = isothermal, 1.4 = adiabatic)
+    pub air_mass: Vec<f64>,         // Trapped air mass at each time step [kg]
+    pub air_volume: Vec<f64>,       // Trapped air volume at each time step [m^3]
+    pub loc: Location,
+    pub r: f32,
+    pub selected: bool,
+}
+
+impl Default for AirValve {
+    fn default() -> Self {
+        AirValve::new( 0 )
+    }
+}
+
+impl AirValve {
+    pub fn new( id: usize ) -> Self {
+        AirValve {
+            id,
+            elevation: 0.0,
+            pressure: vec![ 101325.0 ],
+            consumption: vec![ 0.0 ],
+            inflow_diameter: 50.0e-3,       // 50mm
+            outflow_diameter: 5.0e-3,       // 5mm
+            discharge_coefficient: ( 0.6, 0.6 ),
+            atmospheric: 101325.0,
+            temperature: 293.15,            // 20 degrees C
+            exponent: 1.0,
+            air_mass: vec![ 0.0 ],
+            air_volume: vec![ 0.0 ],
+            loc: Location::new( 0.0, 0.0 ),
+            r: 20.0,
+            selected: false,
+        }
+    }
+
+    pub fn new_elevation( id: usize, elevation: f64 ) -> Self {
+        AirValve {
+            elevation,
+            ..AirValve::new( id )
+        }
+    }
+
+    // Air mass flow rate into the valve at a pipeline pressure p (negative when expelling) [kg/s]
+    pub fn mass_flow(&self, p: f64 ) -> f64 {
+        let rt = GAS_CONSTANT * self.temperature;
+        let p_atm = self.atmospheric;
+        if p < p_atm {
+            let area = self.discharge_coefficient.0 * orifice_area( self.inflow_diameter );
+            let ratio = p.max( 0.0 ) / p_atm;
+            if ratio > CRITICAL_RATIO {
+                let rho_atm = p_atm / rt;
+                area * ( 7.0 * p_atm * rho_atm ).sqrt() * subsonic( ratio )
+            } else {
+                area * 0.686 * p_atm / rt.sqrt()
+            }
+        } else {
+            let area = self.discharge_coefficient.1 * orifice_area( self.outflow_diameter );
+            let ratio = p_atm / p;
+            if ratio > CRITICAL_RATIO {
+                - area * p * ( 7.0 / rt ).sqrt() * subsonic( ratio )
+            } else {
+                - area * 0.686 * p / rt.sqrt()
+            }
+        }
+    }
+
+    // Air mass after a time step dt, admitted or expelled at the pressure p at the end of the step. 
+    // This is negative when more air would be expelled than the valve holds. [kg]
+    pub fn next_air_mass(&self, p: f64, dt: f64 ) -> f64 {
+        *self.air_mass.last().unwrap() + dt * self.mass_flow( p )
+    }
+
+    // Volume of an air mass at pressure p from p ( V / m )^n = p_atm ( R T / p_atm )^n [m^3]
+    pub fn volume(&self, mass: f64, p: f64 ) -> f64 {
+        let specific = GAS_CONSTANT * self.temperature / self.atmospheric;
+        mass * specific * ( self.atmospheric / p.max( 1.0 ) ).powf( 1.0 / self.exponent )
+    }
+
+    // Air volume after a time step dt with the pressure p at the end of the step [m^3]
+    pub fn next_air_volume(&self, p: f64, dt: f64 ) -> f64 {
+        self.volume( self.next_air_mass( p, dt ), p )
+    }
+
+    // Rate of change of the air volume with the pressure at the end of the step [m^3/Pa]
+    pub fn next_air_volume_slope(&self, p: f64, dt: f64 ) -> f64 {
+        let delta = 1.0;
+        ( self.next_air_volume( p + delta, dt ) - self.next_air_volume( p - delta, dt ) ) / ( 2.0 * delta )
+    }
+
+    pub fn add_air_value(&mut self, p: f64, dt: f64 ) {
+        let mass = self.next_air_mass( p, dt ).max( 0.0 );
+        self.air_volume.push( self.volume( mass, p ) );
+        self.air_mass.push( mass );
+    }
+
+    // The float closes the valve once the air is expelled
+    pub fn shut(&mut self) {
+        self.air_volume.push( 0.0 );
+        self.air_mass.push( 0.0 );
+    }
+
+}
+
+// Subsonic orifice flow factor, linear close to a pressure ratio of one so that the flow 
+// has a finite derivative as the valve starts to admit or expel air
+fn subsonic( ratio: f64 ) -> f64 {
+    let factor = |r: f64| ( r.powf( 1.4286 ) - r.powf( 1.714 ) ).max( 0.0 ).sqrt();
+    if ratio > LINEAR_RATIO {
+        factor( LINEAR_RATIO ) * ( 1.0 - ratio ) / ( 1.0 - LINEAR_RATIO )
+    } else {
+        factor( ratio )
+    }
+}
+
+fn orifice_area( diameter: f64 ) -> f64 {
+    0.25 * PI * diameter * diameter
+}
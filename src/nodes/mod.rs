@@ -2,4 +2,6 @@ pub mod pressure;
 pub mod flow;
 pub mod connection;
 pub mod hidden;
-pub mod tank;
\ No newline at end of file
+pub mod tank;
+pub mod air_valve;
+pub mod accumulator;
\ No newline at end of file
@@ -1,5 +1,6 @@
 use crate::location::Location;
-use crate::events::TransientEvent;
+use crate::events::{ self, TransientEvent };
+use crate::series::TimeSeries;
 
 #[derive(Clone, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
 pub struct Pressure {
@@ -11,6 +12,8 @@ pub struct Pressure {
     pub r: f32,
     pub selected: bool,
     pub events: Vec<TransientEvent>,
+    #[serde(default)]
+    pub series: Option<TimeSeries>, // Pressure [Pa] against time
 }
 
 impl Default for Pressure {
@@ -30,6 +33,7 @@ impl Pressure {
             r: 20.0,
             selected: false,
             events: vec![],
+            series: None,
         }
     }
 
@@ -43,6 +47,7 @@ impl Pressure {
             r: 20.0,
             selected: false,
             events: vec![],
+            series: None,
         }
     }
 
@@ -56,6 +61,7 @@ impl Pressure {
             r: 20.0,
             selected: false,
             events: vec![],
+            series: None,
         }
     }
 
@@ -74,17 +80,24 @@ impl Pressure {
         self.consumption = consumption;
     } 
 
-    pub fn add_transient_value( &mut self, time: f64 ) {
-        for event in self.events.iter() {
-            if time >= event.time()  {
-                self.pressure.push( event.value() );
-            } else {
-                self.pressure.push( *self.pressure.last().unwrap() );
-            }
+    // The series gives the steady pressure at t = 0
+    pub fn start_series( &mut self ) {
+        if let Some( series ) = &self.series {
+            self.pressure[0] = series.value( 0.0 );
         }
-        if self.events.len() == 0 {
-            self.pressure.push( *self.pressure.last().unwrap() );
+    }
+
+    // The series sets the pressure until an event (or one spawned by a rule) sets its value
+    pub fn add_transient_value( &mut self, time: f64, spawned: &[TransientEvent] ) {
+        let mut value = match &self.series {
+            Some( series ) => series.value( time ),
+            None => *self.pressure.last().unwrap(),
+        };
+        for ( event, _ ) in events::timeline( &self.events, spawned ) {
+            if time < event.time() { break; }
+            value = event.value();
         }
+        self.pressure.push( value );
         self.consumption.push( self.consumption[0] );
     }
 }
\ No newline at end of file
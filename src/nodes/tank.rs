@@ -1,4 +1,5 @@
 use crate::location::Location;
+use crate::utility;
 use std::f64::consts::PI;
 
 #[derive(Clone, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
@@ -11,6 +12,16 @@ pub struct Tank {
     pub z_min: f64,                 // Minimum fluid level [m]
     pub z_max: f64,                 // Maximum fluid level [m]
     pub diameter: f64,              // Tank diameter [m]
+    #[serde(default)]
+    pub area_data: Vec<(f64, f64)>, // ( level [m], area [m^2] ) for non-cylindrical tanks
+    #[serde(default = "default_atmospheric")]
+    pub atmospheric: f64,           // Pressure at the free surface [Pa]
+    #[serde(default)]
+    pub level: Vec<f64>,            // Fluid level at each time step [m]
+    #[serde(default)]
+    pub overflow: Vec<f64>,         // Spill flow rate while the tank is full at each time step [m^3/s]
+    #[serde(default)]
+    pub air_entry: Vec<f64>,        // Air drawn into the outlet while the tank is empty at each time step [m^3/s]
     pub loc: Location,
     pub r: f32,
     pub selected: bool,
@@ -33,12 +44,18 @@ impl Tank {
             z_min: 0.0,
             z_max: 1.0,
             diameter: 1.0,
+            area_data: vec![],
+            atmospheric: p_atm,
+            level: vec![ 0.5 ],
+            overflow: vec![ 0.0 ],
+            air_entry: vec![ 0.0 ],
             loc: Location::new( 0.0, 0.0 ),
             r: 20.0,
             selected: false,
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new_with_values( id: usize, p_atm: f64, rho: f64, g: f64, diameter: f64, 
         z_init: f64, z_min: f64, z_max: f64 
     ) -> Self {
@@ -51,14 +68,57 @@ impl Tank {
             z_min,
             z_max,
             diameter,
+            area_data: vec![],
+            atmospheric: p_atm,
+            level: vec![ z_init ],
+            overflow: vec![ 0.0 ],
+            air_entry: vec![ 0.0 ],
             loc: Location::new( 0.0, 0.0 ),
             r: 20.0,
             selected: false,
         }
     }
 
+    // Area at the current fluid level
     pub fn area( &self ) -> f64 {
-        0.25 * PI * self.diameter * self.diameter
+        self.area_at( self.level.last().copied().unwrap_or( self.z_init ) )
     }
 
-}
\ No newline at end of file
+    pub fn area_at( &self, level: f64 ) -> f64 {
+        if self.area_data.len() < 2 {
+            0.25 * PI * self.diameter * self.diameter
+        } else {
+            let ( levels, areas ) = utility::split_into_two_vectors( &self.area_data );
+            utility::interpolate( level.clamp( levels[0], levels[levels.len() - 1] ), &levels, &areas )
+        }
+    }
+
+    // Fluid level for a head at the tank [m]
+    pub fn level_at( &self, head: f64, rho: f64, g: f64 ) -> f64 {
+        head - self.elevation - self.atmospheric / ( rho * g )
+    }
+
+    // Head for a fluid level in the tank [m]
+    pub fn head_at( &self, level: f64, rho: f64, g: f64 ) -> f64 {
+        self.elevation + level + self.atmospheric / ( rho * g )
+    }
+
+    pub fn is_full( &self ) -> bool {
+        self.level.last().is_some_and( |level| *level >= self.z_max )
+    }
+
+    pub fn is_empty( &self ) -> bool {
+        self.level.last().is_some_and( |level| *level <= self.z_min )
+    }
+
+    pub fn add_level_value( &mut self, level: f64, overflow: f64, air_entry: f64 ) {
+        self.level.push( level );
+        self.overflow.push( overflow );
+        self.air_entry.push( air_entry );
+    }
+
+}
+
+fn default_atmospheric() -> f64 {
+    101325.0
+}
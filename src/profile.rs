@@ -0,0 +1,70 @@
+// Head and flow along a pipe or bend at each time step
+#[derive(Clone, PartialEq, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct Profile {
+    pub distance: Vec<f64>,         // Distance from the from node [m]
+    pub elevation: Vec<f64>,        // Elevation interpolated between the end nodes [m]
+    pub head: Vec<Vec<f64>>,        // Head at each point for every time step [m]
+    pub flow: Vec<Vec<f64>>,        // Volume flow rate at each point for every time step [m^3/s]
+}
+
+impl Profile {
+    pub fn new( length: f64, segments: usize, elevation: (f64, f64) ) -> Self {
+        let x: Vec<f64> = ( 0..=segments ).map( |i| i as f64 / segments as f64 ).collect();
+        Profile {
+            distance: x.iter().map( |x| x * length ).collect(),
+            elevation: x.iter().map( |x| elevation.0 + x * ( elevation.1 - elevation.0 ) ).collect(),
+            head: Vec::new(),
+            flow: Vec::new(),
+        }
+    }
+
+    pub fn points(&self) -> usize {
+        self.distance.len()
+    }
+
+    pub fn steps(&self) -> usize {
+        self.head.len()
+    }
+
+    pub fn push(&mut self, head: Vec<f64>, flow: Vec<f64> ) {
+        self.head.push( head );
+        self.flow.push( flow );
+    }
+
+    // Pressure at each point for a time step [Pa]
+    pub fn pressure(&self, step: usize, density: f64, g: f64 ) -> Vec<f64> {
+        self.to_pressure( &self.head[step], density, g )
+    }
+
+    // Maximum head at each point over all time steps
+    pub fn max_head(&self) -> Vec<f64> {
+        self.envelope( &self.head, f64::max )
+    }
+
+    // Minimum head at each point over all time steps
+    pub fn min_head(&self) -> Vec<f64> {
+        self.envelope( &self.head, f64::min )
+    }
+
+    pub fn max_pressure(&self, density: f64, g: f64 ) -> Vec<f64> {
+        self.to_pressure( &self.max_head(), density, g )
+    }
+
+    pub fn min_pressure(&self, density: f64, g: f64 ) -> Vec<f64> {
+        self.to_pressure( &self.min_head(), density, g )
+    }
+
+    fn to_pressure(&self, head: &[f64], density: f64, g: f64 ) -> Vec<f64> {
+        head.iter().zip( self.elevation.iter() ).map( |(h, z)| ( h - z ) * density * g ).collect()
+    }
+
+    fn envelope(&self, values: &[Vec<f64>], select: fn(f64, f64) -> f64 ) -> Vec<f64> {
+        let mut result = values.first().cloned().unwrap_or_default();
+        for row in values.iter().skip( 1 ) {
+            for (r, v) in result.iter_mut().zip( row.iter() ) {
+                *r = select( *r, *v );
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,1227 @@
+//! Versioned project file
+//!
+//! A project file is a JSON document holding everything needed to rerun a model: the fluid, the
+//! solver settings, the nodes and the edges (which refer to their nodes by id). Every component
+//! has a `type` field and its physical parameters. Solution histories and display state such as
+//! `width`, `selected` and `r` are not saved. Missing parameters take the default values of the
+//! component and unknown fields are an error, so a misspelt field is never ignored silently.
+//!
+//! The `version` field is increased whenever the format changes. Older files are upgraded by the
+//! migrations in `MIGRATIONS` when they are loaded. Files without a version are the serialised
+//! `Graph` (with optional `fluid` and `solver` fields) written by earlier versions of eki.
+
+use std::collections::HashSet;
+use serde_json::{ json, Map, Value };
+
+use crate::fluid::Fluid;
+use crate::fluids::{ basic_fluid::BasicFluid, water::Water };
+use crate::graph::Graph;
+use crate::node::Node;
+use crate::nodes::{
+    pressure::Pressure, flow::Flow, connection::Connection, tank::Tank, air_valve::AirValve,
+    accumulator::Accumulator,
+};
+use crate::edge::Edge;
+use crate::edges::{
+    pipe::Pipe, valve::{ Valve, ValveType, Trim }, pump::Pump, bend::Bend, size_change::SizeChange,
+    check_valve::CheckValve, safety_valve::SafetyValve, relief_valve::ReliefValve,
+    bursting_disk::BurstingDisk, generic::Generic, open_pipe::OpenPipe,
+    control_valve::{ ControlValve, ControlMode, Actuator },
+};
+use crate::events::{ TransientEvent, Time, Value as EventValue };
+use crate::series::TimeSeries;
+use crate::rules::{ Rule, Action, Condition, Comparison, Target };
+use crate::friction::FrictionModel;
+use crate::location::Location;
+use crate::solver::{ Solver, Settings };
+
+pub const VERSION: u32 = 2;
+
+// Upgrades from each version to the next ( MIGRATIONS[v] converts version v to v + 1 )
+const MIGRATIONS: [fn( Value ) -> Result<Value, ProjectError>; 2] = [ from_graph, pattern_to_series ];
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum ProjectError {
+    Json( String ),                                 // Not valid JSON or not a valid project
+    NewerVersion { version: u32 },                  // Written by a newer version of eki
+    Migration { version: u32, message: String },    // An older file could not be upgraded
+    UnknownNode { id: usize },                      // Edge refers to a node id that is not in the project
+}
+
+impl std::fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectError::Json( message ) => write!(f, "Invalid project file: {}", message),
+            ProjectError::NewerVersion { version } =>
+                write!(f, "Project file version {} is newer than the supported version {}", version, VERSION),
+            ProjectError::Migration { version, message } =>
+                write!(f, "Could not upgrade project file version {}: {}", version, message),
+            ProjectError::UnknownNode { id } => write!(f, "Unknown node id {}", id),
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Project {
+    pub version: u32,
+    #[serde(default)]
+    pub fluid: FluidRecord,
+    #[serde(default)]
+    pub solver: Settings,
+    pub nodes: Vec<NodeRecord>,
+    pub edges: Vec<EdgeRecord>,
+    #[serde(default)]
+    pub rules: Vec<RuleRecord>,
+}
+
+impl Project {
+    pub fn new( fluid: &Fluid, solver: &Solver, graph: &Graph ) -> Self {
+        Project {
+            version: VERSION,
+            fluid: FluidRecord::from( fluid ),
+            solver: solver.settings(),
+            nodes: graph.nodes.iter().map( NodeRecord::from ).collect(),
+            edges: graph.edges.iter().map( EdgeRecord::from ).collect(),
+            rules: graph.rules.iter().map( RuleRecord::from ).collect(),
+        }
+    }
+
+    pub fn fluid(&self) -> Fluid {
+        self.fluid.fluid()
+    }
+
+    pub fn solver(&self) -> Solver {
+        let mut solver = Solver::default();
+        solver.apply_settings( &self.solver );
+        solver
+    }
+
+    pub fn graph(&self) -> Result<Graph, ProjectError> {
+        let mut graph = Graph::new();
+        let mut ids: HashSet<usize> = HashSet::new();
+        for record in self.nodes.iter() {
+            let node = record.node();
+            ids.insert( node.id() );
+            graph.add_node( node );
+        }
+        for record in self.edges.iter() {
+            graph.add_edge( record.edge( &ids )? );
+        }
+        graph.rules = self.rules.iter().map( RuleRecord::rule ).collect();
+        graph.create_id_to_index();
+        Ok( graph )
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty( self ).unwrap()
+    }
+
+    // Read a project file of this or any earlier version
+    pub fn from_json( text: &str ) -> Result<Project, ProjectError> {
+        let value: Value = serde_json::from_str( text ).map_err( |error| ProjectError::Json( error.to_string() ) )?;
+        serde_json::from_value( migrate( value )? ).map_err( |error| ProjectError::Json( error.to_string() ) )
+    }
+
+    // JSON Schema (draft 7) of the current version generated from the component records
+    pub fn schema() -> Value {
+        let variants = |records: Vec<Value>| -> Value {
+            json!({ "oneOf": records.iter().map( |record| object_schema( record, &[ "type" ] ) ).collect::<Vec<_>>() })
+        };
+        let nodes: Vec<Value> = NodeRecord::defaults().iter().map( |node| serde_json::to_value( node ).unwrap() ).collect();
+        let edges: Vec<Value> = EdgeRecord::defaults().iter().map( |edge| serde_json::to_value( edge ).unwrap() ).collect();
+        let fluids: Vec<Value> = [ FluidRecord::Basic( BasicFluidRecord::default() ), FluidRecord::Water( WaterRecord::default() ) ]
+            .iter().map( |fluid| serde_json::to_value( fluid ).unwrap() ).collect();
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Eki project",
+            "type": "object",
+            "properties": {
+                "version": { "const": VERSION },
+                "fluid": variants( fluids ),
+                "solver": object_schema( &serde_json::to_value( Settings::default() ).unwrap(), &[] ),
+                "nodes": { "type": "array", "items": variants( nodes ) },
+                "edges": { "type": "array", "items": variants( edges ) },
+                "rules": { "type": "array", "items": object_schema( &serde_json::to_value( RuleRecord::default() ).unwrap(), &[] ) },
+            },
+            "required": [ "version", "nodes", "edges" ],
+            "additionalProperties": false,
+        })
+    }
+}
+
+// Upgrade a project file to the current version
+pub fn migrate( mut value: Value ) -> Result<Value, ProjectError> {
+    let version = match value.get( "version" ) {
+        None => 0,
+        Some( version ) => version.as_u64().ok_or_else( ||
+            ProjectError::Json( "version is not a whole number".to_string() ) )? as u32,
+    };
+    if version > VERSION { return Err( ProjectError::NewerVersion { version } ); }
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip( version as usize ) {
+        value = migration( value ).map_err( |error| match error {
+            ProjectError::Json( message ) => ProjectError::Migration { version: from as u32, message },
+            error => error,
+        })?;
+        value[ "version" ] = json!( from + 1 );
+    }
+    Ok( value )
+}
+
+// Schema of a record from its serialised default values
+fn object_schema( record: &Value, required: &[&str] ) -> Value {
+    let mut properties = Map::new();
+    for (key, value) in record.as_object().unwrap() {
+        let schema = match ( key.as_str(), value ) {
+            ( "type", Value::String( name ) ) => json!({ "const": name }),
+            ( "events", _ ) => json!({ "type": "array", "items": { "oneOf":
+                EventRecord::defaults().iter().map( |event| object_schema( &serde_json::to_value( event ).unwrap(), &[ "type" ] ) )
+                .collect::<Vec<_>>() } }),
+            ( "series", _ ) => json!({ "oneOf": [ { "type": "null" }, series_schema() ] }),
+            ( "conditions", _ ) => json!({ "type": "array", "items": { "oneOf":
+                RuleRecord::conditions().iter().map( |condition| object_schema( &serde_json::to_value( condition ).unwrap(), &[ "type" ] ) )
+                .collect::<Vec<_>>() } }),
+            ( "comparison", _ ) => json!({ "enum": [ "Above", "Below" ] }),
+            ( "valve_type", _ ) => json!({ "enum": [ "AngleGlobe", "Ball", "Butterfly", "Diaphragm", "Gate", "Globe", 
+                "Needle", "Pinch", "Plug", "Slide" ] }),
+            ( "trim", _ ) => json!({ "enum": [ "Inherent", "Linear", "EqualPercentage" ] }),
+            ( "mode", _ ) => json!({ "enum": [ "PressureReducing", "PressureSustaining", "FlowControl" ] }),
+            ( "actuator", _ ) => {
+                let actuators = [ Actuator::default(), Actuator::Pid { gain: 1.0, integral_time: 0.0, derivative_time: 0.0 } ];
+                json!({ "oneOf": actuators.iter()
+                    .map( |actuator| object_schema( &serde_json::to_value( actuator ).unwrap(), &[ "type" ] ) ).collect::<Vec<_>>() })
+            },
+            ( "friction", _ ) => {
+                let models = [ FrictionModel::Default, FrictionModel::PraksBrkic, FrictionModel::ColebrookWhite,
+                    FrictionModel::SwameeJain, FrictionModel::Haaland, FrictionModel::HazenWilliams { c: 130.0 },
+                    FrictionModel::Manning { n: 0.011 }, FrictionModel::Fixed { f: 0.02 } ];
+                json!({ "oneOf": models.iter()
+                    .map( |model| object_schema( &serde_json::to_value( model ).unwrap(), &[ "type" ] ) ).collect::<Vec<_>>() })
+            },
+            ( "actions", _ ) => json!({ "type": "array", "items": object_schema( &serde_json::to_value( ActionRecord {
+                target: Target::Node { id: 0 }, event: EventRecord::PowerFailure { time: 0.0 } } ).unwrap(), &[ "target", "event" ] ) }),
+            ( "target", _ ) => {
+                let targets = [ Target::Node { id: 0 }, Target::Edge { from: 0, to: 0 } ];
+                json!({ "oneOf": targets.iter()
+                    .map( |target| object_schema( &serde_json::to_value( target ).unwrap(), &[ "type" ] ) ).collect::<Vec<_>>() })
+            },
+            ( "event", _ ) => json!({ "oneOf": EventRecord::defaults().iter()
+                .map( |event| object_schema( &serde_json::to_value( event ).unwrap(), &[ "type" ] ) ).collect::<Vec<_>>() }),
+            _ => value_schema( value ),
+        };
+        properties.insert( key.clone(), schema );
+    }
+    let mut required: Vec<&str> = required.to_vec();
+    for key in [ "id", "from", "to" ] {
+        if record.get( key ).is_some() { required.push( key ); }
+    }
+    json!({ "type": "object", "properties": properties, "required": required, "additionalProperties": false })
+}
+
+fn series_schema() -> Value {
+    let mut schema = object_schema( &serde_json::to_value( TimeSeries::linear( vec![ ( 0.0, 0.0 ) ] ) ).unwrap(), &[ "points" ] );
+    schema[ "properties" ][ "interpolation" ] = json!({ "enum": [ "Linear", "Step" ] });
+    schema
+}
+
+fn value_schema( value: &Value ) -> Value {
+    match value {
+        Value::Bool( _ ) => json!({ "type": "boolean" }),
+        Value::Number( number ) if number.is_u64() => json!({ "type": "integer", "minimum": 0 }),
+        Value::Number( _ ) => json!({ "type": "number" }),
+        Value::String( _ ) => json!({ "type": "string" }),
+        Value::Array( items ) => match items.first() {
+            Some( item ) => json!({ "type": "array", "items": value_schema( item ) }),
+            None => json!({ "type": "array", "items": { "type": "number" } }),
+        },
+        Value::Object( _ ) => object_schema( value, &[] ),
+        Value::Null => json!({}),
+    }
+}
+
+// Fluids
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum FluidRecord {
+    Basic( BasicFluidRecord ),
+    Water( WaterRecord ),
+}
+
+impl Default for FluidRecord {
+    fn default() -> Self {
+        FluidRecord::from( &Fluid::default() )
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BasicFluidRecord {
+    pub density: f64,           // [kg/m^3]
+    pub viscosity: f64,         // Kinematic viscosity [m^2/s]
+    pub bulk_modulus: f64,      // [Pa]
+    pub vapour_pressure: f64,   // [Pa]
+}
+
+impl Default for BasicFluidRecord {
+    fn default() -> Self {
+        let fluid = BasicFluid::default();
+        BasicFluidRecord { density: fluid.rho, viscosity: fluid.nu, bulk_modulus: fluid.bulk, vapour_pressure: fluid.vapour }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaterRecord {
+    pub temperature: f64,       // [K]
+}
+
+impl Default for WaterRecord {
+    fn default() -> Self {
+        WaterRecord { temperature: Water::default().temperature }
+    }
+}
+
+impl From<&Fluid> for FluidRecord {
+    fn from( fluid: &Fluid ) -> Self {
+        match fluid {
+            Fluid::BasicFluid( fluid ) => FluidRecord::Basic( BasicFluidRecord {
+                density: fluid.rho, viscosity: fluid.nu, bulk_modulus: fluid.bulk, vapour_pressure: fluid.vapour
+            }),
+            Fluid::Water( fluid ) => FluidRecord::Water( WaterRecord { temperature: fluid.temperature } ),
+        }
+    }
+}
+
+impl FluidRecord {
+    pub fn fluid(&self) -> Fluid {
+        match self {
+            FluidRecord::Basic( record ) => {
+                let mut fluid = BasicFluid::new( record.density, record.viscosity, record.bulk_modulus );
+                fluid.vapour = record.vapour_pressure;
+                Fluid::BasicFluid( fluid )
+            },
+            FluidRecord::Water( record ) => Fluid::Water( Water::new( record.temperature ) ),
+        }
+    }
+}
+
+// Events
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum EventRecord {
+    InstantaneousChange { value: f64, time: f64 },
+    ValveClosure { exponent: f64, time: f64, duration: f64 },
+    ValveOpening { exponent: f64, time: f64, duration: f64 },
+    PumpShutdown { exponent: f64, time: f64, duration: f64 },
+    PumpStartup { speed: f64, exponent: f64, time: f64, duration: f64 },
+    PowerFailure { time: f64 },
+}
+
+impl EventRecord {
+    fn defaults() -> Vec<EventRecord> {
+        vec![
+            EventRecord::InstantaneousChange { value: 0.0, time: 0.0 },
+            EventRecord::ValveClosure { exponent: 1.0, time: 0.0, duration: 0.0 },
+            EventRecord::ValveOpening { exponent: 1.0, time: 0.0, duration: 0.0 },
+            EventRecord::PumpShutdown { exponent: 1.0, time: 0.0, duration: 0.0 },
+            EventRecord::PumpStartup { speed: 0.0, exponent: 1.0, time: 0.0, duration: 0.0 },
+            EventRecord::PowerFailure { time: 0.0 },
+        ]
+    }
+
+    fn from_events( events: &[TransientEvent] ) -> Vec<EventRecord> {
+        events.iter().filter_map( |event| Some( match event {
+            TransientEvent::None => return None,
+            TransientEvent::InstantaneousChange( value, time ) =>
+                EventRecord::InstantaneousChange { value: value.0, time: time.0 },
+            TransientEvent::ValveClosure( exponent, time, duration ) =>
+                EventRecord::ValveClosure { exponent: exponent.0, time: time.0, duration: duration.0 },
+            TransientEvent::ValveOpening( exponent, time, duration ) =>
+                EventRecord::ValveOpening { exponent: exponent.0, time: time.0, duration: duration.0 },
+            TransientEvent::PumpShutdown( exponent, time, duration ) =>
+                EventRecord::PumpShutdown { exponent: exponent.0, time: time.0, duration: duration.0 },
+            TransientEvent::PumpStartup( speed, exponent, time, duration ) =>
+                EventRecord::PumpStartup { speed: speed.0, exponent: exponent.0, time: time.0, duration: duration.0 },
+            TransientEvent::PowerFailure( time ) => EventRecord::PowerFailure { time: time.0 },
+        })).collect()
+    }
+
+    fn events( records: &[EventRecord] ) -> Vec<TransientEvent> {
+        records.iter().map( |record| match *record {
+            EventRecord::InstantaneousChange { value, time } =>
+                TransientEvent::InstantaneousChange( EventValue( value ), Time( time ) ),
+            EventRecord::ValveClosure { exponent, time, duration } =>
+                TransientEvent::ValveClosure( EventValue( exponent ), Time( time ), Time( duration ) ),
+            EventRecord::ValveOpening { exponent, time, duration } =>
+                TransientEvent::ValveOpening( EventValue( exponent ), Time( time ), Time( duration ) ),
+            EventRecord::PumpShutdown { exponent, time, duration } =>
+                TransientEvent::PumpShutdown( EventValue( exponent ), Time( time ), Time( duration ) ),
+            EventRecord::PumpStartup { speed, exponent, time, duration } =>
+                TransientEvent::PumpStartup( EventValue( speed ), EventValue( exponent ), Time( time ), Time( duration ) ),
+            EventRecord::PowerFailure { time } => TransientEvent::PowerFailure( Time( time ) ),
+        }).collect()
+    }
+}
+
+// Rules
+
+#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleRecord {
+    pub name: String,
+    pub conditions: Vec<Condition>,         // All must hold
+    pub delay: f64,                         // Time the conditions must hold [s]
+    pub latching: bool,
+    pub actions: Vec<ActionRecord>,
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActionRecord {
+    pub target: Target,
+    pub event: EventRecord,                 // Times from the moment the rule fires
+}
+
+impl RuleRecord {
+    fn conditions() -> Vec<Condition> {
+        let ( comparison, value ) = ( Comparison::Above, 0.0 );
+        vec![
+            Condition::Pressure { id: 0, comparison, value },
+            Condition::Head { id: 0, comparison, value },
+            Condition::Level { id: 0, comparison, value },
+            Condition::Flow { from: 0, to: 0, comparison, value },
+            Condition::Time { comparison, value },
+        ]
+    }
+
+    pub fn rule(&self) -> Rule {
+        let actions = self.actions.iter().map( |action| Action {
+            target: action.target,
+            event: EventRecord::events( std::slice::from_ref( &action.event ) ).remove( 0 ),
+        }).collect();
+        Rule { delay: self.delay, latching: self.latching, ..Rule::new( &self.name, self.conditions.clone(), actions ) }
+    }
+}
+
+impl From<&Rule> for RuleRecord {
+    fn from( rule: &Rule ) -> Self {
+        let actions = rule.actions.iter().filter_map( |action| {
+            let event = EventRecord::from_events( std::slice::from_ref( &action.event ) ).pop()?;
+            Some( ActionRecord { target: action.target, event } )
+        }).collect();
+        RuleRecord { name: rule.name.clone(), conditions: rule.conditions.clone(), delay: rule.delay,
+            latching: rule.latching, actions }
+    }
+}
+
+// Nodes
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum NodeRecord {
+    Pressure( PressureRecord ),
+    Flow( FlowRecord ),
+    Connection( ConnectionRecord ),
+    Tank( TankRecord ),
+    AirValve( AirValveRecord ),
+    Accumulator( AccumulatorRecord ),
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PressureRecord {
+    pub id: usize,
+    pub elevation: f64,             // [m]
+    pub location: Location,
+    pub pressure: f64,              // Steady pressure [Pa]
+    pub events: Vec<EventRecord>,
+    pub series: Option<TimeSeries>, // Pressure [Pa] against time
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FlowRecord {
+    pub id: usize,
+    pub elevation: f64,             // [m]
+    pub location: Location,
+    pub consumption: f64,           // Steady mass flow rate into the network [kg/s]
+    pub events: Vec<EventRecord>,
+    pub series: Option<TimeSeries>, // Consumption [kg/s] against time
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConnectionRecord {
+    pub id: usize,
+    pub elevation: f64,             // [m]
+    pub location: Location,
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TankRecord {
+    pub id: usize,
+    pub elevation: f64,             // [m]
+    pub location: Location,
+    pub pressure: f64,              // Steady pressure at the tank base [Pa]
+    pub diameter: f64,              // [m]
+    pub z_init: f64,                // Initial fluid level [m]
+    pub z_min: f64,                 // [m]
+    pub z_max: f64,                 // [m]
+    pub area_data: Vec<(f64, f64)>, // ( level [m], area [m^2] )
+    pub atmospheric: f64,           // [Pa]
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AirValveRecord {
+    pub id: usize,
+    pub elevation: f64,             // [m]
+    pub location: Location,
+    pub inflow_diameter: f64,       // [m]
+    pub outflow_diameter: f64,      // [m]
+    pub discharge_coefficient: (f64, f64),
+    pub atmospheric: f64,           // [Pa]
+    pub temperature: f64,           // [K]
+    pub exponent: f64,
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccumulatorRecord {
+    pub id: usize,
+    pub elevation: f64,             // [m]
+    pub location: Location,
+    pub diameter: f64,              // [m]
+    pub gas_volume: f64,            // [m^3]
+    pub gas_pressure: f64,          // [Pa]
+    pub exponent: f64,
+    pub orifice_diameter: f64,      // [m]
+    pub k_in: f64,
+    pub k_out: f64,
+    pub z_min: f64,                 // [m]
+    pub z_max: f64,                 // [m]
+}
+
+impl Default for PressureRecord { fn default() -> Self { PressureRecord::from( &Pressure::default() ) } }
+impl Default for FlowRecord { fn default() -> Self { FlowRecord::from( &Flow::default() ) } }
+impl Default for ConnectionRecord { fn default() -> Self { ConnectionRecord::from( &Connection::default() ) } }
+impl Default for TankRecord { fn default() -> Self { TankRecord::from( &Tank::default() ) } }
+impl Default for AirValveRecord { fn default() -> Self { AirValveRecord::from( &AirValve::default() ) } }
+impl Default for AccumulatorRecord { fn default() -> Self { AccumulatorRecord::from( &Accumulator::default() ) } }
+
+impl From<&Pressure> for PressureRecord {
+    fn from( node: &Pressure ) -> Self {
+        PressureRecord { id: node.id, elevation: node.elevation, location: node.loc, pressure: node.pressure[0],
+            events: EventRecord::from_events( &node.events ), series: node.series.clone() }
+    }
+}
+
+impl From<&Flow> for FlowRecord {
+    fn from( node: &Flow ) -> Self {
+        FlowRecord { id: node.id, elevation: node.elevation, location: node.loc, consumption: node.consumption[0],
+            events: EventRecord::from_events( &node.events ), series: node.series.clone() }
+    }
+}
+
+impl From<&Connection> for ConnectionRecord {
+    fn from( node: &Connection ) -> Self {
+        ConnectionRecord { id: node.id, elevation: node.elevation, location: node.loc }
+    }
+}
+
+impl From<&Tank> for TankRecord {
+    fn from( node: &Tank ) -> Self {
+        TankRecord { id: node.id, elevation: node.elevation, location: node.loc, pressure: node.pressure[0],
+            diameter: node.diameter,
+            z_init: node.z_init, z_min: node.z_min, z_max: node.z_max, area_data: node.area_data.clone(),
+            atmospheric: node.atmospheric }
+    }
+}
+
+impl From<&AirValve> for AirValveRecord {
+    fn from( node: &AirValve ) -> Self {
+        AirValveRecord { id: node.id, elevation: node.elevation, location: node.loc,
+            inflow_diameter: node.inflow_diameter, outflow_diameter: node.outflow_diameter,
+            discharge_coefficient: node.discharge_coefficient, atmospheric: node.atmospheric,
+            temperature: node.temperature, exponent: node.exponent }
+    }
+}
+
+impl From<&Accumulator> for AccumulatorRecord {
+    fn from( node: &Accumulator ) -> Self {
+        AccumulatorRecord { id: node.id, elevation: node.elevation, location: node.loc, diameter: node.diameter,
+            gas_volume: node.gas_volume, gas_pressure: node.gas_pressure, exponent: node.exponent,
+            orifice_diameter: node.orifice_diameter, k_in: node.k_in, k_out: node.k_out, z_min: node.z_min,
+            z_max: node.z_max }
+    }
+}
+
+impl From<&Node> for NodeRecord {
+    fn from( node: &Node ) -> Self {
+        match node {
+            Node::Pressure( node ) => NodeRecord::Pressure( node.into() ),
+            Node::Flow( node ) => NodeRecord::Flow( node.into() ),
+            Node::Connection( node ) => NodeRecord::Connection( node.into() ),
+            Node::Hidden( node ) => NodeRecord::Connection( ConnectionRecord {
+                id: node.id, elevation: node.elevation, location: node.loc
+            }),
+            Node::Tank( node ) => NodeRecord::Tank( node.into() ),
+            Node::AirValve( node ) => NodeRecord::AirValve( node.into() ),
+            Node::Accumulator( node ) => NodeRecord::Accumulator( node.into() ),
+        }
+    }
+}
+
+impl NodeRecord {
+    fn defaults() -> Vec<NodeRecord> {
+        vec![
+            NodeRecord::Pressure( PressureRecord::default() ),
+            NodeRecord::Flow( FlowRecord::default() ),
+            NodeRecord::Connection( ConnectionRecord::default() ),
+            NodeRecord::Tank( TankRecord::default() ),
+            NodeRecord::AirValve( AirValveRecord::default() ),
+            NodeRecord::Accumulator( AccumulatorRecord::default() ),
+        ]
+    }
+
+    pub fn node(&self) -> Node {
+        match self {
+            NodeRecord::Pressure( record ) => Node::Pressure( Pressure {
+                elevation: record.elevation,
+                pressure: vec![ record.pressure ],
+                loc: record.location,
+                events: EventRecord::events( &record.events ),
+                series: record.series.clone(),
+                ..Pressure::new( record.id )
+            }),
+            NodeRecord::Flow( record ) => Node::Flow( Flow {
+                elevation: record.elevation,
+                consumption: vec![ record.consumption ],
+                loc: record.location,
+                events: EventRecord::events( &record.events ),
+                series: record.series.clone(),
+                ..Flow::new( record.id )
+            }),
+            NodeRecord::Connection( record ) => Node::Connection( Connection {
+                loc: record.location,
+                ..Connection::new_elevation( record.id, record.elevation )
+            }),
+            NodeRecord::Tank( record ) => Node::Tank( Tank {
+                id: record.id,
+                elevation: record.elevation,
+                pressure: vec![ record.pressure ],
+                z_init: record.z_init,
+                z_min: record.z_min,
+                z_max: record.z_max,
+                diameter: record.diameter,
+                area_data: record.area_data.clone(),
+                atmospheric: record.atmospheric,
+                level: vec![ record.z_init ],
+                loc: record.location,
+                ..Tank::default()
+            }),
+            NodeRecord::AirValve( record ) => Node::AirValve( AirValve {
+                inflow_diameter: record.inflow_diameter,
+                outflow_diameter: record.outflow_diameter,
+                discharge_coefficient: record.discharge_coefficient,
+                atmospheric: record.atmospheric,
+                temperature: record.temperature,
+                exponent: record.exponent,
+                loc: record.location,
+                ..AirValve::new_elevation( record.id, record.elevation )
+            }),
+            NodeRecord::Accumulator( record ) => Node::Accumulator( Accumulator {
+                elevation: record.elevation,
+                diameter: record.diameter,
+                gas_volume: record.gas_volume,
+                gas_pressure: record.gas_pressure,
+                exponent: record.exponent,
+                orifice_diameter: record.orifice_diameter,
+                k_in: record.k_in,
+                k_out: record.k_out,
+                z_min: record.z_min,
+                z_max: record.z_max,
+                loc: record.location,
+                ..Accumulator::new( record.id )
+            }),
+        }
+    }
+}
+
+// Edges
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum EdgeRecord {
+    Pipe( PipeRecord ),
+    Valve( ValveRecord ),
+    Pump( PumpRecord ),
+    Bend( BendRecord ),
+    SizeChange( SizeChangeRecord ),
+    CheckValve( CheckValveRecord ),
+    SafetyValve( SafetyValveRecord ),
+    ReliefValve( ReliefValveRecord ),
+    BurstingDisk( BurstingDiskRecord ),
+    Generic( GenericRecord ),
+    OpenPipe( OpenPipeRecord ),
+    ControlValve( ControlValveRecord ),
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PipeRecord {
+    pub from: usize,
+    pub to: usize,
+    pub length: f64,                // [m]
+    pub diameter: f64,              // [m]
+    pub roughness: f64,             // [m]
+    pub thickness: f64,             // [m]
+    pub youngs_modulus: f64,        // [Pa]
+    pub segments: usize,            // Hidden reaches in transient runs (0 = one, or automatic if the solver splits pipes)
+    pub pressure_rating: f64,       // Maximum allowable gauge pressure (0 = not rated) [Pa]
+    pub friction: FrictionModel,
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValveRecord {
+    pub from: usize,
+    pub to: usize,
+    pub diameter: f64,              // [m]
+    pub thickness: f64,             // [m]
+    pub youngs_modulus: f64,        // [Pa]
+    pub valve_type: ValveType,
+    pub trim: Trim,
+    pub invk: Vec<(f64, f64)>,      // ( % open, k^-1 )
+    pub open_percent: f64,          // Steady opening [%]
+    pub events: Vec<EventRecord>,
+    pub series: Option<TimeSeries>, // Opening against time
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PumpRecord {
+    pub from: usize,
+    pub to: usize,
+    pub head_data: Vec<(f64, f64)>,     // ( theta [rad], F_h )
+    pub torque_data: Vec<(f64, f64)>,   // ( theta [rad], F_tau )
+    pub q_rated: f64,                   // [m^3/s]
+    pub h_rated: f64,                   // [m]
+    pub n_rated: f64,                   // [rpm]
+    pub diameter: f64,                  // Impeller diameter [m]
+    pub speed: f64,                     // Steady speed [rpm]
+    pub thickness: f64,                 // [m]
+    pub youngs_modulus: f64,            // [Pa]
+    pub inertia: f64,                   // [kg m^2]
+    pub motor_inertia: f64,             // [kg m^2]
+    pub flywheel: f64,                  // [kg m^2]
+    pub rated_torque: f64,              // [N m]
+    pub events: Vec<EventRecord>,
+    pub series: Option<TimeSeries>,     // Speed [rpm] against time
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BendRecord {
+    pub from: usize,
+    pub to: usize,
+    pub radius: f64,                // [m]
+    pub diameter: f64,              // [m]
+    pub angle: f64,                 // [rad]
+    pub roughness: f64,             // [m]
+    pub thickness: f64,             // [m]
+    pub youngs_modulus: f64,        // [Pa]
+    pub friction: FrictionModel,
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SizeChangeRecord {
+    pub from: usize,
+    pub to: usize,
+    pub diameter: f64,              // From diameter [m]
+    pub beta: f64,                  // To diameter / from diameter
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckValveRecord {
+    pub from: usize,
+    pub to: usize,
+    pub diameter: f64,              // [m]
+    pub thickness: f64,             // [m]
+    pub youngs_modulus: f64,        // [Pa]
+    pub invk: Vec<(f64, f64)>,      // ( % open, k^-1 )
+    pub open_percent: f64,          // Steady opening [%]
+    pub disc_inertia: f64,          // [kg m^2]
+    pub spring_torque: f64,         // [N m]
+    pub flow_torque: f64,           // [m^3]
+    pub open_angle: f64,            // [rad]
+    pub closure_time: f64,          // [s]
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafetyValveRecord {
+    pub from: usize,
+    pub to: usize,
+    pub diameter: f64,              // [m]
+    pub thickness: f64,             // [m]
+    pub youngs_modulus: f64,        // [Pa]
+    pub set_dp: f64,                // [Pa]
+    pub overpressure: f64,          // Rise above set_dp to full lift [Pa]
+    pub blowdown: f64,              // Fall below set_dp at which the valve reseats [Pa]
+    pub opening_time: f64,          // [s]
+    pub closing_time: f64,          // [s]
+    pub invk: Vec<(f64, f64)>,      // ( % open, k^-1 )
+    pub open_percent: f64,          // Steady opening [%]
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReliefValveRecord {
+    pub from: usize,
+    pub to: usize,
+    pub diameter: f64,              // [m]
+    pub thickness: f64,             // [m]
+    pub youngs_modulus: f64,        // [Pa]
+    pub invk: Vec<(f64, f64)>,      // ( % open, k^-1 )
+    pub open_dp: Vec<(f64, f64)>,   // ( dp [Pa], % open )
+    pub open_percent: f64,          // Steady opening [%]
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BurstingDiskRecord {
+    pub from: usize,
+    pub to: usize,
+    pub diameter: f64,              // [m]
+    pub thickness: f64,             // [m]
+    pub youngs_modulus: f64,        // [Pa]
+    pub burst_dp: f64,              // [Pa]
+    pub invk: Vec<(f64, f64)>,      // ( % open, k^-1 )
+    pub open_percent: f64,          // Steady opening [%]
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenericRecord {
+    pub from: usize,
+    pub to: usize,
+    pub coefficients: (f64, f64, f64), // ( A, B, C )
+    pub exponents: (f64, f64),         // ( n, m )
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OpenPipeRecord {
+    pub from: usize,
+    pub to: usize,
+    pub diameter: f64,              // [m]
+    pub k: f64,                     // Loss coefficient
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ControlValveRecord {
+    pub from: usize,
+    pub to: usize,
+    pub diameter: f64,              // [m]
+    pub thickness: f64,             // [m]
+    pub youngs_modulus: f64,        // [Pa]
+    pub invk: Vec<(f64, f64)>,      // ( % open, k^-1 )
+    pub open_percent: f64,          // Steady opening (starting guess) [%]
+    pub mode: ControlMode,
+    pub setpoint: f64,              // Pressure [Pa] or mass flow rate [kg/s]
+    pub actuator: Actuator,
+}
+
+impl Default for PipeRecord { fn default() -> Self { PipeRecord::from( &Pipe::new( 0, 0 ) ) } }
+impl Default for ValveRecord { fn default() -> Self { ValveRecord::from( &Valve::new( 0, 0 ) ) } }
+impl Default for PumpRecord { fn default() -> Self { PumpRecord::from( &Pump::new( 0, 0 ) ) } }
+impl Default for BendRecord { fn default() -> Self { BendRecord::from( &Bend::new( 0, 0 ) ) } }
+impl Default for SizeChangeRecord { fn default() -> Self { SizeChangeRecord::from( &SizeChange::new( 0, 0 ) ) } }
+impl Default for CheckValveRecord { fn default() -> Self { CheckValveRecord::from( &CheckValve::new( 0, 0 ) ) } }
+impl Default for SafetyValveRecord { fn default() -> Self { SafetyValveRecord::from( &SafetyValve::new( 0, 0, 0.0 ) ) } }
+impl Default for ReliefValveRecord { fn default() -> Self { ReliefValveRecord::from( &ReliefValve::new( 0, 0, 0.0, 0.0 ) ) } }
+impl Default for BurstingDiskRecord { fn default() -> Self { BurstingDiskRecord::from( &BurstingDisk::new( 0, 0, 0.0 ) ) } }
+impl Default for GenericRecord { fn default() -> Self { GenericRecord::from( &Generic::new( 0, 0 ) ) } }
+impl Default for OpenPipeRecord { fn default() -> Self { OpenPipeRecord::from( &OpenPipe::new( 0, 0 ) ) } }
+impl Default for ControlValveRecord { 
+    fn default() -> Self { ControlValveRecord::from( &ControlValve::new( 0, 0, ControlMode::PressureReducing, 0.0 ) ) } 
+}
+
+// Steady value of a time history
+fn first( values: &[f64] ) -> f64 {
+    values.first().copied().unwrap_or( 0.0 )
+}
+
+impl From<&Pipe> for PipeRecord {
+    fn from( edge: &Pipe ) -> Self {
+        PipeRecord { from: edge.from, to: edge.to, length: edge.length, diameter: edge.diameter,
+            roughness: edge.roughness, thickness: edge.thickness, youngs_modulus: edge.youngs_modulus,
+            segments: edge.segments, pressure_rating: edge.pressure_rating, friction: edge.friction }
+    }
+}
+
+impl From<&Valve> for ValveRecord {
+    fn from( edge: &Valve ) -> Self {
+        ValveRecord { from: edge.from, to: edge.to, diameter: edge.diameter, thickness: edge.thickness,
+            youngs_modulus: edge.youngs_modulus, valve_type: edge.valve_type.clone(), trim: edge.trim, 
+            invk: edge.invk.clone(), open_percent: first( &edge.open_percent ), events: EventRecord::from_events( &edge.events ),
+            series: edge.series.clone() }
+    }
+}
+
+impl From<&Pump> for PumpRecord {
+    fn from( edge: &Pump ) -> Self {
+        PumpRecord { from: edge.from, to: edge.to, head_data: edge.head_data.clone(),
+            torque_data: edge.torque_data.clone(), q_rated: edge.q_rated, h_rated: edge.h_rated,
+            n_rated: edge.n_rated, diameter: edge.diameter, speed: first( &edge.speed ), thickness: edge.thickness,
+            youngs_modulus: edge.youngs_modulus, inertia: edge.inertia, motor_inertia: edge.motor_inertia,
+            flywheel: edge.flywheel, rated_torque: edge.rated_torque, events: EventRecord::from_events( &edge.events ),
+            series: edge.series.clone() }
+    }
+}
+
+impl From<&Bend> for BendRecord {
+    fn from( edge: &Bend ) -> Self {
+        BendRecord { from: edge.from, to: edge.to, radius: edge.radius, diameter: edge.diameter,
+            angle: edge.angle, roughness: edge.roughness, thickness: edge.thickness,
+            youngs_modulus: edge.youngs_modulus, friction: edge.friction }
+    }
+}
+
+impl From<&SizeChange> for SizeChangeRecord {
+    fn from( edge: &SizeChange ) -> Self {
+        SizeChangeRecord { from: edge.from, to: edge.to, diameter: edge.diameter, beta: edge.beta }
+    }
+}
+
+impl From<&CheckValve> for CheckValveRecord {
+    fn from( edge: &CheckValve ) -> Self {
+        CheckValveRecord { from: edge.from, to: edge.to, diameter: edge.diameter,
+            thickness: edge.thickness, youngs_modulus: edge.youngs_modulus, invk: edge.invk.clone(),
+            open_percent: first( &edge.open_percent ), disc_inertia: edge.disc_inertia,
+            spring_torque: edge.spring_torque, flow_torque: edge.flow_torque, open_angle: edge.open_angle,
+            closure_time: edge.closure_time }
+    }
+}
+
+impl From<&SafetyValve> for SafetyValveRecord {
+    fn from( edge: &SafetyValve ) -> Self {
+        SafetyValveRecord { from: edge.from, to: edge.to, diameter: edge.diameter,
+            thickness: edge.thickness, youngs_modulus: edge.youngs_modulus, set_dp: edge.set_dp,
+            overpressure: edge.overpressure, blowdown: edge.blowdown, opening_time: edge.opening_time,
+            closing_time: edge.closing_time, invk: edge.invk.clone(), open_percent: first( &edge.open_percent ) }
+    }
+}
+
+impl From<&ReliefValve> for ReliefValveRecord {
+    fn from( edge: &ReliefValve ) -> Self {
+        ReliefValveRecord { from: edge.from, to: edge.to, diameter: edge.diameter,
+            thickness: edge.thickness, youngs_modulus: edge.youngs_modulus, invk: edge.invk.clone(),
+            open_dp: edge.open_dp.clone(), open_percent: first( &edge.open_percent ) }
+    }
+}
+
+impl From<&BurstingDisk> for BurstingDiskRecord {
+    fn from( edge: &BurstingDisk ) -> Self {
+        BurstingDiskRecord { from: edge.from, to: edge.to, diameter: edge.diameter,
+            thickness: edge.thickness, youngs_modulus: edge.youngs_modulus, burst_dp: edge.burst_dp,
+            invk: edge.invk.clone(), open_percent: first( &edge.open_percent ) }
+    }
+}
+
+impl From<&Generic> for GenericRecord {
+    fn from( edge: &Generic ) -> Self {
+        GenericRecord { from: edge.from, to: edge.to, coefficients: edge.coefficients,
+            exponents: edge.exponents }
+    }
+}
+
+impl From<&OpenPipe> for OpenPipeRecord {
+    fn from( edge: &OpenPipe ) -> Self {
+        OpenPipeRecord { from: edge.from, to: edge.to, diameter: edge.diameter, k: edge.k }
+    }
+}
+
+impl From<&ControlValve> for ControlValveRecord {
+    fn from( edge: &ControlValve ) -> Self {
+        ControlValveRecord { from: edge.from, to: edge.to, diameter: edge.diameter,
+            thickness: edge.thickness, youngs_modulus: edge.youngs_modulus, invk: edge.invk.clone(),
+            open_percent: first( &edge.open_percent ), mode: edge.mode, setpoint: edge.setpoint,
+            actuator: edge.actuator }
+    }
+}
+
+impl From<&Edge> for EdgeRecord {
+    fn from( edge: &Edge ) -> Self {
+        match edge {
+            Edge::Pipe( edge ) => EdgeRecord::Pipe( edge.into() ),
+            Edge::Valve( edge ) => EdgeRecord::Valve( edge.into() ),
+            Edge::Pump( edge ) => EdgeRecord::Pump( edge.into() ),
+            Edge::Bend( edge ) => EdgeRecord::Bend( edge.into() ),
+            Edge::SizeChange( edge ) => EdgeRecord::SizeChange( edge.into() ),
+            Edge::CheckValve( edge ) => EdgeRecord::CheckValve( edge.into() ),
+            Edge::SafetyValve( edge ) => EdgeRecord::SafetyValve( edge.into() ),
+            Edge::ReliefValve( edge ) => EdgeRecord::ReliefValve( edge.into() ),
+            Edge::BurstingDisk( edge ) => EdgeRecord::BurstingDisk( edge.into() ),
+            Edge::Generic( edge ) => EdgeRecord::Generic( edge.into() ),
+            Edge::OpenPipe( edge ) => EdgeRecord::OpenPipe( edge.into() ),
+            Edge::ControlValve( edge ) => EdgeRecord::ControlValve( edge.into() ),
+        }
+    }
+}
+
+impl EdgeRecord {
+    fn defaults() -> Vec<EdgeRecord> {
+        vec![
+            EdgeRecord::Pipe( PipeRecord::default() ),
+            EdgeRecord::Valve( ValveRecord::default() ),
+            EdgeRecord::Pump( PumpRecord::default() ),
+            EdgeRecord::Bend( BendRecord::default() ),
+            EdgeRecord::SizeChange( SizeChangeRecord::default() ),
+            EdgeRecord::CheckValve( CheckValveRecord::default() ),
+            EdgeRecord::SafetyValve( SafetyValveRecord::default() ),
+            EdgeRecord::ReliefValve( ReliefValveRecord::default() ),
+            EdgeRecord::BurstingDisk( BurstingDiskRecord::default() ),
+            EdgeRecord::Generic( GenericRecord::default() ),
+            EdgeRecord::OpenPipe( OpenPipeRecord::default() ),
+            EdgeRecord::ControlValve( ControlValveRecord::default() ),
+        ]
+    }
+
+    fn ids(&self) -> ( usize, usize ) {
+        match self {
+            EdgeRecord::Pipe( record ) => ( record.from, record.to ),
+            EdgeRecord::Valve( record ) => ( record.from, record.to ),
+            EdgeRecord::Pump( record ) => ( record.from, record.to ),
+            EdgeRecord::Bend( record ) => ( record.from, record.to ),
+            EdgeRecord::SizeChange( record ) => ( record.from, record.to ),
+            EdgeRecord::CheckValve( record ) => ( record.from, record.to ),
+            EdgeRecord::SafetyValve( record ) => ( record.from, record.to ),
+            EdgeRecord::ReliefValve( record ) => ( record.from, record.to ),
+            EdgeRecord::BurstingDisk( record ) => ( record.from, record.to ),
+            EdgeRecord::Generic( record ) => ( record.from, record.to ),
+            EdgeRecord::OpenPipe( record ) => ( record.from, record.to ),
+            EdgeRecord::ControlValve( record ) => ( record.from, record.to ),
+        }
+    }
+
+    pub fn edge(&self, ids: &HashSet<usize> ) -> Result<Edge, ProjectError> {
+        let ( from, to ) = self.ids();
+        if let Some( &id ) = [ from, to ].iter().find( |id| !ids.contains( id ) ) {
+            return Err( ProjectError::UnknownNode { id } );
+        }
+        Ok( match self {
+            EdgeRecord::Pipe( record ) => Edge::Pipe( Pipe {
+                segments: record.segments,
+                pressure_rating: record.pressure_rating,
+                friction: record.friction,
+                ..Pipe::new_params( from, to, record.length, record.diameter, record.roughness, record.thickness,
+                    record.youngs_modulus )
+            }),
+            EdgeRecord::Valve( record ) => Edge::Valve( Valve {
+                diameter: record.diameter,
+                thickness: record.thickness,
+                youngs_modulus: record.youngs_modulus,
+                valve_type: record.valve_type.clone(),
+                trim: record.trim,
+                invk: record.invk.clone(),
+                open_percent: vec![ record.open_percent ],
+                events: EventRecord::events( &record.events ),
+                series: record.series.clone(),
+                ..Valve::new( from, to )
+            }),
+            EdgeRecord::Pump( record ) => Edge::Pump( Pump {
+                head_data: record.head_data.clone(),
+                torque_data: record.torque_data.clone(),
+                q_rated: record.q_rated,
+                h_rated: record.h_rated,
+                n_rated: record.n_rated,
+                diameter: record.diameter,
+                speed: vec![ record.speed ],
+                thickness: record.thickness,
+                youngs_modulus: record.youngs_modulus,
+                inertia: record.inertia,
+                motor_inertia: record.motor_inertia,
+                flywheel: record.flywheel,
+                rated_torque: record.rated_torque,
+                events: EventRecord::events( &record.events ),
+                series: record.series.clone(),
+                ..Pump::new( from, to )
+            }),
+            EdgeRecord::Bend( record ) => Edge::Bend( Bend {
+                friction: record.friction,
+                ..Bend::new_params( from, to, record.radius, record.diameter, record.angle, record.roughness,
+                    record.thickness, record.youngs_modulus )
+            }),
+            EdgeRecord::SizeChange( record ) => Edge::SizeChange( SizeChange::new_params( from, to, record.diameter,
+                record.beta ) ),
+            EdgeRecord::CheckValve( record ) => Edge::CheckValve( CheckValve {
+                diameter: record.diameter,
+                thickness: record.thickness,
+                youngs_modulus: record.youngs_modulus,
+                invk: record.invk.clone(),
+                open_percent: vec![ record.open_percent ],
+                disc_inertia: record.disc_inertia,
+                spring_torque: record.spring_torque,
+                flow_torque: record.flow_torque,
+                open_angle: record.open_angle,
+                closure_time: record.closure_time,
+                ..CheckValve::new( from, to )
+            }),
+            EdgeRecord::SafetyValve( record ) => Edge::SafetyValve( SafetyValve {
+                diameter: record.diameter,
+                thickness: record.thickness,
+                youngs_modulus: record.youngs_modulus,
+                overpressure: record.overpressure,
+                blowdown: record.blowdown,
+                opening_time: record.opening_time,
+                closing_time: record.closing_time,
+                invk: record.invk.clone(),
+                open_percent: vec![ record.open_percent ],
+                ..SafetyValve::new( from, to, record.set_dp )
+            }),
+            EdgeRecord::ReliefValve( record ) => Edge::ReliefValve( ReliefValve {
+                diameter: record.diameter,
+                thickness: record.thickness,
+                youngs_modulus: record.youngs_modulus,
+                invk: record.invk.clone(),
+                open_dp: record.open_dp.clone(),
+                open_percent: vec![ record.open_percent ],
+                ..ReliefValve::new( from, to, 0.0, 0.0 )
+            }),
+            EdgeRecord::BurstingDisk( record ) => Edge::BurstingDisk( BurstingDisk {
+                diameter: record.diameter,
+                thickness: record.thickness,
+                youngs_modulus: record.youngs_modulus,
+                invk: record.invk.clone(),
+                open_percent: vec![ record.open_percent ],
+                ..BurstingDisk::new( from, to, record.burst_dp )
+            }),
+            EdgeRecord::Generic( record ) => Edge::Generic( Generic::new_params( from, to, record.coefficients,
+                record.exponents ) ),
+            EdgeRecord::OpenPipe( record ) => Edge::OpenPipe( OpenPipe::new_params( from, to, record.diameter,
+                record.k ) ),
+            EdgeRecord::ControlValve( record ) => Edge::ControlValve( ControlValve {
+                diameter: record.diameter,
+                thickness: record.thickness,
+                youngs_modulus: record.youngs_modulus,
+                invk: record.invk.clone(),
+                open_percent: vec![ record.open_percent ],
+                actuator: record.actuator,
+                ..ControlValve::new( from, to, record.mode, record.setpoint )
+            }),
+        })
+    }
+}
+
+// Version 0 to 1
+
+// Version 0 is the serialised Graph ( externally tagged nodes and edges, which held copies of their
+// nodes in older files )
+fn from_graph( value: Value ) -> Result<Value, ProjectError> {
+    let error = |message: &str| ProjectError::Json( message.to_string() );
+    let object = value.as_object().ok_or_else( || error( "expected an object" ) )?;
+    let node_defaults: Vec<Value> = NodeRecord::defaults().iter().map( |node| serde_json::to_value( node ).unwrap() ).collect();
+    let edge_defaults: Vec<Value> = EdgeRecord::defaults().iter().map( |edge| serde_json::to_value( edge ).unwrap() ).collect();
+    let mut nodes = Vec::new();
+    for node in object.get( "nodes" ).and_then( Value::as_array ).ok_or_else( || error( "missing nodes" ) )? {
+        let ( name, fields ) = tagged( node )?;
+        let name = if name == "Hidden" { "Connection" } else { name };
+        let mut node = record( name, fields, &node_defaults )?;
+        // Version 1 kept the demand pattern of a flow node
+        for key in [ "pattern", "pattern_step" ] {
+            if let ( "Flow", Some( value ) ) = ( name, fields.get( key ) ) { node[ key ] = value.clone(); }
+        }
+        nodes.push( node );
+    }
+    let mut edges = Vec::new();
+    for edge in object.get( "edges" ).and_then( Value::as_array ).ok_or_else( || error( "missing edges" ) )? {
+        let ( name, fields ) = tagged( edge )?;
+        let mut fields = fields.clone();
+        for key in [ "from", "to" ] {
+            let id = fields.get( key ).and_then( |node| match node {
+                Value::Number( _ ) => Some( node.clone() ),
+                _ => tagged( node ).ok().and_then( |( _, node )| node.get( "id" ).cloned() ),
+            })
+                .ok_or_else( || error( &format!( "{} edge has no {} node", name, key ) ) )?;
+            fields.insert( key.to_string(), id );
+        }
+        edges.push( record( name, &fields, &edge_defaults )? );
+    }
+    let mut project = json!({ "nodes": nodes, "edges": edges });
+    if let Some( fluid ) = object.get( "fluid" ) {
+        let fluid: Fluid = serde_json::from_value( fluid.clone() ).map_err( |e| error( &e.to_string() ) )?;
+        project[ "fluid" ] = serde_json::to_value( FluidRecord::from( &fluid ) ).unwrap();
+    }
+    if let Some( solver ) = object.get( "solver" ) {
+        let defaults = serde_json::to_value( Settings::default() ).unwrap();
+        let solver = solver.as_object().ok_or_else( || error( "solver is not an object" ) )?;
+        project[ "solver" ] = Value::Object( solver.iter()
+            .filter( |( key, _ )| defaults.get( key.as_str() ).is_some() )
+            .map( |( key, value )| ( key.clone(), value.clone() ) ).collect() );
+    }
+    Ok( project )
+}
+
+// Name and fields of an externally tagged enum value
+fn tagged( value: &Value ) -> Result<( &str, &Map<String, Value> ), ProjectError> {
+    value.as_object().filter( |object| object.len() == 1 )
+        .and_then( |object| object.iter().next() )
+        .and_then( |( name, fields )| fields.as_object().map( |fields| ( name.as_str(), fields ) ) )
+        .ok_or_else( || ProjectError::Json( format!( "expected a tagged component, found {}", value ) ) )
+}
+
+// Keep the fields of the record with this type, taking the steady value of any time history
+fn record( name: &str, fields: &Map<String, Value>, defaults: &[Value] ) -> Result<Value, ProjectError> {
+    let default = defaults.iter().find( |record| record[ "type" ] == name )
+        .ok_or_else( || ProjectError::Json( format!( "unknown component {}", name ) ) )?;
+    let mut record = Map::new();
+    record.insert( "type".to_string(), json!( name ) );
+    for ( key, value ) in fields {
+        let key = if key == "loc" { "location" } else { key.as_str() };
+        let Some( default ) = default.get( key ) else { continue };
+        let value = match ( key, value ) {
+            ( "events", Value::Array( events ) ) => Value::Array( events.iter().filter_map( event ).collect() ),
+            ( _, Value::Array( values ) ) if default.is_number() => values.first().cloned().unwrap_or( json!( 0.0 ) ),
+            _ => value.clone(),
+        };
+        record.insert( key.to_string(), value );
+    }
+    Ok( Value::Object( record ) )
+}
+
+// Convert an externally tagged TransientEvent
+fn event( value: &Value ) -> Option<Value> {
+    let event: TransientEvent = serde_json::from_value( value.clone() ).ok()?;
+    EventRecord::from_events( &[ event ] ).pop().map( |record| serde_json::to_value( record ).unwrap() )
+}
+
+// Version 1 to 2
+
+// The demand pattern ( multipliers of the consumption, each lasting pattern_step seconds ) of a flow
+// node becomes a series of consumptions. A node that already had a series ignored its pattern.
+fn pattern_to_series( mut value: Value ) -> Result<Value, ProjectError> {
+    let nodes = value.get_mut( "nodes" ).and_then( Value::as_array_mut )
+        .ok_or_else( || ProjectError::Json( "missing nodes".to_string() ) )?;
+    for node in nodes.iter_mut().filter_map( Value::as_object_mut ) {
+        let pattern = node.remove( "pattern" );
+        let step = node.remove( "pattern_step" ).and_then( |step| step.as_f64() ).unwrap_or( 3600.0 );
+        let Some( pattern ) = pattern else { continue };
+        let multipliers: Vec<f64> = serde_json::from_value( pattern )
+            .map_err( |error| ProjectError::Json( format!( "pattern: {}", error ) ) )?;
+        if multipliers.is_empty() || node.get( "series" ).is_some_and( |series| !series.is_null() ) { continue; }
+        let consumption = node.get( "consumption" ).and_then( Value::as_f64 ).unwrap_or( FlowRecord::default().consumption );
+        let consumptions: Vec<f64> = multipliers.iter().map( |m| m * consumption ).collect();
+        node.insert( "series".to_string(), serde_json::to_value( TimeSeries::pattern( &consumptions, step ) ).unwrap() );
+    }
+    Ok( value )
+}
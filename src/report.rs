@@ -0,0 +1,191 @@
+//! Transient envelope report
+//!
+//! The maximum and minimum pressure and head at each node and at each point along the pipes and
+//! bends, with the time each occurred. Pressures are absolute [Pa]. Ratings are gauge pressures,
+//! so the margin is the rating less the largest gauge pressure (negative when it is exceeded).
+//! A node is rated from an explicit value or otherwise from the lowest rating of its pipes.
+
+use std::collections::HashMap;
+
+use crate::edge::Edge;
+use crate::fluid::Fluid;
+use crate::graph::Graph;
+use crate::solver::Solver;
+
+const ATMOSPHERIC: f64 = 101325.0;     // [Pa]
+const TOLERANCE: f64 = 1.0e-6;         // Round-off allowed below a limit [Pa]
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Extreme {
+    pub value: f64,
+    pub time: f64,                      // [s]
+}
+
+#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct NodeEnvelope {
+    pub id: usize,
+    pub max_pressure: Extreme,          // [Pa]
+    pub min_pressure: Extreme,          // [Pa]
+    pub max_head: Extreme,              // [m]
+    pub min_head: Extreme,              // [m]
+    pub rating: Option<f64>,            // [Pa]
+    pub margin: Option<f64>,            // [Pa]
+}
+
+#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct PipeEnvelope {
+    pub from: usize,
+    pub to: usize,
+    pub distance: Vec<f64>,             // Distance of each point from the from node [m]
+    pub max_pressure: Vec<Extreme>,     // [Pa]
+    pub min_pressure: Vec<Extreme>,     // [Pa]
+    pub max_head: Vec<Extreme>,         // [m]
+    pub min_head: Vec<Extreme>,         // [m]
+    pub rating: Option<f64>,            // [Pa]
+    pub margin: Option<f64>,            // Smallest margin along the pipe [Pa]
+    pub worst_distance: Option<f64>,    // Where the smallest margin occurs [m]
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum Place {
+    Node { id: usize },
+    Pipe { from: usize, to: usize, distance: f64 },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Exceedance {
+    pub place: Place,
+    pub pressure: f64,                  // Lowest pressure [Pa]
+    pub time: f64,                      // [s]
+    pub depth: f64,                     // Amount below the limit [Pa]
+}
+
+#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct TransientReport {
+    pub nodes: Vec<NodeEnvelope>,
+    pub pipes: Vec<PipeEnvelope>,
+    pub vapour: Option<Exceedance>,             // Worst pressure below the vapour pressure
+    pub sub_atmospheric: Option<Exceedance>,    // Worst pressure below atmospheric
+}
+
+impl TransientReport {
+    // Envelope of a run, with explicit node ratings ( node id, rating [Pa] ) taking priority
+    pub fn new( graph: &Graph, solver: &Solver, fluid: &Fluid, ratings: &HashMap<usize, f64> ) -> Self {
+        let ( rho, g ) = ( fluid.density(), solver.gravity() );
+        let time = solver.tnodes();
+        let mut report = TransientReport::default();
+
+        // Lowest rating of the pipes at each node
+        let mut pipe_ratings: HashMap<usize, f64> = HashMap::new();
+        for edge in graph.edges.iter() {
+            if let Edge::Pipe( pipe ) = edge {
+                if pipe.pressure_rating <= 0.0 { continue; }
+                for id in [ pipe.from, pipe.to ] {
+                    let rating = pipe_ratings.entry( id ).or_insert( pipe.pressure_rating );
+                    *rating = rating.min( pipe.pressure_rating );
+                }
+            }
+        }
+
+        for node in graph.nodes.iter() {
+            let mut node = node.clone();
+            let id = node.id();
+            let elevation = *node.elevation();
+            let pressure = node.pressure().clone();
+            let head: Vec<f64> = pressure.iter().map( |p| elevation + p / ( rho * g ) ).collect();
+            let ( max_pressure, min_pressure ) = extremes( &pressure, &time );
+            let ( max_head, min_head ) = extremes( &head, &time );
+            let rating = ratings.get( &id ).or( pipe_ratings.get( &id ) ).copied();
+            report.nodes.push( NodeEnvelope {
+                id, max_pressure, min_pressure, max_head, min_head, rating,
+                margin: rating.map( |rating| rating - ( max_pressure.value - ATMOSPHERIC ) ),
+            });
+            report.exceedance( Place::Node { id }, min_pressure, fluid.vapour_pressure() );
+        }
+
+        let profiles = solver.profiles();
+        for (j, edge) in graph.edges.iter().enumerate() {
+            let Some( length ) = edge.conduit_length() else { continue };
+            let ( from, to ) = edge.id();
+            let rating = match edge {
+                Edge::Pipe( pipe ) if pipe.pressure_rating > 0.0 => Some( pipe.pressure_rating ),
+                _ => None,
+            };
+            let mut envelope = PipeEnvelope { from, to, rating, ..PipeEnvelope::default() };
+            match profiles.get( j ).and_then( |profile| profile.as_ref() ) {
+                Some( profile ) => {
+                    envelope.distance = profile.distance.clone();
+                    for k in 0..profile.points() {
+                        let head: Vec<f64> = profile.head.iter().map( |row| row[k] ).collect();
+                        let pressure: Vec<f64> = head.iter()
+                            .map( |h| ( h - profile.elevation[k] ) * rho * g ).collect();
+                        envelope.push( extremes( &pressure, &time ), extremes( &head, &time ) );
+                    }
+                },
+                // Without a transient profile the pipe is described by its end nodes
+                None => {
+                    envelope.distance = vec![ 0.0, length ];
+                    for id in [ from, to ] {
+                        let node = report.nodes.iter().find( |node| node.id == id ).unwrap();
+                        envelope.push( ( node.max_pressure, node.min_pressure ), ( node.max_head, node.min_head ) );
+                    }
+                },
+            }
+            if let Some( rating ) = rating {
+                let margins = envelope.max_pressure.iter().map( |p| rating - ( p.value - ATMOSPHERIC ) );
+                let ( k, margin ) = margins.enumerate().fold( ( 0, f64::INFINITY ),
+                    |worst, (k, margin)| if margin < worst.1 { ( k, margin ) } else { worst } );
+                envelope.margin = Some( margin );
+                envelope.worst_distance = Some( envelope.distance[k] );
+            }
+            for (k, min_pressure) in envelope.min_pressure.iter().enumerate() {
+                let place = Place::Pipe { from, to, distance: envelope.distance[k] };
+                report.exceedance( place, *min_pressure, fluid.vapour_pressure() );
+            }
+            report.pipes.push( envelope );
+        }
+        report
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty( self ).unwrap()
+    }
+
+    // Smallest margin of all the rated nodes and pipes [Pa]
+    pub fn min_margin(&self) -> Option<f64> {
+        self.nodes.iter().filter_map( |node| node.margin )
+            .chain( self.pipes.iter().filter_map( |pipe| pipe.margin ) )
+            .reduce( f64::min )
+    }
+
+    // Keep the lowest pressure below the vapour pressure and below atmospheric
+    fn exceedance(&mut self, place: Place, min_pressure: Extreme, vapour_pressure: f64 ) {
+        for ( worst, limit ) in [ ( &mut self.vapour, vapour_pressure ), ( &mut self.sub_atmospheric, ATMOSPHERIC ) ] {
+            let depth = limit - min_pressure.value;
+            if depth > TOLERANCE && worst.is_none_or( |worst| depth > worst.depth ) {
+                *worst = Some( Exceedance { place, pressure: min_pressure.value, time: min_pressure.time, depth } );
+            }
+        }
+    }
+}
+
+impl PipeEnvelope {
+    fn push(&mut self, pressure: ( Extreme, Extreme ), head: ( Extreme, Extreme ) ) {
+        self.max_pressure.push( pressure.0 );
+        self.min_pressure.push( pressure.1 );
+        self.max_head.push( head.0 );
+        self.min_head.push( head.1 );
+    }
+}
+
+// Maximum and minimum values with the first time each occurred
+fn extremes( values: &[f64], time: &[f64] ) -> ( Extreme, Extreme ) {
+    if values.is_empty() { return ( Extreme::default(), Extreme::default() ); }
+    let at = |step: usize| Extreme { value: values[step], time: time.get( step ).copied().unwrap_or( 0.0 ) };
+    let ( mut max, mut min ) = ( 0, 0 );
+    for (step, value) in values.iter().enumerate() {
+        if *value > values[max] { max = step; }
+        if *value < values[min] { min = step; }
+    }
+    ( at( max ), at( min ) )
+}
@@ -0,0 +1,336 @@
+//! Results export
+//!
+//! A `Table` collects the node and edge results of a run into time-indexed columns. The first
+//! column is the time and the others are named `<quantity>_<id> [<unit>]` for nodes (e.g.
+//! `p_3 [bar]`) and `<quantity>_<from>_<to> [<unit>]` for edges (e.g. `q_1_2 [L/s]`).
+//!
+//! Tables are written as CSV or in a columnar binary format for large runs. All integers and
+//! values in the binary format are little-endian:
+//!
+//! | Field            | Type                                   |
+//! |------------------|----------------------------------------|
+//! | Magic            | 8 bytes, `EKICOL01`                    |
+//! | Rows             | u64                                    |
+//! | Columns          | u64                                    |
+//! | Names            | u32 byte length and UTF-8 text, each   |
+//! | Values           | `rows` f64 values for each column      |
+//!
+//! In Python the values of column `k` can be read with
+//! `numpy.frombuffer( data, "<f8", rows, offset + 8 * rows * k )`.
+
+use crate::fluid::Fluid;
+use crate::graph::Graph;
+use crate::utility;
+
+const MAGIC: &[u8; 8] = b"EKICOL01";
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum ResultsError {
+    Io( String ),
+    UnknownNode { id: usize },
+    UnknownEdge { from: usize, to: usize },
+    Length { column: String, expected: usize, found: usize }, // Column and time axis differ in length
+    Format( String ),                                           // Not a valid columnar file
+}
+
+impl std::fmt::Display for ResultsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResultsError::Io( message ) => write!(f, "{}", message),
+            ResultsError::UnknownNode { id } => write!(f, "Unknown node id {}", id),
+            ResultsError::UnknownEdge { from, to } => write!(f, "Unknown edge {} -> {}", from, to),
+            ResultsError::Length { column, expected, found } =>
+                write!(f, "Column {} has {} values but there are {} time steps", column, found, expected),
+            ResultsError::Format( message ) => write!(f, "Invalid columnar file: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ResultsError {}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub enum PressureUnit {
+    #[default]
+    Pascal,
+    KiloPascal,
+    Bar,
+    Psi,
+}
+
+impl PressureUnit {
+    pub fn text(&self) -> &'static str {
+        match self {
+            PressureUnit::Pascal => "Pa",
+            PressureUnit::KiloPascal => "kPa",
+            PressureUnit::Bar => "bar",
+            PressureUnit::Psi => "psi",
+        }
+    }
+
+    // Size of the unit [Pa]
+    pub fn scale(&self) -> f64 {
+        match self {
+            PressureUnit::Pascal => 1.0,
+            PressureUnit::KiloPascal => 1.0e3,
+            PressureUnit::Bar => utility::BAR,
+            PressureUnit::Psi => utility::PSI,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub enum FlowUnit {
+    #[default]
+    CubicMetresPerSecond,
+    LitresPerSecond,
+    CubicMetresPerHour,
+    GallonsPerMinute,                   // US gallons
+}
+
+impl FlowUnit {
+    pub fn text(&self) -> &'static str {
+        match self {
+            FlowUnit::CubicMetresPerSecond => "m^3/s",
+            FlowUnit::LitresPerSecond => "L/s",
+            FlowUnit::CubicMetresPerHour => "m^3/h",
+            FlowUnit::GallonsPerMinute => "gpm",
+        }
+    }
+
+    // Size of the unit [m^3/s]
+    pub fn scale(&self) -> f64 {
+        match self {
+            FlowUnit::CubicMetresPerSecond => 1.0,
+            FlowUnit::LitresPerSecond => 1.0e-3,
+            FlowUnit::CubicMetresPerHour => 1.0 / 3600.0,
+            FlowUnit::GallonsPerMinute => utility::GALLON / 60.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum NodeQuantity {
+    Pressure,                           // Absolute or gauge pressure in the pressure unit
+    Head,                               // Piezometric head [m]
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum EdgeQuantity {
+    MassFlow,                           // [kg/s]
+    Flow,                               // Volume flow rate in the flow unit
+    Velocity,                           // Mean velocity [m/s]
+}
+
+// What to export and in which units
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Options {
+    pub nodes: Option<Vec<usize>>,              // Node ids ( None = all nodes )
+    pub edges: Option<Vec<(usize, usize)>>,     // Edge ( from id, to id ) ( None = all edges )
+    pub node_quantities: Vec<NodeQuantity>,
+    pub edge_quantities: Vec<EdgeQuantity>,
+    pub pressure_unit: PressureUnit,
+    pub gauge: bool,                            // Pressure relative to atmospheric ( 101325 Pa )
+    pub flow_unit: FlowUnit,
+    pub every: usize,                           // Keep every nth time step ( the last is always kept )
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            nodes: None,
+            edges: None,
+            node_quantities: vec![ NodeQuantity::Pressure ],
+            edge_quantities: vec![ EdgeQuantity::Flow ],
+            pressure_unit: PressureUnit::Pascal,
+            gauge: false,
+            flow_unit: FlowUnit::CubicMetresPerSecond,
+            every: 1,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Table {
+    pub names: Vec<String>,             // Column names ( the first is the time )
+    pub columns: Vec<Vec<f64>>,         // Values of each column
+}
+
+impl Table {
+    // Collect the results of a run with time axis `time` ( e.g. Solver::tnodes )
+    pub fn new( graph: &Graph, fluid: &Fluid, g: f64, time: &[f64], options: &Options ) -> Result<Table, ResultsError> {
+        let rho = fluid.density();
+        let mut table = Table { names: vec![ "time [s]".to_string() ], columns: vec![ time.to_vec() ] };
+        let ids = match &options.nodes {
+            Some( ids ) => ids.clone(),
+            None => graph.nodes.iter().map( |node| node.id() ).collect(),
+        };
+        for id in ids {
+            let mut node = graph.nodes.iter().find( |node| node.id() == id ).cloned()
+                .ok_or( ResultsError::UnknownNode { id } )?;
+            let elevation = *node.elevation();
+            let pressure = node.pressure().clone();
+            for quantity in options.node_quantities.iter() {
+                let ( name, values ) = match quantity {
+                    NodeQuantity::Pressure => {
+                        let offset = if options.gauge { 101325.0 } else { 0.0 };
+                        let unit = options.pressure_unit;
+                        ( format!( "p_{} [{}]", id, unit.text() ),
+                          pressure.iter().map( |p| ( p - offset ) / unit.scale() ).collect() )
+                    },
+                    NodeQuantity::Head => ( format!( "h_{} [m]", id ),
+                        pressure.iter().map( |p| elevation + p / ( rho * g ) ).collect() ),
+                };
+                table.push( name, values, time.len() )?;
+            }
+        }
+        let ends = match &options.edges {
+            Some( ends ) => ends.clone(),
+            None => graph.edges.iter().map( |edge| edge.id() ).collect(),
+        };
+        for ( from, to ) in ends {
+            let mut edge = graph.edges.iter().find( |edge| edge.id() == ( from, to ) ).cloned()
+                .ok_or( ResultsError::UnknownEdge { from, to } )?;
+            let area = edge.area();
+            let mass_flow = edge.mass_flow().clone();
+            for quantity in options.edge_quantities.iter() {
+                let ( name, values ) = match quantity {
+                    EdgeQuantity::MassFlow => ( format!( "m_{}_{} [kg/s]", from, to ), mass_flow.clone() ),
+                    EdgeQuantity::Flow => {
+                        let unit = options.flow_unit;
+                        ( format!( "q_{}_{} [{}]", from, to, unit.text() ),
+                          mass_flow.iter().map( |m| m / ( rho * unit.scale() ) ).collect() )
+                    },
+                    EdgeQuantity::Velocity => ( format!( "v_{}_{} [m/s]", from, to ),
+                        mass_flow.iter().map( |m| m / ( rho * area ) ).collect() ),
+                };
+                table.push( name, values, time.len() )?;
+            }
+        }
+        table.decimate( options.every );
+        Ok( table )
+    }
+
+    pub fn rows(&self) -> usize {
+        self.columns.first().map_or( 0, |column| column.len() )
+    }
+
+    pub fn column(&self, name: &str ) -> Option<&Vec<f64>> {
+        self.names.iter().position( |column| column == name ).map( |k| &self.columns[k] )
+    }
+
+    fn push(&mut self, name: String, values: Vec<f64>, rows: usize ) -> Result<(), ResultsError> {
+        if values.len() != rows {
+            return Err( ResultsError::Length { column: name, expected: rows, found: values.len() } );
+        }
+        self.names.push( name );
+        self.columns.push( values );
+        Ok(())
+    }
+
+    // Keep every nth row and the last row
+    fn decimate(&mut self, every: usize ) {
+        let rows = self.rows();
+        if every <= 1 || rows == 0 { return; }
+        let keep = |i: usize| i.is_multiple_of( every ) || i == rows - 1;
+        for column in self.columns.iter_mut() {
+            *column = column.iter().enumerate().filter( |(i, _)| keep( *i ) ).map( |(_, v)| *v ).collect();
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut text = self.names.iter().map( |name| csv_field( name ) ).collect::<Vec<_>>().join( "," );
+        text.push( '\n' );
+        for i in 0..self.rows() {
+            let row: Vec<String> = self.columns.iter().map( |column| column[i].to_string() ).collect();
+            text.push_str( &row.join( "," ) );
+            text.push( '\n' );
+        }
+        text
+    }
+
+    pub fn write_csv(&self, path: &std::path::Path ) -> Result<(), ResultsError> {
+        std::fs::write( path, self.to_csv() ).map_err( |error| ResultsError::Io( error.to_string() ) )
+    }
+
+    pub fn to_columnar(&self) -> Vec<u8> {
+        let rows = self.rows();
+        let mut bytes = Vec::with_capacity( 24 + 8 * rows * self.columns.len() );
+        bytes.extend_from_slice( MAGIC );
+        bytes.extend_from_slice( &( rows as u64 ).to_le_bytes() );
+        bytes.extend_from_slice( &( self.columns.len() as u64 ).to_le_bytes() );
+        for name in self.names.iter() {
+            bytes.extend_from_slice( &( name.len() as u32 ).to_le_bytes() );
+            bytes.extend_from_slice( name.as_bytes() );
+        }
+        for column in self.columns.iter() {
+            for value in column.iter() {
+                bytes.extend_from_slice( &value.to_le_bytes() );
+            }
+        }
+        bytes
+    }
+
+    pub fn write_columnar(&self, path: &std::path::Path ) -> Result<(), ResultsError> {
+        std::fs::write( path, self.to_columnar() ).map_err( |error| ResultsError::Io( error.to_string() ) )
+    }
+
+    pub fn from_columnar( bytes: &[u8] ) -> Result<Table, ResultsError> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take( 8 )? != MAGIC {
+            return Err( ResultsError::Format( "missing EKICOL01 header".to_string() ) );
+        }
+        let rows = reader.u64()? as usize;
+        let columns = reader.u64()? as usize;
+        let mut table = Table::default();
+        for _ in 0..columns {
+            let length = u32::from_le_bytes( reader.take( 4 )?.try_into().unwrap() ) as usize;
+            let name = std::str::from_utf8( reader.take( length )? )
+                .map_err( |_| ResultsError::Format( "column name is not UTF-8".to_string() ) )?;
+            table.names.push( name.to_string() );
+        }
+        let length = rows.checked_mul( 8 )
+            .ok_or_else( || ResultsError::Format( "too many rows".to_string() ) )?;
+        for _ in 0..columns {
+            let values = reader.take( length )?.chunks_exact( 8 )
+                .map( |value| f64::from_le_bytes( value.try_into().unwrap() ) ).collect();
+            table.columns.push( values );
+        }
+        if reader.position != bytes.len() {
+            return Err( ResultsError::Format( "unexpected data after the last column".to_string() ) );
+        }
+        Ok( table )
+    }
+
+    pub fn read_columnar( path: &std::path::Path ) -> Result<Table, ResultsError> {
+        let bytes = std::fs::read( path ).map_err( |error| ResultsError::Io( error.to_string() ) )?;
+        Table::from_columnar( &bytes )
+    }
+}
+
+// Quote a CSV field if it contains a separator or a quote
+fn csv_field( text: &str ) -> String {
+    if text.contains( [ ',', '"', '\n' ] ) {
+        format!( "\"{}\"", text.replace( '"', "\"\"" ) )
+    } else {
+        text.to_string()
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize ) -> Result<&'a [u8], ResultsError> {
+        let end = self.position.checked_add( length ).filter( |end| *end <= self.bytes.len() )
+            .ok_or_else( || ResultsError::Format( "file is truncated".to_string() ) )?;
+        let slice = &self.bytes[ self.position..end ];
+        self.position = end;
+        Ok( slice )
+    }
+
+    fn u64(&mut self) -> Result<u64, ResultsError> {
+        Ok( u64::from_le_bytes( self.take( 8 )?.try_into().unwrap() ) )
+    }
+}
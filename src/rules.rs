@@ -0,0 +1,120 @@
+//! Rule-based controls
+//!
+//! A `Rule` spawns events on nodes and edges when all of its conditions hold. The rules of a
+//! `Graph` are checked by the solver at the end of every time step, so the events they spawn act
+//! from the next step. The time of a spawned event is measured from the moment the rule fires.
+//! Spawned events are kept apart from the events of the node or edge for the run and are cleared
+//! at the next steady solution. A spawned event takes over from any event still in progress when
+//! it starts. The solver keeps the state of each rule for the run in a `RuleState`.
+//!
+//! A rule fires once its conditions have held for `delay` seconds. Without latching the delay
+//! restarts whenever a condition fails and the rule may fire again once its conditions have
+//! cleared. A latching rule starts its delay the first time the conditions hold, keeps counting
+//! if they clear and fires only once.
+
+use crate::events::TransientEvent;
+
+const TOLERANCE: f64 = 1.0e-9;          // Allowance for rounding in the solver times [s]
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub enum Comparison {
+    #[default]
+    Above,
+    Below,
+}
+
+impl Comparison {
+    pub fn holds(&self, value: f64, threshold: f64 ) -> bool {
+        match self {
+            Comparison::Above => value > threshold,
+            Comparison::Below => value < threshold,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum Condition {
+    Pressure { id: usize, comparison: Comparison, value: f64 },            // Node pressure [Pa]
+    Head { id: usize, comparison: Comparison, value: f64 },                // Node head [m]
+    Level { id: usize, comparison: Comparison, value: f64 },               // Tank level [m]
+    Flow { from: usize, to: usize, comparison: Comparison, value: f64 },   // Edge mass flow rate [kg/s]
+    Time { comparison: Comparison, value: f64 },                           // [s]
+}
+
+impl Condition {
+    pub fn comparison(&self) -> Comparison {
+        match self {
+            Condition::Pressure { comparison, .. } | Condition::Head { comparison, .. } | Condition::Level { comparison, .. }
+            | Condition::Flow { comparison, .. } | Condition::Time { comparison, .. } => *comparison,
+        }
+    }
+
+    pub fn threshold(&self) -> f64 {
+        match self {
+            Condition::Pressure { value, .. } | Condition::Head { value, .. } | Condition::Level { value, .. }
+            | Condition::Flow { value, .. } | Condition::Time { value, .. } => *value,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum Target {
+    Node { id: usize },
+    Edge { from: usize, to: usize },
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Action {
+    pub target: Target,
+    pub event: TransientEvent,          // Times are measured from the moment the rule fires
+}
+
+#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Rule {
+    pub name: String,
+    pub conditions: Vec<Condition>,     // All must hold
+    pub delay: f64,                     // Time the conditions must hold before the rule fires [s]
+    pub latching: bool,
+    pub actions: Vec<Action>,
+}
+
+// Run state of a rule that the solver carries from one time step to the next
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RuleState {
+    pub since: Option<f64>,             // Time the conditions started to hold [s]
+    pub active: bool,                   // Fired and the conditions have not yet cleared
+    pub fired: Vec<f64>,                // Times at which the rule fired [s]
+}
+
+impl Rule {
+    pub fn new( name: &str, conditions: Vec<Condition>, actions: Vec<Action> ) -> Self {
+        Rule { name: name.to_string(), conditions, actions, ..Rule::default() }
+    }
+
+    // Update the state of the rule with whether its conditions hold at a time and return true
+    // if it fires
+    pub fn update(&self, state: &mut RuleState, time: f64, holds: bool ) -> bool {
+        if self.latching {
+            if !state.fired.is_empty() { return false; }
+            if holds && state.since.is_none() { state.since = Some( time ); }
+        } else {
+            if !holds {
+                state.since = None;
+                state.active = false;
+            }
+            if state.active { return false; }
+            if holds && state.since.is_none() { state.since = Some( time ); }
+        }
+        match state.since {
+            Some( since ) if time - since >= self.delay - TOLERANCE => {
+                state.active = true;
+                state.fired.push( time );
+                true
+            },
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Time series for boundary conditions
+//!
+//! A `TimeSeries` gives a value at any time from a list of ( time [s], value ) points, either
+//! interpolated linearly or held until the next point. A series with a period repeats, so a daily
+//! demand pattern is a step series with a period of 86400 s. Outside the points of a series that
+//! does not repeat the first or last value is held. Series can be read from two column CSV files.
+
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum SeriesError {
+    Io( String ),
+    Empty,
+    Parse { line: usize },                  // Not a pair of numbers
+    Unsorted { point: usize },              // Times do not increase (counting from 1)
+    Period,                                 // Points do not fit in the period
+}
+
+impl std::fmt::Display for SeriesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SeriesError::Io( message ) => write!(f, "{}", message),
+            SeriesError::Empty => write!(f, "The series has no points"),
+            SeriesError::Parse { line } => write!(f, "Expected a time and a value on line {}", line),
+            SeriesError::Unsorted { point } => write!(f, "Time does not increase at point {}", point),
+            SeriesError::Period => write!(f, "The points do not fit within the period"),
+        }
+    }
+}
+
+impl std::error::Error for SeriesError {}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    Step,                                   // Each value holds until the next point
+}
+
+#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TimeSeries {
+    pub points: Vec<(f64, f64)>,            // ( time [s], value )
+    pub interpolation: Interpolation,
+    pub period: f64,                        // Repeat interval (0 = not periodic) [s]
+}
+
+impl TimeSeries {
+    pub fn linear( points: Vec<(f64, f64)> ) -> Self {
+        TimeSeries { points, interpolation: Interpolation::Linear, period: 0.0 }
+    }
+
+    pub fn step( points: Vec<(f64, f64)> ) -> Self {
+        TimeSeries { points, interpolation: Interpolation::Step, period: 0.0 }
+    }
+
+    // Repeating values that each last for step seconds (an EPANET style pattern)
+    pub fn pattern( values: &[f64], step: f64 ) -> Self {
+        let points = values.iter().enumerate().map( |(i, value)| ( i as f64 * step, *value ) ).collect();
+        TimeSeries::step( points ).periodic( values.len() as f64 * step )
+    }
+
+    // The values and step of a series made by pattern (None for any other series)
+    pub fn as_pattern(&self) -> Option<( Vec<f64>, f64 )> {
+        if self.interpolation != Interpolation::Step || self.points.is_empty() { return None; }
+        let step = self.period / self.points.len() as f64;
+        let even = self.points.iter().enumerate().all( |(i, point)| ( point.0 - i as f64 * step ).abs() <= 1.0e-9 * step );
+        if step <= 0.0 || !even { return None; }
+        Some( ( self.points.iter().map( |point| point.1 ).collect(), step ) )
+    }
+
+    pub fn periodic( mut self, period: f64 ) -> Self {
+        self.period = period;
+        self
+    }
+
+    // Read a series from CSV text with a time and a value on each line. A header line and
+    // lines starting with # are skipped.
+    pub fn from_csv( text: &str, interpolation: Interpolation ) -> Result<Self, SeriesError> {
+        let mut points = vec![];
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with( '#' ) { continue; }
+            let fields: Vec<&str> = line.split( ',' ).map( |field| field.trim() ).collect();
+            let numbers: Vec<Option<f64>> = fields.iter().map( |field| field.parse::<f64>().ok() ).collect();
+            match numbers.as_slice() {
+                [ Some( time ), Some( value ) ] => points.push( ( *time, *value ) ),
+                _ if points.is_empty() && numbers.iter().all( |number| number.is_none() ) => continue,
+                _ => return Err( SeriesError::Parse { line: index + 1 } ),
+            }
+        }
+        let series = TimeSeries { points, interpolation, period: 0.0 };
+        series.check()?;
+        Ok( series )
+    }
+
+    pub fn read_csv<P: AsRef<Path>>( path: P, interpolation: Interpolation ) -> Result<Self, SeriesError> {
+        let text = std::fs::read_to_string( path ).map_err( |error| SeriesError::Io( error.to_string() ) )?;
+        TimeSeries::from_csv( &text, interpolation )
+    }
+
+    // Check there are points in increasing time order that fit within the period
+    pub fn check(&self) -> Result<(), SeriesError> {
+        if self.points.is_empty() { return Err( SeriesError::Empty ); }
+        for i in 1..self.points.len() {
+            if self.points[i].0 <= self.points[i - 1].0 { return Err( SeriesError::Unsorted { point: i + 1 } ); }
+        }
+        let ( first, last ) = ( self.points[0].0, self.points[ self.points.len() - 1 ].0 );
+        if self.period > 0.0 && ( first < 0.0 || last >= self.period ) { return Err( SeriesError::Period ); }
+        Ok( () )
+    }
+
+    // Value at a time [s] (zero for a series without points)
+    pub fn value(&self, time: f64 ) -> f64 {
+        let Some( &( first, first_value ) ) = self.points.first() else { return 0.0 };
+        let &( last, last_value ) = self.points.last().unwrap();
+        let periodic = self.period > 0.0;
+        let time = if periodic { time.rem_euclid( self.period ) } else { time };
+        // A periodic series wraps from its last point to its first
+        if time < first {
+            if !periodic { return first_value; }
+            return match self.interpolation {
+                Interpolation::Step => last_value,
+                Interpolation::Linear => lerp( ( last - self.period, last_value ), ( first, first_value ), time ),
+            };
+        }
+        if time >= last {
+            if !periodic || self.interpolation == Interpolation::Step { return last_value; }
+            return lerp( ( last, last_value ), ( first + self.period, first_value ), time );
+        }
+        let upper = self.points.partition_point( |point| point.0 <= time );
+        match self.interpolation {
+            Interpolation::Step => self.points[upper - 1].1,
+            Interpolation::Linear => lerp( self.points[upper - 1], self.points[upper], time ),
+        }
+    }
+}
+
+fn lerp( a: (f64, f64), b: (f64, f64), time: f64 ) -> f64 {
+    if b.0 == a.0 { return b.1; }
+    a.1 + ( b.1 - a.1 ) * ( time - a.0 ) / ( b.0 - a.0 )
+}
@@ -1,7 +1,19 @@
-use ohsl::{vector::Vec64, matrix::Mat64};
-use crate::graph::Graph;
+use ohsl::vector::Vec64;
+use crate::graph::{ Graph, Diagnostic };
 use crate::fluid::Fluid;
+use crate::sparse::{ SparseMatrix, SparseLU, SparseError };
 use crate::utility;
+use crate::characteristics::Reaches;
+use crate::profile::Profile;
+use crate::edge::{ Edge, EdgeState, MAX_REACHES };
+use crate::friction::FrictionModel;
+use crate::rules::RuleState;
+use crate::node::Node;
+use crate::nodes::air_valve::AirValve;
+use crate::edges::control_valve::{ ControlValve, ControlMode, ControlStatus };
+
+const MAX_STATUS_CHANGES: usize = 10;  // Control valve status changes allowed in a steady solve
+const CLOSED_LEAKAGE: f64 = 1.0e-9;     // Flow through a closed control valve per unit head [m^2/s]
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct Solver {
@@ -15,12 +27,42 @@ pub struct Solver {
     g: f64,                     // Acceleration due to gravity [m/s^2]
     tnodes: Vec<f64>,           // Time vector for transient solver [s]
     theta: f64,                 // Numerical scheme parameter
+    #[serde(default = "default_cavitation")]
+    cavitation: bool,           // Form vapour cavities when the pressure falls to the vapour pressure
+    #[serde(default)]
+    friction: FrictionModel,    // Friction model of pipes and bends without one of their own
+    #[serde(default)]
+    split_pipes: bool,          // Split pipes without segments into reaches in lumped transient runs
+    #[serde(skip)]
+    lu: SparseLU,               // Sparse factorisation (analysis is reused between iterations)
+    #[serde(skip)]
+    reaches: Vec<Option<Reaches>>, // Pipe and bend discretisation for the characteristics solver
+    #[serde(skip)]
+    segments: Vec<usize>,       // Reaches of each edge in the transient solver
+    #[serde(skip)]
+    hidden: ( Vec<f64>, Vec<f64> ), // Flow rates and heads in the hidden reaches of the pipes
+    #[serde(skip)]
+    profiles: Vec<Option<Profile>>, // Head and flow along the pipes and bends at each time step
+    #[serde(skip)]
+    cavity: Vec<f64>,           // Vapour cavity volume at the nodes and hidden nodes [m^3]
+    #[serde(skip)]
+    cavity_volumes: Vec<Vec<f64>>, // Vapour cavity volume at each node for every time step [m^3]
+    #[serde(skip)]
+    edge_states: Vec<EdgeState>, // Run state of each edge carried between time steps
+    #[serde(skip)]
+    rule_states: Vec<RuleState>, // Run state of each rule of the network
+}
+
+fn default_cavitation() -> bool {
+    true
 }
 
 #[derive(serde::Serialize, serde::Deserialize, PartialEq, Clone)]
 pub enum SolverType {
     Steady,
     Transient,
+    Characteristics,            // Transient with pipes and bends solved by the method of characteristics
+    ExtendedPeriod,             // Sequence of steady solutions with tank levels integrated between them
 }
 
 impl std::fmt::Debug for SolverType {
@@ -28,6 +70,121 @@ impl std::fmt::Debug for SolverType {
         match self {
             SolverType::Steady => write!(f, "Steady"),
             SolverType::Transient => write!(f, "Transient"),
+            SolverType::Characteristics => write!(f, "Characteristics"),
+            SolverType::ExtendedPeriod => write!(f, "Extended period"),
+        }
+    }
+}
+
+// Solver settings saved in a project file
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub solver_type: SolverType,
+    pub max_iter: usize,        // Maximum number of iterations
+    pub tolerance: f64,         // Tolerance for convergence
+    pub tmax: f64,              // Maximum simulation time [s]
+    pub dt: f64,                // Time step [s]
+    pub g: f64,                 // Acceleration due to gravity [m/s^2]
+    pub theta: f64,             // Numerical scheme parameter
+    pub cavitation: bool,       // Form vapour cavities when the pressure falls to the vapour pressure
+    pub friction: FrictionModel, // Friction model of pipes and bends without one of their own
+    pub split_pipes: bool,      // Split pipes without segments into reaches in lumped transient runs
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Solver::default().settings()
+    }
+}
+
+// Component of the network associated with a solver failure
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum Component {
+    Node(usize),                // Node id
+    Edge(usize, usize),         // Edge ( from id, to id )
+}
+
+impl std::fmt::Display for Component {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Component::Node(id) => write!(f, "node {}", id),
+            Component::Edge(from, to) => write!(f, "edge {} -> {}", from, to),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum SolverError {
+    EmptyNetwork,                           // No nodes or no edges in the network
+    SteadyNotSolved,                        // Transient step requested before the steady solve
+    UnknownNode { id: usize },              // Edge refers to a node id that is not in the network
+    InvalidNetwork { diagnostics: Vec<Diagnostic> }, // Problems found by Graph::validate
+    NotConverged { iterations: usize, residual: f64, component: Option<Component> },
+    SingularMatrix { iterations: usize, residual: f64, component: Option<Component> },
+    NaNResidual { iterations: usize, component: Option<Component> },
+}
+
+impl std::fmt::Display for SolverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolverError::EmptyNetwork => write!(f, "The network has no nodes or edges"),
+            SolverError::SteadyNotSolved => write!(f, "The steady problem has not been solved"),
+            SolverError::UnknownNode { id } => write!(f, "Unknown node id {}", id),
+            SolverError::InvalidNetwork { diagnostics } => {
+                write!(f, "Invalid network")?;
+                for (i, diagnostic) in diagnostics.iter().enumerate() {
+                    write!(f, "{} {}", if i == 0 { ":" } else { ";" }, diagnostic)?;
+                }
+                Ok(())
+            },
+            SolverError::NotConverged { iterations, residual, component } => {
+                write!(f, "Did not converge after {} iterations (residual = {:+.2e}", iterations, residual)?;
+                if let Some( component ) = component { write!(f, " at {}", component)?; }
+                write!(f, ")")
+            },
+            SolverError::SingularMatrix { iterations, component, .. } => {
+                write!(f, "Singular matrix after {} iterations", iterations)?;
+                if let Some( component ) = component { write!(f, " at {}", component)?; }
+                Ok(())
+            },
+            SolverError::NaNResidual { iterations, component } => {
+                write!(f, "NaN residual after {} iterations", iterations)?;
+                if let Some( component ) = component { write!(f, " at {}", component)?; }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl std::error::Error for SolverError {}
+
+impl SolverError {
+    pub fn iterations(&self) -> Option<usize> {
+        match self {
+            SolverError::NotConverged { iterations, .. } => Some( *iterations ),
+            SolverError::SingularMatrix { iterations, .. } => Some( *iterations ),
+            SolverError::NaNResidual { iterations, .. } => Some( *iterations ),
+            _ => None,
+        }
+    }
+
+    pub fn residual(&self) -> Option<f64> {
+        match self {
+            SolverError::NotConverged { residual, .. } => Some( *residual ),
+            SolverError::SingularMatrix { residual, .. } => Some( *residual ),
+            SolverError::NaNResidual { .. } => Some( f64::NAN ),
+            _ => None,
+        }
+    }
+
+    pub fn component(&self) -> Option<Component> {
+        match self {
+            SolverError::NotConverged { component, .. } => *component,
+            SolverError::SingularMatrix { component, .. } => *component,
+            SolverError::NaNResidual { component, .. } => *component,
+            SolverError::UnknownNode { id } => Some( Component::Node( *id ) ),
+            _ => None,
         }
     }
 }
@@ -45,13 +202,25 @@ impl Default for Solver {
             g: 9.80665,
             tnodes: vec![0.0],
             theta: 1.0, // 0 = explicit, 1 = implicit, 0.5 = Crank-Nicolson
+            cavitation: default_cavitation(),
+            friction: FrictionModel::Default,
+            split_pipes: false,
+            lu: SparseLU::new(),
+            reaches: Vec::new(),
+            segments: Vec::new(),
+            hidden: ( Vec::new(), Vec::new() ),
+            profiles: Vec::new(),
+            cavity: Vec::new(),
+            cavity_volumes: Vec::new(),
+            edge_states: Vec::new(),
+            rule_states: Vec::new(),
         }
     }
 }
 
 impl Solver {
 
-    pub fn tnodes(&mut self) -> Vec<f64> {
+    pub fn tnodes(&self) -> Vec<f64> {
         self.tnodes.clone()
     }
 
@@ -63,6 +232,26 @@ impl Solver {
         &mut self.theta
     }
 
+    pub fn cavitation(&mut self) -> &mut bool {
+        &mut self.cavitation
+    }
+
+    // Default friction model of pipes and bends (Default gives Praks-Brkic)
+    pub fn friction(&mut self) -> &mut FrictionModel {
+        &mut self.friction
+    }
+
+    // Split pipes without segments to resolve pressure waves in the lumped transient solver (the
+    // characteristics solver always does)
+    pub fn split_pipes(&mut self) -> &mut bool {
+        &mut self.split_pipes
+    }
+
+    // Vapour cavity volume at each node (by index) for every time step [m^3]
+    pub fn cavity_volumes(&self) -> &Vec<Vec<f64>> {
+        &self.cavity_volumes
+    }
+
     pub fn reset(&mut self) {
         self.solved_steady = false;
         self.solved_transient = false;
@@ -105,29 +294,61 @@ impl Solver {
         &mut self.solver_type
     }
 
+    pub fn settings(&self) -> Settings {
+        Settings {
+            solver_type: self.solver_type.clone(),
+            max_iter: self.max_iter,
+            tolerance: self.tolerance,
+            tmax: self.tmax,
+            dt: self.dt,
+            g: self.g,
+            theta: self.theta,
+            cavitation: self.cavitation,
+            friction: self.friction,
+            split_pipes: self.split_pipes,
+        }
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.solver_type = settings.solver_type.clone();
+        self.max_iter = settings.max_iter;
+        self.tolerance = settings.tolerance;
+        self.tmax = settings.tmax;
+        self.dt = settings.dt;
+        self.g = settings.g;
+        self.theta = settings.theta;
+        self.cavitation = settings.cavitation;
+        self.friction = settings.friction;
+        self.split_pipes = settings.split_pipes;
+    }
+
     pub fn solved(&self) -> (bool, bool) {
         (self.solved_steady, self.solved_transient )
     }
 
     pub fn is_transient(&self) -> bool {
-        self.solver_type == SolverType::Transient
+        self.solver_type == SolverType::Transient || self.solver_type == SolverType::Characteristics
     }
 
     pub fn solve_steady(&mut self, network: &mut Graph, fluid: &Fluid, create_guess: bool ) 
-        -> Result<usize,f64> 
+        -> Result<usize,SolverError> 
     {
-        let nu: f64 = fluid.kinematic_viscosity();
         let (n, m) = ( network.num_nodes(), network.num_edges() );
-        let size = n + m;
-        if size == 0 { return Err(1.0); }
-        if m == 0 { return Err(1.0); }
+        if n + m == 0 || m == 0 { 
+            self.solved_steady = false;
+            return Err(SolverError::EmptyNetwork); 
+        }
 
         network.create_id_to_index();
+        if let Err( error ) = check_network( network ) {
+            self.solved_steady = false;
+            return Err( error );
+        }
+        network.start_series();
+        network.clear_spawned();
+        self.rule_states = vec![ RuleState::default(); network.rules.len() ];
 
-        let k = network.k_matrix();
-        let kt = network.incidence_matrix();
-
-        let ( mut q_guess, mut h_guess ): ( ohsl::Vec64, ohsl::Vec64 );
+        let ( q_guess, h_guess ): ( ohsl::Vec64, ohsl::Vec64 );
         if create_guess {
             ( q_guess, h_guess ) = utility::laminar_guess( network, fluid, self.g );
             //println!( "q_guess = {}", q_guess );
@@ -136,177 +357,925 @@ impl Solver {
             ( q_guess, h_guess ) = network.steady_solution_qh( fluid.density(), self.g );
         }
 
+        let heads: Vec<Option<f64>> = network.nodes.iter_mut().map( |node| 
+            if node.is_known_pressure() || node.is_tank() { 
+                Some( node.steady_head( self.g, fluid.density() ) ) 
+            } else { None } ).collect();
+        let consumption = network.steady_consumption_q( fluid.density() );
+        match self.newton( network, fluid, 0, &heads, &consumption, q_guess, h_guess ) {
+            Ok( steady ) => {
+                network.set_steady_solution( steady.q, steady.h, fluid.density(), self.g );
+                network.set_control_states( 0, &steady.controls );
+                self.solved_steady = true;
+                Ok( steady.iterations )
+            },
+            Err( error ) => {
+                self.solved_steady = false;
+                Err( error )
+            },
+        }
+    }
+
+    // Solve the steady equations at a time step with the head imposed at the nodes where 
+    // heads[i] is known. The openings of the control valves are extra unknowns (through their 
+    // loss coefficients) and the equations are solved again whenever a valve changes status. 
+    // Returns the flow rates, heads, control valve states ( opening, status ) and the number of 
+    // iterations.
+    #[allow(clippy::too_many_arguments)]
+    fn newton(&mut self, network: &Graph, fluid: &Fluid, step: usize, heads: &[Option<f64>], 
+        consumption: &Vec64, mut q_guess: Vec64, mut h_guess: Vec64 ) -> Result<Steady,SolverError> 
+    {
+        let nu: f64 = fluid.kinematic_viscosity();
+        let (n, m) = ( network.num_nodes(), network.num_edges() );
+        let k = network.k_sparse();
+        let kt = network.incidence_sparse();
+        let ends = network.edge_indices();
+        // Control valves with their target head or flow rate
+        let controls: Vec<(usize, &ControlValve, f64)> = network.control_indices().into_iter().map( |j| {
+            let Edge::ControlValve( valve ) = &network.edges[j] else { unreachable!() };
+            let node = match valve.mode {
+                ControlMode::PressureSustaining => ends[j].0,
+                _ => ends[j].1,
+            };
+            let elevation = *network.nodes[node].clone().elevation();
+            ( j, valve, valve.target( elevation, fluid.density(), self.g ) )
+        }).collect();
+        let size = n + m + controls.len();
+        // Active valves start fully open so that they throttle from a converged flow
+        let mut states: Vec<(f64, ControlStatus)> = controls.iter().map( |(_, valve, _)| match valve.status {
+            ControlStatus::Active => ( valve.open_loss(), ControlStatus::Open ),
+            status => {
+                let open = valve.open_percent[ step.min( valve.open_percent.len() - 1 ) ];
+                ( valve.loss_coefficient( open ), status )
+            },
+        }).collect();
+
         let mut iter: usize = 0;
+        let mut total_iter: usize = 0;
         let mut max_residual = 1.0;
-        while iter < self.max_iter && max_residual > self.tolerance {
-            let mut b = Vec64::new( size, 0.0 );
-            let mut mat = Mat64::new( size, size, 0.0 );
-            // Continuity equation at each node
-            let mut continuity_residual = network.steady_consumption_q( fluid.density() );
-            continuity_residual -= kt.clone() * q_guess.clone();
-            for i in 0..n {
-                for j in 0..m {
-                    mat[i][j] = kt[i][j];
+        let mut worst: usize = 0;
+        let mut changes: usize = 0;
+        loop {
+            while iter < self.max_iter && max_residual > self.tolerance {
+                let mut b = Vec64::new( size, 0.0 );
+                let mut triplets = Vec::with_capacity( 5 * m + n );
+                // Continuity equation at each node
+                let mut continuity_residual = consumption.clone();
+                continuity_residual -= kt.multiply( &q_guess );
+                for (j, &(from, to)) in ends.iter().enumerate() {
+                    if heads[from].is_none() { triplets.push( ( from, j, 1.0 ) ); }
+                    if heads[to].is_none() { triplets.push( ( to, j, -1.0 ) ); }
                 }
-                b[i] = continuity_residual[i];
-            }
-            // Fill the resistance Jacobian matrix in bottom left corner
-            let khg = k.clone() * h_guess.clone();
-            for j in 0..m {
-                mat[n + j][j] = - network.edges[j].drdq( q_guess[j], khg[j], nu, self.g, 0 );
-                b[n + j] = network.edges[j].resistance( q_guess[j], khg[j], nu, self.g, 0 );
-            }
-            // Fill the G matrix in bottom right corner
-            for i in 0..m {
-                let drdkh = network.edges[i].drdkh( q_guess[i], khg[i], nu, self.g, 0 );
-                for j in 0..n {
-                    mat[n+i][m+j] = - drdkh * k[i][j];
+                for i in 0..n {
+                    b[i] = continuity_residual[i];
                 }
-            }
-            // Insert boundary conditions 
-            for i in 0..n {
-                if network.nodes[i].is_known_pressure() || network.nodes[i].is_tank() {
-                    // Clear row
-                    for k in 0..n+m {
-                        mat[i][k] = 0.0;
-                        b[i] = 0.0;
+                // Fill the resistance Jacobian matrix in bottom left corner
+                // and the G matrix in bottom right corner
+                let khg = k.multiply( &h_guess );
+                for (j, &(from, to)) in ends.iter().enumerate() {
+                    if controls.iter().any( |control| control.0 == j ) { continue; }
+                    let drdq = network.edges[j].drdq( q_guess[j], khg[j], nu, self.g, step, self.friction );
+                    let drdkh = network.edges[j].drdkh( q_guess[j], khg[j], nu, self.g, step, self.friction );
+                    triplets.push( ( n + j, j, - drdq ) );
+                    triplets.push( ( n + j, m + from, - drdkh ) );
+                    triplets.push( ( n + j, m + to, drdkh ) );
+                    b[n + j] = network.edges[j].resistance( q_guess[j], khg[j], nu, self.g, step, self.friction );
+                }
+                // A control valve holds its setpoint while active, is fully open or passes no flow
+                for (c, &(j, valve, target)) in controls.iter().enumerate() {
+                    let ( from, to ) = ends[j];
+                    let ( loss, status ) = states[c];
+                    let row = n + m + c;
+                    if status == ControlStatus::Closed {
+                        // A closed valve leaks so that the heads either side are still determined
+                        triplets.push( ( n + j, j, 1.0 ) );
+                        triplets.push( ( n + j, m + from, - CLOSED_LEAKAGE ) );
+                        triplets.push( ( n + j, m + to, CLOSED_LEAKAGE ) );
+                        b[n + j] = CLOSED_LEAKAGE * khg[j] - q_guess[j];
+                    } else {
+                        let ( drdq, drdkh, drdk ) = valve.resistance_k_derivatives( q_guess[j], self.g, loss );
+                        triplets.push( ( n + j, j, - drdq ) );
+                        triplets.push( ( n + j, m + from, - drdkh ) );
+                        triplets.push( ( n + j, m + to, drdkh ) );
+                        triplets.push( ( n + j, row, - drdk ) );
+                        b[n + j] = valve.resistance_k( q_guess[j], khg[j], self.g, loss );
+                    }
+                    match ( status, valve.mode ) {
+                        ( ControlStatus::Active, ControlMode::PressureReducing ) => {
+                            triplets.push( ( row, m + to, 1.0 ) );
+                            b[row] = target - h_guess[to];
+                        },
+                        ( ControlStatus::Active, ControlMode::PressureSustaining ) => {
+                            triplets.push( ( row, m + from, 1.0 ) );
+                            b[row] = target - h_guess[from];
+                        },
+                        ( ControlStatus::Active, ControlMode::FlowControl ) => {
+                            triplets.push( ( row, j, 1.0 ) );
+                            b[row] = target - q_guess[j];
+                        },
+                        ( ControlStatus::Open, _ ) => {
+                            triplets.push( ( row, row, 1.0 ) );
+                            b[row] = valve.open_loss() - loss;
+                        },
+                        ( ControlStatus::Closed, _ ) => {
+                            triplets.push( ( row, row, 1.0 ) );
+                        },
+                    }
+                }
+                // Insert boundary conditions 
+                for i in 0..n {
+                    if let Some( head ) = heads[i] {
+                        triplets.push( ( i, m + i, 1.0 ) );
+                        b[i] = head - h_guess[i];
+                    }
+                }
+
+                let mat = SparseMatrix::from_triplets( size, size, &triplets );
+                let correction = match self.lu.factorise( &mat ) {
+                    Ok( () ) => self.lu.solve( &b ),
+                    Err( SparseError::Singular( col ) ) => {
+                        return Err( SolverError::SingularMatrix { 
+                            iterations: iter, residual: max_residual, component: Some( component( network, col ) ) 
+                        });
+                    },
+                };
+
+                //println!( "q_guess = {}", q_guess );
+                //println!( "h_guess = {}", h_guess );
+                //println!( "correction = {}", correction );
+                utility::update_solution( &mut q_guess, &mut h_guess, &correction );
+                // The loss coefficients converge relative to their size
+                let mut scaled = correction.clone();
+                for (c, state) in states.iter_mut().enumerate() {
+                    state.0 += correction[n + m + c];
+                    scaled[n + m + c] /= state.0.abs().max( 1.0 );
+                }
+                
+                iter += 1;
+                let ( index, residual ) = max_correction( &scaled );
+                max_residual = residual;
+                worst = index;
+                // An active valve driven to less loss than fully open is opened straight away 
+                // rather than left to add head to the flow
+                for (c, &(j, valve, _)) in controls.iter().enumerate() {
+                    let ( loss, status ) = states[c];
+                    if status == ControlStatus::Active && loss < valve.open_loss() && q_guess[j] >= 0.0 {
+                        states[c] = ( valve.open_loss(), ControlStatus::Open );
+                        max_residual = max_residual.max( 1.0 );
                     }
-                    mat[i][m+i] = 1.0;
-                    let head = network.nodes[i].steady_head( self.g, fluid.density() );
-                    b[i] = head - h_guess[i];
+                }
+                if max_residual.is_nan() {
+                    return Err( SolverError::NaNResidual { iterations: iter, component: Some( component( network, index ) ) } );
                 }
             }
+            total_iter += iter;
+            if iter >= self.max_iter { break; }
 
-            //println!( "mat = {}", mat );
-            let correction = mat.solve_basic( b.clone() );
+            // Solve again if any control valve changes status
+            let mut changed = false;
+            for (c, &(j, valve, target)) in controls.iter().enumerate() {
+                let ( from, to ) = ends[j];
+                let ( loss, status ) = states[c];
+                let next = valve.next_status( status, loss, q_guess[j], ( h_guess[from], h_guess[to] ), target );
+                if next != status {
+                    states[c].1 = next;
+                    changed = true;
+                }
+            }
+            if !changed {
+                let controls = controls.iter().zip( states ).map( |(&(_, valve, _), ( loss, status ))| match status {
+                    ControlStatus::Active => ( valve.opening_for( 1.0 / loss ), status ),
+                    ControlStatus::Open => ( valve.invk.last().unwrap().0, status ),
+                    ControlStatus::Closed => ( valve.invk[0].0, status ),
+                }).collect();
+                return Ok( Steady { q: q_guess, h: h_guess, controls, iterations: total_iter } );
+            }
+            changes += 1;
+            if changes > MAX_STATUS_CHANGES { break; }
+            ( iter, max_residual ) = ( 0, 1.0 );
+        }
 
-            //println!( "q_guess = {}", q_guess );
-            //println!( "h_guess = {}", h_guess );
-            //println!( "correction = {}", correction );
-            utility::update_solution( &mut q_guess, &mut h_guess, &correction );
-            
-            max_residual = correction.norm_inf();
-            iter += 1;
+        Err( SolverError::NotConverged { 
+            iterations: total_iter, residual: max_residual, component: Some( component( network, worst ) ) 
+        })
+    }
+
+    // The pipe and bend discretisation used by the characteristics solver (None for other edges)
+    pub fn reaches(&self) -> &Vec<Option<Reaches>> {
+        &self.reaches
+    }
+
+    // Head and flow along each pipe and bend (None for other edges)
+    pub fn profiles(&self) -> &Vec<Option<Profile>> {
+        &self.profiles
+    }
+
+    // Run state of each edge at the last time step
+    pub fn edge_states(&self) -> &Vec<EdgeState> {
+        &self.edge_states
+    }
+
+    // Run state of each rule at the last time step
+    pub fn rule_states(&self) -> &Vec<RuleState> {
+        &self.rule_states
+    }
+
+    // Extend the node and edge values for the next time step (the run state of the edges starts
+    // afresh with each run)
+    fn add_transient_values(&mut self, network: &mut Graph, time: f64 ) {
+        if self.tnodes.len() == 1 || self.edge_states.len() != network.num_edges() {
+            self.edge_states = vec![ EdgeState::default(); network.num_edges() ];
         }
+        let dt = time - self.tnodes[ self.tnodes.len() - 1 ];
+        network.add_transient_values( time, dt, &mut self.edge_states );
+    }
 
-        if iter < self.max_iter && !max_residual.is_nan() {
-            network.set_steady_solution( q_guess, h_guess, fluid.density(), self.g );
-            self.solved_steady = true;
-            Ok(iter)
-        } else {
-            self.solved_steady = false;
-            Err(max_residual)
+    // Check the rules at the end of a time step (rules added since the steady solution start the
+    // run state of the rules afresh)
+    fn apply_rules(&mut self, network: &mut Graph, time: f64, step: usize, rho: f64 ) {
+        if self.rule_states.len() != network.rules.len() {
+            self.rule_states = vec![ RuleState::default(); network.rules.len() ];
         }
+        network.apply_rules( time, step, rho, self.g, &mut self.rule_states );
     }
 
-    pub fn time_step(&mut self, network: &mut Graph, fluid: &Fluid ) -> Result<usize,f64> {
+    // Reaches that resolve the pressure waves in an edge. The characteristics solver always splits
+    // pipes and bends while the lumped solver splits pipes automatically only when asked to.
+    fn wave_reaches(&self, edge: &Edge, fluid: &Fluid ) -> usize {
+        match edge {
+            _ if self.solver_type == SolverType::Characteristics => edge.reach_count( fluid, self.dt, true ),
+            Edge::Pipe(_) => edge.reach_count( fluid, self.dt, self.split_pipes ),
+            _ => 1,
+        }
+    }
+
+    fn check_reaches(&self, network: &Graph, fluid: &Fluid ) -> Result<(), SolverError> {
+        let diagnostics: Vec<Diagnostic> = network.edges.iter().enumerate().filter_map( |(edge, e)| {
+            let reaches = self.wave_reaches( e, fluid );
+            ( reaches > MAX_REACHES ).then_some( Diagnostic::TooManyReaches { edge, reaches } )
+        }).collect();
+        if diagnostics.is_empty() { Ok(()) } else { Err( SolverError::InvalidNetwork { diagnostics } ) }
+    }
+
+    pub fn time_step(&mut self, network: &mut Graph, fluid: &Fluid ) -> Result<usize,SolverError> {
+        let (n, m) = ( network.num_nodes(), network.num_edges() );
+        if n + m == 0 || m == 0 { return Err(SolverError::EmptyNetwork); }
+        if !self.solved_steady { return Err(SolverError::SteadyNotSolved); }
+        // The network is checked once at the start of a run (events may be added after the
+        // steady solve) rather than at every step
+        if self.tnodes.len() == 1 { check_network( network )?; }
+        if self.solver_type == SolverType::ExtendedPeriod { return self.period_step( network, fluid ); }
+        if self.tnodes.len() == 1 { self.check_reaches( network, fluid )?; }
+
         let step = self.tnodes.len() - 1;
         //println!("Time step {}", step);
-        let ( qn, hn ) = network.current_solution_qh( fluid.density(), self.g, step ); 
-        let ( mut qg, mut hg ) = ( qn.clone(), hn.clone() );
+        let ( q_network, h_network ) = network.current_solution_qh( fluid.density(), self.g, step ); 
         let dt = *self.dt();
         let invdt = 1.0 / dt;
 
         // Create extra values in vectors using events
         let time = self.tnodes[step] + dt;
-        for node in network.mut_nodes() {
-            node.add_transient_value( time );
+        self.add_transient_values( network, time );
+
+        let ends = network.edge_indices();
+        let m_diag = network.m_diag( fluid, self.g );
+        let moc = self.solver_type == SolverType::Characteristics;
+        let nu = fluid.kinematic_viscosity();
+
+        // Pipes are split into hidden reaches (the characteristics solver handles its own). The
+        // reaches are fixed from the time step at the start of the transient run.
+        if step == 0 || self.profiles.len() != m {
+            self.segments = network.edges.iter().map( |edge|
+                if moc { 1 } else { self.wave_reaches( edge, fluid ) } ).collect();
+            let layout = Layout::new( n, &ends, &self.segments );
+            self.hidden = layout.initial( &q_network, &h_network );
+            self.cavity = vec![ 0.0; n + layout.hidden ];
+            self.cavity_volumes = vec![ vec![ 0.0; step + 1 ]; n ];
+            for (i, node) in network.nodes.iter_mut().enumerate() {
+                if let Node::Accumulator(vessel) = node {
+                    vessel.initialise( h_network[i], fluid.density(), self.g );
+                }
+                if let Node::Tank(tank) = node {
+                    let level = tank.level_at( h_network[i], fluid.density(), self.g );
+                    tank.level = vec![ level; step + 1 ];
+                    tank.overflow = vec![ 0.0; step + 1 ];
+                    tank.air_entry = vec![ 0.0; step + 1 ];
+                }
+            }
+            self.profiles = network.edges.iter().enumerate().map( |(j, edge)| {
+                let length = edge.conduit_length()?;
+                let segments = self.wave_reaches( edge, fluid );
+                let elevation = ( *network.nodes[ends[j].0].elevation(), *network.nodes[ends[j].1].elevation() );
+                let mut profile = Profile::new( length, segments, elevation );
+                let ( head, flow ) = layout.linear_profile( j, segments, &q_network, &h_network );
+                for _ in 0..=step { profile.push( head.clone(), flow.clone() ); }
+                Some( profile )
+            }).collect();
+        }
+        let segments = self.segments.clone();
+        let layout = Layout::new( n, &ends, &segments );
+        let ( big_m, big_n ) = ( m + layout.hidden, n + layout.hidden );
+        let size = big_m + big_n;
+        let ( qn, hn ) = layout.extend( &q_network, &h_network, &self.hidden );
+        let ( mut qg, mut hg ) = ( qn.clone(), hn.clone() );
+
+        // Characteristic constants ( C-, C+, B ) for the pipes and bends
+        let mut characteristics: Vec<Option<(f64, f64, f64)>> = vec![ None; m ];
+        if moc {
+            if step == 0 || self.reaches.len() != m {
+                self.reaches = network.edges.iter().map( |edge| Reaches::new( edge, fluid, dt, self.g ) ).collect();
+                let vapour_head = fluid.vapour_pressure() / ( fluid.density() * self.g );
+                for (j, reach) in self.reaches.iter_mut().enumerate() {
+                    if let Some( reach ) = reach {
+                        let ( from, to ) = ends[j];
+                        reach.initialise( qn[j], hn[from], hn[to] );
+                        if self.cavitation {
+                            reach.set_vapour_head( *network.nodes[from].elevation() + vapour_head, 
+                                *network.nodes[to].elevation() + vapour_head );
+                        }
+                    }
+                }
+            }
+            for (j, reach) in self.reaches.iter().enumerate() {
+                if let Some( reach ) = reach {
+                    let ( cm, cp ) = reach.end_characteristics( &network.edges[j], nu, self.g, step, self.friction );
+                    characteristics[j] = Some( ( cm, cp, reach.impedance ) );
+                }
+            }
+        }
+
+        // Node capacitance (the characteristics carry the pipe capacitance themselves)
+        let mut d_diag = Vec64::new( big_n, 0.0 );
+        if !moc {
+            for &(j, from, to, _) in layout.links.iter() {
+                d_diag[from] += m_diag[j] / segments[j] as f64;
+                d_diag[to] += m_diag[j] / segments[j] as f64;
+            }
         }
-        for edge in network.mut_edges() {
-            edge.add_transient_value( time );
+        // Closed air vessels store liquid relative to the head at their liquid surface
+        let mut h_store = hn.clone();
+        for i in 0..n {
+            if network.nodes[i].is_tank() { d_diag[i] += network.nodes[i].area(); }
+            if let Node::Accumulator(vessel) = &network.nodes[i] {
+                let ( capacitance, head ) = vessel.storage( hn[i], dt, fluid.density(), self.g );
+                d_diag[i] += capacitance;
+                h_store[i] = head;
+            }
         }
 
-        let (n, m) = ( network.num_nodes(), network.num_edges() );
-        let size = n + m;
-        if size == 0 { return Err(1.0); }
-        if m == 0 { return Err(1.0); }
-        if !self.solved_steady { return Err(1.0); }
+        let mut boundary: Vec<bool> = network.nodes.iter()
+            .map( |node| node.is_known_pressure() ).collect();
+        boundary.resize( big_n, false );
+        let consumption = network.consumption_q( step + 1, fluid.density() );
 
-        let kt = network.incidence_matrix();
-        let k = network.k_matrix();
-        let d_diag = network.d_diag( fluid, self.g );
+        // Air valves vent while they hold air ( valve, air volume at the start of the step )
+        let rho_g = fluid.density() * self.g;
+        let air_valves: Vec<Option<(AirValve, f64)>> = network.nodes.iter().map( |node| match node {
+            Node::AirValve(valve) => Some( ( valve.clone(), *valve.air_volume.last().unwrap() ) ),
+            _ => None,
+        }).collect();
+        let air_volume = |i: usize, h: f64| -> Option<f64> {
+            let ( valve, _ ) = air_valves.get( i )?.as_ref()?;
+            Some( valve.next_air_volume( rho_g * ( h - valve.elevation ), dt ) )
+        };
+        let air_slope = |i: usize, h: f64| -> Option<f64> {
+            let ( valve, _ ) = air_valves.get( i )?.as_ref()?;
+            Some( rho_g * valve.next_air_volume_slope( rho_g * ( h - valve.elevation ), dt ) )
+        };
+        let mut venting: Vec<bool> = air_valves.iter().map( |valve| 
+            matches!( valve, Some( ( _, volume ) ) if *volume > 0.0 ) ).collect();
+        let mut shut = vec![ false; n ];
 
-        let mut iter: usize = 0;
-        let mut max_residual: f64 = 1.0;
-        // Iterate to convergence 
-        while iter < self.max_iter && max_residual > self.tolerance {
-            let b_diag = network.b_diag( fluid, self.g, step + 1 ); // Coefficient at the next step
-            // Assemble the matrix problem
-            let mut b = Vec64::new( size, 0.0 );
-            let mut mat = Mat64::new( size, size, 0.0 );
-            let qbar = self.theta * qg.clone() + ( 1.0 - self.theta ) * qn.clone();
-            let hbar = self.theta * hg.clone() + ( 1.0 - self.theta ) * hn.clone();
-            // Continuity equation at each node
-            let mut continuity_residual = network.consumption_q( step + 1, fluid.density() );
-            continuity_residual -= kt.clone() * qbar.clone();
-            let mut hdiff = hg.clone() - hn.clone();
+        // A full tank overflows and an empty one draws air into its outlet ( the head is held at the 
+        // limit until the flow reverses )
+        let tank_limits: Vec<Option<(f64, f64)>> = network.nodes.iter().map( |node| match node {
+            Node::Tank(tank) => Some( ( tank.head_at( tank.z_min, fluid.density(), self.g ), 
+                tank.head_at( tank.z_max, fluid.density(), self.g ) ) ),
+            _ => None,
+        }).collect();
+        let mut held: Vec<Option<f64>> = network.nodes.iter().zip( tank_limits.iter() ).map( |(node, limits)| 
+            match ( node, limits ) {
+                ( Node::Tank(tank), Some( ( _, h_max ) ) ) if tank.is_full() => Some( *h_max ),
+                ( Node::Tank(tank), Some( ( h_min, _ ) ) ) if tank.is_empty() => Some( *h_min ),
+                _ => None,
+            }).collect();
+        held.resize( big_n, None );
+        let mut released = vec![ false; n ];
+
+        // Vapour cavities can form at any node where the head is not imposed
+        let vapour_head: Vec<f64> = layout.elevations( network ).iter()
+            .map( |z| z + fluid.vapour_pressure() / ( fluid.density() * self.g ) ).collect();
+        let can_cavitate: Vec<bool> = ( 0..big_n ).map( |i| 
+            self.cavitation && !boundary[i] && !( i < n && has_storage( &network.nodes[i] ) ) ).collect();
+        let mut cavitating: Vec<bool> = self.cavity.iter().map( |volume| *volume > 0.0 ).collect();
+        let mut collapsed = vec![ false; big_n ];
+        let mut cavity = self.cavity.clone();
+
+        // Continuity residual (inflow - outflow - storage) at each node
+        let theta = self.theta;
+        let continuity = |qg: &Vec64, hg: &Vec64, venting: &[bool]| -> Vec64 {
+            let qbar = theta * qg.clone() + ( 1.0 - theta ) * qn.clone();
+            let mut residual = Vec64::new( big_n, 0.0 );
             for i in 0..n {
-                if network.nodes[i].is_tank() {
-                    hdiff[i] *= d_diag[i] + network.nodes[i].area();
+                residual[i] = consumption[i];
+            }
+            for &(j, from, to, e) in layout.links.iter() {
+                if let Some( ( _, cp, b_moc ) ) = characteristics[j] {
+                    // Flow leaves the from node at the new time and reaches the to node along C+
+                    residual[from] -= qg[e];
+                    residual[to] += ( cp - hg[to] ) / b_moc;
                 } else {
-                    hdiff[i] *= d_diag[i];
+                    residual[from] -= qbar[e];
+                    residual[to] += qbar[e];
                 }
             }
-            continuity_residual -= invdt * hdiff;
-            for i in 0..n {
-                for j in 0..m {
-                    mat[i][j] = self.theta * kt[i][j];
+            for i in 0..big_n {
+                residual[i] -= invdt * d_diag[i] * ( hg[i] - h_store[i] );
+            }
+            // Air drawn into a valve displaces the liquid
+            for (i, valve) in air_valves.iter().enumerate() {
+                if let ( true, Some( ( _, volume ) ) ) = ( venting[i], valve ) {
+                    residual[i] += invdt * ( air_volume( i, hg[i] ).unwrap() - volume );
                 }
-                if network.nodes[i].is_tank() {
-                    mat[i][m+i] = invdt * ( d_diag[i] + network.nodes[i].area() );
-                } else {
-                    mat[i][m+i] = invdt * d_diag[i];
+            }
+            residual
+        };
+
+        // Check valve discs move under the flow at the start of the step
+        for (j, edge) in network.mut_edges().iter_mut().enumerate() {
+            let ( from, to ) = ends[j];
+            edge.swing( qn[j], hn[from] - hn[to], dt, step, fluid.density(), self.g );
+        }
+        // Control valves respond to the pressures and flow rate at the start of the step
+        let pressures: Vec<f64> = network.nodes.iter().map( |node| node.pressure_at( step ) ).collect();
+        for (j, edge) in network.mut_edges().iter_mut().enumerate() {
+            let ( from, to ) = ends[j];
+            edge.actuate( q_network[j] * fluid.density(), ( pressures[from], pressures[to] ), dt, step, fluid.density(),
+                &mut self.edge_states[j] );
+        }
+        let failed = network.power_failures( time );
+
+        let mut total_iter: usize = 0;
+        loop {
+            let mut iter: usize = 0;
+            let mut max_residual: f64 = 1.0;
+            let mut worst: usize = 0;
+            let fixed: Vec<bool> = ( 0..big_n ).map( |i| boundary[i] || cavitating[i] || held[i].is_some() ).collect();
+            // Iterate to convergence 
+            while iter < self.max_iter && max_residual > self.tolerance {
+                // Pumps without power slow down under the hydraulic torque
+                for (j, edge) in network.mut_edges().iter_mut().enumerate() {
+                    if failed[j] { edge.run_down( ( qn[j], qg[j] ), dt, step ); }
+                }
+                let b_diag = network.b_diag( fluid, self.g, step + 1 ); // Coefficient at the next step
+                // Assemble the matrix problem
+                let mut b = Vec64::new( size, 0.0 );
+                let mut triplets = Vec::with_capacity( 5 * big_m + big_n );
+                let qbar = self.theta * qg.clone() + ( 1.0 - self.theta ) * qn.clone();
+                let hbar = self.theta * hg.clone() + ( 1.0 - self.theta ) * hn.clone();
+                // Continuity equation at each node
+                let continuity_residual = continuity( &qg, &hg, &venting );
+                for &(j, from, to, e) in layout.links.iter() {
+                    if let Some( ( _, _, b_moc ) ) = characteristics[j] {
+                        if !fixed[from] { triplets.push( ( from, e, 1.0 ) ); }
+                        if !fixed[to] { triplets.push( ( to, big_m + to, 1.0 / b_moc ) ); }
+                        continue;
+                    }
+                    if !fixed[from] { triplets.push( ( from, e, self.theta ) ); }
+                    if !fixed[to] { triplets.push( ( to, e, - self.theta ) ); }
+                }
+                for i in 0..big_n {
+                    if !fixed[i] {
+                        triplets.push( ( i, big_m + i, invdt * d_diag[i] ) );
+                    }
+                    b[i] = continuity_residual[i];
+                }
+                for i in 0..n {
+                    if let ( true, false, Some( dvdh ) ) = ( venting[i], fixed[i], air_slope( i, hg[i] ) ) {
+                        triplets.push( ( i, big_m + i, - invdt * dvdh ) );
+                    }
+                }
+                // Fill the resistance Jacobian matrix in bottom left corner
+                // and the G matrix in bottom right corner
+                for &(j, from, to, e) in layout.links.iter() {
+                    if let Some( ( cm, _, b_moc ) ) = characteristics[j] {
+                        // C- characteristic at the from node
+                        triplets.push( ( big_n + e, e, 1.0 ) );
+                        triplets.push( ( big_n + e, big_m + from, - 1.0 / b_moc ) );
+                        b[big_n + e] = ( hg[from] - cm ) / b_moc - qg[e];
+                        continue;
+                    }
+                    // A reach of a pipe sees the head difference scaled up to the full length
+                    let scale = segments[j] as f64;
+                    let khbar = scale * ( hbar[from] - hbar[to] );
+                    let r = network.edges[j].resistance( qbar[e], khbar, nu, self.g, step + 1, self.friction );
+                    let drdq = network.edges[j].drdq( qbar[e], khbar, nu, self.g, step + 1, self.friction );
+                    let drdkh = scale * network.edges[j].drdkh( qbar[e], khbar, nu, self.g, step + 1, self.friction );
+                    triplets.push( ( big_n + e, e, invdt * b_diag[j] - self.theta * drdq ) );
+                    triplets.push( ( big_n + e, big_m + from, - self.theta * drdkh ) );
+                    triplets.push( ( big_n + e, big_m + to, self.theta * drdkh ) );
+                    b[big_n + e] = r - invdt * b_diag[j] * ( qg[e] - qn[e] );
+                }
+                // Insert boundary conditions 
+                for i in 0..n {
+                    if boundary[i] {
+                        triplets.push( ( i, big_m + i, self.theta ) );
+                        let head = network.nodes[i].head( self.g, fluid.density() );
+                        b[i] = head[ step + 1 ] - hbar[i];
+                    }
+                }
+                // The head is held at the vapour head while a cavity exists
+                for i in 0..big_n {
+                    if cavitating[i] {
+                        triplets.push( ( i, big_m + i, 1.0 ) );
+                        b[i] = vapour_head[i] - hg[i];
+                    }
+                    if let Some( head ) = held[i] {
+                        triplets.push( ( i, big_m + i, 1.0 ) );
+                        b[i] = head - hg[i];
+                    }
+                }
+
+                let mat = SparseMatrix::from_triplets( size, size, &triplets );
+                let correction = match self.lu.factorise( &mat ) {
+                    Ok( () ) => self.lu.solve( &b ),
+                    Err( SparseError::Singular( col ) ) => {
+                        self.solved_transient = false;
+                        return Err( SolverError::SingularMatrix { 
+                            iterations: total_iter + iter, residual: max_residual, component: Some( layout.component( network, col ) ) 
+                        });
+                    },
+                };
+                let h_old = hg.clone();
+                utility::update_solution( &mut qg, &mut hg, &correction );
+                // The air flow changes character at atmospheric pressure so stop there when crossing it
+                for (i, valve) in air_valves.iter().enumerate() {
+                    if let ( true, Some( ( valve, _ ) ) ) = ( venting[i], valve ) {
+                        let h_atm = valve.elevation + valve.atmospheric / rho_g;
+                        if ( h_old[i] - h_atm ) * ( hg[i] - h_atm ) < 0.0 { hg[i] = h_atm; }
+                    }
+                }
+                iter += 1;
+                let ( index, residual ) = max_correction( &correction );
+                max_residual = residual;
+                worst = index;
+                if max_residual.is_nan() {
+                    self.solved_transient = false;
+                    return Err( SolverError::NaNResidual { 
+                        iterations: total_iter + iter, component: Some( layout.component( network, index ) ) 
+                    });
                 }
-                b[i] = continuity_residual[i];
             }
-            // Fill the resistance Jacobian matrix in bottom left corner
-            let khbar = k.clone() * hbar.clone();
-            for j in 0..m {
-                let r = network.edges[j].resistance( qbar[j], khbar[j], fluid.kinematic_viscosity(), self.g, step + 1 );
-                let drdq = network.edges[j].drdq( qbar[j], khbar[j], fluid.kinematic_viscosity(), self.g, step + 1 );
-                mat[n + j][j] = invdt * b_diag[j] - self.theta * drdq;
-                b[n + j] = r - invdt * b_diag[j] * ( qg[j] - qn[j] );
+            total_iter += iter;
+            if iter >= self.max_iter {
+                self.solved_transient = false;
+                return Err( SolverError::NotConverged { 
+                    iterations: total_iter, residual: max_residual, component: Some( layout.component( network, worst ) ) 
+                });
             }
-            // Fill the G matrix in bottom right corner
-            for i in 0..m {
-                let drdkh = network.edges[i].drdkh( qbar[i], khbar[i], fluid.kinematic_viscosity(), self.g, step + 1 );
-                for j in 0..n {
-                    mat[n+i][m+j] = - self.theta * drdkh * k[i][j];
+
+            // Grow or collapse the cavities and repeat the step if the set of cavities changes
+            let residual = continuity( &qg, &hg, &venting );
+            let mut changed = false;
+            for i in 0..big_n {
+                if cavitating[i] {
+                    let volume = self.cavity[i] - dt * residual[i];
+                    if volume <= 0.0 && self.cavity[i] > 0.0 {
+                        cavitating[i] = false;
+                        collapsed[i] = true;
+                        changed = true;
+                        cavity[i] = 0.0;
+                    } else {
+                        cavity[i] = volume.max( 0.0 );
+                    }
+                } else if can_cavitate[i] && !collapsed[i] && hg[i] < vapour_head[i] {
+                    cavitating[i] = true;
+                    changed = true;
                 }
             }
-            // Insert boundary conditions 
-            for i in 0..n {
-                if network.nodes[i].is_known_pressure() {
-                    // Clear row
-                    for k in 0..size {
-                        mat[i][k] = 0.0;
-                        b[i] = 0.0;
+            // Tanks are held at a limit while they spill or drain and released when the flow reverses
+            for (i, limits) in tank_limits.iter().enumerate() {
+                if let Some( ( h_min, h_max ) ) = limits {
+                    match held[i] {
+                        Some( head ) if ( head == *h_max && residual[i] < 0.0 ) || ( head == *h_min && residual[i] > 0.0 ) => {
+                            held[i] = None;
+                            released[i] = true;
+                            changed = true;
+                        },
+                        None if !released[i] && ( hg[i] > *h_max || hg[i] < *h_min ) => {
+                            held[i] = Some( hg[i].clamp( *h_min, *h_max ) );
+                            changed = true;
+                        },
+                        _ => {},
                     }
-                    mat[i][m+i] = self.theta;
-                    let head = network.nodes[i].head( self.g, fluid.density() );
-                    b[i] = head[ step + 1 ] - hbar[i];
                 }
             }
+            // Air valves open below atmospheric pressure and shut when the air is expelled
+            for (i, valve) in air_valves.iter().enumerate() {
+                if let Some( ( valve, _ ) ) = valve {
+                    let p = rho_g * ( hg[i] - valve.elevation );
+                    if venting[i] && valve.next_air_mass( p, dt ) <= 0.0 {
+                        venting[i] = false;
+                        shut[i] = true;
+                        changed = true;
+                    } else if !venting[i] && !shut[i] && p < valve.atmospheric {
+                        venting[i] = true;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed { break; }
+        }
+        let tank_residual = continuity( &qg, &hg, &venting );
 
-            let correction = mat.solve_basic( b.clone() );
-            utility::update_solution( &mut qg, &mut hg, &correction );
-            max_residual = correction.norm_inf();
-            iter += 1;
-        }
-        
-        if iter < self.max_iter && !max_residual.is_nan() {
-            let t = *self.tnodes.last().unwrap();
-            self.tnodes.push( t + dt );
-            //println!("qg = {:?}", qg);
-            //println!("hg = {:?}", hg);
-            //println!("iter = {}", iter);
-            network.push_transient_solution( qg, hg, fluid, *self.g() );
-            self.solved_transient = true;
-            Ok( iter )
+        if moc {
+            for (j, reach) in self.reaches.iter_mut().enumerate() {
+                if let Some( reach ) = reach {
+                    let ( from, to ) = ends[j];
+                    reach.advance( &network.edges[j], nu, self.g, step, ( hg[from], qg[j], hg[to] ), self.friction );
+                    if let Some( profile ) = &mut self.profiles[j] {
+                        profile.push( reach.head.clone(), reach.flow.clone() );
+                    }
+                }
+            }
         } else {
-            self.solved_transient = false;
-            Err( max_residual )
+            for (j, profile) in self.profiles.iter_mut().enumerate() {
+                if let Some( profile ) = profile {
+                    let ( head, flow ) = layout.profile( j, &qg, &hg );
+                    profile.push( head, flow );
+                }
+            }
+        }
+        for (i, node) in network.nodes.iter_mut().enumerate() {
+            if let Node::AirValve(valve) = node {
+                let p = rho_g * ( hg[i] - valve.elevation );
+                if venting[i] { valve.add_air_value( p, dt ) } else { valve.shut() }
+            }
+            if let Node::Accumulator(vessel) = node {
+                vessel.add_liquid_value( hn[i], hg[i], dt, fluid.density(), self.g );
+            }
+            if let Node::Tank(tank) = node {
+                // The unbalanced flow at a held tank spills over the top or is replaced by air
+                let level = tank.level_at( hg[i], fluid.density(), self.g );
+                let excess = if held[i].is_some() { tank_residual[i] } else { 0.0 };
+                tank.add_level_value( level, excess.max( 0.0 ), ( - excess ).max( 0.0 ) );
+            }
+        }
+        for (i, volume) in self.cavity_volumes.iter_mut().enumerate() {
+            volume.push( cavity[i] );
+        }
+        self.cavity = cavity;
+        self.hidden = layout.hidden_values( &qg, &hg );
+        let t = *self.tnodes.last().unwrap();
+        self.tnodes.push( t + dt );
+        let ( q, h ) = layout.network_values( &qg, &hg );
+        network.push_transient_solution( q, h, fluid, *self.g() );
+        self.apply_rules( network, t + dt, step + 1, fluid.density() );
+        self.solved_transient = true;
+        Ok( total_iter )
+    }
+
+    // Extended-period step: the tank levels are advanced with the flow rates at the start of the 
+    // step and the network is then solved as steady flow with the demands and settings at the 
+    // end of the step. The time step is typically minutes to hours.
+    fn period_step(&mut self, network: &mut Graph, fluid: &Fluid ) -> Result<usize,SolverError> {
+        let ( rho, g ) = ( fluid.density(), self.g );
+        let step = self.tnodes.len() - 1;
+        let ( q_network, h_network ) = network.current_solution_qh( rho, g, step );
+        let dt = self.dt;
+        let time = self.tnodes[step] + dt;
+        let ends = network.edge_indices();
+
+        if step == 0 {
+            for (i, node) in network.nodes.iter_mut().enumerate() {
+                if let Node::Tank(tank) = node {
+                    tank.level = vec![ tank.level_at( h_network[i], rho, g ) ];
+                    tank.overflow = vec![ 0.0 ];
+                    tank.air_entry = vec![ 0.0 ];
+                }
+            }
+        }
+
+        // Tanks fill or drain with the net inflow and spill or draw air at their limits
+        let mut inflow = vec![ 0.0; network.num_nodes() ];
+        for (j, &(from, to)) in ends.iter().enumerate() {
+            inflow[from] -= q_network[j];
+            inflow[to] += q_network[j];
+        }
+        for (i, node) in network.nodes.iter_mut().enumerate() {
+            if let Node::Tank(tank) = node {
+                let area = tank.area();
+                let level = *tank.level.last().unwrap() + dt * inflow[i] / area;
+                let clamped = level.clamp( tank.z_min, tank.z_max );
+                let excess = ( level - clamped ) * area / dt;
+                tank.add_level_value( clamped, excess.max( 0.0 ), ( - excess ).max( 0.0 ) );
+            }
+        }
+
+        self.add_transient_values( network, time );
+
+        let heads: Vec<Option<f64>> = network.nodes.iter_mut().map( |node| match node {
+            Node::Pressure(_) => Some( *node.elevation() + node.pressure()[step + 1] / ( rho * g ) ),
+            Node::Tank(tank) => Some( tank.head_at( *tank.level.last().unwrap(), rho, g ) ),
+            _ => None,
+        }).collect();
+        let consumption = network.consumption_q( step + 1, rho );
+        let steady = self.newton( network, fluid, step + 1, &heads, &consumption, q_network, h_network )?;
+        self.tnodes.push( time );
+        network.push_transient_solution( steady.q, steady.h, fluid, g );
+        network.set_control_states( step + 1, &steady.controls );
+        self.apply_rules( network, time, step + 1, rho );
+        Ok( steady.iterations )
+    }
+}
+
+// Solution of the steady equations
+struct Steady {
+    q: Vec64,
+    h: Vec64,
+    controls: Vec<(f64, ControlStatus)>,    // Opening and status of each control valve
+    iterations: usize,
+}
+
+// Unknowns of the transient problem with pipes split into hidden reaches. The flow rates 
+// of the edges (first reach of a split pipe) come first followed by the hidden flow rates, 
+// then the node heads followed by the hidden heads.
+struct Layout {
+    n: usize,                                   // Number of network nodes
+    m: usize,                                   // Number of network edges
+    hidden: usize,                              // Number of hidden nodes (and hidden flow rates)
+    start: Vec<usize>,                          // First hidden unknown of each edge
+    segments: Vec<usize>,                       // Reaches of each edge
+    links: Vec<(usize, usize, usize, usize)>,   // ( edge, from, to, flow index ) for every reach
+}
+
+impl Layout {
+    fn new( n: usize, ends: &[(usize, usize)], segments: &[usize] ) -> Self {
+        let m = ends.len();
+        let mut start = Vec::with_capacity( m );
+        let mut links = Vec::with_capacity( m );
+        let mut hidden = 0;
+        for (j, &(from, to)) in ends.iter().enumerate() {
+            start.push( hidden );
+            let s = segments[j];
+            for k in 0..s {
+                let flow = if k == 0 { j } else { m + hidden + k - 1 };
+                let a = if k == 0 { from } else { n + hidden + k - 1 };
+                let b = if k == s - 1 { to } else { n + hidden + k };
+                links.push( ( j, a, b, flow ) );
+            }
+            hidden += s - 1;
+        }
+        Layout { n, m, hidden, start, segments: segments.to_vec(), links }
+    }
+
+    // Steady state: uniform flow and linear heads along each pipe
+    fn initial( &self, q: &Vec64, h: &Vec64 ) -> ( Vec<f64>, Vec<f64> ) {
+        let ( mut qh, mut hh ) = ( vec![ 0.0; self.hidden ], vec![ 0.0; self.hidden ] );
+        for j in 0..self.m {
+            let ( head, _ ) = self.linear_profile( j, self.segments[j], q, h );
+            for k in 1..self.segments[j] {
+                qh[self.start[j] + k - 1] = q[j];
+                hh[self.start[j] + k - 1] = head[k];
+            }
         }
+        ( qh, hh )
+    }
+
+    fn linear_profile( &self, j: usize, segments: usize, q: &Vec64, h: &Vec64 ) -> ( Vec<f64>, Vec<f64> ) {
+        let ( from, to ) = ( self.links_of( j )[0].1, self.links_of( j ).last().unwrap().2 );
+        let head = ( 0..=segments ).map( |i| h[from] + ( h[to] - h[from] ) * i as f64 / segments as f64 ).collect();
+        ( head, vec![ q[j]; segments + 1 ] )
+    }
+
+    fn links_of( &self, j: usize ) -> &[(usize, usize, usize, usize)] {
+        let first = self.start[j] + j;
+        &self.links[ first..first + self.segments[j] ]
+    }
+
+    // Full solution from the network values and the hidden values
+    fn extend( &self, q: &Vec64, h: &Vec64, hidden: &( Vec<f64>, Vec<f64> ) ) -> ( Vec64, Vec64 ) {
+        let ( mut qe, mut he ) = ( Vec64::new( self.m + self.hidden, 0.0 ), Vec64::new( self.n + self.hidden, 0.0 ) );
+        for j in 0..self.m { qe[j] = q[j]; }
+        for i in 0..self.n { he[i] = h[i]; }
+        for k in 0..self.hidden {
+            qe[self.m + k] = hidden.0[k];
+            he[self.n + k] = hidden.1[k];
+        }
+        ( qe, he )
+    }
 
+    fn network_values( &self, q: &Vec64, h: &Vec64 ) -> ( Vec64, Vec64 ) {
+        let ( mut qn, mut hn ) = ( Vec64::new( self.m, 0.0 ), Vec64::new( self.n, 0.0 ) );
+        for j in 0..self.m { qn[j] = q[j]; }
+        for i in 0..self.n { hn[i] = h[i]; }
+        ( qn, hn )
+    }
+
+    fn hidden_values( &self, q: &Vec64, h: &Vec64 ) -> ( Vec<f64>, Vec<f64> ) {
+        let ( mut qh, mut hh ) = ( vec![ 0.0; self.hidden ], vec![ 0.0; self.hidden ] );
+        for k in 0..self.hidden {
+            qh[k] = q[self.m + k];
+            hh[k] = h[self.n + k];
+        }
+        ( qh, hh )
+    }
+
+    // Elevation of the nodes and hidden nodes (interpolated along the pipes)
+    fn elevations( &self, network: &mut Graph ) -> Vec<f64> {
+        let mut z: Vec<f64> = network.nodes.iter_mut().map( |node| *node.elevation() ).collect();
+        z.resize( self.n + self.hidden, 0.0 );
+        for j in 0..self.m {
+            let links = self.links_of( j );
+            let ( from, to ) = ( links[0].1, links.last().unwrap().2 );
+            for k in 1..self.segments[j] {
+                let x = k as f64 / self.segments[j] as f64;
+                z[self.n + self.start[j] + k - 1] = z[from] + x * ( z[to] - z[from] );
+            }
+        }
+        z
+    }
+
+    // Head and flow at the reach ends (the flow at an interior point is the mean of its reaches)
+    fn profile( &self, j: usize, q: &Vec64, h: &Vec64 ) -> ( Vec<f64>, Vec<f64> ) {
+        let links = self.links_of( j );
+        let mut head = vec![ h[links[0].1] ];
+        let mut flow = vec![ q[links[0].3] ];
+        for (k, link) in links.iter().enumerate() {
+            head.push( h[link.2] );
+            match links.get( k + 1 ) {
+                Some( next ) => flow.push( 0.5 * ( q[link.3] + q[next.3] ) ),
+                None => flow.push( q[link.3] ),
+            }
+        }
+        ( head, flow )
+    }
+
+    // The network component associated with an unknown (hidden unknowns belong to their pipe)
+    fn component( &self, network: &Graph, index: usize ) -> Component {
+        let big_m = self.m + self.hidden;
+        let j = if index < self.m {
+            index
+        } else if index < big_m {
+            self.owner( index - self.m )
+        } else if index - big_m < self.n {
+            return Component::Node( network.nodes[index - big_m].id() );
+        } else {
+            self.owner( index - big_m - self.n )
+        };
+        let (ifrom, ito) = network.edges[j].id();
+        Component::Edge( ifrom, ito )
+    }
+
+    // Edge that owns a hidden unknown
+    fn owner( &self, k: usize ) -> usize {
+        ( 0..self.m ).find( |&j| self.start[j] <= k && k < self.start[j] + self.segments[j] - 1 ).unwrap_or( 0 )
+    }
+}
+
+// Validate the network before assembling the system
+fn check_network( network: &Graph ) -> Result<(), SolverError> {
+    let diagnostics = network.validate();
+    if diagnostics.is_empty() { return Ok(()); }
+    // Unknown node ids keep their own error as no matrix can be built at all
+    if let Some( Diagnostic::DanglingEdge { id, .. } ) = diagnostics.iter()
+        .find( |diagnostic| matches!( diagnostic, Diagnostic::DanglingEdge { .. } ) ) {
+        return Err( SolverError::UnknownNode { id: *id } );
+    }
+    Err( SolverError::InvalidNetwork { diagnostics } )
+}
+
+// The network component associated with an unknown (flow rates first, then heads)
+// Control valve openings follow the heads and belong to their valve
+fn component( network: &Graph, index: usize ) -> Component {
+    let (n, m) = ( network.num_nodes(), network.num_edges() );
+    if index < m {
+        let (ifrom, ito) = network.edges[index].id();
+        Component::Edge( ifrom, ito )
+    } else if index < m + n {
+        Component::Node( network.nodes[index - m].id() )
+    } else {
+        let (ifrom, ito) = network.edges[ network.control_indices()[index - m - n] ].id();
+        Component::Edge( ifrom, ito )
+    }
+}
+
+// Tanks, air valves and air vessels hold their head above the vapour pressure
+fn has_storage( node: &Node ) -> bool {
+    node.is_tank() || node.is_air_valve() || node.is_accumulator()
+}
+
+// Index and absolute value of the largest correction (NaN values take precedence)
+fn max_correction( correction: &Vec64 ) -> (usize, f64) {
+    let mut worst = ( 0, 0.0 );
+    for i in 0..correction.size() {
+        let value = correction[i].abs();
+        if value.is_nan() {
+            return ( i, value );
+        }
+        if value > worst.1 {
+            worst = ( i, value );
+        }
     }
+    worst
 }
\ No newline at end of file
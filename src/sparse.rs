@@ -0,0 +1,397 @@
+use std::cmp::Reverse;
+use std::collections::{ BTreeSet, BinaryHeap };
+use ohsl::vector::Vec64;
+
+const NONE: usize = usize::MAX;
+const PIVOT_THRESHOLD: f64 = 0.1;       // Relative size of an acceptable pivot when analysing
+const REFACTOR_THRESHOLD: f64 = 1.0e-3; // Relative size of an acceptable pivot when refactorising
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SparseError {
+    Singular( usize ),          // No non-zero pivot could be found for this column
+}
+
+// A sparse matrix stored in compressed sparse column (CSC) format
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct SparseMatrix {
+    rows: usize,
+    cols: usize,
+    col_ptr: Vec<usize>,
+    row_index: Vec<usize>,
+    values: Vec<f64>,
+}
+
+impl SparseMatrix {
+    // Create a matrix from (row, col, value) entries, duplicate entries are summed
+    pub fn from_triplets( rows: usize, cols: usize, triplets: &[(usize, usize, f64)] ) -> Self {
+        let mut start = vec![ 0; cols + 1 ];
+        for &( row, col, _ ) in triplets {
+            assert!( row < rows && col < cols, "from_triplets error: entry outside matrix" );
+            start[ col + 1 ] += 1;
+        }
+        for j in 0..cols {
+            start[ j + 1 ] += start[ j ];
+        }
+        let mut next = start.clone();
+        let mut unsorted = vec![ ( 0, 0.0 ); triplets.len() ];
+        for &( row, col, value ) in triplets {
+            unsorted[ next[ col ] ] = ( row, value );
+            next[ col ] += 1;
+        }
+
+        let mut col_ptr = vec![ 0; cols + 1 ];
+        let mut row_index = Vec::with_capacity( triplets.len() );
+        let mut values = Vec::with_capacity( triplets.len() );
+        for j in 0..cols {
+            let column = &mut unsorted[ start[ j ]..start[ j + 1 ] ];
+            column.sort_by_key( |entry| entry.0 );
+            for &( row, value ) in column.iter() {
+                if row_index.len() > col_ptr[ j ] && row_index.last() == Some( &row ) {
+                    *values.last_mut().unwrap() += value;
+                } else {
+                    row_index.push( row );
+                    values.push( value );
+                }
+            }
+            col_ptr[ j + 1 ] = row_index.len();
+        }
+        SparseMatrix { rows, cols, col_ptr, row_index, values }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    // Number of stored (structurally non-zero) entries
+    pub fn nnz(&self) -> usize {
+        self.row_index.len()
+    }
+
+    pub fn get(&self, row: usize, col: usize ) -> f64 {
+        let range = self.col_ptr[ col ]..self.col_ptr[ col + 1 ];
+        match self.row_index[ range.clone() ].binary_search( &row ) {
+            Ok( p ) => self.values[ range.start + p ],
+            Err( _ ) => 0.0,
+        }
+    }
+
+    fn column(&self, col: usize ) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let range = self.col_ptr[ col ]..self.col_ptr[ col + 1 ];
+        self.row_index[ range.clone() ].iter().copied().zip( self.values[ range ].iter().copied() )
+    }
+
+    pub fn multiply(&self, vec: &Vec64 ) -> Vec64 {
+        assert_eq!( self.cols, vec.size() );
+        let mut result = Vec64::new( self.rows, 0.0 );
+        for j in 0..self.cols {
+            for ( i, value ) in self.column( j ) {
+                result[ i ] += value * vec[ j ];
+            }
+        }
+        result
+    }
+
+    pub fn transpose(&self) -> SparseMatrix {
+        let mut triplets = Vec::with_capacity( self.nnz() );
+        for j in 0..self.cols {
+            for ( i, value ) in self.column( j ) {
+                triplets.push( ( j, i, value ) );
+            }
+        }
+        SparseMatrix::from_triplets( self.cols, self.rows, &triplets )
+    }
+
+    // True if both matrices have the same dimensions and the same non-zero pattern
+    pub fn same_pattern(&self, other: &SparseMatrix ) -> bool {
+        self.rows == other.rows && self.cols == other.cols
+            && self.col_ptr == other.col_ptr && self.row_index == other.row_index
+    }
+}
+
+// Sparse LU factorisation P A Q = L U of a square matrix (left-looking, partial pivoting).
+// The column ordering, pivot sequence and the patterns of L and U are kept so that matrices
+// with the same pattern are refactorised numerically without repeating the analysis.
+#[derive(Clone, Debug, Default)]
+pub struct SparseLU {
+    pattern: SparseMatrix,          // Matrix used for the last analysis (pattern only is used)
+    analysed: bool,
+    analyses: usize,                // Number of symbolic analyses performed
+    col_order: Vec<usize>,          // Column of A factorised at each step
+    pivot_row: Vec<usize>,          // Row of A used as the pivot at each step
+    pivot_step: Vec<usize>,         // Step at which each row of A is used as a pivot
+    l_ptr: Vec<usize>,
+    l_rows: Vec<usize>,             // Rows of A below the pivot in each column of L
+    l_values: Vec<f64>,
+    u_ptr: Vec<usize>,
+    u_steps: Vec<usize>,            // Pivot steps above the diagonal in each column of U
+    u_values: Vec<f64>,
+    u_diag: Vec<f64>,
+}
+
+impl SparseLU {
+    pub fn new() -> Self {
+        SparseLU::default()
+    }
+
+    pub fn size(&self) -> usize {
+        self.col_order.len()
+    }
+
+    // Number of times the symbolic analysis (ordering and pivoting) has been performed
+    pub fn analyses(&self) -> usize {
+        self.analyses
+    }
+
+    // Factorise the matrix, reusing the previous analysis if the pattern is unchanged
+    pub fn factorise(&mut self, a: &SparseMatrix ) -> Result<(), SparseError> {
+        assert_eq!( a.rows(), a.cols(), "factorise error: matrix is not square" );
+        if self.analysed && self.pattern.same_pattern( a ) && self.refactorise( a ) {
+            return Ok( () );
+        }
+        self.analyse( a )
+    }
+
+    // Solve A x = b using the current factorisation
+    pub fn solve(&self, b: &Vec64 ) -> Vec64 {
+        let n = self.size();
+        assert_eq!( b.size(), n );
+        // Forward substitution L y = P b
+        let mut c = b.clone();
+        let mut y = Vec64::new( n, 0.0 );
+        for k in 0..n {
+            let yk = c[ self.pivot_row[ k ] ];
+            y[ k ] = yk;
+            if yk == 0.0 { continue; }
+            for p in self.l_ptr[ k ]..self.l_ptr[ k + 1 ] {
+                c[ self.l_rows[ p ] ] -= self.l_values[ p ] * yk;
+            }
+        }
+        // Backward substitution U z = y
+        for j in ( 0..n ).rev() {
+            y[ j ] /= self.u_diag[ j ];
+            let zj = y[ j ];
+            for p in self.u_ptr[ j ]..self.u_ptr[ j + 1 ] {
+                y[ self.u_steps[ p ] ] -= self.u_values[ p ] * zj;
+            }
+        }
+        // Undo the column ordering x = Q z
+        let mut x = Vec64::new( n, 0.0 );
+        for j in 0..n {
+            x[ self.col_order[ j ] ] = y[ j ];
+        }
+        x
+    }
+
+    // Full factorisation: choose the column ordering and pivots and build the patterns of L and U
+    fn analyse(&mut self, a: &SparseMatrix ) -> Result<(), SparseError> {
+        let n = a.cols();
+        self.analysed = false;
+        self.analyses += 1;
+        self.col_order = column_ordering( a );
+        self.pivot_row = vec![ NONE; n ];
+        self.pivot_step = vec![ NONE; n ];
+        self.l_ptr = vec![ 0 ];
+        self.l_rows.clear();
+        self.l_values.clear();
+        self.u_ptr = vec![ 0 ];
+        self.u_steps.clear();
+        self.u_values.clear();
+        self.u_diag = vec![ 0.0; n ];
+
+        let mut row_count = vec![ 0; n ];
+        for &row in a.row_index.iter() {
+            row_count[ row ] += 1;
+        }
+
+        let mut x = vec![ 0.0; n ];
+        let mut touched = vec![ NONE; n ];     // Column step at which a row was last touched
+        let mut queued = vec![ NONE; n ];      // Column step at which a pivot step was last queued
+        let mut rows: Vec<usize> = Vec::new();
+        let mut heap: BinaryHeap<Reverse<usize>> = BinaryHeap::new();
+
+        for j in 0..n {
+            let col = self.col_order[ j ];
+            rows.clear();
+            for ( row, value ) in a.column( col ) {
+                touched[ row ] = j;
+                rows.push( row );
+                x[ row ] = value;
+                let step = self.pivot_step[ row ];
+                if step != NONE && queued[ step ] != j {
+                    queued[ step ] = j;
+                    heap.push( Reverse( step ) );
+                }
+            }
+            // Sparse triangular solve with the columns of L found so far (in pivot order)
+            while let Some( Reverse( k ) ) = heap.pop() {
+                let xk = x[ self.pivot_row[ k ] ];
+                self.u_steps.push( k );
+                self.u_values.push( xk );
+                for p in self.l_ptr[ k ]..self.l_ptr[ k + 1 ] {
+                    let row = self.l_rows[ p ];
+                    if touched[ row ] != j {
+                        touched[ row ] = j;
+                        rows.push( row );
+                        x[ row ] = 0.0;
+                    }
+                    x[ row ] -= self.l_values[ p ] * xk;
+                    let step = self.pivot_step[ row ];
+                    if step != NONE && queued[ step ] != j {
+                        queued[ step ] = j;
+                        heap.push( Reverse( step ) );
+                    }
+                }
+            }
+            self.u_ptr.push( self.u_steps.len() );
+
+            // Partial pivoting, preferring sparse rows among those of acceptable size
+            let max = rows.iter()
+                .filter( |&&row| self.pivot_step[ row ] == NONE )
+                .fold( 0.0_f64, |max, &row| max.max( x[ row ].abs() ) );
+            if max == 0.0 || !max.is_finite() {
+                return Err( SparseError::Singular( col ) );
+            }
+            let mut pivot = NONE;
+            for &row in rows.iter() {
+                if self.pivot_step[ row ] != NONE || x[ row ].abs() < PIVOT_THRESHOLD * max {
+                    continue;
+                }
+                if pivot == NONE || row_count[ row ] < row_count[ pivot ]
+                    || ( row_count[ row ] == row_count[ pivot ] && x[ row ].abs() > x[ pivot ].abs() ) {
+                    pivot = row;
+                }
+            }
+            let diag = x[ pivot ];
+            self.u_diag[ j ] = diag;
+            self.pivot_row[ j ] = pivot;
+            self.pivot_step[ pivot ] = j;
+            for &row in rows.iter() {
+                if self.pivot_step[ row ] == NONE {
+                    self.l_rows.push( row );
+                    self.l_values.push( x[ row ] / diag );
+                }
+            }
+            self.l_ptr.push( self.l_rows.len() );
+        }
+
+        self.pattern = a.clone();
+        self.analysed = true;
+        Ok( () )
+    }
+
+    // Numerical factorisation using the existing analysis, returns false if a pivot is too small
+    fn refactorise(&mut self, a: &SparseMatrix ) -> bool {
+        let n = self.size();
+        let mut x = vec![ 0.0; n ];
+        for j in 0..n {
+            for ( row, value ) in a.column( self.col_order[ j ] ) {
+                x[ row ] = value;
+            }
+            for p in self.u_ptr[ j ]..self.u_ptr[ j + 1 ] {
+                let k = self.u_steps[ p ];
+                let xk = x[ self.pivot_row[ k ] ];
+                self.u_values[ p ] = xk;
+                for q in self.l_ptr[ k ]..self.l_ptr[ k + 1 ] {
+                    x[ self.l_rows[ q ] ] -= self.l_values[ q ] * xk;
+                }
+            }
+            let diag = x[ self.pivot_row[ j ] ];
+            let range = self.l_ptr[ j ]..self.l_ptr[ j + 1 ];
+            let max = self.l_rows[ range.clone() ].iter()
+                .fold( 0.0_f64, |max, &row| max.max( x[ row ].abs() ) );
+            if diag == 0.0 || !diag.is_finite() || diag.abs() < REFACTOR_THRESHOLD * max {
+                return false;
+            }
+            self.u_diag[ j ] = diag;
+            for p in range {
+                self.l_values[ p ] = x[ self.l_rows[ p ] ] / diag;
+            }
+            // Clear the work vector
+            x[ self.pivot_row[ j ] ] = 0.0;
+            for p in self.u_ptr[ j ]..self.u_ptr[ j + 1 ] {
+                x[ self.pivot_row[ self.u_steps[ p ] ] ] = 0.0;
+            }
+            for p in self.l_ptr[ j ]..self.l_ptr[ j + 1 ] {
+                x[ self.l_rows[ p ] ] = 0.0;
+            }
+        }
+        true
+    }
+}
+
+// Minimum degree ordering of the columns based on the pattern of A^T A (this
+// bounds the fill-in of L and U for any choice of row pivots). Each row of A is a clique in
+// A^T A so the rows are used as the initial elements of a quotient graph.
+fn column_ordering( a: &SparseMatrix ) -> Vec<usize> {
+    let n = a.cols();
+    let at = a.transpose();
+    let mut members: Vec<Vec<usize>> = ( 0..at.cols() )
+        .map( |row| at.column( row ).map( |( col, _ )| col ).collect() ).collect();
+    let mut elements: Vec<Vec<usize>> = vec![ Vec::new(); n ];  // Elements containing each column
+    for ( e, cols ) in members.iter().enumerate() {
+        for &col in cols.iter() {
+            elements[ col ].push( e );
+        }
+    }
+    let mut absorbed = vec![ false; members.len() ];
+    let mut eliminated = vec![ false; n ];
+    let mut mark = vec![ NONE; n ];
+
+    // Number of other columns sharing an element with v
+    let mut seen = vec![ NONE; n ];
+    let mut external_degree = |v: usize, elements: &[Vec<usize>], members: &[Vec<usize>]| {
+        let mut count = 0;
+        for &e in elements[ v ].iter() {
+            for &u in members[ e ].iter() {
+                if u != v && seen[ u ] != v {
+                    seen[ u ] = v;
+                    count += 1;
+                }
+            }
+        }
+        for &e in elements[ v ].iter() {
+            for &u in members[ e ].iter() {
+                seen[ u ] = NONE;
+            }
+        }
+        count
+    };
+    let mut degree: Vec<usize> = ( 0..n ).map( |v| external_degree( v, &elements, &members ) ).collect();
+    let mut queue: BTreeSet<(usize, usize)> = ( 0..n ).map( |v| ( degree[ v ], v ) ).collect();
+    let mut order = Vec::with_capacity( n );
+    while let Some( ( _, v ) ) = queue.pop_first() {
+        let step = order.len();
+        order.push( v );
+        eliminated[ v ] = true;
+        // The new element is the union of the elements containing v (which are absorbed)
+        let mut new_members = Vec::new();
+        for &e in elements[ v ].iter() {
+            if absorbed[ e ] { continue; }
+            for &u in members[ e ].iter() {
+                if !eliminated[ u ] && mark[ u ] != step {
+                    mark[ u ] = step;
+                    new_members.push( u );
+                }
+            }
+            absorbed[ e ] = true;
+            members[ e ].clear();
+        }
+        elements[ v ].clear();
+        let new_element = members.len();
+        members.push( new_members );
+        absorbed.push( false );
+        for i in 0..members[ new_element ].len() {
+            let u = members[ new_element ][ i ];
+            queue.remove( &( degree[ u ], u ) );
+            elements[ u ].retain( |&e| !absorbed[ e ] );
+            elements[ u ].push( new_element );
+            degree[ u ] = external_degree( u, &elements, &members );
+            queue.insert( ( degree[ u ], u ) );
+        }
+    }
+    order
+}
@@ -1,38 +1,26 @@
-use ohsl::{ vector::Vec64, matrix::Mat64 };
+use ohsl::vector::Vec64;
 use crate::graph::Graph;
 use crate::fluid::Fluid;
-use crate::node::Node;
+use crate::sparse::{ SparseMatrix, SparseLU };
+use crate::friction::FrictionModel;
 
-pub fn max_value( values: &mut Vec<f64>) -> f64 {
+// Sizes of the non-SI units used for input and output
+pub const BAR: f64 = 1.0e5;                     // [Pa]
+pub const PSI: f64 = 6894.757293168361;         // Pound-force per square inch [Pa]
+pub const GALLON: f64 = 3.785411784e-3;         // US gallon [m^3]
+
+pub fn max_value( values: &mut [f64] ) -> f64 {
     let max = values.iter_mut().max_by(|a, b| a.partial_cmp(b).unwrap());
     *max.unwrap()
 }
 
-pub fn min_value( values: &mut Vec<f64>) -> f64 {
+pub fn min_value( values: &mut [f64] ) -> f64 {
     let min = values.iter_mut().min_by(|a, b| a.partial_cmp(b).unwrap());
     *min.unwrap()
 }
 
 pub fn friction_factor( relative: f64, reynolds: f64 ) -> f64 {
-    if reynolds < 2100.0 {
-        64.0 / reynolds 
-    } else if reynolds > 3000.0 {
-        //TODO Praks-Brkic ( temporary )
-        let a = reynolds * relative / 8.0897;
-        let b = reynolds.ln() - 0.779626;
-        let x = a + b;
-        let c = x.ln();
-        let k = 0.8685972 * ( b - c + ( c / ( x - 0.5588 * c + 1.2079 ) ) );
-        1.0 / ( k * k )
-    } else {
-        let k1 = (64.0/reynolds).powi( 12 );
-        let c = 1.0 / ( (0.833* reynolds.powf(1.282) / reynolds.powf(1.007) ) + ( 0.27 * relative ) 
-        + (110.0*relative/ reynolds ));
-        let a = 0.8687 * (c.powi( 16 )).ln();
-        let b = ( 13269.0 / reynolds ).powi( 16 );
-        let k2 = (a+b).powf( -1.5 );
-        (k1+k2).powf( 0.08333333333 )
-    }
+    FrictionModel::PraksBrkic.darcy( relative, reynolds ).0
 }
 
 pub fn update_solution( qg: &mut Vec64, hg: &mut Vec64, correction: &Vec64 ) {
@@ -47,53 +35,52 @@ pub fn update_solution( qg: &mut Vec64, hg: &mut Vec64, correction: &Vec64 ) {
 
 pub fn laminar_guess( net: &Graph, fluid: &Fluid, g: f64 ) -> (Vec64, Vec64) {
     let ( num_nodes, numel ) = ( net.num_nodes(), net.num_edges() );
-    let mut k_matrix = Mat64::new( num_nodes, num_nodes, 0.0 );
-
-    for i in 0..numel {
-        let (ifrom, ito) = net.edges()[i].id();
-        let ( a, b ) = ( net.index(ifrom), net.index(ito) );
-        let k = net.edges()[i].k_laminar( fluid.kinematic_viscosity() );
-        k_matrix[a][a] += k;
-        k_matrix[b][b] += k;
-        k_matrix[a][b] -= k;
-        k_matrix[b][a] -= k;
-    }
+    let ends = net.edge_indices();
 
-    let mut boundary_conditions = Vec::<Node>::new();
-    for node in 0..num_nodes {
-        if !net.nodes()[node].is_connection() {
-            boundary_conditions.push( net.nodes()[node].clone() );
+    // Known heads at pressure and tank nodes (None for unknown heads)
+    let mut known_head: Vec<Option<f64>> = vec![ None; num_nodes ];
+    let mut consumption = Vec64::new( num_nodes, 0.0 );
+    for (i, node) in net.nodes.iter().enumerate() {
+        let mut node = node.clone();
+        if node.is_known_pressure() || node.is_tank() {
+            let pressure = *node.steady_pressure();
+            let elevation = *node.elevation();
+            let val = elevation + pressure / ( fluid.density() * g );
+            known_head[i] = Some( val );
+            consumption[i] = val;                   // make consumption[j] = Hj
+        } else if !node.is_connection() {
+            consumption[i] += *node.steady_consumption() / fluid.density();
         }
     }
 
-    let mut consumption = Vec64::new( num_nodes, 0.0 );
-    for mut bc in boundary_conditions {
-        let node = net.index( bc.id() );
-        if bc.is_known_pressure() || bc.is_tank() {
-            let pressure = *bc.steady_pressure();
-            let elevation = *bc.elevation();
-            let val = elevation + pressure / ( fluid.density() * g );
-            for i in 0..num_nodes {                     // (a) - add contributions
-                consumption[i] -= k_matrix[i][node] * val;
-            }
-            for i in 0..num_nodes {                     // (b) - zero row and column
-                k_matrix[node][i] = 0.0;
-                k_matrix[i][node] = 0.0;
+    // Assemble the laminar conductance matrix with the known head rows and columns removed
+    let mut triplets = Vec::with_capacity( 4 * numel + num_nodes );
+    for (i, &(a, b)) in ends.iter().enumerate() {
+        let k = net.edges[i].k_laminar( fluid.kinematic_viscosity() );
+        for ( row, col, value ) in [ (a, a, k), (b, b, k), (a, b, -k), (b, a, -k) ] {
+            match ( known_head[row], known_head[col] ) {
+                ( Some( _ ), _ ) => {},
+                ( None, Some( val ) ) => consumption[row] -= value * val, // add contributions
+                ( None, None ) => triplets.push( ( row, col, value ) ),
             }
-            k_matrix[node][node] = 1.0;            // (c) - make mat(j,j) = 1
-            consumption[node] = val;               // (d) - make consumption[j] = Hj
-
-        } else { 
-            consumption[node] += *bc.steady_consumption() / fluid.density();
         }
     }
-    let head = k_matrix.solve_basic( consumption.clone() ); // Solve to find the heads at each node
+    for (i, head) in known_head.iter().enumerate() {
+        if head.is_some() {
+            triplets.push( ( i, i, 1.0 ) );         // make mat(j,j) = 1
+        }
+    }
+    let k_matrix = SparseMatrix::from_triplets( num_nodes, num_nodes, &triplets );
+    let mut lu = SparseLU::new();
+    // Solve to find the heads at each node
+    let head = match lu.factorise( &k_matrix ) {
+        Ok( () ) => lu.solve( &consumption ),
+        Err( _ ) => Vec64::new( num_nodes, f64::NAN ),
+    };
     let mut flow_rate = Vec64::new( numel, 0.0 ); // Flow rate in each pipe (assuming friction factor = 0.1)
-    for i in 0..numel {
-        let (ifrom, ito) = net.edges()[i].id();
-        let ( a, b ) = ( net.index(ifrom), net.index(ito) );
+    for (i, &(a, b)) in ends.iter().enumerate() {
         let ( h_initial, h_final ) = ( head[a], head[b] );
-        flow_rate[i] = net.edges()[i].darcy_approx( h_initial - h_final, g ) * ( h_initial - h_final );
+        flow_rate[i] = net.edges[i].darcy_approx( h_initial - h_final, g ) * ( h_initial - h_final );
         if flow_rate[i].is_nan() {
             flow_rate[i] = 0.0001;
         }
@@ -106,7 +93,7 @@ pub fn relative_error( x: f64, x_approx: f64 ) -> f64 {
     rel.abs()
 }
 
-pub fn interpolate( x: f64, x_data: &Vec<f64>, y_data: &Vec<f64> ) -> f64 {
+pub fn interpolate( x: f64, x_data: &[f64], y_data: &[f64] ) -> f64 {
     assert_eq!( x_data.len(), y_data.len() );
     let n = x_data.len();
     let mut i = 1;
@@ -121,12 +108,12 @@ pub fn interpolate( x: f64, x_data: &Vec<f64>, y_data: &Vec<f64> ) -> f64 {
     y_1 + slope * ( x - x_1 )
 }
 
-pub fn split_into_two_vectors( data: &Vec<(f64, f64)> ) -> (Vec<f64>, Vec<f64>) {
+pub fn split_into_two_vectors( data: &[(f64, f64)] ) -> (Vec<f64>, Vec<f64>) {
     let mut x = Vec::<f64>::new();
     let mut y = Vec::<f64>::new();
-    for i in 0..data.len() {
-        x.push( data[i].0 );
-        y.push( data[i].1 );
+    for point in data {
+        x.push( point.0 );
+        y.push( point.1 );
     }
     ( x, y )
 }
\ No newline at end of file
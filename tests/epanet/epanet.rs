@@ -0,0 +1,212 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::edge::Edge;
+use eki::edges::control_valve::{ ControlMode, ControlStatus };
+use eki::epanet::{ self, EpanetError };
+use eki::friction::FrictionModel;
+use eki::solver::Solver;
+use eki::series::TimeSeries;
+use std::f64::consts::PI;
+
+mod export;
+
+pub const NETWORK: &str = "
+[TITLE]
+Reservoir feeding a tank and a demand through a pump
+
+[JUNCTIONS]
+;ID   Elev   Demand   Pattern
+ 10   5      0
+ 11   2      4        daily
+
+[RESERVOIRS]
+ 1    10
+
+[TANKS]
+;ID  Elev  InitLvl  MinLvl  MaxLvl  Diam  MinVol  VolCurve
+ 2   20    3        1       6       8     0
+
+[PIPES]
+;ID  Node1  Node2  Length  Diam  Roughness  MinorLoss  Status
+ p1  1      10     200      150   0.05       0          Open
+ p3  2      11     500      100   0.05       0          Open
+ p4  11     20     100      100   0.05       0          Open
+
+[PUMPS]
+ k1  10     2      HEAD pumpcurve
+
+[VALVES]
+;ID  Node1  Node2  Diam  Type  Setting  MinorLoss
+ v1  20     21     100   TCV   5        0
+
+[RESERVOIRS]
+ 21   0
+
+[JUNCTIONS]
+ 20   1      0
+
+[PATTERNS]
+ daily  0.5  1.0  1.5
+
+[CURVES]
+ pumpcurve  20  40
+
+[QUALITY]
+ 10   0.5
+
+[TIMES]
+ Duration            24:00
+ Hydraulic Timestep  0:30
+ Pattern Timestep    2:00
+
+[OPTIONS]
+ Units     LPS
+ Headloss  D-W
+ Quality   None
+
+[COORDINATES]
+ 10   100.0  200.0
+ 2    300.5  250.0
+
+[END]
+";
+
+#[test]
+fn import() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let mut imported = epanet::import( NETWORK, &fluid, solver.gravity() ).unwrap();
+    let network = &mut imported.graph;
+    assert_eq!( network.num_nodes(), 6 );
+    assert_eq!( network.num_edges(), 5 );
+    // The EPANET ids are numbers so they are kept
+    assert_eq!( imported.node_ids["11"], 11 );
+    assert_eq!( imported.edge_ids["k1"], 3 );
+    assert!( network.nodes()[ network.index( 10 ) ].is_connection() );
+    assert!( network.nodes()[ network.index( 1 ) ].is_known_pressure() );
+    let Node::Flow( demand ) = network.nodes()[ network.index( 11 ) ].clone() else { panic!( "Expected a flow node" ) };
+    assert_eq!( demand.elevation, 2.0 );
+    assert!( ( demand.consumption[0] + 4.0e-3 * fluid.density() ).abs() < 1.0e-10 );
+    // The demand pattern becomes a series of consumptions
+    let consumption = - 4.0e-3 * fluid.density();
+    let pattern = [ 0.5, 1.0, 1.5 ].map( |m| m * consumption );
+    assert_eq!( demand.series, Some( TimeSeries::pattern( &pattern, 7200.0 ) ) );
+    let Node::Tank( tank ) = network.nodes()[ network.index( 2 ) ].clone() else { panic!( "Expected a tank" ) };
+    assert_eq!( ( tank.elevation, tank.z_init, tank.z_min, tank.z_max, tank.diameter ), ( 20.0, 3.0, 1.0, 6.0, 8.0 ) );
+    assert_eq!( ( tank.loc.x, tank.loc.y ), ( 300.5, 250.0 ) );
+    let Edge::Pipe( pipe ) = network.edges()[0].clone() else { panic!( "Expected a pipe" ) };
+    assert_eq!( ( pipe.length, pipe.diameter, pipe.roughness ), ( 200.0, 0.15, 0.05e-3 ) );
+    // A single point curve has its design point as the rated point
+    let Edge::Pump( pump ) = network.edges()[3].clone() else { panic!( "Expected a pump" ) };
+    assert!( ( pump.q_rated - 0.02 ).abs() < 1.0e-12 );
+    assert_eq!( pump.h_rated, 40.0 );
+    assert!( ( pump.f_h( PI / 2.0 ) - 1.33 ).abs() < 1.0e-12 );
+    let Edge::Valve( valve ) = network.edges()[4].clone() else { panic!( "Expected a valve" ) };
+    assert_eq!( valve.interpolate_invk( 1.0 ), 0.2 );
+    assert_eq!( imported.duration, Some( 86400.0 ) );
+    assert_eq!( imported.hydraulic_step, Some( 1800.0 ) );
+    assert_eq!( imported.warnings.len(), 2 );
+    assert!( imported.warnings[0].contains( "[QUALITY]" ) );
+    assert!( imported.warnings[1].contains( "Quality" ) );
+    assert!( solver.solve_steady( network, &fluid, true ).is_ok() );
+}
+
+#[test]
+fn hazen_williams() {
+    let fluid = Fluid::default();
+    let text = "[OPTIONS]\n Units CMH\n[RESERVOIRS]\n a 10\n b 0\n[PIPES]\n ab a b 1000 300 120\n";
+    let imported = epanet::import( text, &fluid, 9.80665 ).unwrap();
+    assert!( imported.node_ids.values().all( |id| *id == 1 || *id == 2 ) );
+    let Edge::Pipe( pipe ) = imported.graph.edges()[0].clone() else { panic!( "Expected a pipe" ) };
+    // Both formulas give the same gradient at 1 m/s
+    let q = pipe.area();
+    let gradient = 10.67 * q.powf( 1.852 ) / ( 120.0_f64.powf( 1.852 ) * 0.3_f64.powf( 4.8704 ) );
+    let f = pipe.friction_factor( q, fluid.kinematic_viscosity(), FrictionModel::Default );
+    assert!( ( f / ( 2.0 * 9.80665 * 0.3 ) - gradient ).abs() < 0.02 * gradient );
+}
+
+#[test]
+fn errors() {
+    let fluid = Fluid::default();
+    let text = "[RESERVOIRS]\n a 10\n b ten\n";
+    let error = epanet::import( text, &fluid, 9.80665 ).err().unwrap();
+    assert_eq!( error, EpanetError::Parse { line: 3, message: "'ten' is not a number".to_string() } );
+    let text = "[RESERVOIRS]\n a 10\n[PIPES]\n ab a c 1000 300 120\n";
+    let error = epanet::import( text, &fluid, 9.80665 ).err().unwrap();
+    assert_eq!( error.to_string(), "Line 4: Unknown node in link ab" );
+    assert!( matches!( epanet::read( "missing.inp", &fluid, 9.80665 ), Err( EpanetError::Io( _ ) ) ) );
+}
+
+#[test]
+fn status() {
+    let fluid = Fluid::default();
+    let text = "[RESERVOIRS]\n a 10\n b 0\n[PUMPS]\n k1 a b HEAD c\n k2 a b HEAD c\n k3 a b HEAD c\n[VALVES]\n v1 a b 100 TCV 5 0\n\
+        [CURVES]\n c 0.01 50\n[STATUS]\n k1 Closed\n k2 0.5\n v1 CLOSED\n k3 Shut\n";
+    let imported = epanet::import( text, &fluid, 9.80665 ).unwrap();
+    // A closed pump is stopped and a number sets its relative speed
+    let Edge::Pump( pump ) = imported.graph.edges()[0].clone() else { panic!( "Expected a pump" ) };
+    assert_eq!( pump.speed, vec![ 0.0 ] );
+    let Edge::Pump( pump ) = imported.graph.edges()[1].clone() else { panic!( "Expected a pump" ) };
+    assert_eq!( pump.speed, vec![ 0.5 * pump.n_rated ] );
+    let Edge::Valve( valve ) = imported.graph.edges()[3].clone() else { panic!( "Expected a valve" ) };
+    assert_eq!( valve.open_percent, vec![ 0.0 ] );
+    assert_eq!( imported.warnings, vec![ "Status SHUT of link k3 was ignored".to_string() ] );
+}
+
+#[test]
+fn patterns() {
+    let fluid = Fluid::default();
+    let g = 9.80665;
+    let text = "[OPTIONS]\n Units LPS\n[RESERVOIRS]\n a 10 p\n b 0\n[PUMPS]\n ab a b HEAD c SPEED 0.5 PATTERN s\n[CURVES]\n c 0.01 50\n\
+        [PATTERNS]\n p 1 1.5\n s 1 2\n[TIMES]\n Pattern Timestep 0:30\n";
+    let mut imported = epanet::import( text, &fluid, g ).unwrap();
+    assert!( imported.warnings.is_empty() );
+    // The head pattern of the reservoir becomes a series of pressures at its elevation
+    let series = imported.graph.mut_nodes()[0].series().unwrap().clone().unwrap();
+    assert_eq!( series.period, 3600.0 );
+    assert_eq!( series.value( 0.0 ), 101325.0 );
+    assert!( ( series.value( 1800.0 ) - ( 101325.0 + fluid.density() * g * 5.0 ) ).abs() < 1.0e-9 );
+    // The speed pattern multiplies the pump speed
+    let Edge::Pump( pump ) = imported.graph.edges()[0].clone() else { panic!( "Expected a pump" ) };
+    let series = pump.series.unwrap();
+    assert_eq!( series.value( 0.0 ), pump.speed[0] );
+    assert_eq!( series.value( 2000.0 ), 2.0 * pump.speed[0] );
+    let exported = epanet::export( &imported.graph, &fluid, g, epanet::Headloss::DarcyWeisbach, FrictionModel::Default );
+    assert!( exported.report.contains( &"Node 1: time series is not written".to_string() ) );
+    assert!( exported.report.contains( &"Link 1: time series is not written".to_string() ) );
+}
+
+#[test]
+fn control_valves() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let g = solver.gravity();
+    let text = "[OPTIONS]\n Units LPS\n[RESERVOIRS]\n 1 60\n 6 0\n[JUNCTIONS]\n 2 0 0\n 3 0 0\n 4 0 5\n 5 0 0\n\
+        [PIPES]\n 12 1 2 500 100 130\n 34 3 4 500 100 130\n 15 1 5 500 100 130\n\
+        [VALVES]\n v1 2 3 100 PRV 30 2\n v2 5 6 100 FCV 2\n";
+    let mut imported = epanet::import( text, &fluid, g ).unwrap();
+    let network = &mut imported.graph;
+    let Edge::ControlValve( prv ) = network.edges()[3].clone() else { panic!( "Expected a control valve" ) };
+    assert_eq!( prv.mode, ControlMode::PressureReducing );
+    assert!( ( prv.setpoint - 101325.0 - 30.0 * fluid.density() * g ).abs() < 1.0e-9 );
+    // The minor loss is the loss when fully open
+    assert!( ( prv.interpolate_invk( 1.0 ) - 0.5 ).abs() < 1.0e-12 );
+    let Edge::ControlValve( fcv ) = network.edges()[4].clone() else { panic!( "Expected a control valve" ) };
+    assert_eq!( fcv.mode, ControlMode::FlowControl );
+    assert!( ( fcv.setpoint - 2.0e-3 * fluid.density() ).abs() < 1.0e-12 );
+    // The PRV holds its setting and the FCV limits the flow to the lower reservoir
+    assert!( solver.solve_steady( network, &fluid, true ).is_ok() );
+    assert!( ( network.nodes()[ network.index( 3 ) ].pressure_at( 0 ) - prv.setpoint ).abs() < 1.0e-3 );
+    let Edge::ControlValve( fcv ) = network.edges()[4].clone() else { panic!( "Expected a control valve" ) };
+    assert_eq!( fcv.status, ControlStatus::Active );
+    assert!( ( fcv.mass_flow[0] - fcv.setpoint ).abs() < 1.0e-6 );
+
+    let exported = epanet::export( network, &fluid, g, epanet::Headloss::DarcyWeisbach, FrictionModel::Default );
+    assert_eq!( exported.report.len(), 2 );
+    assert!( exported.report[0].contains( "Pressure Reducing Valve written with its fully open loss" ) );
+    let reimported = epanet::import( &exported.text, &fluid, g ).unwrap().graph;
+    let Edge::ControlValve( copy ) = reimported.edges()[3].clone() else { panic!( "Expected a control valve" ) };
+    assert_eq!( copy.mode, prv.mode );
+    assert!( ( copy.setpoint - prv.setpoint ).abs() < 1.0e-6 );
+    assert!( ( copy.interpolate_invk( 1.0 ) - 0.5 ).abs() < 1.0e-12 );
+}
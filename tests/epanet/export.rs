@@ -0,0 +1,162 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, connection::Connection, flow::Flow };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, bend::Bend, size_change::SizeChange, safety_valve::SafetyValve, bursting_disk::BurstingDisk };
+use eki::epanet::{ self, Headloss };
+use eki::friction::FrictionModel;
+use eki::graph::Graph;
+use eki::solver::Solver;
+use std::f64::consts::PI;
+
+#[test]
+fn round_trip() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let g = solver.gravity();
+    let mut original = epanet::import( super::NETWORK, &fluid, g ).unwrap().graph;
+    assert!( solver.solve_steady( &mut original, &fluid, true ).is_ok() );
+    let exported = epanet::export( &original, &fluid, g, Headloss::DarcyWeisbach, FrictionModel::Default );
+    assert!( exported.report.is_empty() );
+    let imported = epanet::import( &exported.text, &fluid, g ).unwrap();
+    assert!( imported.warnings.is_empty() );
+    let mut network = imported.graph;
+    assert_eq!( network.num_nodes(), original.num_nodes() );
+    assert_eq!( network.num_edges(), original.num_edges() );
+    for node in original.nodes() {
+        let copy = network.nodes()[ network.index( node.id() ) ].clone();
+        assert_eq!( copy.loc(), node.loc() );
+        match ( node, copy ) {
+            ( Node::Flow( a ), Node::Flow( b ) ) => {
+                assert!( ( a.consumption[0] - b.consumption[0] ).abs() < 1.0e-10 );
+                let ( a, b ) = ( a.series.unwrap().as_pattern().unwrap(), b.series.unwrap().as_pattern().unwrap() );
+                assert_eq!( a.1, b.1 );
+                for ( a, b ) in a.0.iter().zip( &b.0 ) {
+                    assert!( ( a - b ).abs() < 1.0e-10 );
+                }
+            },
+            ( Node::Tank( a ), Node::Tank( b ) ) => {
+                assert_eq!( ( a.elevation, a.z_init, a.z_min, a.z_max, a.diameter ), 
+                    ( b.elevation, b.z_init, b.z_min, b.z_max, b.diameter ) );
+            },
+            ( a, b ) => assert_eq!( a.to_string(), b.to_string() ),
+        }
+    }
+    let ( Edge::Pipe( a ), Edge::Pipe( b ) ) = ( original.edges()[1].clone(), network.edges()[1].clone() ) else { 
+        panic!( "Expected pipes" ) 
+    };
+    assert!( ( a.roughness - b.roughness ).abs() < 1.0e-12 );
+    // The pump curve and valve loss give the same steady solution
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    for (a, b) in original.edges().iter_mut().zip( network.edges().iter_mut() ) {
+        let ( qa, qb ) = ( *a.steady_mass_flow(), *b.steady_mass_flow() );
+        assert!( ( qa - qb ).abs() < 0.01 * qa.abs() );
+    }
+}
+
+#[test]
+fn hazen_williams() {
+    let fluid = Fluid::default();
+    let g = 9.80665;
+    let mut network = Graph::new();
+    let ( a, b ) = ( Node::Pressure( Pressure::new( 1 ) ), Node::Pressure( Pressure::new( 2 ) ) );
+    network.add_node( a.clone() );
+    network.add_node( b.clone() );
+    network.add_edge( Edge::Pipe( Pipe::new_params( a.id(), b.id(), 500.0, 0.2, 0.1e-3, 5.0e-3, 2.0e11 ) ) );
+    for headloss in [ Headloss::HazenWilliams, Headloss::ChezyManning ] {
+        let exported = epanet::export( &network, &fluid, g, headloss, FrictionModel::Default );
+        assert!( exported.text.contains( &headloss.text() ) );
+        let Edge::Pipe( pipe ) = epanet::import( &exported.text, &fluid, g ).unwrap().graph.edges()[0].clone() else {
+            panic!( "Expected a pipe" )
+        };
+        assert!( ( pipe.roughness - 0.1e-3 ).abs() < 0.05e-3 );
+        // The pipe keeps the formula of the file
+        assert!( matches!( ( headloss, pipe.friction ), ( Headloss::HazenWilliams, FrictionModel::HazenWilliams { .. } )
+            | ( Headloss::ChezyManning, FrictionModel::Manning { .. } ) ) );
+    }
+}
+
+#[test]
+fn eki_only_components() {
+    let fluid = Fluid::default();
+    let g = 9.80665;
+    let mut network = Graph::new();
+    let nodes: Vec<Node> = ( 1..6 ).map( |id| Node::Connection( Connection::new( id ) ) ).collect();
+    for node in nodes.iter() { network.add_node( node.clone() ); }
+    let ( n1, n2, n3, n4, n5 ) = ( nodes[0].clone(), nodes[1].clone(), nodes[2].clone(), nodes[3].clone(), nodes[4].clone() );
+    network.add_edge( Edge::Bend( Bend::new( n1.id(), n2.id() ) ) );
+    network.add_edge( Edge::SizeChange( SizeChange::new_params( n2.id(), n3.id(), 0.1, 0.5 ) ) );
+    network.add_edge( Edge::SafetyValve( SafetyValve::new( n3.id(), n4.id(), 1.0e5 ) ) );
+    network.add_edge( Edge::BurstingDisk( BurstingDisk::new( n4.id(), n5.id(), 1.0e6 ) ) );
+    let exported = epanet::export( &network, &fluid, g, Headloss::DarcyWeisbach, FrictionModel::Default );
+    assert_eq!( exported.report.len(), 4 );
+    assert!( exported.report[0].starts_with( "Link 1: Bend" ) );
+    assert!( exported.report[1].starts_with( "Link 2: Size Change" ) );
+    assert!( exported.report[2].contains( "general purpose valve" ) );
+    assert!( exported.text.contains( "GPV" ) );
+    // The safety valve and bursting disk are closed in the steady state
+    assert!( exported.text.contains( " 3        Closed" ) && exported.text.contains( " 4        Closed" ) );
+    let imported = epanet::import( &exported.text, &fluid, g ).unwrap();
+    assert_eq!( imported.graph.num_edges(), 4 );
+}
+
+#[test]
+fn bend_friction() {
+    let fluid = Fluid::default();
+    let g = 9.80665;
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new( 1 ) ) );
+    network.add_node( Node::Pressure( Pressure::new( 2 ) ) );
+    let bend = Bend::new( 1, 2 );
+    network.add_edge( Edge::Bend( bend.clone() ) );
+    // The minor loss of a bend that follows the solver uses the default model of the solver
+    let model = FrictionModel::Fixed { f: 0.03 };
+    let ( q, nu ) = ( bend.area(), fluid.kinematic_viscosity() );
+    let k = bend.k( q, nu, model ) - 0.03 * bend.length() / bend.diameter;
+    let exported = epanet::export( &network, &fluid, g, Headloss::DarcyWeisbach, model );
+    assert_eq!( exported.report[0], format!( "Link 1: Bend written as a pipe with a minor loss of {:.4}", k ) );
+    let default = epanet::export( &network, &fluid, g, Headloss::DarcyWeisbach, FrictionModel::Default );
+    assert_ne!( default.report[0], exported.report[0] );
+}
+
+#[test]
+fn pipe_friction() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let g = solver.gravity();
+    let models = [ FrictionModel::HazenWilliams { c: 120.0 }, FrictionModel::Manning { n: 0.012 }, FrictionModel::Fixed { f: 0.02 } ];
+    let formulas = [ Headloss::HazenWilliams, Headloss::ChezyManning, Headloss::DarcyWeisbach ];
+    for model in models {
+        for headloss in formulas {
+            // A model of the formula is written as it is and gives the same loss at any flow rate,
+            // others give the same loss at the matching velocity of 1 m/s
+            let exact = matches!( ( model, headloss ), ( FrictionModel::HazenWilliams { .. }, Headloss::HazenWilliams )
+                | ( FrictionModel::Manning { .. }, Headloss::ChezyManning ) );
+            let velocities: &[f64] = if exact { &[ 0.5, 1.0, 2.0 ] } else { &[ 1.0 ] };
+            for velocity in velocities {
+                let mut network = Graph::new();
+                network.add_node( Node::Pressure( Pressure::new_with_value( 1, 101325.0 + 5.0e5 ) ) );
+                let demand = - fluid.density() * velocity * 0.25 * PI * 0.2 * 0.2;
+                network.add_node( Node::Flow( Flow::new_with_value( 2, demand ) ) );
+                let mut pipe = Pipe::new_params( 1, 2, 500.0, 0.2, 0.1e-3, 5.0e-3, 2.0e11 );
+                pipe.friction = model;
+                network.add_edge( Edge::Pipe( pipe ) );
+                let exported = epanet::export( &network, &fluid, g, headloss, FrictionModel::Default );
+                let mut imported = epanet::import( &exported.text, &fluid, g ).unwrap().graph;
+                let mut losses = vec![];
+                for network in [ &mut network, &mut imported ] {
+                    assert!( solver.solve_steady( network, &fluid, true ).is_ok() );
+                    let rho_g = fluid.density() * g;
+                    let mut head = |id: usize| {
+                        let index = network.index( id );
+                        let node = &mut network.mut_nodes()[index];
+                        *node.elevation() + node.pressure()[0] / rho_g
+                    };
+                    losses.push( head( 1 ) - head( 2 ) );
+                }
+                let tolerance = if exact { 1.0e-9 } else { 1.0e-3 };
+                assert!( ( losses[1] / losses[0] - 1.0 ).abs() < tolerance, "{:?} as {}: {:?}", model, headloss.text(), losses );
+            }
+        }
+    }
+}
@@ -21,6 +21,9 @@ fn fluid() {
     assert_eq!(fluid.density(), 1000.0);
     assert_eq!(fluid.kinematic_viscosity(), 1.0e-6);
     assert_eq!(fluid.bulk_modulus(), 2.0e9);
+    assert_eq!(fluid.vapour_pressure(), 1705.0);
+    *fluid.vapour().unwrap() = 2339.0;
+    assert_eq!(fluid.vapour_pressure(), 2339.0);
 }
 
 #[test]
@@ -29,10 +32,13 @@ fn water() {
     assert_eq!(fluid.density(), 998.21 );
     assert_eq!(fluid.kinematic_viscosity(), 1.002e-3 / 998.21 );
     assert_eq!(fluid.bulk_modulus(), 998.21 * 1481. * 1481. );
+    assert_eq!(fluid.vapour_pressure(), 2339.0 );
+    assert!(fluid.vapour().is_none());
     if let Some(temp) = fluid.temperature() {
         *temp = 273.15 + 47.0;                                      // Water @ 47 degrees C
     }
     assert_eq!(fluid.density(), 0.5 * ( 989.79 + 988.92 ) );
+    assert_eq!(fluid.vapour_pressure(), 0.5 * ( 10100.0 + 11180.0 ) );
     assert_eq!(fluid.min_temperature().unwrap(), 273.15 );
     assert_eq!(fluid.max_temperature().unwrap(), 273.15 + 100.0 );
 }
@@ -2,8 +2,8 @@ use eki::Connection;
 use eki::node::Node;
 use eki::nodes::{ pressure::Pressure, flow::Flow };
 use eki::edge::Edge;
-use eki::edges::{ pipe::Pipe };
-use eki::graph::Graph;
+use eki::edges::{ pipe::Pipe, pump::Pump, valve::Valve };
+use eki::graph::{ Graph, Diagnostic };
 use ohsl::Mat64;
 
 #[test]
@@ -21,7 +21,7 @@ fn add_node_edge() {
     let node_to = Node::Flow( Flow::new( 1 ) );
     graph.add_node( node_to.clone() );
     assert_eq!( graph.num_nodes(), 2 );
-    let edge = Edge::Pipe( Pipe::new( node_from, node_to ) );
+    let edge = Edge::Pipe( Pipe::new( node_from.id(), node_to.id() ) );
     graph.add_edge( edge );
     assert_eq!( graph.num_edges(), 1 );
     graph.create_id_to_index();
@@ -55,10 +55,10 @@ fn add_node_edge_2() {
 
     assert_eq!( graph.num_nodes(), 3 );
     
-    let edge_pipe = Edge::Pipe( Pipe::new( node_from, node_mid.clone() ) );
+    let edge_pipe = Edge::Pipe( Pipe::new( node_from.id(), node_mid.id() ) );
     graph.add_edge( edge_pipe );
 
-    let edge_valve = Edge::Pipe( Pipe::new( node_mid, node_to ) );
+    let edge_valve = Edge::Pipe( Pipe::new( node_mid.id(), node_to.id() ) );
     graph.add_edge( edge_valve );
 
     assert_eq!( graph.num_edges(), 2 );
@@ -77,4 +77,81 @@ fn add_node_edge_2() {
             assert_eq!( k_matrix[i][j], graph.k_matrix()[i][j] );
         }
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn k_sparse() {
+    let mut graph = Graph::new();
+    let node_from = Node::Flow( Flow::new( 1 ) );
+    graph.add_node( node_from.clone() );
+    let node_mid = Node::Connection( Connection::new( 2 ) );
+    graph.add_node( node_mid.clone() );
+    let node_to = Node::Pressure( Pressure::new( 3 ) );
+    graph.add_node( node_to.clone() );
+    graph.add_edge( Edge::Pipe( Pipe::new( node_from.id(), node_mid.id() ) ) );
+    graph.add_edge( Edge::Pipe( Pipe::new( node_mid.id(), node_to.id() ) ) );
+    graph.create_id_to_index();
+
+    let ( k_dense, k_sparse ) = ( graph.k_matrix(), graph.k_sparse() );
+    let ( kt_dense, kt_sparse ) = ( graph.incidence_matrix(), graph.incidence_sparse() );
+    assert_eq!( k_sparse.nnz(), 4 );
+    for i in 0..2 {
+        for j in 0..3 {
+            assert_eq!( k_dense[i][j], k_sparse.get( i, j ) );
+            assert_eq!( kt_dense[j][i], kt_sparse.get( j, i ) );
+        }
+    }
+    assert_eq!( graph.edge_indices(), vec![ (0, 1), (1, 2) ] );
+}
+
+#[test]
+fn validate() {
+    let mut graph = Graph::new();
+    let node_from = Node::Pressure( Pressure::new( 1 ) );
+    graph.add_node( node_from.clone() );
+    let node_mid = Node::Connection( Connection::new( 2 ) );
+    graph.add_node( node_mid.clone() );
+    let node_to = Node::Pressure( Pressure::new( 3 ) );
+    graph.add_node( node_to.clone() );
+    graph.add_edge( Edge::Pipe( Pipe::new( node_from.id(), node_mid.id() ) ) );
+    graph.add_edge( Edge::Valve( Valve::new( node_mid.id(), node_to.id() ) ) );
+    assert!( graph.validate().is_empty() );
+}
+
+#[test]
+fn validate_diagnostics() {
+    let mut graph = Graph::new();
+    let node_from = Node::Pressure( Pressure::new( 1 ) );
+    graph.add_node( node_from.clone() );
+    let node_mid = Node::Connection( Connection::new( 2 ) );
+    graph.add_node( node_mid.clone() );
+    graph.add_node( Node::Connection( Connection::new( 2 ) ) );
+    let node_to = Node::Pressure( Pressure::new( 3 ) );
+    graph.add_node( node_to.clone() );
+    let island = ( Node::Flow( Flow::new( 4 ) ), Node::Connection( Connection::new( 5 ) ) );
+    graph.add_node( island.0.clone() );
+    graph.add_node( island.1.clone() );
+
+    let mut pipe = Pipe::new( node_from.id(), node_mid.id() );
+    pipe.length = 0.0;
+    pipe.diameter = 0.0;
+    graph.add_edge( Edge::Pipe( pipe ) );
+    let mut valve = Valve::new( node_mid.id(), node_to.id() );
+    valve.invk.reverse();
+    graph.add_edge( Edge::Valve( valve ) );
+    let mut pump = Pump::new( island.0.id(), island.1.id() );
+    pump.head_data.clear();
+    graph.add_edge( Edge::Pump( pump ) );
+    graph.add_edge( Edge::Pipe( Pipe::new( node_to.id(), Node::Connection( Connection::new( 9 ) ).id() ) ) );
+
+    let diagnostics = graph.validate();
+    assert_eq!( diagnostics, vec![ 
+        Diagnostic::DuplicateNodeId { id: 2 },
+        Diagnostic::DanglingEdge { edge: 3, id: 9 },
+        Diagnostic::NoHeadReference { nodes: vec![ 4, 5 ] },
+        Diagnostic::ZeroLength { edge: 0 },
+        Diagnostic::ZeroDiameter { edge: 0 },
+        Diagnostic::NonMonotoneValveTable { edge: 1 },
+        Diagnostic::EmptyPumpCurve { edge: 2 },
+    ] );
+}
@@ -0,0 +1,167 @@
+use eki::fluid::Fluid;
+use eki::fluids::water::Water;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, flow::Flow, connection::Connection, tank::Tank };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, valve::Valve, pump::Pump, check_valve::CheckValve, safety_valve::SafetyValve };
+use eki::events::{ TransientEvent, Time, Value };
+use eki::friction::FrictionModel;
+use eki::graph::Graph;
+use eki::project::{ self, Project, ProjectError };
+use eki::solver::{ Solver, SolverType };
+use eki::series::TimeSeries;
+
+// Reservoir - pump - check valve - pipe - junction - valve - demand, with a tank and a safety valve
+fn network() -> ( Fluid, Solver, Graph ) {
+    let fluid = Fluid::Water( Water::new( 293.15 ) );
+    let mut solver = Solver::default();
+    *solver.solver_type() = SolverType::Characteristics;
+    *solver.dt() = 0.01;
+    *solver.friction() = FrictionModel::ColebrookWhite;
+    let mut graph = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 1, 101325.0 + 2.0e4 ) );
+    let mut suction = Connection::new( 2 );
+    suction.loc.x = 50.0;
+    let suction = Node::Connection( suction );
+    let discharge = Node::Connection( Connection::new_elevation( 3, 1.0 ) );
+    let junction = Node::Connection( Connection::new_elevation( 4, 2.0 ) );
+    let mut demand = Flow::new_elevation( 5, 2.0 );
+    demand.consumption = vec![ - 1.5 ];
+    demand.series = Some( TimeSeries::pattern( &[ - 0.75, - 2.25 ], 3600.0 ) );
+    let demand = Node::Flow( demand );
+    let tank = Node::Tank( Tank::new_with_values( 6, 101325.0, 1000.0, 9.80665, 3.0, 2.5, 0.5, 5.0 ) );
+    let vent = Node::Pressure( Pressure::new_with_value( 7, 101325.0 ) );
+    for node in [ &reservoir, &suction, &discharge, &junction, &demand, &tank, &vent ] {
+        graph.add_node( node.clone() );
+    }
+    let mut pump = Pump::new( reservoir.id(), suction.id() );
+    pump.events = vec![ TransientEvent::PowerFailure( Time( 0.5 ) ) ];
+    graph.add_edge( Edge::Pump( pump ) );
+    graph.add_edge( Edge::CheckValve( CheckValve::new( suction.id(), discharge.id() ) ) );
+    let mut pipe = Pipe::new_params( discharge.id(), junction.id(), 250.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    pipe.segments = 8;
+    pipe.friction = FrictionModel::HazenWilliams { c: 130.0 };
+    graph.add_edge( Edge::Pipe( pipe ) );
+    let mut valve = Valve::new( junction.id(), demand.id() );
+    valve.events = vec![ TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.1 ), Time( 0.5 ) ) ];
+    graph.add_edge( Edge::Valve( valve ) );
+    graph.add_edge( Edge::Pipe( Pipe::new( junction.id(), tank.id() ) ) );
+    graph.add_edge( Edge::SafetyValve( SafetyValve::new( junction.id(), vent.id(), 5.0e5 ) ) );
+    ( fluid, solver, graph )
+}
+
+#[test]
+fn round_trip() {
+    let ( fluid, solver, graph ) = network();
+    let saved = Project::new( &fluid, &solver, &graph );
+    assert_eq!( saved.version, project::VERSION );
+    let text = saved.to_json();
+    assert!( !text.contains( "mass_flow" ) && !text.contains( "selected" ) );
+    let loaded = Project::from_json( &text ).unwrap();
+    assert_eq!( loaded, saved );
+    assert_eq!( loaded.fluid(), fluid );
+    assert_eq!( loaded.solver().settings(), solver.settings() );
+    let copy = loaded.graph().unwrap();
+    assert_eq!( copy.nodes, graph.nodes );
+    assert_eq!( copy.edges, graph.edges );
+}
+
+#[test]
+fn same_solution() {
+    let ( fluid, mut solver, mut graph ) = network();
+    let loaded = Project::from_json( &Project::new( &fluid, &solver, &graph ).to_json() ).unwrap();
+    let ( fluid_copy, mut solver_copy, mut copy ) = ( loaded.fluid(), loaded.solver(), loaded.graph().unwrap() );
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    assert!( solver_copy.solve_steady( &mut copy, &fluid_copy, true ).is_ok() );
+    for ( a, b ) in graph.edges.iter_mut().zip( copy.edges.iter_mut() ) {
+        assert_eq!( a.mass_flow(), b.mass_flow() );
+    }
+}
+
+#[test]
+fn defaults_and_unknown_fields() {
+    let text = r#"{
+        "version": 1,
+        "nodes": [
+            { "type": "Pressure", "id": 1, "pressure": 201325.0 },
+            { "type": "Flow", "id": 2, "consumption": -2.0 }
+        ],
+        "edges": [ { "type": "Pipe", "from": 1, "to": 2, "length": 50.0 } ]
+    }"#;
+    let loaded = Project::from_json( text ).unwrap();
+    assert_eq!( loaded.fluid(), Fluid::default() );
+    let graph = loaded.graph().unwrap();
+    let Edge::Pipe( pipe ) = &graph.edges[0] else { panic!( "Expected a pipe" ) };
+    let default = Pipe::new( Node::default().id(), Node::default().id() );
+    assert_eq!( ( pipe.length, pipe.diameter, pipe.roughness ), ( 50.0, default.diameter, default.roughness ) );
+    assert_eq!( pipe.from, 1 );
+    // A misspelt parameter is an error rather than silently taking its default value
+    let misspelt = text.replace( "\"length\"", "\"lenght\"" );
+    assert!( matches!( Project::from_json( &misspelt ), Err( ProjectError::Json( message ) ) if message.contains( "lenght" ) ) );
+    let unknown = text.replace( "\"to\": 2", "\"to\": 3" );
+    assert_eq!( Project::from_json( &unknown ).unwrap().graph().err(), Some( ProjectError::UnknownNode { id: 3 } ) );
+    let newer = text.replace( "\"version\": 1", "\"version\": 99" );
+    assert_eq!( Project::from_json( &newer ), Err( ProjectError::NewerVersion { version: 99 } ) );
+}
+
+#[test]
+fn legacy_graph() {
+    let ( fluid, mut solver, mut graph ) = network();
+    // Files written before versioning hold the serialised graph including its results
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    let mut legacy = serde_json::to_value( &graph ).unwrap();
+    legacy[ "fluid" ] = serde_json::to_value( &fluid ).unwrap();
+    legacy[ "solver" ] = serde_json::to_value( &solver ).unwrap();
+    let loaded = Project::from_json( &legacy.to_string() ).unwrap();
+    assert_eq!( loaded.version, project::VERSION );
+    assert_eq!( loaded, Project::new( &fluid, &solver, &graph ) );
+    let error = Project::from_json( r#"{ "nodes": [ { "Pipe": {} } ], "edges": [] }"# ).unwrap_err();
+    assert!( matches!( error, ProjectError::Migration { version: 0, .. } ) );
+}
+
+#[test]
+fn pattern_migration() {
+    // Version 1 flow nodes had a pattern of demand multipliers
+    let text = r#"{
+        "version": 1,
+        "nodes": [
+            { "type": "Flow", "id": 1, "consumption": -2.0, "pattern": [ 0.5, 1.5 ], "pattern_step": 1800.0 },
+            { "type": "Flow", "id": 2, "pattern": [ 2.0 ], "series": { "points": [ [ 0.0, -1.0 ] ] } },
+            { "type": "Flow", "id": 3, "pattern": [] }
+        ],
+        "edges": []
+    }"#;
+    let loaded = Project::from_json( text ).unwrap();
+    assert_eq!( loaded.version, project::VERSION );
+    let graph = loaded.graph().unwrap();
+    let series: Vec<Option<TimeSeries>> = graph.nodes.iter().map( |node| match node {
+        Node::Flow( flow ) => flow.series.clone(),
+        _ => panic!( "Expected a flow node" ),
+    }).collect();
+    assert_eq!( series[0], Some( TimeSeries::pattern( &[ -1.0, -3.0 ], 1800.0 ) ) );
+    // A series took the place of the pattern
+    assert_eq!( series[1], Some( TimeSeries::linear( vec![ ( 0.0, -1.0 ) ] ) ) );
+    assert_eq!( series[2], None );
+}
+
+#[test]
+fn schema() {
+    let schema = Project::schema();
+    assert_eq!( schema[ "properties" ][ "version" ][ "const" ], project::VERSION );
+    let nodes = schema[ "properties" ][ "nodes" ][ "items" ][ "oneOf" ].as_array().unwrap();
+    let edges = schema[ "properties" ][ "edges" ][ "items" ][ "oneOf" ].as_array().unwrap();
+    assert_eq!( ( nodes.len(), edges.len() ), ( 6, 12 ) );
+    let pipe = edges.iter().find( |edge| edge[ "properties" ][ "type" ][ "const" ] == "Pipe" ).unwrap();
+    assert_eq!( pipe[ "additionalProperties" ], false );
+    assert_eq!( pipe[ "properties" ][ "length" ][ "type" ], "number" );
+    assert_eq!( pipe[ "properties" ][ "segments" ][ "type" ], "integer" );
+    assert_eq!( pipe[ "required" ], serde_json::json!( [ "type", "from", "to" ] ) );
+    assert_eq!( pipe[ "properties" ][ "friction" ][ "oneOf" ].as_array().unwrap().len(), 8 );
+    assert_eq!( schema[ "properties" ][ "solver" ][ "properties" ][ "friction" ][ "oneOf" ].as_array().unwrap().len(), 8 );
+    let valve = edges.iter().find( |edge| edge[ "properties" ][ "type" ][ "const" ] == "Valve" ).unwrap();
+    assert_eq!( valve[ "properties" ][ "events" ][ "items" ][ "oneOf" ].as_array().unwrap().len(), 6 );
+    assert_eq!( valve[ "properties" ][ "trim" ][ "enum" ].as_array().unwrap().len(), 3 );
+    let control = edges.iter().find( |edge| edge[ "properties" ][ "type" ][ "const" ] == "ControlValve" ).unwrap();
+    assert_eq!( control[ "properties" ][ "mode" ][ "enum" ].as_array().unwrap().len(), 3 );
+    assert_eq!( control[ "properties" ][ "actuator" ][ "oneOf" ].as_array().unwrap().len(), 2 );
+}
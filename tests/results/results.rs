@@ -0,0 +1,155 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, connection::Connection };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, valve::Valve };
+use eki::events::{ TransientEvent, Time, Value };
+use eki::graph::Graph;
+use eki::results::{ Table, Options, ResultsError, NodeQuantity, EdgeQuantity, PressureUnit, FlowUnit };
+use eki::solver::Solver;
+
+#[test]
+fn columns_and_units() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    // Reservoir - pipe - valve closing to atmosphere, run for 20 steps
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 1.0e5 ) ) );
+    network.add_node( Node::Connection( Connection::new_elevation( 1, 2.0 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_with_value( 2, 101325.0 ) ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 0, 1, 200.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    let mut valve = Edge::Valve( Valve::new( 1, 2 ) );
+    valve.add_event( TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) );
+    network.add_edge( valve );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    for _step in 0..20 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    let g = solver.gravity();
+    let options = Options {
+        nodes: Some( vec![ 1 ] ),
+        edges: Some( vec![ ( 0, 1 ) ] ),
+        node_quantities: vec![ NodeQuantity::Pressure, NodeQuantity::Head ],
+        edge_quantities: vec![ EdgeQuantity::MassFlow, EdgeQuantity::Flow, EdgeQuantity::Velocity ],
+        pressure_unit: PressureUnit::Bar,
+        gauge: true,
+        flow_unit: FlowUnit::LitresPerSecond,
+        ..Options::default()
+    };
+    let table = Table::new( &network, &fluid, g, &solver.tnodes(), &options ).unwrap();
+    assert_eq!( table.names, vec![ "time [s]", "p_1 [bar]", "h_1 [m]", "m_0_1 [kg/s]", "q_0_1 [L/s]", "v_0_1 [m/s]" ] );
+    assert_eq!( table.rows(), 21 );
+    let pressure = network.mut_nodes()[1].pressure().clone();
+    let mass_flow = network.mut_edges()[0].mass_flow().clone();
+    let area = network.edges()[0].area();
+    let rho = fluid.density();
+    for i in 0..21 {
+        assert_eq!( table.columns[0][i], solver.tnodes()[i] );
+        assert!( ( table.columns[1][i] - ( pressure[i] - 101325.0 ) / 1.0e5 ).abs() < 1.0e-12 );
+        assert!( ( table.columns[2][i] - ( 2.0 + pressure[i] / ( rho * g ) ) ).abs() < 1.0e-10 );
+        assert_eq!( table.columns[3][i], mass_flow[i] );
+        assert!( ( table.columns[4][i] - 1.0e3 * mass_flow[i] / rho ).abs() < 1.0e-10 );
+        assert!( ( table.columns[5][i] - mass_flow[i] / ( rho * area ) ).abs() < 1.0e-12 );
+    }
+}
+
+#[test]
+fn decimation_and_csv() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    // Reservoir - pipe - valve closing to atmosphere, run for 20 steps
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 1.0e5 ) ) );
+    network.add_node( Node::Connection( Connection::new_elevation( 1, 2.0 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_with_value( 2, 101325.0 ) ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 0, 1, 200.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    let mut valve = Edge::Valve( Valve::new( 1, 2 ) );
+    valve.add_event( TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) );
+    network.add_edge( valve );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    for _step in 0..20 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    let options = Options { every: 3, ..Options::default() };
+    let table = Table::new( &network, &fluid, solver.gravity(), &solver.tnodes(), &options ).unwrap();
+    // All nodes and edges by default
+    assert_eq!( table.names.len(), 1 + 3 + 2 );
+    // Every third step and the last one
+    assert_eq!( table.rows(), 8 );
+    let time = table.column( "time [s]" ).unwrap();
+    assert!( ( time[1] - 0.03 ).abs() < 1.0e-12 );
+    assert!( ( time[7] - 0.2 ).abs() < 1.0e-12 );
+    let csv = table.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!( lines.len(), 9 );
+    assert_eq!( lines[0], "time [s],p_0 [Pa],p_1 [Pa],p_2 [Pa],q_0_1 [m^3/s],q_1_2 [m^3/s]" );
+    let last: Vec<f64> = lines[8].split( ',' ).map( |value| value.parse().unwrap() ).collect();
+    assert_eq!( last, table.columns.iter().map( |column| column[7] ).collect::<Vec<f64>>() );
+}
+
+#[test]
+fn columnar() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    // Reservoir - pipe - valve closing to atmosphere, run for 20 steps
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 1.0e5 ) ) );
+    network.add_node( Node::Connection( Connection::new_elevation( 1, 2.0 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_with_value( 2, 101325.0 ) ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 0, 1, 200.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    let mut valve = Edge::Valve( Valve::new( 1, 2 ) );
+    valve.add_event( TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) );
+    network.add_edge( valve );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    for _step in 0..20 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    let table = Table::new( &network, &fluid, solver.gravity(), &solver.tnodes(), &Options::default() ).unwrap();
+    let bytes = table.to_columnar();
+    assert_eq!( &bytes[0..8], b"EKICOL01" );
+    assert_eq!( Table::from_columnar( &bytes ).unwrap(), table );
+    // A file name of its own so that concurrent test runs do not share it
+    let path = std::env::temp_dir().join( format!( "eki_results_columnar_{}.bin", std::process::id() ) );
+    table.write_columnar( &path ).unwrap();
+    assert_eq!( Table::read_columnar( &path ).unwrap(), table );
+    std::fs::remove_file( &path ).unwrap();
+    assert!( matches!( Table::from_columnar( &bytes[..bytes.len() - 1] ), Err( ResultsError::Format( _ ) ) ) );
+    // A row count that overflows the column length
+    let mut header = b"EKICOL01".to_vec();
+    header.extend_from_slice( &u64::MAX.to_le_bytes() );
+    header.extend_from_slice( &1_u64.to_le_bytes() );
+    header.extend_from_slice( &1_u32.to_le_bytes() );
+    header.push( b't' );
+    assert_eq!( Table::from_columnar( &header ), Err( ResultsError::Format( "too many rows".to_string() ) ) );
+}
+
+#[test]
+fn errors() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    // Reservoir - pipe - valve closing to atmosphere, run for 20 steps
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 1.0e5 ) ) );
+    network.add_node( Node::Connection( Connection::new_elevation( 1, 2.0 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_with_value( 2, 101325.0 ) ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 0, 1, 200.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    let mut valve = Edge::Valve( Valve::new( 1, 2 ) );
+    valve.add_event( TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) );
+    network.add_edge( valve );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    for _step in 0..20 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    let g = solver.gravity();
+    let time = solver.tnodes();
+    let options = Options { nodes: Some( vec![ 7 ] ), ..Options::default() };
+    assert_eq!( Table::new( &network, &fluid, g, &time, &options ), Err( ResultsError::UnknownNode { id: 7 } ) );
+    let options = Options { edges: Some( vec![ ( 1, 0 ) ] ), ..Options::default() };
+    assert_eq!( Table::new( &network, &fluid, g, &time, &options ), Err( ResultsError::UnknownEdge { from: 1, to: 0 } ) );
+    let result = Table::new( &network, &fluid, g, &time[..5], &Options::default() );
+    assert!( matches!( result, Err( ResultsError::Length { expected: 5, found: 21, .. } ) ) );
+}
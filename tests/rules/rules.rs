@@ -0,0 +1,281 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, connection::Connection, tank::Tank, flow::Flow };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, pump::Pump, valve::Valve };
+use eki::events::{ TransientEvent, Time, Value };
+use eki::graph::{ Graph, Diagnostic };
+use eki::project::Project;
+use eki::rules::{ Rule, RuleState, Condition, Comparison, Action, Target };
+use eki::solver::{ Solver, SolverType };
+
+#[test]
+fn delay_and_latching() {
+    let mut rule = Rule::new( "Hold", vec![], vec![] );
+    rule.delay = 2.0;
+    let mut state = RuleState::default();
+    // The conditions must hold for the whole delay
+    assert!( !rule.update( &mut state, 0.0, true ) );
+    assert!( !rule.update( &mut state, 1.0, false ) );
+    assert!( !rule.update( &mut state, 2.0, true ) );
+    assert!( !rule.update( &mut state, 3.0, true ) );
+    assert!( rule.update( &mut state, 4.0, true ) );
+    // It fires again only after the conditions have cleared
+    assert!( !rule.update( &mut state, 7.0, true ) );
+    assert!( !rule.update( &mut state, 8.0, false ) );
+    assert!( !rule.update( &mut state, 9.0, true ) );
+    assert!( rule.update( &mut state, 11.0, true ) );
+    assert_eq!( state.fired, vec![ 4.0, 11.0 ] );
+    // A latching rule keeps counting once the conditions have held and fires once
+    let rule = Rule { delay: 2.0, latching: true, ..Rule::new( "Latch", vec![], vec![] ) };
+    let mut state = RuleState::default();
+    assert!( !rule.update( &mut state, 0.0, false ) );
+    assert!( !rule.update( &mut state, 1.0, true ) );
+    assert!( !rule.update( &mut state, 2.0, false ) );
+    assert!( rule.update( &mut state, 3.0, false ) );
+    assert!( !rule.update( &mut state, 10.0, true ) );
+    assert_eq!( state.fired, vec![ 3.0 ] );
+}
+
+#[test]
+fn trip_on_low_flow() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 50;
+    // Reservoir - pump - pipe - valve - elevated reservoir with the valve closing from t = 0
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new( 0 ) ) );
+    network.add_node( Node::Connection( Connection::new( 1 ) ) );
+    network.add_node( Node::Connection( Connection::new( 2 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_elevation( 3, 280.0 ) ) );
+    let mut pump = Pump::new( 0, 1 );
+    pump.motor_inertia = 0.5;
+    network.add_edge( Edge::Pump( pump ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 1, 2, 500.0, 0.3, 0.05e-3, 10.0e-3, 2.0e11 ) ) );
+    let mut valve = Valve::new( 2, 3 );
+    valve.diameter = 0.15;
+    valve.events = vec![ TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) ];
+    network.add_edge( Edge::Valve( valve ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    let trip = Action { target: Target::Edge { from: 0, to: 1 }, event: TransientEvent::PowerFailure( Time( 0.0 ) ) };
+    network.rules.push( Rule::new( "Trip on low flow",
+        vec![ Condition::Flow { from: 1, to: 2, comparison: Comparison::Below, value: 190.0 } ], vec![ trip ] ) );
+    for _step in 0..90 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    let time = solver.tnodes();
+    let flow = network.mut_edges()[1].mass_flow().clone();
+    let step = flow.iter().position( |m| *m < 190.0 ).expect( "The closure reduces the flow" );
+    // The rule fires at the end of the first step below the limit and trips the pump from then
+    assert_eq!( solver.rule_states()[0].fired, vec![ time[step] ] );
+    // The event is kept apart from the events of the pump
+    let pump_target = Target::Edge { from: 0, to: 1 };
+    assert_eq!( network.spawned( pump_target ), [ TransientEvent::PowerFailure( Time( time[step] ) ) ] );
+    let Edge::Pump( pump ) = network.edges()[0].clone() else { panic!( "Expected a pump" ) };
+    assert!( pump.events.is_empty() );
+    assert!( pump.speed[..=step].iter().all( |n| *n == 11300.0 ) );
+    assert!( pump.speed[step + 1..].windows( 2 ).all( |n| n[1] < n[0] ) );
+    // The events spawned by the rule are removed for the next run
+    assert!( solver.solve_steady( &mut network, &fluid, false ).is_ok() );
+    assert!( network.spawned( pump_target ).is_empty() );
+    assert_eq!( solver.rule_states(), &vec![ RuleState::default() ] );
+}
+
+#[test]
+fn tank_level_control() {
+    // Open the throttled inlet half an hour after the tank level falls below 1.9 m
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.solver_type() = SolverType::ExtendedPeriod;
+    *solver.dt() = 600.0;
+    let rho_g = fluid.density() * solver.gravity();
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new_with_value( 0, 101325.0 + rho_g * 5.0 ) ) );
+    network.add_node( Node::Tank( Tank::new_with_values( 1, 101325.0, fluid.density(), solver.gravity(), 10.0, 2.0, 0.5, 4.0 ) ) );
+    network.add_node( Node::Flow( Flow { consumption: vec![ -5.0 ], ..Flow::new_elevation( 2, -20.0 ) } ) );
+    network.add_node( Node::Connection( Connection::new( 3 ) ) );
+    let mut valve = Valve::new( 0, 3 );
+    valve.diameter = 0.1;
+    valve.open_percent = vec![ 0.4 ];
+    network.add_edge( Edge::Valve( valve ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 3, 1, 1000.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 1, 2, 500.0, 0.15, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    let open = Action { target: Target::Edge { from: 0, to: 3 }, event: TransientEvent::InstantaneousChange( Value( 1.0 ), Time( 0.0 ) ) };
+    let mut rule = Rule::new( "Low level", vec![ Condition::Level { id: 1, comparison: Comparison::Below, value: 1.9 } ], vec![ open ] );
+    rule.delay = 1800.0;
+    network.rules.push( rule );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    for _step in 0..24 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    let time = solver.tnodes();
+    let Node::Tank( tank ) = network.nodes()[1].clone() else { panic!( "Expected a tank" ) };
+    let below = tank.level.iter().position( |z| *z < 1.9 ).expect( "The tank drains" );
+    let fired = below + 3;
+    assert_eq!( solver.rule_states()[0].fired, vec![ time[fired] ] );
+    let open = network.mut_edges()[0].open_percent().unwrap().clone();
+    assert!( open[..=fired].iter().all( |open| *open == 0.4 ) );
+    assert!( open[fired + 1..].iter().all( |open| *open == 1.0 ) );
+    // The opened inlet slows the fall of the tank
+    let fall = |k: usize| tank.level[k] - tank.level[k + 1];
+    assert!( fall( fired + 2 ) < fall( fired - 1 ) );
+}
+
+#[test]
+fn invalid_rules() {
+    // Reservoir - pump - pipe - valve - elevated reservoir with the valve closing from t = 0
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new( 0 ) ) );
+    network.add_node( Node::Connection( Connection::new( 1 ) ) );
+    network.add_node( Node::Connection( Connection::new( 2 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_elevation( 3, 280.0 ) ) );
+    let mut pump = Pump::new( 0, 1 );
+    pump.motor_inertia = 0.5;
+    network.add_edge( Edge::Pump( pump ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 1, 2, 500.0, 0.3, 0.05e-3, 10.0e-3, 2.0e11 ) ) );
+    let mut valve = Valve::new( 2, 3 );
+    valve.diameter = 0.15;
+    valve.events = vec![ TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) ];
+    network.add_edge( Edge::Valve( valve ) );
+    let trip = |target| Action { target, event: TransientEvent::PowerFailure( Time( 0.0 ) ) };
+    let time = Condition::Time { comparison: Comparison::Above, value: 1.0 };
+    network.rules = vec![
+        Rule::new( "Valid", vec![ time, Condition::Flow { from: 1, to: 2, comparison: Comparison::Below, value: 0.0 } ],
+            vec![ trip( Target::Edge { from: 0, to: 1 } ), trip( Target::Node { id: 0 } ) ] ),
+        Rule::new( "Missing node", vec![ Condition::Head { id: 7, comparison: Comparison::Below, value: 0.0 } ], vec![] ),
+        Rule::new( "Not a tank", vec![ Condition::Level { id: 1, comparison: Comparison::Below, value: 0.0 } ], vec![] ),
+        Rule::new( "No events on pipes", vec![ time ], vec![ trip( Target::Edge { from: 1, to: 2 } ) ] ),
+    ];
+    let diagnostics = network.validate();
+    assert_eq!( diagnostics, ( 1..4 ).map( |rule| Diagnostic::InvalidRule { rule } ).collect::<Vec<_>>() );
+    assert_eq!( diagnostics[0].to_string(), "Rule 1 refers to a missing or unsuitable node or edge" );
+}
+
+#[test]
+fn project_rules() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 50;
+    // Reservoir - pump - pipe - valve - elevated reservoir with the valve closing from t = 0
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new( 0 ) ) );
+    network.add_node( Node::Connection( Connection::new( 1 ) ) );
+    network.add_node( Node::Connection( Connection::new( 2 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_elevation( 3, 280.0 ) ) );
+    let mut pump = Pump::new( 0, 1 );
+    pump.motor_inertia = 0.5;
+    network.add_edge( Edge::Pump( pump ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 1, 2, 500.0, 0.3, 0.05e-3, 10.0e-3, 2.0e11 ) ) );
+    let mut valve = Valve::new( 2, 3 );
+    valve.diameter = 0.15;
+    valve.events = vec![ TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) ];
+    network.add_edge( Edge::Valve( valve ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    let mut rule = Rule::new( "Restart",
+        vec![ Condition::Head { id: 0, comparison: Comparison::Above, value: 5.0 }, Condition::Time { comparison: Comparison::Above, value: 1.0 } ],
+        vec![ Action { target: Target::Edge { from: 0, to: 1 }, event: TransientEvent::PumpStartup( Value( 11300.0 ), Value( 1.0 ), Time( 0.5 ), Time( 2.0 ) ) } ] );
+    rule.delay = 0.5;
+    rule.latching = true;
+    network.rules.push( rule.clone() );
+    let project = Project::new( &fluid, &solver, &network );
+    let text = project.to_json();
+    assert!( text.contains( "\"type\": \"Head\"" ) && text.contains( "\"comparison\": \"Above\"" ) );
+    let loaded = Project::from_json( &text ).unwrap();
+    assert_eq!( loaded, project );
+    assert_eq!( loaded.graph().unwrap().rules, vec![ rule ] );
+    let schema = Project::schema().to_string();
+    assert!( schema.contains( "\"rules\"" ) && schema.contains( "\"const\":\"Level\"" ) );
+}
+
+#[test]
+fn rule_during_event() {
+    // Reopen the valve over 0.5 s once it has been closing for half a second
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 50;
+    // Reservoir - pump - pipe - valve - elevated reservoir with the valve closing from t = 0
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new( 0 ) ) );
+    network.add_node( Node::Connection( Connection::new( 1 ) ) );
+    network.add_node( Node::Connection( Connection::new( 2 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_elevation( 3, 280.0 ) ) );
+    let mut pump = Pump::new( 0, 1 );
+    pump.motor_inertia = 0.5;
+    network.add_edge( Edge::Pump( pump ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 1, 2, 500.0, 0.3, 0.05e-3, 10.0e-3, 2.0e11 ) ) );
+    let mut valve = Valve::new( 2, 3 );
+    valve.diameter = 0.15;
+    valve.events = vec![ TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) ];
+    network.add_edge( Edge::Valve( valve ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    let reopen = Action { target: Target::Edge { from: 2, to: 3 }, event: TransientEvent::ValveOpening( Value( 1.0 ), Time( 0.0 ), Time( 0.5 ) ) };
+    network.rules.push( Rule::new( "Reopen", vec![ Condition::Time { comparison: Comparison::Above, value: 0.5 } ], vec![ reopen ] ) );
+    for _step in 0..150 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    // The spawned event supersedes the closure from the moment it starts
+    let time = solver.tnodes();
+    let fired = solver.rule_states()[0].fired[0];
+    let step = time.iter().position( |t| *t == fired ).unwrap();
+    let open = network.mut_edges()[2].open_percent().unwrap().clone();
+    let start = 1.0 - fired;
+    for (t, open) in time.iter().zip( &open ).skip( step + 1 ) {
+        let tau = ( ( t - fired ) / 0.5 ).min( 1.0 );
+        assert!( ( open - ( start + ( 1.0 - start ) * tau ) ).abs() < 1.0e-12 );
+    }
+    assert!( open[step + 1] < open[0] );
+    assert_eq!( *open.last().unwrap(), 1.0 );
+    // The scheduled events of the valve are still valid
+    assert!( network.validate().is_empty() );
+}
+
+#[test]
+fn save_after_run() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 50;
+    // Reservoir - pump - pipe - valve - elevated reservoir with the valve closing from t = 0
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new( 0 ) ) );
+    network.add_node( Node::Connection( Connection::new( 1 ) ) );
+    network.add_node( Node::Connection( Connection::new( 2 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_elevation( 3, 280.0 ) ) );
+    let mut pump = Pump::new( 0, 1 );
+    pump.motor_inertia = 0.5;
+    network.add_edge( Edge::Pump( pump ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 1, 2, 500.0, 0.3, 0.05e-3, 10.0e-3, 2.0e11 ) ) );
+    let mut valve = Valve::new( 2, 3 );
+    valve.diameter = 0.15;
+    valve.events = vec![ TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) ];
+    network.add_edge( Edge::Valve( valve ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    let trip = Action { target: Target::Edge { from: 0, to: 1 }, event: TransientEvent::PowerFailure( Time( 0.0 ) ) };
+    network.rules.push( Rule::new( "Trip on low flow",
+        vec![ Condition::Flow { from: 1, to: 2, comparison: Comparison::Below, value: 190.0 } ], vec![ trip ] ) );
+    let before = Project::new( &fluid, &solver, &network );
+    let rules = serde_json::to_string( &network.rules ).unwrap();
+    for _step in 0..90 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    let fired = solver.rule_states()[0].fired.clone();
+    assert_eq!( fired.len(), 1 );
+    // The run state of the rules is kept by the solver
+    assert_eq!( serde_json::to_string( &network.rules ).unwrap(), rules );
+    // The events spawned in the run are not saved with the pump
+    let after = Project::new( &fluid, &solver, &network );
+    assert_eq!( after, before );
+    let mut loaded = Project::from_json( &after.to_json() ).unwrap().graph().unwrap();
+    assert!( loaded.mut_edges()[0].events().unwrap().is_empty() );
+    // The loaded network runs as the original did
+    let mut solver = after.solver();
+    assert!( solver.solve_steady( &mut loaded, &fluid, true ).is_ok() );
+    for _step in 0..90 {
+        assert!( solver.time_step( &mut loaded, &fluid ).is_ok() );
+    }
+    assert_eq!( solver.rule_states()[0].fired, fired );
+    assert_eq!( loaded.spawned( Target::Edge { from: 0, to: 1 } ), [ TransientEvent::PowerFailure( Time( fired[0] ) ) ] );
+}
@@ -0,0 +1,173 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, connection::Connection, tank::Tank, flow::Flow };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, valve::Valve };
+use eki::graph::{ Graph, Diagnostic };
+use eki::project::Project;
+use eki::series::{ TimeSeries, Interpolation, SeriesError };
+use eki::solver::{ Solver, SolverType, SolverError, Component };
+
+#[test]
+fn values() {
+    let series = TimeSeries::linear( vec![ ( 1.0, 10.0 ), ( 3.0, 20.0 ), ( 4.0, 0.0 ) ] );
+    assert_eq!( series.value( 0.0 ), 10.0 );
+    assert_eq!( series.value( 2.0 ), 15.0 );
+    assert_eq!( series.value( 3.5 ), 10.0 );
+    assert_eq!( series.value( 10.0 ), 0.0 );
+    let series = TimeSeries::step( vec![ ( 1.0, 10.0 ), ( 3.0, 20.0 ) ] );
+    assert_eq!( series.value( 0.0 ), 10.0 );
+    assert_eq!( series.value( 2.9 ), 10.0 );
+    assert_eq!( series.value( 3.0 ), 20.0 );
+    // A periodic linear series wraps from its last point to its first
+    let series = TimeSeries::linear( vec![ ( 0.0, 0.0 ), ( 5.0, 10.0 ) ] ).periodic( 10.0 );
+    assert_eq!( series.value( 2.5 ), 5.0 );
+    assert_eq!( series.value( 7.5 ), 5.0 );
+    assert_eq!( series.value( 12.5 ), 5.0 );
+    assert_eq!( series.value( -2.5 ), 5.0 );
+    let series = TimeSeries::pattern( &[ 0.5, 1.0, 1.5 ], 3600.0 );
+    assert_eq!( series.period, 3.0 * 3600.0 );
+    assert_eq!( series.value( 1800.0 ), 0.5 );
+    assert_eq!( series.value( 7200.0 ), 1.5 );
+    assert_eq!( series.value( 10800.0 ), 0.5 );
+    assert_eq!( series.as_pattern(), Some( ( vec![ 0.5, 1.0, 1.5 ], 3600.0 ) ) );
+    assert_eq!( TimeSeries::step( vec![ ( 0.0, 1.0 ), ( 1.0, 2.0 ) ] ).as_pattern(), None );
+    assert_eq!( TimeSeries::linear( vec![ ( 0.0, 1.0 ) ] ).periodic( 1.0 ).as_pattern(), None );
+    assert_eq!( TimeSeries::default().value( 1.0 ), 0.0 );
+}
+
+#[test]
+fn csv() {
+    let text = "# Reservoir pressure\ntime [s], pressure [Pa]\n0, 101325\n 10 , 201325\n\n20,101325\n";
+    let series = TimeSeries::from_csv( text, Interpolation::Linear ).unwrap();
+    assert_eq!( series.points, vec![ ( 0.0, 101325.0 ), ( 10.0, 201325.0 ), ( 20.0, 101325.0 ) ] );
+    assert_eq!( series.value( 5.0 ), 151325.0 );
+    assert_eq!( TimeSeries::from_csv( "0,1\n1,x\n", Interpolation::Step ), Err( SeriesError::Parse { line: 2 } ) );
+    assert_eq!( TimeSeries::from_csv( "0,1\n1,2,3\n", Interpolation::Step ), Err( SeriesError::Parse { line: 2 } ) );
+    assert_eq!( TimeSeries::from_csv( "0,1\n2,2\n1,3\n", Interpolation::Step ), Err( SeriesError::Unsorted { point: 3 } ) );
+    assert_eq!( TimeSeries::from_csv( "time,value\n", Interpolation::Step ), Err( SeriesError::Empty ) );
+    assert!( matches!( TimeSeries::read_csv( "missing.csv", Interpolation::Step ), Err( SeriesError::Io( _ ) ) ) );
+    let path = std::env::temp_dir().join( "eki_series.csv" );
+    std::fs::write( &path, text ).unwrap();
+    assert_eq!( TimeSeries::read_csv( &path, Interpolation::Linear ).unwrap(), series );
+    std::fs::remove_file( &path ).unwrap();
+    assert_eq!( series.clone().periodic( 20.0 ).check(), Err( SeriesError::Period ) );
+    assert_eq!( series.periodic( 30.0 ).check(), Ok( () ) );
+}
+
+// Reservoir - pipe - valve - outlet with the reservoir pressure and valve opening set by series
+fn transient() -> Graph {
+    let mut network = Graph::new();
+    let mut reservoir = Pressure::new( 0 );
+    reservoir.series = Some( TimeSeries::linear( vec![ ( 0.0, 101325.0 + 2.0e5 ), ( 0.2, 101325.0 + 3.0e5 ) ] ) );
+    network.add_node( Node::Pressure( reservoir ) );
+    network.add_node( Node::Connection( Connection::new( 1 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_with_value( 2, 101325.0 ) ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 0, 1, 200.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    let mut valve = Valve::new( 1, 2 );
+    valve.diameter = 0.1;
+    valve.series = Some( TimeSeries::step( vec![ ( 0.0, 1.0 ), ( 0.1, 0.9 ) ] ) );
+    network.add_edge( Edge::Valve( valve ) );
+    network
+}
+
+#[test]
+fn invalid_series() {
+    let mut network = transient();
+    assert!( network.validate().is_empty() );
+    // Times that do not increase and points past the end of the period
+    let reservoir = network.mut_nodes()[0].series().unwrap();
+    *reservoir = Some( TimeSeries::linear( vec![ ( 0.0, 101325.0 ), ( 0.2, 201325.0 ), ( 0.1, 101325.0 ) ] ) );
+    let valve = network.mut_edges()[1].series().unwrap();
+    *valve = Some( TimeSeries::step( vec![ ( 0.0, 1.0 ), ( 0.1, 0.9 ) ] ).periodic( 0.1 ) );
+    let diagnostics = vec![
+        Diagnostic::InvalidSeries { component: Component::Node( 0 ), error: SeriesError::Unsorted { point: 3 } },
+        Diagnostic::InvalidSeries { component: Component::Edge( 1, 2 ), error: SeriesError::Period },
+    ];
+    assert_eq!( network.validate(), diagnostics );
+    assert_eq!( diagnostics[1].to_string(), "Edge ( 1, 2 ) has an invalid time series: The points do not fit within the period" );
+    let mut solver = Solver::default();
+    let result = solver.solve_steady( &mut network, &Fluid::default(), true );
+    assert_eq!( result, Err( SolverError::InvalidNetwork { diagnostics } ) );
+}
+
+#[test]
+fn transient_boundaries() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    let mut network = transient();
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    for _step in 0..30 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    let time = solver.tnodes();
+    let pressure = network.mut_nodes()[0].pressure().clone();
+    let Edge::Valve( valve ) = network.edges()[1].clone() else { panic!( "Expected a valve" ) };
+    let ( reservoir, opening ) = ( network.mut_nodes()[0].series().unwrap().clone().unwrap(), valve.series.unwrap() );
+    // No values are prepared by hand, the series are evaluated at every solver time
+    assert_eq!( pressure.len(), time.len() );
+    assert_eq!( valve.open_percent.len(), time.len() );
+    for (step, t) in time.iter().enumerate() {
+        assert!( ( pressure[step] - reservoir.value( *t ) ).abs() < 1.0e-6 );
+        assert_eq!( valve.open_percent[step], opening.value( *t ) );
+    }
+    assert!( ( *pressure.last().unwrap() - ( 101325.0 + 3.0e5 ) ).abs() < 1.0e-6 );
+    assert_eq!( *valve.open_percent.last().unwrap(), 0.9 );
+}
+
+#[test]
+fn steady_start() {
+    // The steady values are taken from the series at t = 0
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let mut network = transient();
+    *network.mut_nodes()[0].pressure() = vec![ 101325.0 ];
+    *network.mut_edges()[1].series().unwrap() = Some( TimeSeries::linear( vec![ ( 0.0, 0.8 ) ] ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    assert!( ( network.mut_nodes()[0].pressure()[0] - ( 101325.0 + 2.0e5 ) ).abs() < 1.0e-6 );
+    let Edge::Valve( valve ) = network.edges()[1].clone() else { panic!( "Expected a valve" ) };
+    assert_eq!( valve.open_percent[0], 0.8 );
+}
+
+#[test]
+fn extended_period_demand() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.solver_type() = SolverType::ExtendedPeriod;
+    *solver.dt() = 1800.0;
+    let rho_g = fluid.density() * solver.gravity();
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new_with_value( 0, 101325.0 + rho_g * 5.0 ) ) );
+    network.add_node( Node::Tank( Tank::new_with_values( 1, 101325.0, fluid.density(), solver.gravity(), 10.0, 2.0, 0.5, 4.0 ) ) );
+    let mut demand = Flow::new_elevation( 2, -20.0 );
+    demand.series = Some( TimeSeries::linear( vec![ ( 0.0, -2.0 ), ( 3600.0, -6.0 ) ] ).periodic( 7200.0 ) );
+    network.add_node( Node::Flow( demand ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 0, 1, 1000.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( 1, 2, 500.0, 0.15, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    for _step in 0..8 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    let consumption = network.mut_nodes()[2].consumption().clone();
+    assert_eq!( consumption, vec![ -2.0, -4.0, -6.0, -4.0, -2.0, -4.0, -6.0, -4.0, -2.0 ] );
+    let outflow = network.mut_edges()[1].mass_flow().clone();
+    for step in 0..consumption.len() {
+        assert!( ( outflow[step] + consumption[step] ).abs() < 1.0e-6 );
+    }
+}
+
+#[test]
+fn project_round_trip() {
+    let project = Project::new( &Fluid::default(), &Solver::default(), &transient() );
+    let text = project.to_json();
+    assert!( text.contains( "\"interpolation\": \"Step\"" ) );
+    let loaded = Project::from_json( &text ).unwrap();
+    assert_eq!( loaded, project );
+    let mut graph = loaded.graph().unwrap();
+    assert_eq!( graph.mut_nodes()[0].series().unwrap(), &transient().mut_nodes()[0].series().unwrap().clone() );
+    assert_eq!( graph.mut_edges()[1].series().unwrap(), &transient().mut_edges()[1].series().unwrap().clone() );
+    // The schema describes the series
+    let schema = Project::schema().to_string();
+    assert!( schema.contains( "\"enum\":[\"Linear\",\"Step\"]" ) );
+}
@@ -0,0 +1,92 @@
+use eki::sparse::{ SparseMatrix, SparseLU, SparseError };
+use ohsl::{ Mat64, Vec64 };
+
+fn example_triplets() -> Vec<(usize, usize, f64)> {
+    // Zero diagonal in the first row so that pivoting is required
+    vec![
+        ( 0, 1, 2.0 ), ( 0, 3, -1.0 ),
+        ( 1, 0, 4.0 ), ( 1, 1, 1.0 ), ( 1, 2, 1.0 ),
+        ( 2, 2, 3.0 ), ( 2, 3, 2.0 ),
+        ( 3, 0, 1.0 ), ( 3, 3, 5.0 ),
+    ]
+}
+
+fn dense( triplets: &[(usize, usize, f64)], size: usize ) -> Mat64 {
+    let mut mat = Mat64::new( size, size, 0.0 );
+    for &( row, col, value ) in triplets {
+        mat[row][col] += value;
+    }
+    mat
+}
+
+#[test]
+fn from_triplets() {
+    let mut triplets = example_triplets();
+    triplets.push( ( 2, 2, 1.0 ) ); // Duplicate entries are summed
+    let mat = SparseMatrix::from_triplets( 4, 4, &triplets );
+    assert_eq!( mat.rows(), 4 );
+    assert_eq!( mat.cols(), 4 );
+    assert_eq!( mat.nnz(), 9 );
+    assert_eq!( mat.get( 2, 2 ), 4.0 );
+    assert_eq!( mat.get( 0, 0 ), 0.0 );
+    let transpose = mat.transpose();
+    assert_eq!( transpose.get( 3, 0 ), -1.0 );
+}
+
+#[test]
+fn multiply() {
+    let triplets = example_triplets();
+    let mat = SparseMatrix::from_triplets( 4, 4, &triplets );
+    let x = Vec64::create( vec![ 1.0, 2.0, 3.0, 4.0 ] );
+    let sparse = mat.multiply( &x );
+    let dense = dense( &triplets, 4 ) * x;
+    for i in 0..4 {
+        assert!( ( sparse[i] - dense[i] ).abs() < 1.0e-12 );
+    }
+}
+
+#[test]
+fn solve_matches_dense() {
+    let triplets = example_triplets();
+    let mat = SparseMatrix::from_triplets( 4, 4, &triplets );
+    let b = Vec64::create( vec![ 1.0, -2.0, 0.5, 3.0 ] );
+    let mut lu = SparseLU::new();
+    assert!( lu.factorise( &mat ).is_ok() );
+    let x = lu.solve( &b );
+    let expected = dense( &triplets, 4 ).solve_basic( b );
+    for i in 0..4 {
+        assert!( ( x[i] - expected[i] ).abs() < 1.0e-12 );
+    }
+}
+
+#[test]
+fn analysis_reused() {
+    let triplets = example_triplets();
+    let mut lu = SparseLU::new();
+    assert!( lu.factorise( &SparseMatrix::from_triplets( 4, 4, &triplets ) ).is_ok() );
+    assert_eq!( lu.analyses(), 1 );
+    // Same pattern with new values is refactorised without a new analysis
+    let scaled: Vec<(usize, usize, f64)> = triplets.iter()
+        .map( |&( row, col, value )| ( row, col, value * ( 1.0 + row as f64 ) ) ).collect();
+    assert!( lu.factorise( &SparseMatrix::from_triplets( 4, 4, &scaled ) ).is_ok() );
+    assert_eq!( lu.analyses(), 1 );
+    let b = Vec64::create( vec![ 1.0, 1.0, 1.0, 1.0 ] );
+    let x = lu.solve( &b );
+    let expected = dense( &scaled, 4 ).solve_basic( b );
+    for i in 0..4 {
+        assert!( ( x[i] - expected[i] ).abs() < 1.0e-12 );
+    }
+    // A new pattern requires a new analysis
+    let mut extra = triplets.clone();
+    extra.push( ( 0, 0, 1.0 ) );
+    assert!( lu.factorise( &SparseMatrix::from_triplets( 4, 4, &extra ) ).is_ok() );
+    assert_eq!( lu.analyses(), 2 );
+}
+
+#[test]
+fn singular() {
+    let triplets = vec![ ( 0, 0, 1.0 ), ( 1, 0, 1.0 ), ( 0, 1, 0.0 ) ];
+    let mut lu = SparseLU::new();
+    let result = lu.factorise( &SparseMatrix::from_triplets( 2, 2, &triplets ) );
+    assert_eq!( result, Err( SparseError::Singular( 1 ) ) );
+}
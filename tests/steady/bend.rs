@@ -1,7 +1,9 @@
+use std::f64::consts::PI;
 use eki::node::Node;
 use eki::nodes::{ pressure::Pressure, connection::Connection };
 use eki::edge::Edge;
 use eki::edges::{ bend::Bend, pipe::Pipe };
+use eki::friction::FrictionModel;
 use eki::graph::Graph;
 use eki::fluid::Fluid;
 use eki::solver::Solver;
@@ -21,7 +23,7 @@ fn create_bend() {
     let thickness = 5.0e-3;              // 5mm pipe
     let youngs_modulus = 2.0e11;         // Steel pipe
     let bend = Bend::new_params( 
-        node_from, node_to, radius, diameter, angle, roughness, thickness, youngs_modulus 
+        node_from.id(), node_to.id(), radius, diameter, angle, roughness, thickness, youngs_modulus 
     );
     assert_eq!( bend.radius, radius );
     assert_eq!( bend.length(), radius * angle );
@@ -41,19 +43,19 @@ fn pipe_bend_pipe() {
     let node_to = Node::Pressure( Pressure::new( 4 ) );
     graph.add_node( node_to.clone() );
 
-    let edge = Edge::Pipe( Pipe::new( node_from, node_c1.clone() ) );
+    let edge = Edge::Pipe( Pipe::new( node_from.id(), node_c1.id() ) );
     graph.add_edge( edge );
 
-    let edge = Edge::Bend( Bend::new( node_c1, node_c2.clone() ) );
+    let edge = Edge::Bend( Bend::new( node_c1.id(), node_c2.id() ) );
     graph.add_edge( edge );
 
-    let edge = Edge::Pipe( Pipe::new( node_c2, node_to ) );
+    let edge = Edge::Pipe( Pipe::new( node_c2.id(), node_to.id() ) );
     graph.add_edge( edge );
 
     let fluid = Fluid::new_basic( 999.7, 1.3063e-6, 2.15e9 ); // Water @ 10 degrees C
     let mut solver = Solver::default();
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let mass_flow = *graph.edges()[1].steady_mass_flow();
     //assert_eq!( mass_flow, 4.59 );
@@ -77,7 +79,7 @@ fn bend_resistance() {
     let thickness = 5.0e-3;              // 5mm pipe
     let youngs_modulus = 2.0e11;         // Steel pipe
     let bend = Bend::new_params( 
-        node_from, node_to, radius, diameter, angle, roughness, thickness, youngs_modulus 
+        node_from.id(), node_to.id(), radius, diameter, angle, roughness, thickness, youngs_modulus 
     );
     assert_eq!( bend.radius, radius );
     assert_eq!( bend.length(), radius * angle );
@@ -90,19 +92,19 @@ fn bend_resistance() {
     let dh = 0.5;
     let nu = 0.001/1000.;
     let g = 9.81;
-    assert_eq!(bend.resistance(q, dh, nu, g), 0.0);
+    assert_eq!(bend.resistance(q, dh, nu, g, FrictionModel::Default), 0.0);
     
 
     // with flow resistance
     let q = 0.1;
-    let f = bend.friction_factor(q, nu);
+    let f = bend.friction_factor(q, nu, FrictionModel::Default);
     let rd = radius / diameter;
     let s = ( 0.5 * bend.angle ).sin();
-    let pow = rd.powf( 4. * bend.angle / 3.1415 );    
-    assert_eq!(bend.k(q, nu), f * bend.angle * rd + ( 0.1 + 2.4 * f ) * s + ( 6.6 * f * ( s.sqrt() + s ) / pow ));
+    let pow = rd.powf( 4. * bend.angle / PI );    
+    assert_eq!(bend.k(q, nu, FrictionModel::Default), f * bend.angle * rd + ( 0.1 + 2.4 * f ) * s + ( 6.6 * f * ( s.sqrt() + s ) / pow ));
 
     let k = f * bend.angle * rd + ( 0.1 + 2.4 * f ) * s + ( 6.6 * f * ( s.sqrt() + s ) / pow );
-    assert_eq!(bend.resistance(q, dh, nu, g), - ( k * q * q.abs() / ( 2. * bend.area() ) ) + g * bend.area() * dh);
+    assert_eq!(bend.resistance(q, dh, nu, g, FrictionModel::Default), - ( k * q * q.abs() / ( 2. * bend.area() ) ) + g * bend.area() * dh);
 
 
 }
@@ -19,7 +19,7 @@ fn steady_bursting_disk() {
     let node_to = Node::Pressure( Pressure::new( 1 ) );
     graph.add_node( node_to.clone() );
     let burst_dp = 1.0e4; 
-    let mut bursting_disk = Edge::BurstingDisk( BurstingDisk::new( node_from, node_to, burst_dp ) );
+    let mut bursting_disk = Edge::BurstingDisk( BurstingDisk::new( node_from.id(), node_to.id(), burst_dp ) );
     *bursting_disk.invk_values().unwrap() = vec![ 
         (0.0, 0.0),
         (1.0, 1. / 0.25),
@@ -30,7 +30,7 @@ fn steady_bursting_disk() {
     
     
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     // Flow should be zero because the bursting disk is closed initially.
     let mass_flow = (*graph.edges()[0].mass_flow())[0];
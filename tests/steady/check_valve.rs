@@ -18,7 +18,7 @@ fn steady_check_valve() {
     graph.add_node( node_from.clone() );
     let node_to = Node::Pressure( Pressure::new( 1 ) );
     graph.add_node( node_to.clone() );
-    let mut check_valve = Edge::CheckValve( CheckValve::new( node_from, node_to ) );
+    let mut check_valve = Edge::CheckValve( CheckValve::new( node_from.id(), node_to.id() ) );
     *check_valve.invk_values().unwrap() = vec![ 
         (0.0, 0.0),
         (1.0, 1. / 0.25),
@@ -29,7 +29,7 @@ fn steady_check_valve() {
     
     
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     // Flow should be positive because the check valve is open initially.
     let mass_flow = (*graph.edges()[0].mass_flow())[0];
@@ -0,0 +1,125 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, flow::Flow, connection::Connection };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, control_valve::{ ControlValve, ControlMode, ControlStatus } };
+use eki::graph::{ Graph, Diagnostic };
+use eki::solver::{ Solver, SolverError };
+
+const ATMOSPHERIC: f64 = 101325.0;
+
+// Reservoir at 6 bar gauge - pipe - control valve - pipe - downstream node
+fn zone( mode: ControlMode, setpoint: f64, downstream: Node ) -> Graph {
+    let mut graph = Graph::new();
+    graph.add_node( Node::Pressure( Pressure::new_with_value( 0, ATMOSPHERIC + 6.0e5 ) ) );
+    graph.add_node( Node::Connection( Connection::new( 1 ) ) );
+    graph.add_node( Node::Connection( Connection::new( 2 ) ) );
+    graph.add_node( downstream );
+    graph.add_edge( Edge::Pipe( Pipe::new_params( 0, 1, 500.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    let mut valve = ControlValve::new( 1, 2, mode, setpoint );
+    valve.diameter = 0.1;
+    graph.add_edge( Edge::ControlValve( valve ) );
+    graph.add_edge( Edge::Pipe( Pipe::new_params( 2, 3, 500.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    graph
+}
+
+fn state( graph: &Graph ) -> ( f64, ControlStatus ) {
+    let Edge::ControlValve( valve ) = &graph.edges[1] else { panic!( "Expected a control valve" ) };
+    ( valve.open_percent[0], valve.status )
+}
+
+#[test]
+fn pressure_reducing() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let setpoint = ATMOSPHERIC + 3.0e5;
+    let demand = || Node::Flow( Flow::new_with_value( 3, -10.0 ) );
+    let mut graph = zone( ControlMode::PressureReducing, setpoint, demand() );
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    let ( open, status ) = state( &graph );
+    assert_eq!( status, ControlStatus::Active );
+    assert!( open > 0.0 && open < 1.0 );
+    assert!( ( graph.nodes[2].pressure_at( 0 ) - setpoint ).abs() < 1.0e-3 );
+    assert!( ( *graph.edges[1].clone().steady_mass_flow() - 10.0 ).abs() < 1.0e-6 );
+
+    // A setting above the pressure upstream leaves the valve fully open
+    let mut graph = zone( ControlMode::PressureReducing, ATMOSPHERIC + 7.0e5, demand() );
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    assert_eq!( state( &graph ), ( 1.0, ControlStatus::Open ) );
+    assert!( graph.nodes[2].pressure_at( 0 ) < ATMOSPHERIC + 6.0e5 );
+
+    // A reservoir downstream above the setting shuts the valve
+    let reservoir = Node::Pressure( Pressure::new_with_value( 3, ATMOSPHERIC + 4.0e5 ) );
+    let mut graph = zone( ControlMode::PressureReducing, setpoint, reservoir );
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    assert_eq!( state( &graph ), ( 0.0, ControlStatus::Closed ) );
+    assert!( graph.edges[1].clone().steady_mass_flow().abs() < 1.0e-4 );
+    assert!( ( graph.nodes[2].pressure_at( 0 ) - ATMOSPHERIC - 4.0e5 ).abs() < 1.0 );
+}
+
+#[test]
+fn pressure_sustaining() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let setpoint = ATMOSPHERIC + 5.0e5;
+    let outlet = || Node::Pressure( Pressure::new( 3 ) );
+    let mut graph = zone( ControlMode::PressureSustaining, setpoint, outlet() );
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    let ( open, status ) = state( &graph );
+    assert_eq!( status, ControlStatus::Active );
+    assert!( open > 0.0 && open < 1.0 );
+    assert!( ( graph.nodes[1].pressure_at( 0 ) - setpoint ).abs() < 1.0e-3 );
+
+    // The valve shuts when the upstream pressure cannot reach the setting
+    let mut graph = zone( ControlMode::PressureSustaining, ATMOSPHERIC + 7.0e5, outlet() );
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    assert_eq!( state( &graph ), ( 0.0, ControlStatus::Closed ) );
+    assert!( ( graph.nodes[1].pressure_at( 0 ) - ATMOSPHERIC - 6.0e5 ).abs() < 1.0 );
+}
+
+#[test]
+fn flow_control() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let outlet = || Node::Pressure( Pressure::new( 3 ) );
+    let mut graph = zone( ControlMode::FlowControl, 12.0, outlet() );
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    let ( open, status ) = state( &graph );
+    assert_eq!( status, ControlStatus::Active );
+    assert!( open > 0.0 && open < 1.0 );
+    assert!( ( *graph.edges[1].clone().steady_mass_flow() - 12.0 ).abs() < 1.0e-6 );
+
+    // More flow than the pipes can carry leaves the valve fully open
+    let mut graph = zone( ControlMode::FlowControl, 200.0, outlet() );
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    assert_eq!( state( &graph ), ( 1.0, ControlStatus::Open ) );
+    assert!( *graph.edges[1].clone().steady_mass_flow() < 200.0 );
+}
+
+#[test]
+fn invalid_setpoint() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let outlet = Node::Pressure( Pressure::new( 3 ) );
+    let mut graph = zone( ControlMode::FlowControl, 0.0, outlet.clone() );
+    // A pressure reducing valve cannot set the pressure of a reservoir
+    graph.add_node( Node::Pressure( Pressure::new( 4 ) ) );
+    graph.add_edge( Edge::ControlValve( ControlValve::new( 3, 4, ControlMode::PressureReducing, ATMOSPHERIC ) ) );
+    let diagnostics = graph.validate();
+    assert_eq!( diagnostics, vec![ Diagnostic::InvalidSetpoint { edge: 1 }, Diagnostic::InvalidSetpoint { edge: 3 } ] );
+    assert_eq!( diagnostics[0].to_string(), "Edge 1 has a setpoint it cannot control" );
+    assert!( matches!( solver.solve_steady( &mut graph, &fluid, true ), Err( SolverError::InvalidNetwork { .. } ) ) );
+}
+
+#[test]
+fn short_table() {
+    let outlet = Node::Pressure( Pressure::new( 3 ) );
+    let mut graph = zone( ControlMode::FlowControl, 5.0, outlet );
+    assert!( graph.validate().is_empty() );
+    // The opening cannot be found from an empty or single point table
+    for invk in [ vec![], vec![ ( 1.0, 1.0 ) ] ] {
+        let Edge::ControlValve( valve ) = &mut graph.edges[1] else { panic!( "Expected a control valve" ) };
+        valve.invk = invk;
+        assert_eq!( graph.validate(), vec![ Diagnostic::NonMonotoneValveTable { edge: 1 } ] );
+    }
+}
@@ -0,0 +1,113 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::pressure::Pressure;
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, bend::Bend };
+use eki::friction::FrictionModel;
+use eki::graph::Graph;
+use eki::solver::Solver;
+use eki::utility;
+
+const MODELS: [FrictionModel; 7] = [
+    FrictionModel::PraksBrkic,
+    FrictionModel::ColebrookWhite,
+    FrictionModel::SwameeJain,
+    FrictionModel::Haaland,
+    FrictionModel::HazenWilliams { c: 130.0 },
+    FrictionModel::Manning { n: 0.011 },
+    FrictionModel::Fixed { f: 0.02 },
+];
+
+#[test]
+fn colebrook_white() {
+    let ( relative, reynolds ) = ( 1.0e-4, 1.0e5 );
+    let ( f, _ ) = FrictionModel::ColebrookWhite.darcy( relative, reynolds );
+    // Satisfies the Colebrook-White equation
+    let residual = 1.0 / f.sqrt() + 2.0 * ( relative / 3.7 + 2.51 / ( reynolds * f.sqrt() ) ).log10();
+    assert!( residual.abs() < 1.0e-10 );
+    assert!( ( f - 0.018516 ).abs() < 1.0e-5 );
+    // The explicit approximations are close to it
+    for model in [ FrictionModel::PraksBrkic, FrictionModel::SwameeJain, FrictionModel::Haaland ] {
+        assert!( utility::relative_error( f, model.darcy( relative, reynolds ).0 ) < 0.02 );
+    }
+    // A model that follows the solver takes its default
+    assert_eq!( FrictionModel::Default.or( FrictionModel::Default ), FrictionModel::PraksBrkic );
+    assert_eq!( FrictionModel::Default.or( FrictionModel::Haaland ), FrictionModel::Haaland );
+    assert_eq!( FrictionModel::SwameeJain.or( FrictionModel::Haaland ), FrictionModel::SwameeJain );
+    assert_eq!( FrictionModel::ColebrookWhite.text(), "Colebrook-White" );
+}
+
+#[test]
+fn derivatives() {
+    let ( diameter, roughness, nu ) = ( 0.1, 0.05e-3, 1.0e-6 );
+    // Laminar, transitional and turbulent flow rates
+    for q in [ 1.0e-4, 2.0e-4, 1.0e-2 ] {
+        for model in MODELS {
+            let ( f, df ) = model.friction( q, diameter, roughness, nu );
+            let delta = 1.0e-6 * q;
+            let f_plus = model.friction_factor( q + delta, diameter, roughness, nu );
+            let f_minus = model.friction_factor( q - delta, diameter, roughness, nu );
+            let numerical = ( f_plus - f_minus ) / ( 2.0 * delta );
+            assert!( f > 0.0 );
+            assert!( ( df - numerical ).abs() <= 1.0e-5 * df.abs().max( f / q ), "{:?} at q = {}", model, q );
+        }
+    }
+    // Hazen-Williams with C = 130 in a 100 mm pipe at 10 l/s ( 0.0190 m/m )
+    let f = FrictionModel::HazenWilliams { c: 130.0 }.friction_factor( 1.0e-2, diameter, roughness, nu );
+    let velocity = 1.0e-2 / ( 0.25 * std::f64::consts::PI * diameter * diameter );
+    let gradient = f * velocity * velocity / ( 2.0 * 9.80665 * diameter );
+    assert!( ( gradient - 0.0190 ).abs() < 1.0e-4 );
+}
+
+#[test]
+fn resistance_derivatives() {
+    let ( nu, g, dh ) = ( 1.0e-6, 9.81, 0.5 );
+    let pipe = Pipe::new( 0, 1 );
+    let bend = Bend::new( 0, 1 );
+    for q in [ -1.0e-2, -1.0e-6, 2.0e-5, 3.0e-3 ] {
+        for model in MODELS {
+            for edge in [ Edge::Pipe( pipe.clone() ), Edge::Bend( bend.clone() ) ] {
+                let drdq = edge.drdq( q, dh, nu, g, 0, model );
+                let delta = 1.0e-4 * q.abs();
+                let r_plus = edge.resistance( q + delta, dh, nu, g, 0, model );
+                let r_minus = edge.resistance( q - delta, dh, nu, g, 0, model );
+                let numerical = ( r_plus - r_minus ) / ( 2.0 * delta );
+                assert!( utility::relative_error( numerical, drdq ) < 1.0e-4, "{:?} at q = {}", model, q );
+                let r = edge.resistance( q, dh + 1.0, nu, g, 0, model ) - edge.resistance( q, dh, nu, g, 0, model );
+                assert!( ( edge.drdkh( q, dh, nu, g, 0, model ) - r ).abs() < 1.0e-12 );
+            }
+        }
+    }
+}
+
+#[test]
+fn solver_default() {
+    let mut graph = Graph::new();
+    graph.add_node( Node::Pressure( Pressure::new_with_value( 0, 121325.0 ) ) );
+    graph.add_node( Node::Pressure( Pressure::new( 1 ) ) );
+    graph.add_node( Node::Pressure( Pressure::new( 2 ) ) );
+    graph.add_edge( Edge::Pipe( Pipe::new( 0, 1 ) ) );
+    let mut pipe = Pipe::new( 0, 2 );
+    pipe.friction = FrictionModel::Fixed { f: 0.03 };
+    graph.add_edge( Edge::Pipe( pipe ) );
+    let fluid = Fluid::new_basic( 997.0, 1.1375e-6, 2.15e9 );
+    let mut solver = Solver::default();
+    *solver.friction() = FrictionModel::Fixed { f: 0.02 };
+    let result = solver.solve_steady( &mut graph, &fluid, true );
+    assert!( result.is_ok() );
+    // Darcy-Weisbach with a fixed friction factor for a head loss of 20000 Pa
+    let pipe = Pipe::new( 0, 1 );
+    let g = *solver.g();
+    let head = 20000.0 / ( 997.0 * g );
+    let expected = | f: f64 | 997.0 * pipe.area() * ( 2.0 * g * pipe.diameter * head / ( f * pipe.length ) ).sqrt();
+    let mass_flow = *graph.edges()[0].steady_mass_flow();
+    assert!( utility::relative_error( expected( 0.02 ), mass_flow ) < 1.0e-6 );
+    let mass_flow = *graph.edges()[1].steady_mass_flow();
+    assert!( utility::relative_error( expected( 0.03 ), mass_flow ) < 1.0e-6 );
+    // The same solution with the iterated Colebrook-White equation
+    *solver.friction() = FrictionModel::ColebrookWhite;
+    let result = solver.solve_steady( &mut graph, &fluid, true );
+    assert!( result.is_ok() );
+    let mass_flow = *graph.edges()[0].steady_mass_flow();
+    assert!( utility::relative_error( 6.7865862, mass_flow ) < 0.01 );
+}
@@ -25,15 +25,15 @@ fn generic_component_test() {
     let coefficients = ( 1.0, 2.0, 3.0 );
     let exponents = ( 1.5, 2.7 );
     let generic = Edge::Generic( Generic::new_params( 
-        node_from, 
-        node_to,
+        node_from.id(), 
+        node_to.id(),
         coefficients,
         exponents,
     ) );
     graph.add_edge( generic );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
     if let Ok(iter) = result {
         assert_eq!( iter, 9 ); // Takes many iterations to converge (need a better laminar guess)
     }
@@ -18,14 +18,14 @@ fn steady_open_pipe() {
     graph.add_node( node_from.clone() );
     let node_to = Node::Pressure( Pressure::new( 1 ) );
     graph.add_node( node_to.clone() );
-    let mut open_pipe = Edge::OpenPipe( OpenPipe::new( node_from, node_to ) );
+    let mut open_pipe = Edge::OpenPipe( OpenPipe::new( node_from.id(), node_to.id() ) );
     *open_pipe.k().unwrap() = 1.5;                   // K = 1.5
     *open_pipe.diameter().unwrap() = 50.0e-3;        // D = 50mm
     graph.add_edge( open_pipe );
     
     
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
     
     let h_from = graph.nodes()[0].steady_head( solver.gravity(), fluid.density() );
     assert_eq!( h_from, 20.0 );
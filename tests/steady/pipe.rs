@@ -29,15 +29,15 @@ fn two_pipes() {
 
     let (t, y) = (5.0e-3, 2.0e11);
     let (l, d, r) = (100.0, 50.0e-3, 0.0);
-    let pipe1 = Edge::Pipe( Pipe::new_params( node1.clone(), node2.clone(), l, d, r, t, y ) );
+    let pipe1 = Edge::Pipe( Pipe::new_params( node1.id(), node2.id(), l, d, r, t, y ) );
     let (l, d, r) = (200.0, 50.0e-3, 0.0);
-    let pipe2 = Edge::Pipe( Pipe::new_params( node2.clone(), node3.clone(), l, d, r, t, y ) );
+    let pipe2 = Edge::Pipe( Pipe::new_params( node2.id(), node3.id(), l, d, r, t, y ) );
 
     graph.add_edge( pipe1 );
     graph.add_edge( pipe2 );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let h0 = graph.nodes()[0].steady_head( solver.gravity(), fluid.density() );
     assert!( (h0 - 20.0).abs() < 1.0e-8 );
@@ -67,14 +67,14 @@ fn turbulent_water() {
     let (t, y) = (5.0e-3, 2.0e11);
     let roughness = 0.5e-4;
     let pipe = Edge::Pipe( 
-        Pipe::new_params( node_from.clone(), node_to.clone(), 
+        Pipe::new_params( node_from.id(), node_to.id(), 
             length, diameter, roughness, t, y ) 
     );
     graph.add_edge( pipe );
 
     let mut solver = Solver::default();
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let volume_flow = *graph.edges()[0].steady_mass_flow() / fluid.density();
     let flow_usgpm = volume_flow / 0.000063141414; 
@@ -121,14 +121,14 @@ fn turbulent_oil() {
     let (t, y) = (5.0e-3, 2.0e11);
     let roughness = 0.0018 * 0.0254; // 0.0018 in
     let pipe = Edge::Pipe( 
-        Pipe::new_params( node_from.clone(), node_to.clone(), 
+        Pipe::new_params( node_from.id(), node_to.id(), 
             length, diameter, roughness, t, y ) 
     );
     graph.add_edge( pipe );
 
     let mut solver = Solver::default();
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let volume_flow = *graph.edges()[0].steady_mass_flow() / fluid.density();
     let flow_usgpm = volume_flow / 0.000063141414; 
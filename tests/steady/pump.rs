@@ -10,7 +10,7 @@ use eki::solver::Solver;
 fn interpolate_head_data() {
     let from = Node::Pressure( Pressure::new_elevation( 0, 0.0 ) );
     let to = Node::Pressure( Pressure::new_elevation( 1, 20.0 ) );
-    let pump = Pump::new( from, to );
+    let pump = Pump::new( from.id(), to.id() );
     assert_eq!( pump.f_h( 0.0 ), -0.55 );
     let theta = (30.0_f64).to_radians();
     let h = pump.f_h( theta );
@@ -27,7 +27,7 @@ fn interpolate_head_data() {
 fn resistance() {
     let from = Node::Pressure( Pressure::new_elevation( 0, 0.0 ) );
     let to = Node::Pressure( Pressure::new_elevation( 1, 100.0 ) );
-    let mut pump = Pump::new( from, to );
+    let mut pump = Pump::new( from.id(), to.id() );
     let flow = 300.0 / ( 60.0 * 60.0 );         // 300 m^3/hour
     pump.speed[0] = 5650.0 / 3.0_f64.sqrt();    // 3262.03 rpm
     let dh = 0.0;
@@ -47,7 +47,7 @@ fn basic_steady_pump() {
     graph.add_node( node_to.clone() );
 
     let steady_speed: f64 = 5000.0;
-    let new_pump = Pump::new( node_from, node_to );
+    let new_pump = Pump::new( node_from.id(), node_to.id() );
     let mut pump = Edge::Pump( new_pump.clone() );
     if let Some(speed) = pump.speed() {
         speed[0] = steady_speed;
@@ -56,10 +56,10 @@ fn basic_steady_pump() {
 
     let mut solver = Solver::default();
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    if let Err(residual) = result {
-        println!( "residual = {}", residual );
+    if let Err(error) = &result {
+        println!( "error = {}", error );
     } 
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
     let volume_flow = *graph.edges()[0].steady_mass_flow() / fluid.density();
     
     let q = volume_flow / new_pump.q_rated;
@@ -110,16 +110,16 @@ fn pump_and_pipe() {
     ));
     graph.add_edge( pump );
 
-    let mut pipe = Edge::Pipe( Pipe::new( connection, node_to ) );
+    let mut pipe = Edge::Pipe( Pipe::new( connection.id(), node_to.id() ) );
     *pipe.length().unwrap() = 100.0;
     graph.add_edge( pipe );
 
     let mut solver = Solver::default();
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    if let Err(residual) = result {
-        println!( "residual = {}", residual );
+    if let Err(error) = &result {
+        println!( "error = {}", error );
     } 
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let volume_flow = *graph.edges()[0].steady_mass_flow() / fluid.density();
     assert_eq!( volume_flow, 0.005979870051971544 );            //TODO get value from FD
@@ -165,7 +165,7 @@ fn pump_and_valve() {
     let node_to = Node::Pressure( Pressure::new_elevation( 4, 10.0 ) );
     graph.add_node( node_to.clone() );
 
-    let mut pipe = Edge::Pipe( Pipe::new( node_from, connection1.clone() ) );
+    let mut pipe = Edge::Pipe( Pipe::new( node_from.id(), connection1.id() ) );
     *pipe.steady_mass_flow() = 0.002 * fluid.density();
     graph.add_edge( pipe );
 
@@ -192,7 +192,7 @@ fn pump_and_valve() {
     *pump.steady_mass_flow() = 0.002 * fluid.density();
     graph.add_edge( pump );
 
-    let mut valve = Edge::Valve( Valve::new( connection2, connection3.clone() ) );
+    let mut valve = Edge::Valve( Valve::new( connection2.id(), connection3.id() ) );
     *valve.k_values().unwrap() = vec![ 
         (0.000, 1.0e16),
         (5.000, 1200.0),
@@ -210,7 +210,7 @@ fn pump_and_valve() {
     *valve.steady_mass_flow() = 0.002 * fluid.density();
     graph.add_edge( valve );
 
-    let mut pipe = Edge::Pipe( Pipe::new( connection3, node_to ) );
+    let mut pipe = Edge::Pipe( Pipe::new( connection3.id(), node_to.id() ) );
     *pipe.length().unwrap() = 100.0;
     *pipe.steady_mass_flow() = 0.002 * fluid.density();
     graph.add_edge( pipe );
@@ -219,10 +219,10 @@ fn pump_and_valve() {
     *solver.max_iter() = 30;
     let result = solver.solve_steady( &mut graph, &fluid, true );
     assert_eq!( *graph.edges()[3].length().unwrap(), 100.0 );
-    if let Err(residual) = result {
-        println!( "residual = {}", residual );
+    if let Err(error) = &result {
+        println!( "error = {}", error );
     } 
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let mass_flow = *graph.edges()[0].steady_mass_flow();
     //assert_eq!( mass_flow, 6.825793 );
@@ -21,7 +21,7 @@ fn steady_relief_valve() {
     let dp_open = 1000.; 
     let dp_full = 5000.;
 
-    let mut relief_valve = Edge::ReliefValve( ReliefValve::new( node_from, node_to, dp_open, dp_full ) );
+    let mut relief_valve = Edge::ReliefValve( ReliefValve::new( node_from.id(), node_to.id(), dp_open, dp_full ) );
     *relief_valve.invk_values().unwrap() = vec![ 
         (0.0, 0.0),
         (1.0, 1. / 0.25),
@@ -35,7 +35,7 @@ fn steady_relief_valve() {
     graph.add_edge( relief_valve );
     
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     // Flow should be zero because the relief valve is closed initially.
     let mass_flow = (*graph.edges()[0].mass_flow())[0];
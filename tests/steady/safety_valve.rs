@@ -19,7 +19,7 @@ fn steady_safety_valve() {
     let node_to = Node::Pressure( Pressure::new( 1 ) );
     graph.add_node( node_to.clone() );
     let set_pressure = 111325.0; 
-    let mut safety_valve = Edge::SafetyValve( SafetyValve::new( node_from, node_to, set_pressure ) );
+    let mut safety_valve = Edge::SafetyValve( SafetyValve::new( node_from.id(), node_to.id(), set_pressure ) );
     *safety_valve.invk_values().unwrap() = vec![ 
         (0.0, 0.0),
         (1.0, 1. / 0.25),
@@ -30,7 +30,7 @@ fn steady_safety_valve() {
     
     
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     // Flow should be zero because the safety valve is closed initially.
     let mass_flow = (*graph.edges()[0].mass_flow())[0];
@@ -25,12 +25,12 @@ fn contraction() {
 
     let beta = 0.5; // < 1 = Contraction
     let size_change = Edge::SizeChange( SizeChange::new_params( 
-        node_from.clone(), node_to.clone(), 100.0e-3, beta
+        node_from.id(), node_to.id(), 100.0e-3, beta
     ) );
     graph.add_edge( size_change );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let q = *graph.edges()[0].steady_mass_flow() / fluid.density();
     assert!( (q - 0.2298781702).abs() < 1.0e-8 );
@@ -50,12 +50,12 @@ fn expansion() {
 
     let beta = 2.0; // > 1 = Expansion
     let size_change = Edge::SizeChange( SizeChange::new_params( 
-        node_from.clone(), node_to.clone(), 100.0e-3, beta
+        node_from.id(), node_to.id(), 100.0e-3, beta
     ) );
     graph.add_edge( size_change );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let q = *graph.edges()[0].steady_mass_flow() / fluid.density();
     assert!( (q - 0.2074048708).abs() < 1.0e-8 );
@@ -75,12 +75,12 @@ fn contraction_reverse_flow() {
 
     let beta = 0.5; // < 1 = Contraction
     let size_change = Edge::SizeChange( SizeChange::new_params( 
-        node_from.clone(), node_to.clone(), 100.0e-3, beta
+        node_from.id(), node_to.id(), 100.0e-3, beta
     ) );
     graph.add_edge( size_change );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let q = *graph.edges()[0].steady_mass_flow() / fluid.density();
     assert!( (q + 0.05185121771).abs() < 1.0e-8 );
@@ -100,12 +100,12 @@ fn expansion_reverse_flow() {
 
     let beta = 2.0; // > 1 = Expansion
     let size_change = Edge::SizeChange( SizeChange::new_params( 
-        node_from.clone(), node_to.clone(), 100.0e-3, beta
+        node_from.id(), node_to.id(), 100.0e-3, beta
     ) );
     graph.add_edge( size_change );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let q = *graph.edges()[0].steady_mass_flow() / fluid.density();
     assert!( (q + 0.9195126808).abs() < 1.0e-8 );
@@ -1,10 +1,10 @@
 use eki::fluid::Fluid;
 use eki::node::Node;
-use eki::nodes::{ pressure::Pressure };
+use eki::nodes::{ pressure::Pressure, flow::Flow, connection::Connection };
 use eki::edge::Edge;
-use eki::edges::{ pipe::Pipe };
-use eki::graph::Graph;
-use eki::solver::{Solver, SolverType};
+use eki::edges::{ pipe::Pipe, generic::Generic };
+use eki::graph::{ Graph, Diagnostic };
+use eki::solver::{Solver, SolverType, SolverError, Component};
 
 mod three_reservoirs;
 mod pipe;
@@ -19,6 +19,8 @@ mod relief_valve;
 mod bursting_disk;
 mod generic;
 mod open_pipe;
+mod control_valve;
+mod friction;
 
 #[test]
 fn default() {
@@ -29,8 +31,8 @@ fn default() {
     assert_eq!( *solver.dt(), 0.1 );
     assert_eq!( *solver.g(), 9.80665 );
     let ( steady, transient ) = solver.solved();
-    assert_eq!( steady, false ); 
-    assert_eq!( transient, false );
+    assert!( !steady ); 
+    assert!( !transient );
     assert_eq!( *solver.solver_type(), SolverType::Steady ); 
 }
 
@@ -66,12 +68,12 @@ fn single_pipe() {
     graph.add_node( node_from.clone() );
     let node_to = Node::Pressure( Pressure::new( 1 ) );
     graph.add_node( node_to.clone() );
-    let edge = Edge::Pipe( Pipe::new( node_from, node_to ) );
+    let edge = Edge::Pipe( Pipe::new( node_from.id(), node_to.id() ) );
     graph.add_edge( edge );
     let fluid = Fluid::new_basic( 997.0, 1.1375e-6, 2.15e9 );
     let mut solver = Solver::default();
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
     if let Ok(iter) = result {
         assert_eq!( iter, 5 );
     }
@@ -88,7 +90,7 @@ fn initial_guess() {
     let node_to = Node::Pressure( Pressure::new( 1 ) );
     graph.add_node( node_to.clone() );
     
-    let edge = Edge::Pipe( Pipe::new( node_from, node_to ) );
+    let edge = Edge::Pipe( Pipe::new( node_from.id(), node_to.id() ) );
     graph.add_edge( edge );
     let fluid = Fluid::new_basic( 997.0, 1.1375e-6, 2.15e9 );
     let mut solver = Solver::default();
@@ -105,7 +107,7 @@ fn initial_guess() {
     // Change steady pressure of from node
     *graph.mut_nodes()[0].steady_pressure() = 122325.0;
     let result = solver.solve_steady( &mut graph, &fluid, create_guess );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
     if let Ok(iter) = result {
         assert!( iter < iterations );
     }
@@ -114,10 +116,111 @@ fn initial_guess() {
     // Change the length of the pipe
     *graph.mut_edges()[0].length().unwrap() = 11.0;
     let result = solver.solve_steady( &mut graph, &fluid, create_guess );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
     if let Ok(iter) = result {
         assert!( iter < iterations );
     }
     let mass_flow = *graph.edges()[0].steady_mass_flow();
     assert!( ( mass_flow - 6.6243271 ).abs() < 1.0e-6 );
-}
\ No newline at end of file
+}
+
+#[test]
+fn empty_network() {
+    let mut graph = Graph::new();
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let result = solver.solve_steady( &mut graph, &fluid, true );
+    assert_eq!( result, Err( SolverError::EmptyNetwork ) );
+    let result = solver.time_step( &mut graph, &fluid );
+    assert_eq!( result, Err( SolverError::EmptyNetwork ) );
+}
+
+#[test]
+fn steady_not_solved() {
+    let mut graph = Graph::new();
+    let node_from = Node::Pressure( Pressure::new_with_value( 0, 121325.0 ) );
+    graph.add_node( node_from.clone() );
+    let node_to = Node::Pressure( Pressure::new( 1 ) );
+    graph.add_node( node_to.clone() );
+    graph.add_edge( Edge::Pipe( Pipe::new( node_from.id(), node_to.id() ) ) );
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let result = solver.time_step( &mut graph, &fluid );
+    assert_eq!( result, Err( SolverError::SteadyNotSolved ) );
+}
+
+#[test]
+fn unknown_node() {
+    let mut graph = Graph::new();
+    let node_from = Node::Pressure( Pressure::new_with_value( 0, 121325.0 ) );
+    graph.add_node( node_from.clone() );
+    let node_to = Node::Pressure( Pressure::new( 7 ) );
+    graph.add_edge( Edge::Pipe( Pipe::new( node_from.id(), node_to.id() ) ) );
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let result = solver.solve_steady( &mut graph, &fluid, true );
+    assert_eq!( result, Err( SolverError::UnknownNode { id: 7 } ) );
+    assert_eq!( result.unwrap_err().component(), Some( Component::Node( 7 ) ) );
+}
+
+#[test]
+fn not_converged() {
+    let mut graph = Graph::new();
+    let node_from = Node::Pressure( Pressure::new_with_value( 0, 121325.0 ) );
+    graph.add_node( node_from.clone() );
+    let node_to = Node::Pressure( Pressure::new( 1 ) );
+    graph.add_node( node_to.clone() );
+    graph.add_edge( Edge::Pipe( Pipe::new( node_from.id(), node_to.id() ) ) );
+    let fluid = Fluid::new_basic( 997.0, 1.1375e-6, 2.15e9 );
+    let mut solver = Solver::default();
+    *solver.max_iter() = 2;
+    let result = solver.solve_steady( &mut graph, &fluid, true );
+    match result {
+        Err( SolverError::NotConverged { iterations, residual, component } ) => {
+            assert_eq!( iterations, 2 );
+            assert!( residual > *solver.tolerance() );
+            assert_eq!( component, Some( Component::Edge( 0, 1 ) ) );
+        },
+        _ => panic!( "Expected NotConverged, got {:?}", result ),
+    }
+    assert_eq!( solver.solved(), ( false, false ) );
+}
+
+#[test]
+fn singular_matrix() {
+    // Generic edges with no flow dependence leave the flow rates undetermined
+    let mut graph = Graph::new();
+    let node_from = Node::Pressure( Pressure::new_with_value( 0, 121325.0 ) );
+    graph.add_node( node_from.clone() );
+    let node_mid = Node::Connection( Connection::new( 1 ) );
+    graph.add_node( node_mid.clone() );
+    let node_to = Node::Pressure( Pressure::new( 2 ) );
+    graph.add_node( node_to.clone() );
+    graph.add_edge( Edge::Generic( Generic::new( node_from.id(), node_mid.id() ) ) );
+    graph.add_edge( Edge::Generic( Generic::new( node_mid.id(), node_to.id() ) ) );
+    let fluid = Fluid::new_basic( 997.0, 1.1375e-6, 2.15e9 );
+    let mut solver = Solver::default();
+    let result = solver.solve_steady( &mut graph, &fluid, false );
+    match result {
+        Err( SolverError::SingularMatrix { iterations, .. } ) => assert_eq!( iterations, 0 ),
+        _ => panic!( "Expected SingularMatrix, got {:?}", result ),
+    }
+}
+
+#[test]
+fn invalid_network() {
+    // Flow boundaries only, so no head reference
+    let mut graph = Graph::new();
+    let node_from = Node::Flow( Flow::new_with_value( 0, 0.1 ) );
+    graph.add_node( node_from.clone() );
+    let node_to = Node::Flow( Flow::new_with_value( 1, -0.1 ) );
+    graph.add_node( node_to.clone() );
+    graph.add_edge( Edge::Pipe( Pipe::new( node_from.id(), node_to.id() ) ) );
+    let fluid = Fluid::new_basic( 997.0, 1.1375e-6, 2.15e9 );
+    let mut solver = Solver::default();
+    let result = solver.solve_steady( &mut graph, &fluid, false );
+    assert_eq!( result, Err( SolverError::InvalidNetwork { 
+        diagnostics: vec![ Diagnostic::NoHeadReference { nodes: vec![ 0, 1 ] } ] 
+    } ) );
+    assert_eq!( solver.solved(), ( false, false ) );
+}
@@ -29,15 +29,15 @@ fn pipe_tank_pipe() {
 
     let (t, y) = (5.0e-3, 2.0e11);
     let (l, d, r) = (100.0, 50.0e-3, 0.0);
-    let pipe1 = Edge::Pipe( Pipe::new_params( node1.clone(), node2.clone(), l, d, r, t, y ) );
+    let pipe1 = Edge::Pipe( Pipe::new_params( node1.id(), node2.id(), l, d, r, t, y ) );
     let (l, d, r) = (200.0, 50.0e-3, 0.0);
-    let pipe2 = Edge::Pipe( Pipe::new_params( node2.clone(), node3.clone(), l, d, r, t, y ) );
+    let pipe2 = Edge::Pipe( Pipe::new_params( node2.id(), node3.id(), l, d, r, t, y ) );
 
     graph.add_edge( pipe1 );
     graph.add_edge( pipe2 );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let rhog = fluid.density() * solver.gravity();
     let h0 = graph.nodes()[0].steady_head( solver.gravity(), fluid.density() );
@@ -84,11 +84,11 @@ fn tank_pipe_tank() {
     graph.add_node( node_from.clone() );
     graph.add_node( node_to.clone() );
 
-    let pipe = Edge::Pipe( Pipe::new( node_from.clone(), node_to.clone()) );
+    let pipe = Edge::Pipe( Pipe::new( node_from.id(), node_to.id()) );
     graph.add_edge( pipe );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let h0 = graph.nodes()[0].steady_head( solver.gravity(), fluid.density() );
     assert!( (h0 - (101325.0 / ( fluid.density() * solver.gravity() ) + 1.5 )  ).abs() < 1.0e-8 );
@@ -133,11 +133,11 @@ fn tank_pipe_tank_elevation() {
     graph.add_node( node_from.clone() );
     graph.add_node( node_to.clone() );
 
-    let pipe = Edge::Pipe( Pipe::new( node_from.clone(), node_to.clone()) );
+    let pipe = Edge::Pipe( Pipe::new( node_from.id(), node_to.id()) );
     graph.add_edge( pipe );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let h0 = graph.nodes()[0].steady_head( solver.gravity(), fluid.density() );
     assert!( (h0 - (101325.0 / ( fluid.density() * solver.gravity() ) + 1.5 )  ).abs() < 1.0e-8 );
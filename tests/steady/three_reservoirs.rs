@@ -30,12 +30,12 @@ fn solve() {
 
     let (t, y) = (5.0e-3, 2.0e11);
     let (l, d, r) = (1500.0, 250.0e-3, 0.5e-3);
-    let pipe1 = Edge::Pipe( Pipe::new_params( node1.clone(), node4.clone(), l, d, r, t, y ) );
+    let pipe1 = Edge::Pipe( Pipe::new_params( node1.id(), node4.id(), l, d, r, t, y ) );
     let (l, d, r) = (2000.0, 300.0e-3, 0.5e-3);
-    let pipe2 = Edge::Pipe( Pipe::new_params( node2.clone(), node4.clone(), l, d, r, t, y ) );
+    let pipe2 = Edge::Pipe( Pipe::new_params( node2.id(), node4.id(), l, d, r, t, y ) );
     let (l, d, r) = (3000.0, 250.0e-3, 0.5e-3);
-    let pipe3 = Edge::Pipe( Pipe::new_params( node3.clone(), node4.clone(), l, d, r, t, y ) );
-    let pipe4 = Edge::Pipe( Pipe::new( node4.clone(), node5.clone() ) );
+    let pipe3 = Edge::Pipe( Pipe::new_params( node3.id(), node4.id(), l, d, r, t, y ) );
+    let pipe4 = Edge::Pipe( Pipe::new( node4.id(), node5.id() ) );
 
     graph.add_edge( pipe1 );
     graph.add_edge( pipe2 );
@@ -44,7 +44,7 @@ fn solve() {
  
     let mut solver = Solver::default(); 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     // Flow rate from highest reservoir
     let volume_flow = *graph.edges()[1].steady_mass_flow() / fluid.density();
@@ -18,7 +18,7 @@ fn steady_valve() {
     graph.add_node( node_from.clone() );
     let node_to = Node::Pressure( Pressure::new( 1 ) );
     graph.add_node( node_to.clone() );
-    let mut valve = Edge::Valve( Valve::new( node_from, node_to ) );
+    let mut valve = Edge::Valve( Valve::new( node_from.id(), node_to.id() ) );
     *valve.invk_values().unwrap() = vec![ 
         (0.0, 0.0),
         (0.5, 1. / 7.0),
@@ -30,7 +30,7 @@ fn steady_valve() {
     
     
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
     
     let h_from = graph.nodes()[0].steady_head( solver.gravity(), fluid.density() );
     assert_eq!( h_from, 20.0 );
@@ -10,8 +10,26 @@ mod edge;
 #[path = "graph/graph.rs"]
 mod graph;
 
+#[path = "sparse/sparse.rs"]
+mod sparse;
+
 #[path = "steady/steady.rs"]
 mod steady;
 
 #[path = "transient/transient.rs"]
-mod transient;
\ No newline at end of file
+mod transient;
+
+#[path = "epanet/epanet.rs"]
+mod epanet;
+
+#[path = "project/project.rs"]
+mod project;
+
+#[path = "results/results.rs"]
+mod results;
+
+#[path = "series/series.rs"]
+mod series;
+
+#[path = "rules/rules.rs"]
+mod rules;
@@ -0,0 +1,58 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, connection::Connection, accumulator::Accumulator };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, valve::Valve };
+use eki::graph::Graph;
+use eki::solver::Solver;
+use eki::events::{ TransientEvent, Time, Value };
+use super::run;
+
+#[test]
+fn accumulator() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 60;
+    // Reservoir - pipe - junction - valve with a fast closure
+    let mut network = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 2.0e5 ) );
+    network.add_node( reservoir.clone() );
+    let junction = Node::Connection( Connection::new( 1 ) );
+    network.add_node( junction.clone() );
+    let outlet = Node::Pressure( Pressure::new_with_value( 2, 101325.0 + 1.5e5 ) );
+    network.add_node( outlet.clone() );
+    let pipe = Pipe::new_params( reservoir.id(), junction.id(), 500.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    let mut valve = Edge::Valve( Valve::new( junction.id(), outlet.id() ) );
+    *valve.diameter().unwrap() = 0.1;
+    valve.add_event( TransientEvent::InstantaneousChange( Value( 0.02 ), Time( 0.0 ) ) );
+    network.add_edge( valve );
+    // The same network protected by an air vessel at the junction
+    let mut network_vessel = network.clone();
+    network_vessel.mut_nodes()[1] = Node::Accumulator( Accumulator::new( 1 ) );
+
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 200 );
+    let unprotected = network.mut_nodes()[1].max_pressure();
+
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 60;
+    let mut network = network_vessel;
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    let steady = network.mut_nodes()[1].steady_head( solver.gravity(), fluid.density() );
+    run( &mut solver, &mut network, &fluid, 200 );
+    let Node::Accumulator( vessel ) = network.mut_nodes()[1].clone() else { panic!( "Expected an accumulator" ) };
+    // The vessel starts in equilibrium with the network 
+    let initial = vessel.liquid_volume[0];
+    assert!( initial > 0.0 );
+    assert!( ( vessel.vessel_head( initial, fluid.density(), solver.gravity() ) - steady ).abs() < 1.0e-6 );
+    // The vessel absorbs the flow that can no longer leave through the valve
+    assert_eq!( vessel.liquid_volume.len(), 201 );
+    let fullest = vessel.liquid_volume.iter().cloned().fold( f64::MIN, f64::max );
+    assert!( fullest > initial );
+    assert!( fullest <= vessel.z_max * vessel.area() );
+    let max_pressure = network.mut_nodes()[1].max_pressure();
+    assert!( max_pressure < 0.5 * unprotected );
+}
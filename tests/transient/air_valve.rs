@@ -0,0 +1,59 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, connection::Connection, air_valve::AirValve };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, valve::Valve };
+use eki::graph::Graph;
+use eki::solver::Solver;
+use eki::events::{ TransientEvent, Time, Value };
+use super::run;
+
+#[test]
+fn air_valve() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 60;
+    // Reservoir - valve - pipe - high point - pipe - outlet. Closing the valve lets the downstream
+    // column run on and pulls the high point below atmospheric pressure.
+    let mut network = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 3.0e5 ) );
+    network.add_node( reservoir.clone() );
+    let valve_node = Node::Connection( Connection::new( 1 ) );
+    network.add_node( valve_node.clone() );
+    let high = Node::Connection( Connection::new_elevation( 2, 10.0 ) );
+    network.add_node( high.clone() );
+    let outlet = Node::Pressure( Pressure::new( 3 ) );
+    network.add_node( outlet.clone() );
+    let mut valve = Edge::Valve( Valve::new( reservoir.id(), valve_node.id() ) );
+    *valve.diameter().unwrap() = 0.1;
+    valve.add_event( TransientEvent::InstantaneousChange( Value( 0.02 ), Time( 0.0 ) ) );
+    network.add_edge( valve );
+    let pipe = Pipe::new_params( valve_node.id(), high.id(), 300.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    let pipe = Pipe::new_params( high.id(), outlet.id(), 300.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    let mut network_air = network.clone();
+    network_air.mut_nodes()[2] = Node::AirValve( AirValve::new_elevation( 2, 10.0 ) );
+
+    // Without an air valve the high point drops to the vapour pressure
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 150 );
+    assert!( ( network.mut_nodes()[2].min_pressure() - fluid.vapour_pressure() ).abs() < 1.0e-3 );
+
+    // The air valve admits air and holds the high point close to atmospheric pressure
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 60;
+    let mut network = network_air;
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 150 );
+    assert!( network.mut_nodes()[2].min_pressure() > 0.9 * 101325.0 );
+    let volume = network.mut_nodes()[2].air_volume().unwrap().clone();
+    assert_eq!( volume.len(), 151 );
+    assert_eq!( volume[0], 0.0 );
+    assert!( *volume.last().unwrap() > 0.0 );
+    // The downstream column keeps draining so the air pocket grows
+    let first = volume.iter().position( |v| *v > 0.0 ).unwrap();
+    assert!( volume[first..].windows( 2 ).all( |v| v[1] >= v[0] ) );
+}
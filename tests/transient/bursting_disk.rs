@@ -36,7 +36,7 @@ fn transient_bursting_disk() {
     graph.add_node( node_to.clone() );
 
     let burst_dp = 1.0e4; 
-    let mut bursting_disk = Edge::BurstingDisk( BurstingDisk::new( node_from, node_to, burst_dp ) );
+    let mut bursting_disk = Edge::BurstingDisk( BurstingDisk::new( node_from.id(), node_to.id(), burst_dp ) );
     *bursting_disk.invk_values().unwrap() = vec![ 
         (0.0, 0.0),
         (1.0, 1. / 0.25),
@@ -46,7 +46,7 @@ fn transient_bursting_disk() {
     graph.add_edge( bursting_disk );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     // Flow should be zero because the bursting disk is closed initially.
     let mass_flow = (*graph.edges()[0].mass_flow())[0];
@@ -60,7 +60,7 @@ fn transient_bursting_disk() {
         let mass_flow = (*graph.edges()[0].mass_flow())[step];
         let q = mass_flow / fluid.density();
         println!( "step = {}, result = {:?}, q = {:.2e}", step, result, q );
-        assert!( result.is_ok() && !result.is_err() );
+        assert!( result.is_ok() );
     }
 
     // Check that the bursting disk opens when the pressure difference is above the set dp
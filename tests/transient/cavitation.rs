@@ -0,0 +1,116 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, connection::Connection };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, valve::Valve };
+use eki::graph::Graph;
+use eki::solver::{ Solver, SolverType };
+use eki::events::{ TransientEvent, Time, Value };
+use super::run;
+
+#[test]
+fn negative_pressure_without_cavitation() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.solver_type() = SolverType::Transient;
+    *solver.cavitation() = false;
+    *solver.split_pipes() = true;
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 60;
+    let mut network = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 2.0e5 ) );
+    network.add_node( reservoir.clone() );
+    let valve_node = Node::Connection( Connection::new( 1 ) );
+    network.add_node( valve_node.clone() );
+    let outlet = Node::Pressure( Pressure::new( 2 ) );
+    network.add_node( outlet.clone() );
+    let pipe = Pipe::new_params( reservoir.id(), valve_node.id(), 120.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    // A fast closure drops the valve pressure below vapour pressure
+    let mut valve = Edge::Valve( Valve::new( valve_node.id(), outlet.id() ) );
+    *valve.diameter().unwrap() = 0.1;
+    valve.add_event( TransientEvent::InstantaneousChange( Value( 0.02 ), Time( 0.0 ) ) );
+    network.add_edge( valve );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 60 );
+    assert!( network.nodes()[1].min_pressure() < 0.0 );
+    assert!( solver.cavity_volumes()[1].iter().all( |volume| *volume == 0.0 ) );
+}
+
+#[test]
+fn vapour_cavity() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.solver_type() = SolverType::Transient;
+    *solver.cavitation() = true;
+    *solver.split_pipes() = true;
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 60;
+    let mut network = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 2.0e5 ) );
+    network.add_node( reservoir.clone() );
+    let valve_node = Node::Connection( Connection::new( 1 ) );
+    network.add_node( valve_node.clone() );
+    let outlet = Node::Pressure( Pressure::new( 2 ) );
+    network.add_node( outlet.clone() );
+    let pipe = Pipe::new_params( reservoir.id(), valve_node.id(), 120.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    // A fast closure drops the valve pressure below vapour pressure
+    let mut valve = Edge::Valve( Valve::new( valve_node.id(), outlet.id() ) );
+    *valve.diameter().unwrap() = 0.1;
+    valve.add_event( TransientEvent::InstantaneousChange( Value( 0.02 ), Time( 0.0 ) ) );
+    network.add_edge( valve );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 60 );
+    let vapour = fluid.vapour_pressure();
+    assert!( network.nodes()[1].min_pressure() >= vapour - 1.0e-3 );
+    // Cavities also form at the hidden nodes along the pipe
+    let profile = solver.profiles()[0].as_ref().unwrap();
+    assert!( profile.points() > 2 );
+    let min_pressure = profile.min_pressure( fluid.density(), solver.gravity() );
+    assert!( min_pressure.iter().all( |p| *p >= vapour - 1.0e-3 ) );
+    assert!( min_pressure.iter().any( |p| ( p - vapour ).abs() < 1.0e-3 ) );
+}
+
+#[test]
+fn vapour_cavity_characteristics() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.solver_type() = SolverType::Characteristics;
+    *solver.cavitation() = true;
+    *solver.split_pipes() = true;
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 60;
+    let mut network = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 2.0e5 ) );
+    network.add_node( reservoir.clone() );
+    let valve_node = Node::Connection( Connection::new( 1 ) );
+    network.add_node( valve_node.clone() );
+    let outlet = Node::Pressure( Pressure::new( 2 ) );
+    network.add_node( outlet.clone() );
+    let pipe = Pipe::new_params( reservoir.id(), valve_node.id(), 120.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    // A fast closure drops the valve pressure below vapour pressure
+    let mut valve = Edge::Valve( Valve::new( valve_node.id(), outlet.id() ) );
+    *valve.diameter().unwrap() = 0.1;
+    valve.add_event( TransientEvent::InstantaneousChange( Value( 0.02 ), Time( 0.0 ) ) );
+    network.add_edge( valve );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 150 );
+    let vapour = fluid.vapour_pressure();
+    let volume = solver.cavity_volumes()[1].clone();
+    let pressure = network.nodes()[1].pressure().clone();
+    assert_eq!( volume.len(), pressure.len() );
+    assert!( pressure.iter().all( |p| *p >= vapour - 1.0e-3 ) );
+    // The cavity forms at the valve, grows and then collapses
+    let formed = volume.iter().position( |v| *v > 0.0 ).unwrap();
+    let collapsed = formed + volume[formed..].iter().position( |v| *v == 0.0 ).unwrap();
+    for p in &pressure[formed..collapsed] {
+        assert!( ( p - vapour ).abs() < 1.0e-3 );
+    }
+    let max_volume = volume.iter().cloned().fold( 0.0, f64::max );
+    assert!( max_volume > 1.0e-3 );
+    // Collapse of the cavity produces a pressure spike
+    let spike = pressure[collapsed..].iter().cloned().fold( 0.0, f64::max );
+    assert!( spike > 2.0 * pressure[0] );
+}
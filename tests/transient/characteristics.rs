@@ -0,0 +1,59 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::connection::Connection;
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, valve::Valve };
+use eki::friction::FrictionModel;
+use eki::solver::SolverType;
+use eki::characteristics::Reaches;
+
+use super::example3_1;
+
+#[test]
+fn reaches() {
+    let fluid = Fluid::new_basic( 997.0, 1.1375e-6, 2.15e9 );
+    let pipe = Edge::Pipe( Pipe {
+        friction: FrictionModel::Fixed { f: 0.018 },
+        ..Pipe::new_params( Node::Connection( Connection::new( 0 ) ).id(),
+            Node::Connection( Connection::new( 1 ) ).id(), 610.0, 0.5, 0.0, 1.0803e-2, 2.0e11 )
+    });
+    let a = pipe.wave_speed( &fluid ).unwrap();
+    assert!( ( a - 1200.0 ).abs() < 1.0 );
+    let reaches = Reaches::new( &pipe, &fluid, 0.1, 9.806 ).unwrap();
+    assert_eq!( reaches.segments, 5 );
+    assert!( ( reaches.wave_speed - 1220.0 ).abs() < 1.0e-8 );
+    assert_eq!( reaches.head.len(), 6 );
+    let valve = Edge::Valve( Valve::new( Node::Connection( Connection::new( 0 ) ).id(), 
+        Node::Connection( Connection::new( 1 ) ).id() ) );
+    assert!( Reaches::new( &valve, &fluid, 0.1, 9.806 ).is_none() );
+}
+
+#[test]
+fn streeter_and_wylie() {
+    let ( mut solver, mut network, fluid ) = example3_1( SolverType::Characteristics, 1, 0 );
+    let streeter = [
+        143.49, 154.28, 165.79, 178.08, 191.11 
+    ];
+    for (step, expected) in streeter.iter().enumerate().skip( 1 ) {
+        let result = solver.time_step( &mut network, &fluid );
+        assert!( result.is_ok() );
+        let valve_pressure = (*network.nodes()[1].pressure())[step];
+        let valve_head = valve_pressure / ( fluid.density() * solver.gravity() );
+        let relative_error = ( expected - valve_head ) / expected;
+        assert!( relative_error.abs() < 5.0e-4 );
+    }
+    // Run through the closure (the closed valve law converges linearly to zero flow)
+    *solver.max_iter() = 50;
+    for _step in 5..42 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    let max_head = network.nodes()[1].max_pressure() / ( fluid.density() * solver.gravity() );
+    assert!( max_head > 191.11 && max_head < 400.0 );
+    let reaches = solver.reaches()[0].as_ref().unwrap();
+    assert_eq!( reaches.segments, 5 );
+    assert!( ( reaches.head[0] - 150.0 ).abs() < 1.0e-8 );
+    assert!( solver.reaches()[1].is_none() );
+    let profile = solver.profiles()[0].as_ref().unwrap();
+    assert_eq!( profile.steps(), 42 );
+    assert_eq!( profile.head.last().unwrap(), &reaches.head );
+}
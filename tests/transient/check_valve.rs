@@ -39,7 +39,7 @@ fn transient_check_valve() {
         rho_g * 10.0 + atmospheric_pressure,
     ];
     graph.add_node( node_to.clone() );
-    let mut check_valve = Edge::CheckValve( CheckValve::new( node_from, node_to ) );
+    let mut check_valve = Edge::CheckValve( CheckValve::new( node_from.id(), node_to.id() ) );
     *check_valve.invk_values().unwrap() = vec![ 
         (0.0, 0.0),
         (1.0, 1. / 0.25),
@@ -49,7 +49,7 @@ fn transient_check_valve() {
     graph.add_edge( check_valve );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     // Flow should be positive because the check valve is open initially.
     let mass_flow = (*graph.edges()[0].mass_flow())[0];
@@ -63,7 +63,7 @@ fn transient_check_valve() {
         let mass_flow = (*graph.edges()[0].mass_flow())[step];
         let q = mass_flow / fluid.density();
         println!( "step = {}, result = {:?}, q = {}", step, result, q );
-        assert!( result.is_ok() && !result.is_err() );
+        assert!( result.is_ok() );
     }
 
     // Check that the check valve closes
@@ -84,4 +84,49 @@ fn transient_check_valve() {
 
 
 
-}
\ No newline at end of file
+}
+// Downstream head rising from 0 to 10m over 2s reverses the flow through a swing check valve
+fn swing_check_valve( disc_inertia: f64 ) -> ( Graph, Fluid ) {
+    let mut graph = Graph::new();
+    let fluid = Fluid::new_basic( 997.0, 1.1375e-6, 2.15e9 );
+    let dt = 0.01;
+    let n = 200;
+
+    let mut solver = Solver::default();
+    *solver.dt() = dt;
+    *solver.max_iter() = 50;
+    let rho_g = fluid.density() * solver.gravity();
+    let atmospheric_pressure = 101325.0;
+    let mut node_from = Node::Pressure( Pressure::new( 0 ) );
+    *node_from.pressure() = vec![ rho_g * 5.0 + atmospheric_pressure; n + 1 ];
+    graph.add_node( node_from.clone() );
+    let mut node_to = Node::Pressure( Pressure::new( 1 ) );
+    *node_to.pressure() = ( 0..=n ).map( |step| rho_g * 10.0 * step as f64 / n as f64 + atmospheric_pressure ).collect();
+    graph.add_node( node_to.clone() );
+    let mut check_valve = CheckValve::new( node_from.id(), node_to.id() );
+    check_valve.diameter = 50.0e-3;
+    check_valve.disc_inertia = disc_inertia;
+    graph.add_edge( Edge::CheckValve( check_valve ) );
+
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    for _step in 0..n {
+        assert!( solver.time_step( &mut graph, &fluid ).is_ok() );
+    }
+    ( graph, fluid )
+}
+
+#[test]
+fn dynamic_check_valve() {
+    let ( mut graph, fluid ) = swing_check_valve( 1.0e-4 );
+    let Edge::CheckValve( valve ) = &graph.mut_edges()[0] else { panic!( "Expected a check valve" ) };
+    // The disc passes through partially open positions before it seats
+    assert!( valve.open_percent.iter().any( |x| *x > 0.0 && *x < 1.0 ) );
+    assert_eq!( *valve.open_percent.last().unwrap(), 0.0 );
+    let slam = valve.slam_velocity( fluid.density() ).unwrap();
+    assert!( slam < 0.0 );
+
+    // A heavier disc lags further behind the flow reversal
+    let ( mut graph, _ ) = swing_check_valve( 1.0e-3 );
+    let Edge::CheckValve( heavy ) = &graph.mut_edges()[0] else { panic!( "Expected a check valve" ) };
+    assert!( heavy.slam_velocity( fluid.density() ).unwrap() < slam );
+}
@@ -0,0 +1,71 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, flow::Flow, connection::Connection };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, control_valve::{ ControlValve, ControlMode, Actuator } };
+use eki::events::{ TransientEvent, Time, Value };
+use eki::graph::Graph;
+use eki::solver::Solver;
+
+const ATMOSPHERIC: f64 = 101325.0;
+const SETPOINT: f64 = ATMOSPHERIC + 3.0e5;
+
+// Reservoir at 6 bar gauge - pipe - pressure reducing valve - pipe - demand rising from 10 to 
+// 15 kg/s after 1 s
+fn zone( actuator: Actuator ) -> Graph {
+    let mut graph = Graph::new();
+    graph.add_node( Node::Pressure( Pressure::new_with_value( 0, ATMOSPHERIC + 6.0e5 ) ) );
+    graph.add_node( Node::Connection( Connection::new( 1 ) ) );
+    graph.add_node( Node::Connection( Connection::new( 2 ) ) );
+    let mut demand = Node::Flow( Flow::new_with_value( 3, -10.0 ) );
+    demand.add_event( TransientEvent::InstantaneousChange( Value( -15.0 ), Time( 1.0 ) ) );
+    graph.add_node( demand );
+    graph.add_edge( Edge::Pipe( Pipe::new_params( 0, 1, 500.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    let mut valve = ControlValve::new( 1, 2, ControlMode::PressureReducing, SETPOINT );
+    valve.diameter = 0.1;
+    valve.actuator = actuator;
+    graph.add_edge( Edge::ControlValve( valve ) );
+    graph.add_edge( Edge::Pipe( Pipe::new_params( 2, 3, 500.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    graph
+}
+
+// Run for 30 s and return the pressures downstream of the valve and its openings
+fn run( actuator: Actuator ) -> ( Vec<f64>, Vec<f64> ) {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.05;
+    let mut graph = zone( actuator );
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    for _step in 0..600 {
+        assert!( solver.time_step( &mut graph, &fluid ).is_ok() );
+    }
+    let pressure = graph.nodes[2].pressure().clone();
+    let open = graph.edges[1].open_percent().unwrap().clone();
+    ( pressure, open )
+}
+
+#[test]
+fn first_order() {
+    let ( pressure, open ) = run( Actuator::FirstOrder { time_constant: 2.0 } );
+    assert_eq!( pressure.len(), 601 );
+    assert!( ( pressure[0] - SETPOINT ).abs() < 1.0e-3 );
+    // The pressure falls as the demand rises before the valve has moved
+    assert!( pressure[21..40].iter().any( |p| *p < SETPOINT - 1.0e5 ) );
+    // The valve opens further to bring the pressure back to the setpoint
+    assert!( open.iter().all( |x| ( 0.0..=1.0 ).contains( x ) ) );
+    assert!( open[600] > open[0] + 0.05 );
+    assert!( ( pressure[600] - SETPOINT ).abs() < 6.0e3 );
+}
+
+#[test]
+fn pid() {
+    let ( pressure, open ) = run( Actuator::Pid { gain: 0.1, integral_time: 1.0, derivative_time: 0.0 } );
+    assert!( ( pressure[0] - SETPOINT ).abs() < 1.0e-3 );
+    assert!( pressure[21..40].iter().any( |p| *p < SETPOINT - 5.0e4 ) );
+    // The integral action removes the offset left by the proportional term
+    assert!( open[600] > open[0] + 0.05 );
+    assert!( ( pressure[600] - SETPOINT ).abs() < 1.0e3 );
+    // Both actuators settle on the opening that holds the setpoint at the new demand
+    let ( _, first_order ) = run( Actuator::FirstOrder { time_constant: 2.0 } );
+    assert!( ( open[600] - first_order[600] ).abs() < 5.0e-3 );
+}
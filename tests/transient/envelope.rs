@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, connection::Connection };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, valve::Valve };
+use eki::events::{ TransientEvent, Time, Value };
+use eki::graph::Graph;
+use eki::report::{ TransientReport, Place };
+use eki::solver::Solver;
+use super::run;
+
+#[test]
+fn node_envelope() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.split_pipes() = true;
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 50;
+    // Reservoir - pipe - valve - pipe - outlet with the valve part way through a 1 s closure
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 3.0e5 ) ) );
+    network.add_node( Node::Connection( Connection::new( 1 ) ) );
+    network.add_node( Node::Connection( Connection::new( 2 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_with_value( 3, 101325.0 ) ) );
+    let mut supply = Pipe::new_params( 0, 1, 400.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    supply.pressure_rating = 10.0e5;
+    network.add_edge( Edge::Pipe( supply ) );
+    let mut valve = Edge::Valve( Valve::new( 1, 2 ) );
+    *valve.diameter().unwrap() = 0.1;
+    valve.add_event( TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) );
+    network.add_edge( valve );
+    let mut delivery = Pipe::new_params( 2, 3, 400.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    delivery.pressure_rating = 2.0e5;
+    network.add_edge( Edge::Pipe( delivery ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 95 );
+    let report = TransientReport::new( &network, &solver, &fluid, &HashMap::new() );
+    let time = solver.tnodes();
+    assert_eq!( report.nodes.len(), 4 );
+    for ( envelope, node ) in report.nodes.iter().zip( network.mut_nodes() ) {
+        let pressure = node.pressure().clone();
+        assert_eq!( envelope.max_pressure.value, node.max_pressure() );
+        assert_eq!( envelope.min_pressure.value, node.min_pressure() );
+        let step = pressure.iter().position( |p| *p == envelope.max_pressure.value ).unwrap();
+        assert_eq!( envelope.max_pressure.time, time[step] );
+        let g = solver.gravity();
+        assert!( ( envelope.max_head.value - envelope.max_pressure.value / ( fluid.density() * g ) ).abs() < 1.0e-9 );
+    }
+    // The surge upstream of the valve
+    assert!( report.nodes[1].max_pressure.value > 101325.0 + 5.0e5 );
+    assert!( report.nodes[1].max_pressure.time > 0.0 );
+}
+
+#[test]
+fn ratings() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.split_pipes() = true;
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 50;
+    // Reservoir - pipe - valve - pipe - outlet with the valve part way through a 1 s closure
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 3.0e5 ) ) );
+    network.add_node( Node::Connection( Connection::new( 1 ) ) );
+    network.add_node( Node::Connection( Connection::new( 2 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_with_value( 3, 101325.0 ) ) );
+    let mut supply = Pipe::new_params( 0, 1, 400.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    supply.pressure_rating = 10.0e5;
+    network.add_edge( Edge::Pipe( supply ) );
+    let mut valve = Edge::Valve( Valve::new( 1, 2 ) );
+    *valve.diameter().unwrap() = 0.1;
+    valve.add_event( TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) );
+    network.add_edge( valve );
+    let mut delivery = Pipe::new_params( 2, 3, 400.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    delivery.pressure_rating = 2.0e5;
+    network.add_edge( Edge::Pipe( delivery ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 95 );
+    let report = TransientReport::new( &network, &solver, &fluid, &HashMap::new() );
+    // Nodes take the lowest rating of their pipes
+    let ratings: Vec<Option<f64>> = report.nodes.iter().map( |node| node.rating ).collect();
+    assert_eq!( ratings, vec![ Some( 10.0e5 ), Some( 10.0e5 ), Some( 2.0e5 ), Some( 2.0e5 ) ] );
+    let upstream = &report.nodes[1];
+    assert_eq!( upstream.margin, Some( 10.0e5 - ( upstream.max_pressure.value - 101325.0 ) ) );
+    // The surge exceeds the rating of the supply pipe
+    assert!( upstream.margin.unwrap() < 0.0 );
+    // The margin along a pipe is the smallest over its points
+    let supply = &report.pipes[0];
+    assert_eq!( ( supply.from, supply.to ), ( 0, 1 ) );
+    assert_eq!( supply.distance.len(), supply.max_pressure.len() );
+    let worst = supply.max_pressure.iter().map( |p| p.value ).fold( f64::MIN, f64::max );
+    assert_eq!( supply.margin, Some( 10.0e5 - ( worst - 101325.0 ) ) );
+    assert_eq!( *supply.distance.last().unwrap(), 400.0 );
+    // The valve is not a pipe
+    assert_eq!( report.pipes.len(), 2 );
+    assert!( report.min_margin().unwrap() <= supply.margin.unwrap() );
+    // Explicit node ratings take priority
+    let report = TransientReport::new( &network, &solver, &fluid, &HashMap::from( [ ( 1, 20.0e5 ) ] ) );
+    assert_eq!( report.nodes[1].rating, Some( 20.0e5 ) );
+    assert!( report.nodes[1].margin.unwrap() > 0.0 );
+}
+
+#[test]
+fn exceedance() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.split_pipes() = true;
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 50;
+    // Reservoir - pipe - valve - pipe - outlet with the valve part way through a 1 s closure
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 3.0e5 ) ) );
+    network.add_node( Node::Connection( Connection::new( 1 ) ) );
+    network.add_node( Node::Connection( Connection::new( 2 ) ) );
+    network.add_node( Node::Pressure( Pressure::new_with_value( 3, 101325.0 ) ) );
+    let mut supply = Pipe::new_params( 0, 1, 400.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    supply.pressure_rating = 10.0e5;
+    network.add_edge( Edge::Pipe( supply ) );
+    let mut valve = Edge::Valve( Valve::new( 1, 2 ) );
+    *valve.diameter().unwrap() = 0.1;
+    valve.add_event( TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ) );
+    network.add_edge( valve );
+    let mut delivery = Pipe::new_params( 2, 3, 400.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    delivery.pressure_rating = 2.0e5;
+    network.add_edge( Edge::Pipe( delivery ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 95 );
+    let report = TransientReport::new( &network, &solver, &fluid, &HashMap::new() );
+    let worst = report.sub_atmospheric.expect( "The pressure falls below atmospheric downstream of the valve" );
+    assert!( worst.pressure < 101325.0 );
+    assert!( ( worst.depth - ( 101325.0 - worst.pressure ) ).abs() < 1.0e-9 );
+    // Nothing anywhere is lower than the reported worst pressure
+    let lowest = report.nodes.iter().map( |node| node.min_pressure.value )
+        .chain( report.pipes.iter().flat_map( |pipe| pipe.min_pressure.iter().map( |p| p.value ) ) )
+        .fold( f64::MAX, f64::min );
+    assert_eq!( worst.pressure, lowest );
+    match worst.place {
+        Place::Node { id } => assert_eq!( id, 2 ),
+        Place::Pipe { from, to, .. } => assert_eq!( ( from, to ), ( 2, 3 ) ),
+    }
+    let json = report.to_json();
+    assert_eq!( serde_json::from_str::<TransientReport>( &json ).unwrap(), report );
+}
@@ -1,10 +1,10 @@
 use eki::fluid::Fluid;
 use eki::node::Node;
-use eki::nodes::{ pressure::Pressure, connection::Connection };
+use eki::nodes::{ pressure::Pressure, connection::Connection, flow::Flow };
 use eki::edge::Edge;
 use eki::edges::{ pipe::Pipe, valve::Valve, pump::Pump };
-use eki::graph::Graph;
-use eki::solver::Solver;
+use eki::graph::{ Graph, Diagnostic };
+use eki::solver::{ Solver, SolverType, SolverError };
 use eki::events::{TransientEvent, Time, Value};
 
 #[test]
@@ -30,13 +30,13 @@ fn add_transient_values() {
     assert_eq!( *node_to.pressure(), vec![ 101325.0 ] );
     network.add_node( node_to.clone() );
     
-    let pipe = Edge::Pipe( Pipe::new( node_from, connection.clone() ) );
+    let pipe = Edge::Pipe( Pipe::new( node_from.id(), connection.id() ) );
     network.add_edge( pipe );
-    let pipe = Edge::Pipe( Pipe::new( connection, node_to.clone() ) );
+    let pipe = Edge::Pipe( Pipe::new( connection.id(), node_to.id() ) );
     network.add_edge( pipe );
 
     let steady_result = solver.solve_steady( &mut network, &fluid, true );
-    assert!( steady_result.is_ok() && !steady_result.is_err() );
+    assert!( steady_result.is_ok() );
 
     // Take a time step but add transient values first (Before event)
     let dt = 0.3; // Less than event time
@@ -94,7 +94,7 @@ fn valve_closure() {
     assert_eq!( *node_to.pressure(), vec![ 101325.0 ] );
     network.add_node( node_to.clone() );
 
-    let mut valve = Edge::Valve( Valve::new( node_from, node_to ) );
+    let mut valve = Edge::Valve( Valve::new( node_from.id(), node_to.id() ) );
     *valve.invk_values().unwrap() = vec![ 
         (0.0, 0.0),
         (0.5, 1. / 7.0),
@@ -111,7 +111,7 @@ fn valve_closure() {
     network.add_edge( valve );
 
     let steady_result = solver.solve_steady( &mut network, &fluid, true );
-    assert!( steady_result.is_ok() && !steady_result.is_err() );
+    assert!( steady_result.is_ok() );
 
     let open_percent = network.edges()[0].open_percent().unwrap().clone();
     assert_eq!( open_percent, vec![ 1.0 ] );
@@ -132,12 +132,9 @@ fn valve_closure() {
     // Another step
     let result = solver.time_step( &mut network, &fluid );
 
-    match result {
-        Ok(iterations) => {
-            assert!( iterations < 21 );
-            // Takes 20 iterations (we should be using a smaller time step)
-        },
-        Err(_) => {}
+    if let Ok(iterations) = result {
+        assert!( iterations < 21 );
+        // Takes 20 iterations (we should be using a smaller time step)
     }
     
     let open_percent = network.edges()[0].open_percent().unwrap().clone();
@@ -161,7 +158,7 @@ fn pump_linear_shutdown() {
     let node_to = Node::Pressure( Pressure::new_elevation( 2, 10.0 ) );
     network.add_node( node_to.clone() );
 
-    let new_pump = Pump::new( node_from, node_to );
+    let new_pump = Pump::new( node_from.id(), node_to.id() );
     let mut pump = Edge::Pump( new_pump.clone() );
     let exponent = Value( 1.0 );  // Linear closing
     let event_time = Time( 0.0 );
@@ -172,10 +169,10 @@ fn pump_linear_shutdown() {
 
     let mut solver = Solver::default();
     let result = solver.solve_steady( &mut network, &fluid, true );
-    if let Err(residual) = result {
-        println!( "residual = {}", residual );
+    if let Err(error) = &result {
+        println!( "error = {}", error );
     } 
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     let volume_flow = *network.edges()[0].steady_mass_flow() / fluid.density();
     let q = volume_flow / new_pump.q_rated;
@@ -204,8 +201,8 @@ fn pump_linear_shutdown() {
             println!( "iterations = {}, dt = {}, speed = {}, mass_flow = {}", iter, *solver.dt(), *speed.last().unwrap(), *mass_flow.last().unwrap() );
             *solver.dt() *= if iter < 5 { 1.0 } else { 0.5 }; // Adaptive time-stepping
         },
-        Err( residual ) => {
-            println!("Residual = {:+.2e}", residual );
+        Err( error ) => {
+            println!("Error = {}", error );
         }
     } 
 
@@ -228,8 +225,8 @@ fn pump_linear_shutdown() {
                 *solver.dt() *= if iter < 5 { 1.1 } else { 0.5 }; // Adaptive time-stepping
                 time += *solver.dt();
             },
-            Err( residual ) => {
-                println!("Residual = {:+.2e}", residual );
+            Err( error ) => {
+                println!("Error = {}", error );
                 break;
             }
         }
@@ -239,4 +236,87 @@ fn pump_linear_shutdown() {
     println!( "time = {:?}", time );
     let speed = network.edges()[0].speed().unwrap().clone();
     assert_eq!( *speed.last().unwrap(), 0.0 ); 
-}
\ No newline at end of file
+}
+
+#[test]
+fn staged_valve_closure() {
+    // Partial closure, a linear closure from there and then a full reopening (given out of order)
+    let mut valve = Valve::new( 0, 1 );
+    valve.events = vec![
+        TransientEvent::ValveOpening( Value( 1.0 ), Time( 0.6 ), Time( 0.2 ) ),
+        TransientEvent::InstantaneousChange( Value( 0.6 ), Time( 0.1 ) ),
+        TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.3 ), Time( 0.2 ) ),
+    ];
+    for time in [ 0.05, 0.1, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 1.0 ] {
+        valve.add_transient_value( time, &[] );
+    }
+    // One value per step, each event starting from where the previous one finished
+    let expected = [ 1.0, 1.0, 0.6, 0.6, 0.3, 0.0, 0.0, 0.5, 1.0, 1.0 ];
+    assert_eq!( valve.open_percent.len(), expected.len() );
+    for ( open, expected ) in valve.open_percent.iter().zip( expected ) {
+        assert!( ( open - expected ).abs() < 1.0e-12 );
+    }
+}
+
+#[test]
+fn overlapping_events() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    let mut network = Graph::new();
+    network.add_node( Node::Pressure( Pressure::new_with_value( 0, 121325.0 ) ) );
+    network.add_node( Node::Pressure( Pressure::new( 1 ) ) );
+    let mut valve = Valve::new( 0, 1 );
+    valve.events = vec![
+        TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 1.0 ) ),
+        TransientEvent::ValveOpening( Value( 1.0 ), Time( 0.5 ), Time( 1.0 ) ),
+    ];
+    network.add_edge( Edge::Valve( valve ) );
+    let diagnostic = Diagnostic::OverlappingEdgeEvents { edge: 0, events: ( 0, 1 ) };
+    assert_eq!( network.validate(), vec![ diagnostic.clone() ] );
+    assert_eq!( diagnostic.to_string(), "Edge 0 has event 1 starting before event 0 has finished" );
+    let Err( SolverError::InvalidNetwork { diagnostics } ) = solver.solve_steady( &mut network, &fluid, true ) else {
+        panic!( "Expected the overlapping events to be rejected" )
+    };
+    assert_eq!( diagnostics, vec![ diagnostic ] );
+    // Events that follow on are accepted, as are changes at a node at different times
+    network.mut_edges()[0].events().unwrap()[1] = TransientEvent::ValveOpening( Value( 1.0 ), Time( 1.0 ), Time( 1.0 ) );
+    network.mut_nodes()[0].add_event( TransientEvent::InstantaneousChange( Value( 131325.0 ), Time( 0.5 ) ) );
+    assert!( network.validate().is_empty() );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    // Events added after the steady solve are checked at the start of the transient run
+    network.mut_nodes()[0].add_event( TransientEvent::InstantaneousChange( Value( 111325.0 ), Time( 0.5 ) ) );
+    let diagnostic = Diagnostic::OverlappingNodeEvents { id: 0, events: ( 0, 1 ) };
+    assert_eq!( network.validate(), vec![ diagnostic.clone() ] );
+    *solver.solver_type() = SolverType::Transient;
+    assert_eq!( solver.time_step( &mut network, &fluid ), Err( SolverError::InvalidNetwork { diagnostics: vec![ diagnostic ] } ) );
+}
+
+#[test]
+fn flow_node_values() {
+    let fluid = Fluid::default();
+    let events = [
+        TransientEvent::InstantaneousChange( Value( -0.2 ), Time( 0.15 ) ),
+        TransientEvent::InstantaneousChange( Value( -0.3 ), Time( 0.35 ) ),
+    ];
+    let expected = [
+        vec![ -0.1; 6 ],
+        vec![ -0.1, -0.1, -0.2, -0.2, -0.2, -0.2 ],
+        vec![ -0.1, -0.1, -0.2, -0.2, -0.3, -0.3 ],
+    ];
+    for ( count, expected ) in expected.iter().enumerate() {
+        let mut solver = Solver::default();
+        *solver.dt() = 0.1;
+        let mut network = Graph::new();
+        network.add_node( Node::Pressure( Pressure::new_with_value( 0, 121325.0 ) ) );
+        let mut flow = Flow::new( 1 );
+        flow.events = events[..count].to_vec();
+        network.add_node( Node::Flow( flow ) );
+        network.add_edge( Edge::Pipe( Pipe::new( 0, 1 ) ) );
+        assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+        super::run( &mut solver, &mut network, &fluid, 5 );
+        // One pressure (from the solution) and one consumption per time node whatever the number of events
+        let steps = solver.tnodes().len();
+        assert_eq!( network.nodes()[1].pressure().len(), steps );
+        assert_eq!( network.nodes()[1].consumption(), expected );
+    }
+}
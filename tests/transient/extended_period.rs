@@ -0,0 +1,120 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, tank::Tank, flow::Flow };
+use eki::edge::Edge;
+use eki::edges::pipe::Pipe;
+use eki::graph::Graph;
+use eki::solver::{ Solver, SolverType };
+use eki::series::TimeSeries;
+use super::run;
+
+#[test]
+fn demand_pattern() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.solver_type() = SolverType::ExtendedPeriod;
+    *solver.dt() = 3600.0;
+    let rho_g = fluid.density() * solver.gravity();
+    // Reservoir - pipe - tank - pipe - demand with an hourly step and a three hour demand pattern
+    let mut network = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + rho_g * 5.0 ) );
+    network.add_node( reservoir.clone() );
+    let tank = Node::Tank( Tank::new_with_values( 1, 101325.0, fluid.density(), solver.gravity(), 10.0, 2.0, 0.5, 4.0 ) );
+    network.add_node( tank.clone() );
+    let mut demand = Flow::new_elevation( 2, -20.0 );
+    demand.consumption = vec![ - 5.0 ];
+    demand.series = Some( TimeSeries::pattern( &[ - 5.0 * 0.5, - 5.0, - 5.0 * 1.5 ], 3600.0 ) );
+    let demand = Node::Flow( demand );
+    network.add_node( demand.clone() );
+    network.add_edge( Edge::Pipe( Pipe::new_params( reservoir.id(), tank.id(), 1000.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( tank.id(), demand.id(), 500.0, 0.15, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 24 );
+    assert_eq!( solver.tnodes().len(), 25 );
+    assert_eq!( *solver.tnodes().last().unwrap(), 24.0 * 3600.0 );
+    let consumption = network.mut_nodes()[2].consumption().clone();
+    assert_eq!( consumption.len(), 25 );
+    // The pattern repeats every three hours from the start of the run
+    assert_eq!( consumption[0], - 5.0 * 0.5 );
+    assert_eq!( consumption[1], - 5.0 );
+    assert_eq!( consumption[2], - 5.0 * 1.5 );
+    assert_eq!( consumption[3], - 5.0 * 0.5 );
+    assert_eq!( consumption[4], - 5.0 );
+    assert_eq!( network.mut_nodes()[2].pressure().len(), 25 );
+    // Each steady solution delivers the demand at the end of its step
+    for edge in network.mut_edges() {
+        assert_eq!( edge.mass_flow().len(), 25 );
+    }
+    let outflow = network.mut_edges()[1].mass_flow().clone();
+    for step in 1..25 {
+        assert!( ( outflow[step] + consumption[step] ).abs() < 1.0e-6 );
+    }
+}
+
+#[test]
+fn tank_balance() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.solver_type() = SolverType::ExtendedPeriod;
+    *solver.dt() = 3600.0;
+    let rho_g = fluid.density() * solver.gravity();
+    // Reservoir - pipe - tank - pipe - demand with an hourly step
+    let mut network = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + rho_g * 5.0 ) );
+    network.add_node( reservoir.clone() );
+    let tank = Node::Tank( Tank::new_with_values( 1, 101325.0, fluid.density(), solver.gravity(), 10.0, 2.0, 0.5, 4.0 ) );
+    network.add_node( tank.clone() );
+    let mut demand = Flow::new_elevation( 2, -20.0 );
+    demand.consumption = vec![ - 5.0 ];
+    let demand = Node::Flow( demand );
+    network.add_node( demand.clone() );
+    network.add_edge( Edge::Pipe( Pipe::new_params( reservoir.id(), tank.id(), 1000.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( tank.id(), demand.id(), 500.0, 0.15, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 10 );
+    let rho = fluid.density();
+    let inflow = network.mut_edges()[0].mass_flow().clone();
+    let outflow = network.mut_edges()[1].mass_flow().clone();
+    let Node::Tank( tank ) = network.mut_nodes()[1].clone() else { panic!( "Expected a tank" ) };
+    assert_eq!( tank.level.len(), 11 );
+    assert!( ( tank.level[0] - 2.0 ).abs() < 1.0e-10 );
+    // The level changes with the net inflow at the start of each step
+    for step in 0..10 {
+        let rise = 3600.0 * ( inflow[step] - outflow[step] ) / ( rho * tank.area() );
+        assert!( ( tank.level[step + 1] - tank.level[step] - rise ).abs() < 1.0e-10 );
+    }
+    // The tank head is imposed from its level
+    let pressure = network.mut_nodes()[1].pressure().clone();
+    for (p, z) in pressure.iter().zip( &tank.level ).take( 11 ) {
+        let level = ( p - 101325.0 ) / ( rho * solver.gravity() );
+        assert!( ( level - z ).abs() < 1.0e-8 );
+    }
+}
+
+#[test]
+fn tank_overflow() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.solver_type() = SolverType::ExtendedPeriod;
+    *solver.dt() = 3600.0;
+    let rho_g = fluid.density() * solver.gravity();
+    // Reservoir - pipe - tank - pipe - demand with an hourly step
+    let mut network = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + rho_g * 30.0 ) );
+    network.add_node( reservoir.clone() );
+    let tank = Node::Tank( Tank::new_with_values( 1, 101325.0, fluid.density(), solver.gravity(), 10.0, 2.0, 0.5, 4.0 ) );
+    network.add_node( tank.clone() );
+    let mut demand = Flow::new_elevation( 2, -20.0 );
+    demand.consumption = vec![ - 5.0 ];
+    let demand = Node::Flow( demand );
+    network.add_node( demand.clone() );
+    network.add_edge( Edge::Pipe( Pipe::new_params( reservoir.id(), tank.id(), 1000.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    network.add_edge( Edge::Pipe( Pipe::new_params( tank.id(), demand.id(), 500.0, 0.15, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 24 );
+    let Node::Tank( tank ) = network.mut_nodes()[1].clone() else { panic!( "Expected a tank" ) };
+    assert!( tank.level.windows( 2 ).all( |w| w[1] >= w[0] ) );
+    assert_eq!( *tank.level.last().unwrap(), tank.z_max );
+    assert!( *tank.overflow.last().unwrap() > 0.0 );
+    assert!( tank.air_entry.iter().all( |q| *q == 0.0 ) );
+}
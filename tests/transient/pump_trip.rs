@@ -0,0 +1,125 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, connection::Connection };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, pump::Pump };
+use eki::graph::Graph;
+use eki::solver::Solver;
+use eki::events::{ TransientEvent, Time, Value };
+use super::run;
+
+#[test]
+fn power_failure() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 50;
+    // Reservoir - pump - pipe - elevated reservoir with a power failure at the pump
+    let mut network = Graph::new();
+    let suction = Node::Pressure( Pressure::new( 0 ) );
+    network.add_node( suction.clone() );
+    let delivery = Node::Connection( Connection::new( 1 ) );
+    network.add_node( delivery.clone() );
+    let reservoir = Node::Pressure( Pressure::new_elevation( 2, 300.0 ) );
+    network.add_node( reservoir.clone() );
+    let mut pump = Pump::new( suction.id(), delivery.id() );
+    pump.motor_inertia = 0.5;
+    pump.events.push( TransientEvent::PowerFailure( Time( 0.0 ) ) );
+    network.add_edge( Edge::Pump( pump ) );
+    let pipe = Pipe::new_params( delivery.id(), reservoir.id(), 500.0, 0.3, 0.05e-3, 10.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 100 );
+    let Edge::Pump( pump ) = &network.mut_edges()[0] else { panic!( "Expected a pump" ) };
+    assert_eq!( pump.speed.len(), 101 );
+    assert!( pump.speed.windows( 2 ).all( |n| n[1] < n[0] ) );
+    // The speed history satisfies I dw/dt = - T with the computed flow rates
+    let q: Vec<f64> = pump.mass_flow.iter().map( |m| m / fluid.density() ).collect();
+    let rate = *solver.dt() * 30.0 / ( std::f64::consts::PI * pump.total_inertia() );
+    for k in 0..100 {
+        let torque = pump.hydraulic_torque( pump.speed[k], q[k] ) + pump.hydraulic_torque( pump.speed[k + 1], q[k + 1] );
+        assert!( ( pump.speed[k + 1] - pump.speed[k] + 0.5 * rate * torque ).abs() < 1.0e-6 );
+    }
+    // The flow into the elevated reservoir falls as the pump slows down
+    assert!( q[100] < 0.5 * q[0] );
+}
+
+#[test]
+fn flywheel() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 50;
+    // Reservoir - pump - pipe - elevated reservoir with a power failure at the pump
+    let mut network = Graph::new();
+    let suction = Node::Pressure( Pressure::new( 0 ) );
+    network.add_node( suction.clone() );
+    let delivery = Node::Connection( Connection::new( 1 ) );
+    network.add_node( delivery.clone() );
+    let reservoir = Node::Pressure( Pressure::new_elevation( 2, 300.0 ) );
+    network.add_node( reservoir.clone() );
+    let mut pump = Pump::new( suction.id(), delivery.id() );
+    pump.motor_inertia = 0.5;
+    pump.events.push( TransientEvent::PowerFailure( Time( 0.0 ) ) );
+    network.add_edge( Edge::Pump( pump ) );
+    let pipe = Pipe::new_params( delivery.id(), reservoir.id(), 500.0, 0.3, 0.05e-3, 10.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    // The same network with a flywheel on the pump
+    let mut network_fw = network.clone();
+    let Edge::Pump( pump ) = &mut network_fw.mut_edges()[0] else { panic!( "Expected a pump" ) };
+    pump.flywheel = 20.0;
+    let mut solver_fw = Solver::default();
+    *solver_fw.dt() = 0.01;
+    *solver_fw.max_iter() = 50;
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    assert!( solver_fw.solve_steady( &mut network_fw, &fluid, true ).is_ok() );
+    run( &mut solver, &mut network, &fluid, 50 );
+    run( &mut solver_fw, &mut network_fw, &fluid, 50 );
+    let speed = *network.mut_edges()[0].speed().unwrap().last().unwrap();
+    let speed_fw = *network_fw.mut_edges()[0].speed().unwrap().last().unwrap();
+    assert!( speed_fw > speed );
+    // The extra inertia keeps the pump delivering for longer
+    let flow = *network.mut_edges()[1].mass_flow().last().unwrap();
+    let flow_fw = *network_fw.mut_edges()[1].mass_flow().last().unwrap();
+    assert!( flow_fw > flow );
+}
+
+#[test]
+fn restart() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 50;
+    // Reservoir - pump - pipe - elevated reservoir with a power failure at the pump
+    let mut network = Graph::new();
+    let suction = Node::Pressure( Pressure::new( 0 ) );
+    network.add_node( suction.clone() );
+    let delivery = Node::Connection( Connection::new( 1 ) );
+    network.add_node( delivery.clone() );
+    let reservoir = Node::Pressure( Pressure::new_elevation( 2, 300.0 ) );
+    network.add_node( reservoir.clone() );
+    let mut pump = Pump::new( suction.id(), delivery.id() );
+    pump.motor_inertia = 0.5;
+    pump.events.push( TransientEvent::PowerFailure( Time( 0.0 ) ) );
+    network.add_edge( Edge::Pump( pump ) );
+    let pipe = Pipe::new_params( delivery.id(), reservoir.id(), 500.0, 0.3, 0.05e-3, 10.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    // The pump is restarted before it has run down
+    network.mut_edges()[0].add_event( TransientEvent::PumpStartup( Value( 11300.0 ), Value( 1.0 ), Time( 0.2 ), Time( 0.2 ) ) );
+    run( &mut solver, &mut network, &fluid, 60 );
+    let time = solver.tnodes();
+    let Edge::Pump( pump ) = &network.mut_edges()[0] else { panic!( "Expected a pump" ) };
+    assert_eq!( pump.speed.len(), time.len() );
+    let start = time.iter().position( |t| *t >= 0.2 ).unwrap();
+    assert!( pump.speed[..start].windows( 2 ).all( |n| n[1] < n[0] ) );
+    // The startup begins from the run-down speed and not from rest
+    let run_down = pump.speed[start - 1];
+    assert_eq!( solver.edge_states()[0].restart, Some( ( 1, run_down ) ) );
+    assert!( run_down > 0.5 * 11300.0 );
+    for (t, speed) in time.iter().zip( &pump.speed ).skip( start ) {
+        let tau = ( ( t - 0.2 ) / 0.2 ).min( 1.0 );
+        assert!( ( speed - ( run_down + ( 11300.0 - run_down ) * tau ) ).abs() < 1.0e-9 );
+    }
+    assert_eq!( *pump.speed.last().unwrap(), 11300.0 );
+}
@@ -1,8 +1,9 @@
 use eki::fluid::Fluid;
 use eki::node::Node;
-use eki::nodes::{ pressure::Pressure };
+use eki::nodes::{ pressure::Pressure, connection::Connection };
 use eki::edge::Edge;
-use eki::edges::{ relief_valve::ReliefValve };
+use eki::edges::{ relief_valve::ReliefValve, pipe::Pipe, valve::Valve };
+use eki::events::{ TransientEvent, Time, Value };
 use eki::graph::Graph;
 use eki::solver::Solver;
 
@@ -38,7 +39,7 @@ fn transient_relief_valve() {
     let dp_open = 1000.; 
     let dp_full = 5000.;
 
-    let mut relief_valve = Edge::ReliefValve( ReliefValve::new( node_from, node_to, dp_open, dp_full ) );
+    let mut relief_valve = Edge::ReliefValve( ReliefValve::new( node_from.id(), node_to.id(), dp_open, dp_full ) );
     *relief_valve.invk_values().unwrap() = vec![ 
         (0.0, 0.0),
         (0.5, 1. / 8.0 ),
@@ -54,7 +55,7 @@ fn transient_relief_valve() {
     graph.add_edge( relief_valve );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     // Flow should be zero because the safety valve is closed initially.
     let mass_flow = (*graph.edges()[0].mass_flow())[0];
@@ -68,7 +69,7 @@ fn transient_relief_valve() {
         let mass_flow = (*graph.edges()[0].mass_flow())[step];
         let q = mass_flow / fluid.density();
         println!( "step = {}, result = {:?}, q = {:.2e}", step, result, q );
-        assert!( result.is_ok() && !result.is_err() );
+        assert!( result.is_ok() );
     }
 
     // Check that the valve opens at the correct pressure difference.
@@ -86,4 +87,44 @@ fn transient_relief_valve() {
         assert_eq!( open_percent[10], 1.0 );
     }
 
-}
\ No newline at end of file
+}
+// The valve opens from the pressure the solver calculates at its inlet
+#[test]
+fn relief_valve_surge() {
+    let mut graph = Graph::new();
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.01;
+    *solver.max_iter() = 100;            // Reseating needs extra iterations at zero flow
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + 2.0e5 ) );
+    let junction = Node::Connection( Connection::new( 1 ) );
+    let outlet = Node::Pressure( Pressure::new_with_value( 2, 101325.0 ) );
+    let vent = Node::Pressure( Pressure::new_with_value( 3, 101325.0 ) );
+    for node in [ &reservoir, &junction, &outlet, &vent ] {
+        graph.add_node( node.clone() );
+    }
+    graph.add_edge( Edge::Pipe( Pipe::new_params( 0, 1, 500.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 ) ) );
+    let mut valve = Edge::Valve( Valve::new( 1, 2 ) );
+    *valve.diameter().unwrap() = 0.1;
+    valve.add_event( TransientEvent::ValveClosure( Value( 1.0 ), Time( 0.0 ), Time( 0.5 ) ) );
+    graph.add_edge( valve );
+    let ( dp_open, dp_full ) = ( 1.0e5, 3.0e5 );
+    let mut relief = ReliefValve::new( 1, 3, dp_open, dp_full );
+    relief.diameter = 0.05;
+    relief.invk = vec![ ( 0.0, 0.0 ), ( 0.1, 1.0 ), ( 1.0, 4.0 ) ];
+    graph.add_edge( Edge::ReliefValve( relief ) );
+
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    for _step in 0..40 {
+        assert!( solver.time_step( &mut graph, &fluid ).is_ok() );
+    }
+    let pressure = graph.mut_nodes()[1].pressure().clone();
+    let Edge::ReliefValve( relief ) = graph.edges()[2].clone() else { panic!( "Expected a relief valve" ) };
+    assert_eq!( relief.open_percent.len(), pressure.len() );
+    assert!( relief.open_percent.iter().any( |open| *open > 0.0 ) );
+    // Each opening follows from the junction pressure at the end of the previous step
+    for step in 1..pressure.len() {
+        let dp = pressure[step - 1] - 101325.0;
+        assert!( ( relief.open_percent[step] - relief.open_percent_from_dp( dp ) ).abs() < 1.0e-12 );
+    }
+}
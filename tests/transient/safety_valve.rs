@@ -35,7 +35,7 @@ fn transient_safety_valve() {
     *node_to.pressure() = vec![ atmospheric_pressure; n ];
     graph.add_node( node_to.clone() );
     let set_pressure = 10000.0; 
-    let mut safety_valve = Edge::SafetyValve( SafetyValve::new( node_from, node_to, set_pressure ) );
+    let mut safety_valve = Edge::SafetyValve( SafetyValve::new( node_from.id(), node_to.id(), set_pressure ) );
     *safety_valve.invk_values().unwrap() = vec![ 
         (0.0, 0.0),
         (1.0, 1. / 0.25),
@@ -45,7 +45,7 @@ fn transient_safety_valve() {
     graph.add_edge( safety_valve );
 
     let result = solver.solve_steady( &mut graph, &fluid, true );
-    assert!( result.is_ok() && !result.is_err() );
+    assert!( result.is_ok() );
 
     // Flow should be zero because the safety valve is closed initially.
     let mass_flow = (*graph.edges()[0].mass_flow())[0];
@@ -59,7 +59,7 @@ fn transient_safety_valve() {
         let mass_flow = (*graph.edges()[0].mass_flow())[step];
         let q = mass_flow / fluid.density();
         println!( "step = {}, result = {:?}, q = {:.2e}", step, result, q );
-        assert!( result.is_ok() && !result.is_err() );
+        assert!( result.is_ok() );
     }
 
     // Check that the safety valve opens when the pressure difference is above the set dp
@@ -80,4 +80,62 @@ fn transient_safety_valve() {
 
 
 
-}
\ No newline at end of file
+}
+
+#[test]
+fn blowdown_and_lift() {
+    let mut graph = Graph::new();
+    let fluid = Fluid::new_basic( 997.0, 1.1375e-6, 2.15e9 );
+    let dt = 0.1;
+    let mut solver = Solver::default();
+    *solver.dt() = dt;
+    *solver.max_iter() = 50;    // The flow through the valve is zero once it reseats
+
+    let atmospheric_pressure = 101325.0;
+    let dp = [ 0.0, 5000.0, 12000.0, 20000.0, 20000.0, 14000.0, 9000.0, 7000.0, 9000.0, 11000.0 ];
+    let mut node_from = Node::Pressure( Pressure::new( 0 ) );
+    *node_from.pressure() = dp.iter().map( |dp| atmospheric_pressure + dp ).collect();
+    graph.add_node( node_from.clone() );
+    let mut node_to = Node::Pressure( Pressure::new( 1 ) );
+    *node_to.pressure() = vec![ atmospheric_pressure; dp.len() ];
+    graph.add_node( node_to.clone() );
+    // Reseats at 8 kPa and reaches full lift at 20 kPa
+    let mut valve = SafetyValve::new( node_from.id(), node_to.id(), 10000.0 );
+    valve.overpressure = 10000.0;
+    valve.blowdown = 2000.0;
+    valve.opening_time = 0.2;
+    valve.closing_time = 0.4;
+    valve.diameter = 50.0e-3;
+    graph.add_edge( Edge::SafetyValve( valve ) );
+
+    assert!( solver.solve_steady( &mut graph, &fluid, true ).is_ok() );
+    for _step in 0..dp.len() {
+        assert!( solver.time_step( &mut graph, &fluid ).is_ok() );
+    }
+
+    // Each lift follows the pressure difference at the start of the step
+    let lift = graph.edges()[0].open_percent().unwrap().clone();
+    let expected = [ 
+        0.0, 0.0, 0.0,
+        1.0 / 3.0,      // Pops part way above the set pressure difference
+        5.0 / 6.0,      // Limited by the opening time
+        1.0, 
+        0.75,           // Limited by the closing time
+        0.5,            // Stays open below the set pressure difference
+        0.25,           // Reseats below the blowdown
+        0.0,            // Closed until the set pressure difference is exceeded again
+        0.25,
+    ];
+    assert_eq!( lift.len(), expected.len() );
+    for (lift, expected) in lift.iter().zip( expected ) {
+        assert!( ( lift - expected ).abs() < 1.0e-12 );
+    }
+    // The valve passes flow in proportion to its lift
+    let mass_flow = graph.edges()[0].mass_flow().clone();
+    assert!( mass_flow[2].abs() < 1.0e-4 );
+    assert!( mass_flow[3] > 0.0 && mass_flow[3] < mass_flow[5] );
+    assert!( mass_flow[7] > 0.0 );
+    assert!( mass_flow[9].abs() < 1.0e-4 );
+    // The solver keeps whether the valve has lifted
+    assert!( solver.edge_states()[0].lifted );
+}
@@ -0,0 +1,86 @@
+use eki::solver::{ SolverType, SolverError };
+use eki::graph::Diagnostic;
+use eki::profile::Profile;
+
+use super::example3_1;
+
+#[test]
+fn automatic_segments() {
+    // A single pipe split automatically matches five pipes created by hand
+    let ( mut solver, mut network, fluid ) = example3_1( SolverType::Transient, 1, 0 );
+    let ( mut solver_5, mut network_5, _ ) = example3_1( SolverType::Transient, 5, 1 );
+    assert_eq!( network.num_edges(), 2 );
+    for step in 1..20 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+        assert!( solver_5.time_step( &mut network_5, &fluid ).is_ok() );
+        let valve_pressure = (*network.nodes()[1].pressure())[step];
+        let valve_pressure_5 = (*network_5.nodes()[5].pressure())[step];
+        assert!( ( ( valve_pressure - valve_pressure_5 ) / valve_pressure_5 ).abs() < 1.0e-6 );
+        let flow = network.edges()[0].mass_flow()[step];
+        let flow_5 = network_5.edges()[0].mass_flow()[step];
+        assert!( ( flow - flow_5 ).abs() < 1.0e-6 );
+    }
+    assert_eq!( network.edges()[0].mass_flow().len(), 20 );
+}
+
+#[test]
+fn profiles() {
+    let ( mut solver, mut network, fluid ) = example3_1( SolverType::Transient, 1, 0 );
+    for _step in 1..20 {
+        assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    }
+    assert!( solver.profiles()[1].is_none() );
+    let profile: &Profile = solver.profiles()[0].as_ref().unwrap();
+    assert_eq!( profile.points(), 6 );
+    assert_eq!( profile.steps(), 20 );
+    assert_eq!( profile.distance, vec![ 0.0, 120.0, 240.0, 360.0, 480.0, 600.0 ] );
+    let ( rho, g ) = ( fluid.density(), solver.gravity() );
+    for step in 0..20 {
+        // The ends of the profile are the network values
+        let pressure = profile.pressure( step, rho, g );
+        assert!( ( pressure[5] - (*network.nodes()[1].pressure())[step] ).abs() < 1.0e-6 );
+        assert!( ( pressure[0] - (*network.nodes()[0].pressure())[step] ).abs() < 1.0e-6 );
+        assert!( ( profile.flow[step][0] * rho - network.edges()[0].mass_flow()[step] ).abs() < 1.0e-6 );
+    }
+    // The pressure wave grows towards the valve
+    let max_head = profile.max_head();
+    let min_head = profile.min_head();
+    for i in 0..5 {
+        assert!( max_head[i + 1] > max_head[i] );
+        assert!( min_head[i] <= profile.head[0][i] );
+    }
+    let max_pressure = profile.max_pressure( rho, g );
+    assert!( ( max_pressure[5] - network.nodes()[1].max_pressure() ).abs() < 1.0e-6 );
+}
+
+#[test]
+fn fixed_segments() {
+    let ( mut solver, mut network, fluid ) = example3_1( SolverType::Transient, 1, 2 );
+    assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    let profile = solver.profiles()[0].as_ref().unwrap();
+    assert_eq!( profile.points(), 3 );
+    assert_eq!( profile.steps(), 2 );
+    // Steady profile is linear
+    let head = &profile.head[0];
+    assert!( ( head[1] - 0.5 * ( head[0] + head[2] ) ).abs() < 1.0e-8 );
+}
+
+#[test]
+fn unsplit_pipes() {
+    // Pipes without segments are a single reach unless the solver splits them
+    let ( mut solver, mut network, fluid ) = example3_1( SolverType::Transient, 1, 0 );
+    *solver.split_pipes() = false;
+    assert!( solver.time_step( &mut network, &fluid ).is_ok() );
+    assert_eq!( solver.profiles()[0].as_ref().unwrap().points(), 2 );
+}
+
+#[test]
+fn too_many_reaches() {
+    let ( mut solver, mut network, fluid ) = example3_1( SolverType::Transient, 1, 0 );
+    *solver.dt() = 1.0e-4;
+    let Err( SolverError::InvalidNetwork { diagnostics } ) = solver.time_step( &mut network, &fluid ) else {
+        panic!( "Expected the pipe to need too many reaches" )
+    };
+    assert_eq!( diagnostics, vec![ Diagnostic::TooManyReaches { edge: 0, reaches: 5000 } ] );
+    assert_eq!( diagnostics[0].to_string(), "Edge 0 needs 5000 reaches, more than the limit of 1000" );
+}
@@ -3,6 +3,7 @@ use eki::node::Node;
 use eki::nodes::{ pressure::Pressure, connection::Connection };
 use eki::edge::Edge;
 use eki::edges::{ pipe::Pipe, valve::Valve };
+use eki::friction::FrictionModel;
 use eki::graph::Graph;
 use eki::solver::Solver;
 
@@ -26,9 +27,9 @@ fn example3_1() {
     *pipe_start.pressure() = vec![reservoir_pressure; n]; // Initialise transient pressure values
     network.add_node( pipe_start.clone() );
 
-    // Choose parameters to fit the example (which uses a fixed friction factor = 0.018)
+    // The example uses a fixed friction factor = 0.018
     let (thickness, youngs) = (1.0803e-2, 2.0e11);
-    let (length, diameter, roughness) = (120.0, 0.5, 0.3155006e-3);
+    let (length, diameter, roughness) = (120.0, 0.5, 0.0);
 
     let mut pipe_end = Node::Connection( Connection::new( 0 ) );
 
@@ -36,9 +37,10 @@ fn example3_1() {
     for j in 0..num_sections {
         pipe_end = Node::Connection( Connection::new( j + 2 ) );
         network.add_node( pipe_end.clone() );
-        let pipe = Edge::Pipe( 
-            Pipe::new_params( pipe_start.clone(), pipe_end.clone(), length, diameter, roughness, thickness, youngs )
-        );
+        let pipe = Edge::Pipe( Pipe {
+            friction: FrictionModel::Fixed { f: 0.018 },
+            ..Pipe::new_params( pipe_start.id(), pipe_end.id(), length, diameter, roughness, thickness, youngs )
+        });
         network.add_edge( pipe.clone() );
         pipe_start = pipe_end.clone();
     }
@@ -54,7 +56,7 @@ fn example3_1() {
     //let min_k = 2. * solver.gravity() * area * area * dh / ( 0.477 * 0.477 );
     let cd_av = 0.477 / ( 2.0 * solver.gravity() * dh ).sqrt();
     let min_k = area * area / ( cd_av * cd_av );
-    let mut valve = Edge::Valve( Valve::new( pipe_end, outlet ) );
+    let mut valve = Edge::Valve( Valve::new( pipe_end.id(), outlet.id() ) );
     *valve.invk_values().unwrap() = vec![ 
         (0.0, 0.0 ),
         (0.1, 1.0 / (min_k / (0.1*0.1))),
@@ -85,7 +87,7 @@ fn example3_1() {
 
     // Check steady results
     let steady_result = solver.solve_steady( &mut network, &fluid, true );
-    assert!( steady_result.is_ok() && !steady_result.is_err() );
+    assert!( steady_result.is_ok() );
     if let Ok(iter) = steady_result {
         assert!( iter < 10 );
     }
@@ -101,16 +103,16 @@ fn example3_1() {
     assert!( ( volume_flow - 0.477 ).abs() < 1.0e-3 );
 
     // Check transient results
-    let streeter = vec![
+    let streeter = [
         143.49, 154.28, 165.79, 178.08, 191.11 
     ];
 
-    for step in 1..5 {
+    for (step, expected) in streeter.iter().enumerate().skip( 1 ) {
         let result = solver.time_step( &mut network, &fluid );
-        assert!( result.is_ok() && !result.is_err() );
+        assert!( result.is_ok() );
         let valve_pressure = (*network.nodes()[5].pressure())[step];
         let valve_head = valve_pressure / ( fluid.density() * solver.gravity() );
-        let relative_error = ( streeter[step] - valve_head ) / streeter[step];
+        let relative_error = ( expected - valve_head ) / expected;
         assert!( relative_error.abs() < 0.01 );
     }
     
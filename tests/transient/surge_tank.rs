@@ -30,11 +30,11 @@ fn pipe_tank_valve() {
     network.add_node( node_2.clone() );
 
     // Pipe
-    let pipe = Edge::Pipe( Pipe::new( node_0.clone(), node_1.clone()) );
+    let pipe = Edge::Pipe( Pipe::new( node_0.id(), node_1.id()) );
     network.add_edge( pipe );
 
     // Valve
-    let mut valve = Edge::Valve( Valve::new( node_1.clone(), node_2.clone() ) );
+    let mut valve = Edge::Valve( Valve::new( node_1.id(), node_2.id() ) );
     let exponent = Value( 1.5 );  // Linear closing
     let event_time = Time( 0.0 );
     let closing_time = Time( 1.0 );
@@ -44,7 +44,7 @@ fn pipe_tank_valve() {
 
     // Solve steady
     let steady_result = solver.solve_steady( &mut network, &fluid, true );
-    assert!( steady_result.is_ok() && !steady_result.is_err() );
+    assert!( steady_result.is_ok() );
 
     let open_percent = network.edges()[1].open_percent().unwrap().clone();
     assert_eq!( open_percent, vec![ 1.0 ] );
@@ -62,8 +62,8 @@ fn pipe_tank_valve() {
             Ok( iter ) => {
                 *solver.dt() *= if iter < 5 { 1.1 } else { 0.5 }; // Adaptive time-stepping
             },
-            Err( residual ) => {
-                println!("Residual = {:+.2e}", residual );
+            Err( error ) => {
+                println!("Error = {}", error );
             }
         }
     }
@@ -100,11 +100,11 @@ fn pipe_tank_valve() {
     new_network.add_node( node_2.clone() );
 
     // Pipe
-    let pipe = Edge::Pipe( Pipe::new( node_0.clone(), node_1.clone()) );
+    let pipe = Edge::Pipe( Pipe::new( node_0.id(), node_1.id()) );
     new_network.add_edge( pipe );
 
     // Valve
-    let mut valve = Edge::Valve( Valve::new( node_1.clone(), node_2.clone() ) );
+    let mut valve = Edge::Valve( Valve::new( node_1.id(), node_2.id() ) );
     let exponent = Value( 1.5 );  // Linear closing
     let event_time = Time( 0.0 );
     let closing_time = Time( 1.0 );
@@ -114,7 +114,7 @@ fn pipe_tank_valve() {
 
     // Solve steady
     let steady_result = new_solver.solve_steady( &mut new_network, &fluid, true );
-    assert!( steady_result.is_ok() && !steady_result.is_err() );
+    assert!( steady_result.is_ok() );
 
     // Transient solve (pipe - connection - valve)
     t = 0.0;
@@ -128,8 +128,8 @@ fn pipe_tank_valve() {
             Ok( iter ) => {
                 *new_solver.dt() *= if iter < 5 { 1.1 } else { 0.5 }; // Adaptive time-stepping TODO option for on/off
             },
-            Err( residual ) => {
-                println!("Residual = {:+.2e}", residual );
+            Err( error ) => {
+                println!("Error = {}", error );
             }
         }
     }
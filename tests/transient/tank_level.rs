@@ -0,0 +1,108 @@
+use eki::fluid::Fluid;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, tank::Tank };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, valve::Valve };
+use eki::graph::Graph;
+use eki::solver::Solver;
+use super::run;
+
+// The tank after a number of time steps
+fn run_tank( solver: &mut Solver, network: &mut Graph, fluid: &Fluid, steps: usize ) -> Tank {
+    run( solver, network, fluid, steps );
+    let Node::Tank( tank ) = network.mut_nodes()[1].clone() else { panic!( "Expected a tank" ) };
+    tank
+}
+
+#[test]
+fn overflow() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.1;
+    *solver.max_iter() = 50;
+    let rho_g = fluid.density() * solver.gravity();
+    // Reservoir - pipe - tank - valve - outlet. The tank fills from the reservoir until it overflows.
+    let mut network = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + rho_g * 20.0 ) );
+    network.add_node( reservoir.clone() );
+    let tank = Node::Tank( Tank::new_with_values( 1, 101325.0, fluid.density(), solver.gravity(), 0.5, 1.0, 0.2, 1.2 ) );
+    network.add_node( tank.clone() );
+    let outlet = Node::Pressure( Pressure::new( 2 ) );
+    network.add_node( outlet.clone() );
+    let pipe = Pipe::new_params( reservoir.id(), tank.id(), 100.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    network.add_edge( Edge::Valve( Valve::new( tank.id(), outlet.id() ) ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    let tank = run_tank( &mut solver, &mut network, &fluid, 100 );
+    assert_eq!( tank.level.len(), 101 );
+    assert!( ( tank.level[0] - 1.0 ).abs() < 1.0e-10 );
+    assert!( tank.level.iter().all( |level| *level <= tank.z_max + 1.0e-10 ) );
+    assert!( ( tank.level.last().unwrap() - tank.z_max ).abs() < 1.0e-10 );
+    // The spill takes the difference between the inflow and outflow
+    let mass_flow: Vec<f64> = network.mut_edges().iter_mut().map( |edge| *edge.mass_flow().last().unwrap() ).collect();
+    let spill = ( mass_flow[0] - mass_flow[1] ) / fluid.density();
+    assert!( spill > 0.0 );
+    assert!( ( tank.overflow.last().unwrap() - spill ).abs() < 1.0e-8 );
+    assert!( tank.air_entry.iter().all( |q| *q == 0.0 ) );
+}
+
+#[test]
+fn draining() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.1;
+    *solver.max_iter() = 50;
+    let rho_g = fluid.density() * solver.gravity();
+    // Reservoir - pipe - tank - valve - outlet. The reservoir head is below the tank level so it drains.
+    let mut network = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + rho_g * 0.3 ) );
+    network.add_node( reservoir.clone() );
+    let tank = Node::Tank( Tank::new_with_values( 1, 101325.0, fluid.density(), solver.gravity(), 0.5, 1.0, 0.2, 1.2 ) );
+    network.add_node( tank.clone() );
+    let outlet = Node::Pressure( Pressure::new( 2 ) );
+    network.add_node( outlet.clone() );
+    let pipe = Pipe::new_params( reservoir.id(), tank.id(), 100.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    network.add_edge( Edge::Valve( Valve::new( tank.id(), outlet.id() ) ) );
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    let tank = run_tank( &mut solver, &mut network, &fluid, 300 );
+    assert!( tank.level.iter().all( |level| *level >= tank.z_min - 1.0e-10 ) );
+    assert!( ( tank.level.last().unwrap() - tank.z_min ).abs() < 1.0e-10 );
+    assert!( *tank.air_entry.last().unwrap() > 0.0 );
+    assert!( tank.overflow.iter().all( |q| *q == 0.0 ) );
+}
+
+#[test]
+fn area_table() {
+    let fluid = Fluid::default();
+    let mut solver = Solver::default();
+    *solver.dt() = 0.1;
+    *solver.max_iter() = 50;
+    let rho_g = fluid.density() * solver.gravity();
+    // Reservoir - pipe - tank - valve - outlet
+    let mut network = Graph::new();
+    let reservoir = Node::Pressure( Pressure::new_with_value( 0, 101325.0 + rho_g * 20.0 ) );
+    network.add_node( reservoir.clone() );
+    let tank = Node::Tank( Tank::new_with_values( 1, 101325.0, fluid.density(), solver.gravity(), 0.5, 1.0, 0.2, 1.2 ) );
+    network.add_node( tank.clone() );
+    let outlet = Node::Pressure( Pressure::new( 2 ) );
+    network.add_node( outlet.clone() );
+    let pipe = Pipe::new_params( reservoir.id(), tank.id(), 100.0, 0.1, 0.05e-3, 5.0e-3, 2.0e11 );
+    network.add_edge( Edge::Pipe( pipe ) );
+    network.add_edge( Edge::Valve( Valve::new( tank.id(), outlet.id() ) ) );
+    let mut network_wide = network.clone();
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    let cylinder = run_tank( &mut solver, &mut network, &fluid, 3 );
+    // A tank twice as wide fills half as fast
+    let area = 2.0 * cylinder.area_at( 1.0 );
+    let Node::Tank( tank ) = &mut network_wide.mut_nodes()[1] else { panic!( "Expected a tank" ) };
+    tank.area_data = vec![ ( 0.0, area ), ( 2.0, area ) ];
+    let mut solver = Solver::default();
+    *solver.dt() = 0.1;
+    *solver.max_iter() = 50;
+    assert!( solver.solve_steady( &mut network_wide, &fluid, true ).is_ok() );
+    let wide = run_tank( &mut solver, &mut network_wide, &fluid, 3 );
+    assert_eq!( wide.area(), area );
+    let rise = ( cylinder.level[1] - 1.0, wide.level[1] - 1.0 );
+    assert!( ( rise.1 / rise.0 - 0.5 ).abs() < 0.05 );
+}
@@ -5,6 +5,15 @@ use eki::edge::Edge;
 use eki::edges::{ pipe::Pipe };
 use eki::graph::Graph;*/
 use eki::solver::{Solver, SolverType};
+use eki::fluid::Fluid;
+use eki::graph::Graph;
+use eki::node::Node;
+use eki::nodes::{ pressure::Pressure, connection::Connection };
+use eki::edge::Edge;
+use eki::edges::{ pipe::Pipe, valve::Valve };
+use eki::friction::FrictionModel;
+
+use std::f64::consts::PI;
 
 mod streeter_and_wylie;
 mod events;
@@ -13,6 +22,16 @@ mod check_valve;
 mod safety_valve;
 mod relief_valve;
 mod bursting_disk;
+mod characteristics;
+mod segments;
+mod cavitation;
+mod pump_trip;
+mod air_valve;
+mod accumulator;
+mod tank_level;
+mod extended_period;
+mod envelope;
+mod control_valve;
 
 #[test]
 fn initialise() {
@@ -23,6 +42,72 @@ fn initialise() {
     assert_eq!( *solver.theta(), 0.5 );
 }
 
+// Take a number of time steps, each of which must succeed
+pub fn run( solver: &mut Solver, network: &mut Graph, fluid: &Fluid, steps: usize ) {
+    for _step in 0..steps {
+        assert!( solver.time_step( network, fluid ).is_ok() );
+    }
+}
+
+// Streeter & Wylie example 3.1 with the 600m pipe made of `sections` pipes split into `segments`
+// (0 = left to the solver) and the valve closing over 2.1s. The valve is node `sections`.
+pub fn example3_1( solver_type: SolverType, sections: usize, segments: usize ) -> ( Solver, Graph, Fluid ) {
+    let dt = 0.1;
+    let n = 43;
+    let mut solver = Solver::default();
+    *solver.solver_type() = solver_type;
+    *solver.split_pipes() = true;
+    *solver.g() = 9.806;
+    *solver.dt() = dt;
+    let mut network = Graph::new();
+    let fluid = Fluid::new_basic( 997.0, 1.1375e-6, 2.15e9 );
+
+    let reservoir_pressure = fluid.density() * solver.gravity() * 150.0;
+    let mut pipe_start = Node::Pressure( Pressure::new_with_value( 1, reservoir_pressure ) );
+    *pipe_start.pressure() = vec![ reservoir_pressure; n ];
+    network.add_node( pipe_start.clone() );
+    let (thickness, youngs) = (1.0803e-2, 2.0e11);
+    // The example uses a fixed friction factor = 0.018
+    let (length, diameter, roughness) = (600.0 / sections as f64, 0.5, 0.0);
+    let mut pipe_end = pipe_start.clone();
+    for j in 0..sections {
+        pipe_end = Node::Connection( Connection::new( j + 2 ) );
+        network.add_node( pipe_end.clone() );
+        let mut pipe = Pipe::new_params( pipe_start.id(), pipe_end.id(), length, diameter, roughness, thickness, youngs );
+        pipe.friction = FrictionModel::Fixed { f: 0.018 };
+        pipe.segments = segments;
+        network.add_edge( Edge::Pipe( pipe ) );
+        pipe_start = pipe_end.clone();
+    }
+
+    // Heads are gauge values as in the example
+    let dh = 143.49;
+    let mut outlet = Node::Pressure( Pressure::new_with_value( sections + 2, 0.0 ) );
+    *outlet.pressure() = vec![ 0.0; n ];
+    network.add_node( outlet.clone() );
+
+    let area = PI * 0.5 * 0.5 / 4.;
+    let cd_av = 0.477 / ( 2.0 * solver.gravity() * dh ).sqrt();
+    let min_k = area * area / ( cd_av * cd_av );
+    let mut valve = Edge::Valve( Valve::new( pipe_end.id(), outlet.id() ) );
+    *valve.invk_values().unwrap() = ( 0..=100 ).map( |i| {
+        let tau = i as f64 / 100.0;
+        ( tau, tau * tau / min_k )
+    }).collect();
+    *valve.steady_open_percent() = 1.0;
+    let tc: f64 = 2.1;
+    for i in 1..n {
+        let t = i as f64 * dt;
+        let tau = if t <= tc { ( 1.0 - ( t / tc ) ).powf( 1.5 ) } else { 0.0 };
+        (*valve.open_percent().unwrap()).push( tau );
+    }
+    *valve.diameter().unwrap() = 0.5;
+    network.add_edge( valve );
+
+    assert!( solver.solve_steady( &mut network, &fluid, true ).is_ok() );
+    ( solver, network, fluid )
+}
+
 /*#[test]
 fn time_step() {
     let mut solver = Solver::default();
@@ -43,9 +128,9 @@ fn time_step() {
     assert_eq!( *node_to.consumption(), vec![ -0.1, -0.05 ] );
     network.add_node( node_to.clone() );
     
-    let edge = Edge::Pipe( Pipe::new( node_from, connection.clone() ) );
+    let edge = Edge::Pipe( Pipe::new( node_from.id(), connection.id() ) );
     network.add_edge( edge );
-    let edge = Edge::Pipe( Pipe::new( connection, node_to.clone() ) );
+    let edge = Edge::Pipe( Pipe::new( connection.id(), node_to.id() ) );
     network.add_edge( edge );
 
     let fluid = Fluid::new( 997.0, 1.1375e-6, 2.15e9 );